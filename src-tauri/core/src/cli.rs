@@ -0,0 +1,216 @@
+//! `proxypal <command>`: the headless front end, run by the `proxypal`
+//! binary. All work is done by `headless`.
+
+use serde::Serialize;
+
+use crate::headless;
+
+const USAGE: &str = "\
+Usage: proxypal <command> [--json]
+
+Commands:
+  start                      Start CLIProxyAPI in the background
+  stop                       Stop the CLIProxyAPI ProxyPal started
+  status                     Show whether the proxy is running
+  run                        Run CLIProxyAPI in the foreground, restarting it after crashes
+  config show                Print config.json (secrets stay vault references)
+  config get <key>           Print one setting, e.g. `port` or `copilot.port`
+  config set <key> <value>   Change a setting; the value is JSON or a plain string
+  agents configure <agent>   Point an agent (claude-code, codex, opencode, ...) at the proxy
+  usage report               Show usage statistics
+  auth list                  List CLIProxyAPI credential files
+  autostart on|off|status    Run the proxy at login (a systemd user unit on Linux)
+
+Environment:
+  PROXYPAL_SIDECAR           CLIProxyAPI binary to run instead of the bundled one
+  PROXYPAL_VAULT_PASSPHRASE  Unlocks a passphrase-protected secret vault
+";
+
+/// Run a CLI command and return the process exit code
+pub fn main(args: Vec<String>) -> i32 {
+    let json = args.iter().any(|a| a == "--json");
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| *a != "--json")
+        .collect();
+
+    if let Err(e) = headless::init() {
+        eprintln!("Error: {}", e);
+        return 1;
+    }
+    match dispatch(&args, json) {
+        Ok(()) => 0,
+        Err(CliError::Usage) => {
+            eprint!("{}", USAGE);
+            2
+        }
+        Err(CliError::Failed(message)) => {
+            eprintln!("Error: {}", message);
+            1
+        }
+    }
+}
+
+enum CliError {
+    Usage,
+    Failed(String),
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::Failed(message)
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), CliError> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", text);
+    Ok(())
+}
+
+fn print_status(status: &headless::HeadlessStatus, json: bool) -> Result<(), CliError> {
+    if json {
+        return print_json(status);
+    }
+    match status.pid {
+        Some(pid) if status.running => println!(
+            "CLIProxyAPI {}running on port {} (PID {}){}",
+            status
+                .version
+                .as_deref()
+                .map(|v| format!("{} ", v))
+                .unwrap_or_default(),
+            status.port,
+            pid,
+            if status.ready {
+                ""
+            } else {
+                ", not answering yet"
+            }
+        ),
+        _ => println!("CLIProxyAPI is not running"),
+    }
+    if status.running {
+        println!("Endpoint: {}", status.endpoint);
+    }
+    Ok(())
+}
+
+fn dispatch(args: &[&str], json: bool) -> Result<(), CliError> {
+    match args {
+        ["start"] => print_status(&headless::start()?, json),
+        ["stop"] => {
+            if headless::stop() {
+                println!("CLIProxyAPI stopped");
+            } else {
+                println!("CLIProxyAPI is not running");
+            }
+            Ok(())
+        }
+        ["status"] => print_status(&headless::status(), json),
+        ["run"] => Ok(headless::run_foreground()?),
+        ["config", "show"] => print_json(&headless::config_value()?),
+        ["config", "get", key] => print_json(&headless::config_get(key)?),
+        ["config", "set", key, value] => {
+            let applied = headless::config_set(key, value)?;
+            if applied {
+                println!("Saved {}; the running proxy reloads it", key);
+            } else {
+                println!("Saved {}", key);
+            }
+            Ok(())
+        }
+        ["agents", "configure", agent] => print_json(&headless::configure(agent)?),
+        ["usage", "report"] | ["usage"] => {
+            let stats = headless::usage()?;
+            if json {
+                return print_json(&stats);
+            }
+            println!(
+                "Requests: {} ({} ok, {} failed), {} today",
+                stats.total_requests,
+                stats.success_count,
+                stats.failure_count,
+                stats.requests_today
+            );
+            println!(
+                "Tokens:   {} ({} in, {} out, {} cached), {} today",
+                stats.total_tokens,
+                stats.input_tokens,
+                stats.output_tokens,
+                stats.cached_tokens,
+                stats.tokens_today
+            );
+            for provider in &stats.providers {
+                println!(
+                    "  {:<20} {:>8} requests {:>12} tokens",
+                    provider.provider, provider.requests, provider.tokens
+                );
+            }
+            for model in &stats.models {
+                println!(
+                    "  {:<40} {:>8} requests {:>12} tokens",
+                    model.model, model.requests, model.tokens
+                );
+            }
+            Ok(())
+        }
+        ["auth", "list"] | ["auth"] => {
+            let (files, counts) = headless::auth_files();
+            if json {
+                return print_json(&serde_json::json!({ "files": files, "counts": counts }));
+            }
+            if files.is_empty() {
+                println!("No credential files");
+            }
+            for file in &files {
+                println!(
+                    "{:<12} {}{}",
+                    file.provider,
+                    file.name,
+                    if file.disabled { " (disabled)" } else { "" }
+                );
+            }
+            Ok(())
+        }
+        ["autostart", "on"] => {
+            headless::set_daemon_autostart(true)?;
+            println!("The proxy will run at login");
+            Ok(())
+        }
+        ["autostart", "off"] => {
+            headless::set_daemon_autostart(false)?;
+            println!("The proxy will no longer run at login");
+            Ok(())
+        }
+        ["autostart", "status"] | ["autostart"] => {
+            let enabled = headless::daemon_autostart();
+            if json {
+                return print_json(&serde_json::json!({ "enabled": enabled }));
+            }
+            println!("Run at login: {}", if enabled { "on" } else { "off" });
+            Ok(())
+        }
+        ["help"] | ["--help"] => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(CliError::Usage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_commands_are_usage_errors() {
+        assert!(matches!(dispatch(&[], false), Err(CliError::Usage)));
+        assert!(matches!(
+            dispatch(&["config", "set", "port"], false),
+            Err(CliError::Usage)
+        ));
+        assert!(matches!(dispatch(&["--minimized"], false), Err(CliError::Usage)));
+    }
+}
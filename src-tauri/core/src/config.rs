@@ -0,0 +1,682 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::helpers::migration::{config_version_of, migrate_config_value};
+use crate::types::{
+    cloudflare::CloudflareConfig, AmpModelMapping, AmpOpenAIProvider, Budget,
+    ClaudeApiKey, CodexApiKey, ControlApiConfig, CopilotConfig, CustomConfigMergeSettings, GeminiApiKey,
+    SnapshotRetention, SshConfig, VertexApiKey,
+};
+
+/// Id of the proxy instance that runs on the app config
+pub const DEFAULT_INSTANCE_ID: &str = "default";
+
+/// Schema version written by this build; see `helpers::migration` for the upgrade chain
+pub const CURRENT_CONFIG_VERSION: u8 = 2;
+
+/// App configuration persisted to config.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppConfig {
+    pub port: u16,
+    pub auto_start: bool,
+    pub launch_at_login: bool,
+    #[serde(default)]
+    pub debug: bool,
+    #[serde(default)]
+    pub proxy_url: String,
+    #[serde(default)]
+    pub proxy_username: String,
+    #[serde(default)]
+    pub proxy_password: String,
+    #[serde(default)]
+    pub use_system_proxy: bool,
+    #[serde(default)]
+    pub request_retry: u16,
+    #[serde(default)]
+    pub quota_switch_project: bool,
+    #[serde(default)]
+    pub quota_switch_preview_model: bool,
+    #[serde(default = "default_usage_stats_enabled")]
+    pub usage_stats_enabled: bool,
+    #[serde(default)]
+    pub request_logging: bool,
+    #[serde(default)]
+    pub logging_to_file: bool,
+    #[serde(default = "default_logs_max_total_size_mb")]
+    pub logs_max_total_size_mb: u32,
+    #[serde(default = "default_config_version")]
+    pub config_version: u8,
+    #[serde(default)]
+    pub amp_api_key: String,
+    #[serde(default)]
+    pub amp_model_mappings: Vec<AmpModelMapping>,
+    #[serde(default)]
+    pub amp_openai_providers: Vec<AmpOpenAIProvider>,
+    #[serde(default)]
+    pub amp_routing_mode: String,
+    #[serde(default = "default_routing_strategy")]
+    pub routing_strategy: String,
+    #[serde(default)]
+    pub copilot: CopilotConfig,
+    #[serde(default)]
+    pub force_model_mappings: bool,
+    #[serde(default)]
+    pub claude_api_keys: Vec<ClaudeApiKey>,
+    #[serde(default)]
+    pub gemini_api_keys: Vec<GeminiApiKey>,
+    #[serde(default)]
+    pub codex_api_keys: Vec<CodexApiKey>,
+    #[serde(default)]
+    pub vertex_api_keys: Vec<VertexApiKey>,
+    #[serde(default)]
+    pub thinking_budget_mode: String,
+    #[serde(default)]
+    pub thinking_budget_custom: u32,
+    #[serde(default = "default_gemini_thinking_injection")]
+    pub gemini_thinking_injection: bool,
+    #[serde(default)]
+    pub reasoning_effort_level: String,
+    #[serde(default = "default_close_to_tray")]
+    pub close_to_tray: bool,
+    #[serde(default)]
+    pub max_retry_interval: i32,
+    #[serde(default = "default_proxy_api_key")]
+    pub proxy_api_key: String,
+    #[serde(default = "default_management_key")]
+    pub management_key: String,
+    #[serde(default)]
+    pub commercial_mode: bool,
+    #[serde(default)]
+    pub ws_auth: bool,
+    #[serde(default)]
+    pub sidebar_pinned: bool,
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    #[serde(default)]
+    pub ssh_configs: Vec<SshConfig>,
+    #[serde(default)]
+    pub cloudflare_configs: Vec<CloudflareConfig>,
+    #[serde(default = "default_disable_control_panel")]
+    pub disable_control_panel: bool,
+    #[serde(default)]
+    pub custom_config_merge: CustomConfigMergeSettings,
+    #[serde(default)]
+    pub snapshot_retention: SnapshotRetention,
+    #[serde(default)]
+    pub auto_select_port: bool,
+    #[serde(default)]
+    pub sidecar_output_to_file: bool,
+    #[serde(default)]
+    pub control_api: ControlApiConfig,
+    #[serde(default = "default_usage_retention_days")]
+    pub usage_retention_days: u32, // Days of per-request usage to keep, 0 for all
+    #[serde(default)]
+    pub budgets: Vec<Budget>,
+}
+
+fn default_disable_control_panel() -> bool {
+    true
+}
+
+fn default_management_key() -> String {
+    "proxypal-mgmt-key".to_string()
+}
+
+fn default_proxy_api_key() -> String {
+    "proxypal-local".to_string()
+}
+
+fn default_close_to_tray() -> bool {
+    true
+}
+
+fn default_usage_stats_enabled() -> bool {
+    true
+}
+
+fn default_logs_max_total_size_mb() -> u32 {
+    100
+}
+
+fn default_usage_retention_days() -> u32 {
+    90
+}
+
+fn default_config_version() -> u8 {
+    CURRENT_CONFIG_VERSION
+}
+
+fn default_routing_strategy() -> String {
+    "round-robin".to_string()
+}
+
+fn default_gemini_thinking_injection() -> bool {
+    true
+}
+
+fn default_locale() -> String {
+    "en".to_string()
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            port: 8317,
+            auto_start: true,
+            launch_at_login: false,
+            debug: false,
+            proxy_url: String::new(),
+            proxy_username: String::new(),
+            proxy_password: String::new(),
+            use_system_proxy: false,
+            request_retry: 0,
+            quota_switch_project: false,
+            quota_switch_preview_model: false,
+            usage_stats_enabled: true,
+            request_logging: true,
+            logging_to_file: true,
+            logs_max_total_size_mb: 100,
+            config_version: CURRENT_CONFIG_VERSION,
+            amp_api_key: String::new(),
+            amp_model_mappings: Vec::new(),
+            amp_openai_providers: Vec::new(),
+            amp_routing_mode: "mappings".to_string(),
+            sidebar_pinned: false,
+            routing_strategy: "round-robin".to_string(),
+            copilot: CopilotConfig::default(),
+            force_model_mappings: true,
+            claude_api_keys: Vec::new(),
+            gemini_api_keys: Vec::new(),
+            codex_api_keys: Vec::new(),
+            vertex_api_keys: Vec::new(),
+            thinking_budget_mode: "medium".to_string(),
+            thinking_budget_custom: 16000,
+            gemini_thinking_injection: true,
+            reasoning_effort_level: "medium".to_string(),
+            close_to_tray: true,
+            max_retry_interval: 0,
+            proxy_api_key: "proxypal-local".to_string(),
+            management_key: "proxypal-mgmt-key".to_string(),
+            commercial_mode: false,
+            ws_auth: false,
+            locale: "en".to_string(),
+            ssh_configs: Vec::new(),
+            cloudflare_configs: Vec::new(),
+            disable_control_panel: true,
+            custom_config_merge: CustomConfigMergeSettings::default(),
+            snapshot_retention: SnapshotRetention::default(),
+            auto_select_port: false,
+            sidecar_output_to_file: false,
+            control_api: ControlApiConfig::default(),
+            usage_retention_days: 90,
+            budgets: Vec::new(),
+        }
+    }
+}
+
+/// Get the proxypal config directory, creating it if needed
+pub fn get_proxypal_config_dir() -> std::path::PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| {
+            eprintln!(
+                "[ProxyPal] Warning: Could not determine config directory, using current directory"
+            );
+            std::path::PathBuf::from(".")
+        })
+        .join("proxypal");
+
+    if let Err(e) = std::fs::create_dir_all(&config_dir) {
+        eprintln!(
+            "[ProxyPal] Error: Failed to create config directory '{}': {}",
+            config_dir.display(),
+            e
+        );
+    }
+
+    config_dir
+}
+
+/// Config file path
+pub fn get_config_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("config.json")
+}
+
+/// Directory holding named config profiles
+pub fn get_profiles_dir() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("profiles")
+}
+
+/// Active profile marker file path
+pub fn get_active_profile_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("active-profile.json")
+}
+
+/// Encrypted secret vault path
+pub fn get_vault_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("secrets.vault")
+}
+
+/// Generated vault key file path (key-file mode only)
+pub fn get_vault_key_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("vault.key")
+}
+
+/// Rotating copy of the sidecar's stdout/stderr (when enabled)
+pub fn get_sidecar_output_log_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("sidecar-output.log")
+}
+
+/// PID file for the sidecar ProxyPal spawned
+pub fn get_sidecar_pid_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("cli-proxy-api.pid")
+}
+
+/// CLIProxyAPI versions installed at runtime, one directory per version
+pub fn get_sidecars_dir() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("sidecars")
+}
+
+/// The user's model prices, overriding the shipped catalog
+pub fn get_pricing_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("pricing.json")
+}
+
+/// Thresholds announced and accounts paused by each budget
+pub fn get_budget_state_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("budgets-state.json")
+}
+
+/// List of the proxy instances besides the default one
+pub fn get_instances_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("instances.json")
+}
+
+/// Directory of a proxy instance. The default instance uses the config
+/// directory itself, so its files keep their usual places.
+pub fn get_instance_dir(id: &str) -> std::path::PathBuf {
+    if id == DEFAULT_INSTANCE_ID {
+        return get_proxypal_config_dir();
+    }
+    get_proxypal_config_dir().join("instances").join(id)
+}
+
+/// Auth status file path
+pub fn get_auth_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("auth.json")
+}
+
+/// Why config.json could not be loaded. An unparseable file is moved aside so
+/// saving defaults never overwrites the user's keys; one that is left in place
+/// (unreadable, or from a newer ProxyPal) blocks saving instead.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigLoadError {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quarantined_path: Option<String>,
+}
+
+static CONFIG_LOAD_ERROR: Mutex<Option<ConfigLoadError>> = Mutex::new(None);
+
+/// Load config from file, falling back to defaults if it can't be loaded.
+/// The failure is kept for `get_config_load_error`.
+pub fn load_config() -> AppConfig {
+    let loaded = load_config_from_path(&get_config_path());
+    match loaded {
+        Ok(config) => {
+            *CONFIG_LOAD_ERROR.lock().unwrap() = None;
+            config
+        }
+        Err(e) => {
+            eprintln!("[ProxyPal] {}. Falling back to defaults.", e.message);
+            *CONFIG_LOAD_ERROR.lock().unwrap() = Some(e);
+            AppConfig::default()
+        }
+    }
+}
+
+/// Last config.json load failure, if any
+pub fn last_config_load_error() -> Option<ConfigLoadError> {
+    CONFIG_LOAD_ERROR.lock().unwrap().clone()
+}
+
+fn timestamp_suffix() -> String {
+    chrono::Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+/// Move an unusable config file aside, e.g. config.json -> config.json.corrupt-20250101-120000
+fn quarantine_config_file(path: &Path) -> Option<PathBuf> {
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let target = path.with_file_name(format!("{}.corrupt-{}", file_name, timestamp_suffix()));
+    match std::fs::rename(path, &target) {
+        Ok(_) => {
+            eprintln!(
+                "[ProxyPal] Moved unreadable config '{}' to '{}'",
+                path.display(),
+                target.display()
+            );
+            Some(target)
+        }
+        Err(e) => {
+            eprintln!(
+                "[ProxyPal] Failed to quarantine config '{}': {}",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+fn quarantine_with_error(path: &Path, message: String) -> ConfigLoadError {
+    let quarantined_path = quarantine_config_file(path);
+    ConfigLoadError {
+        message,
+        quarantined_path: quarantined_path.map(|p| p.display().to_string()),
+    }
+}
+
+/// Copy the file before migrating, e.g. config.json -> config.v1-20250101-120000.bak
+fn write_migration_backup(path: &Path, from_version: u8) -> Result<PathBuf, String> {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "config".to_string());
+    let backup = path.with_file_name(format!("{}.v{}-{}.bak", stem, from_version, timestamp_suffix()));
+    std::fs::copy(path, &backup)
+        .map_err(|e| format!("Failed to write config backup '{}': {}", backup.display(), e))?;
+    Ok(backup)
+}
+
+/// Delete the migration backups and quarantined copies of config.json in
+/// `dir`. They hold whatever keys the file had, so they go when the vault is
+/// enabled. Returns the removed files.
+pub fn remove_config_backups_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let backup = name.starts_with("config.v") && name.ends_with(".bak");
+            backup || name.starts_with("config.json.corrupt-")
+        })
+        .filter(|path| match std::fs::remove_file(path) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("[ProxyPal] Failed to remove '{}': {}", path.display(), e);
+                false
+            }
+        })
+        .collect()
+}
+
+pub fn load_config_from_path(path: &Path) -> Result<AppConfig, ConfigLoadError> {
+    if !path.exists() {
+        return Ok(AppConfig::default());
+    }
+
+    let data = std::fs::read_to_string(path).map_err(|e| ConfigLoadError {
+        message: format!("Failed to read config file '{}': {}", path.display(), e),
+        quarantined_path: None,
+    })?;
+
+    let mut value = match serde_json::from_str::<serde_json::Value>(&data) {
+        Ok(value) => value,
+        Err(e) => {
+            return Err(quarantine_with_error(
+                path,
+                format!("Failed to parse config file '{}': {}", path.display(), e),
+            ))
+        }
+    };
+
+    let from_version = config_version_of(&value);
+    if from_version > CURRENT_CONFIG_VERSION {
+        return Err(ConfigLoadError {
+            message: format!(
+                "Config file '{}' is from a newer ProxyPal (config version {}, this one \
+                 knows up to {})",
+                path.display(),
+                from_version,
+                CURRENT_CONFIG_VERSION
+            ),
+            quarantined_path: None,
+        });
+    }
+    let migrated = from_version < CURRENT_CONFIG_VERSION;
+    if migrated {
+        let backup = write_migration_backup(path, from_version).map_err(|message| ConfigLoadError {
+            message,
+            quarantined_path: None,
+        })?;
+        eprintln!("[ProxyPal] Backed up config to '{}' before migrating", backup.display());
+        if let Err(e) = migrate_config_value(&mut value) {
+            return Err(quarantine_with_error(path, e));
+        }
+    }
+
+    let config = match serde_json::from_value::<AppConfig>(value) {
+        Ok(config) => config,
+        Err(e) => {
+            return Err(quarantine_with_error(
+                path,
+                format!("Failed to parse config file '{}': {}", path.display(), e),
+            ))
+        }
+    };
+
+    if migrated {
+        let _ = save_config_to_path(path, &config);
+    }
+
+    Ok(config)
+}
+
+/// Save config to file
+/// Uses atomic write (write to temp file then rename) to prevent corruption
+pub fn save_config_to_file(config: &AppConfig) -> Result<(), String> {
+    // The defaults we fell back to must not replace a file that is still there
+    if let Some(error) = last_config_load_error().filter(|e| e.quarantined_path.is_none()) {
+        return Err(format!(
+            "Not saving over config.json, which could not be loaded: {}. \
+             Fix or move the file, then reload the config.",
+            error.message
+        ));
+    }
+    save_config_to_path(&get_config_path(), config)
+}
+
+pub fn save_config_to_path(path: &Path, config: &AppConfig) -> Result<(), String> {
+    let config_dir = path.parent().ok_or("Invalid config path")?;
+
+    // Ensure config directory exists
+    if let Err(e) = std::fs::create_dir_all(config_dir) {
+        return Err(format!(
+            "Failed to create config directory '{}': {}",
+            config_dir.display(),
+            e
+        ));
+    }
+
+    // With the secret vault enabled, keys are stored as vault references
+    let sealed = crate::helpers::secrets::seal_for_save(config)?;
+    let config = sealed.as_ref().unwrap_or(config);
+
+    // Serialize config to JSON
+    let data = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    // Write to temporary file first, then rename for atomic write
+    let temp_path = path.with_extension("tmp");
+
+    // Try writing to temp file with retry for Windows file locking issues
+    let mut last_error = String::new();
+    for attempt in 0..3 {
+        match std::fs::write(&temp_path, &data) {
+            Ok(_) => break,
+            Err(e) => {
+                last_error = e.to_string();
+                if attempt < 2 {
+                    eprintln!(
+                        "[ProxyPal] Save attempt {} failed, retrying: {}",
+                        attempt + 1,
+                        e
+                    );
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                }
+            }
+        }
+    }
+
+    // Verify temp file was written successfully
+    if !temp_path.exists() {
+        return Err(format!(
+            "Failed to write config to temp file (attempted 3 times): {}",
+            last_error
+        ));
+    }
+
+    // Atomic rename from temp to actual config file
+    std::fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to rename temp file to config: {}", e))?;
+
+    eprintln!("[ProxyPal] Config saved successfully to: {:?}", path);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::fs;
+
+    #[test]
+    fn load_config_from_missing_file_returns_defaults() {
+        let dir = test_dir("config-missing");
+        let path = dir.join("config.json");
+
+        let loaded = load_config_from_path(&path).unwrap();
+
+        assert_eq!(loaded.port, AppConfig::default().port);
+        assert_eq!(
+            loaded.routing_strategy,
+            AppConfig::default().routing_strategy
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn load_config_from_invalid_json_quarantines_file() {
+        let dir = test_dir("config-invalid");
+        let path = dir.join("config.json");
+        fs::write(&path, "{ invalid json").unwrap();
+
+        let err = load_config_from_path(&path).unwrap_err();
+
+        assert!(err.message.contains("Failed to parse config file"));
+        assert!(!path.exists());
+        let quarantined = std::path::PathBuf::from(err.quarantined_path.unwrap());
+        assert!(quarantined
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("config.json.corrupt-"));
+        assert_eq!(fs::read_to_string(quarantined).unwrap(), "{ invalid json");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn load_config_with_wrong_field_types_quarantines_file() {
+        let dir = test_dir("config-bad-types");
+        let path = dir.join("config.json");
+        fs::write(&path, r#"{"port": "not-a-number", "autoStart": true, "launchAtLogin": false}"#)
+            .unwrap();
+
+        let err = load_config_from_path(&path).unwrap_err();
+
+        assert!(err.quarantined_path.is_some());
+        assert!(!path.exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn load_config_from_newer_version_is_refused_and_left_alone() {
+        let dir = test_dir("config-newer");
+        let path = dir.join("config.json");
+        let json = format!(r#"{{"port": 8317, "configVersion": {}}}"#, CURRENT_CONFIG_VERSION + 1);
+        fs::write(&path, &json).unwrap();
+
+        let err = load_config_from_path(&path).unwrap_err();
+
+        assert!(err.message.contains("newer ProxyPal"));
+        assert!(err.quarantined_path.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), json);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn load_config_migrates_deprecated_openai_provider() {
+        let dir = test_dir("config-migrate");
+        let path = dir.join("config.json");
+
+        let legacy_json = r#"{
+  "port": 8317,
+  "autoStart": true,
+  "launchAtLogin": false,
+  "ampOpenaiProvider": {
+    "id": "",
+    "name": "Legacy",
+    "apiKey": "test-key",
+    "baseUrl": "https://api.openai.com/v1",
+    "models": [
+      { "name": "gpt-4.1", "enabled": true }
+    ],
+    "enabled": true
+  },
+  "ampOpenaiProviders": []
+}"#;
+
+        fs::write(&path, legacy_json).unwrap();
+        let loaded = load_config_from_path(&path).unwrap();
+
+        assert_eq!(loaded.amp_openai_providers.len(), 1);
+        assert_eq!(loaded.amp_openai_providers[0].name, "Legacy");
+        assert!(!loaded.amp_openai_providers[0].id.is_empty());
+        assert_eq!(loaded.config_version, CURRENT_CONFIG_VERSION);
+
+        let persisted = fs::read_to_string(&path).unwrap();
+        assert!(persisted.contains("ampOpenaiProviders"));
+        assert!(!persisted.contains("\"ampOpenaiProvider\""));
+
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("config.v1-") && name.ends_with(".bak"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(dir.join(&backups[0])).unwrap(), legacy_json);
+
+        // Enabling the vault removes it, and only it
+        fs::write(dir.join("config.json.corrupt-20250101-120000"), "{").unwrap();
+        let mut removed = remove_config_backups_in(&dir);
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![dir.join("config.json.corrupt-20250101-120000"), dir.join(&backups[0])]
+        );
+        assert!(path.exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,508 @@
+//! ProxyPal without the Tauri GUI, for build servers and remote dev boxes.
+//!
+//! Everything here works from config.json and the files next to it instead of
+//! AppState and an AppHandle, reusing the same config generation, sidecar
+//! ownership, restart policy, usage sync, auth scanning and agent setup as the
+//! app. A sidecar started here is recorded in the same PID file as the app's:
+//! the app adopts one it finds running instead of starting a second, either
+//! side can stop the other's, and neither restarts a sidecar the other
+//! stopped.
+
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::{
+    get_proxypal_config_dir, get_sidecar_output_log_path, get_sidecar_pid_path, get_sidecars_dir,
+    load_config, save_config_to_file, AppConfig,
+};
+use crate::helpers::agent_config::configure_agent;
+use crate::helpers::auth_dir::{auth_file_provider, get_cli_proxy_auth_dir, scan_auth_dir};
+use crate::helpers::autostart::{self, LoginItem};
+use crate::helpers::log_watcher::start_log_watcher;
+use crate::helpers::secrets;
+use crate::helpers::usage_recorder;
+use crate::helpers::usage_stats::compute_usage_stats;
+use crate::helpers::usage_sync::sync_usage_from_proxy_blocking;
+use crate::proxy::binaries::active_binary;
+use crate::proxy::capabilities::VERSION_HEADER;
+use crate::proxy::config_yaml::write_proxy_config_file;
+use crate::proxy::ports::{
+    apply_port_change, find_free_port, is_port_free, propagate_port_to_agents,
+};
+use crate::proxy::process::{
+    cleanup_stale_sidecar, command, is_recorded_at, port_owner, record_sidecar, running_sidecar,
+    stop_sidecar,
+};
+use crate::proxy::supervisor::{ExitDecision, RestartPolicy, Supervisor};
+use crate::proxy::validation::{configured_models, summarize_errors, validate_config};
+use crate::types::{AuthStatus, AvailableModel, UsageStats};
+
+/// Overrides the sidecar binary, e.g. on a box without the bundled one
+const SIDECAR_ENV: &str = "PROXYPAL_SIDECAR";
+/// Unlocks a passphrase vault without a prompt
+const PASSPHRASE_ENV: &str = "PROXYPAL_VAULT_PASSPHRASE";
+/// How often `run` adds the proxy's token counts to the logged requests
+const USAGE_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadlessStatus {
+    pub running: bool,
+    pub ready: bool, // Answering on the management API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub port: u16,
+    pub endpoint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthFile {
+    pub name: String,
+    pub provider: String,
+    pub disabled: bool,
+}
+
+/// The startup work `run()` does that the CLI needs too
+pub fn init() -> Result<(), String> {
+    secrets::init_vault();
+    let status = secrets::vault_status();
+    if status.enabled && !status.unlocked {
+        if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+            secrets::unlock_vault(&passphrase)?;
+        }
+    }
+    Ok(())
+}
+
+fn sidecar_path() -> Result<PathBuf, String> {
+    if let Ok(path) = std::env::var(SIDECAR_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    if let Some(path) = active_binary(&get_sidecars_dir()) {
+        return Ok(path);
+    }
+    // Tauri installs the sidecar next to the main executable
+    let name = if cfg!(windows) {
+        "cli-proxy-api.exe"
+    } else {
+        "cli-proxy-api"
+    };
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)))
+        .filter(|path| path.is_file());
+    bundled.ok_or_else(|| {
+        format!(
+            "CLIProxyAPI not found. Install a version in the app, or set {} to its path.",
+            SIDECAR_ENV
+        )
+    })
+}
+
+/// Move to a free port like the app does, or explain who has ours
+fn ensure_port(mut config: AppConfig) -> Result<AppConfig, String> {
+    let port = config.port;
+    if is_port_free(port) {
+        return Ok(config);
+    }
+    let occupant = port_owner(port)
+        .map(|(pid, name)| format!("PID {} ({})", pid, name))
+        .unwrap_or_else(|| "another process".to_string());
+    let reserved: Vec<u16> = if config.copilot.enabled {
+        vec![config.copilot.port]
+    } else {
+        Vec::new()
+    };
+    let free_port = config
+        .auto_select_port
+        .then(|| find_free_port(port, &reserved))
+        .flatten();
+    let Some(free_port) = free_port else {
+        return Err(format!(
+            "Port {} is in use by {}. Stop it, choose another port, or enable automatic port selection.",
+            port, occupant
+        ));
+    };
+    println!(
+        "[ProxyPal] Port {} is in use by {}, switching to port {}",
+        port, occupant, free_port
+    );
+    apply_port_change(&mut config, port, free_port);
+    save_config_to_file(&config)?;
+    if let Some(home) = dirs::home_dir() {
+        let (_, errors) = propagate_port_to_agents(&home, port, free_port);
+        for error in errors {
+            eprintln!("[ProxyPal] {}", error);
+        }
+    }
+    Ok(config)
+}
+
+/// Write proxy-config.yaml and spawn CLIProxyAPI on it. In the foreground
+/// its output goes to our terminal, otherwise to sidecar-output.log.
+fn spawn_sidecar(config: &AppConfig, foreground: bool) -> Result<Child, String> {
+    let validation = validate_config(config, Some(&configured_models(config)));
+    for warning in validation.warnings() {
+        eprintln!(
+            "[ProxyPal] Config warning: {}: {}",
+            warning.field, warning.message
+        );
+    }
+    if !validation.valid {
+        return Err(format!(
+            "Invalid configuration: {}",
+            summarize_errors(&validation)
+        ));
+    }
+
+    let (proxy_config_path, _) = write_proxy_config_file(config)?;
+    let config_dir = proxy_config_path
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let sidecar = sidecar_path()?;
+
+    let mut cmd = command(&sidecar.to_string_lossy());
+    cmd.env("WRITABLE_PATH", &config_dir)
+        .arg("--config")
+        .arg(&proxy_config_path)
+        .stdin(Stdio::null());
+    if !foreground {
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(get_sidecar_output_log_path())
+            .map_err(|e| format!("Failed to open sidecar output log: {}", e))?;
+        let log_err = log.try_clone().map_err(|e| e.to_string())?;
+        cmd.stdout(log).stderr(log_err);
+    }
+
+    let child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", sidecar.display(), e))?;
+    record_sidecar(child.id(), config.port);
+    Ok(child)
+}
+
+/// One management API request; Some(version header) if the sidecar answered
+fn probe(port: u16) -> Option<Option<String>> {
+    let client = reqwest::blocking::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .ok()?;
+    let response = client
+        .get(format!(
+            "http://127.0.0.1:{}/v0/management/config.yaml",
+            port
+        ))
+        .header("X-Management-Key", crate::get_management_key().ok()?)
+        .send()
+        .ok()
+        .filter(|r| r.status().is_success())?;
+    Some(
+        response
+            .headers()
+            .get(VERSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+    )
+}
+
+pub fn status() -> HeadlessStatus {
+    let config = load_config();
+    let running = running_sidecar();
+    let port = running.map(|(_, port)| port).unwrap_or(config.port);
+    let probed = running.and_then(|_| probe(port));
+    HeadlessStatus {
+        running: running.is_some(),
+        ready: probed.is_some(),
+        pid: running.map(|(pid, _)| pid),
+        port,
+        endpoint: format!("http://localhost:{}/v1", port),
+        version: probed.flatten(),
+    }
+}
+
+/// Start CLIProxyAPI in the background and wait until it answers
+pub fn start() -> Result<HeadlessStatus, String> {
+    if running_sidecar().is_some() {
+        return Ok(status());
+    }
+    cleanup_stale_sidecar();
+    let config = ensure_port(load_config())?;
+    let mut child = spawn_sidecar(&config, false)?;
+
+    // 25 attempts × 200ms = 5s max, like the app
+    for _ in 0..25 {
+        std::thread::sleep(Duration::from_millis(200));
+        if let Ok(Some(exit)) = child.try_wait() {
+            return Err(format!(
+                "CLIProxyAPI exited during startup ({}); see {}",
+                exit,
+                get_sidecar_output_log_path().display()
+            ));
+        }
+        if probe(config.port).is_some() {
+            break;
+        }
+    }
+    Ok(status())
+}
+
+/// Stop the recorded sidecar. False if none was running.
+pub fn stop() -> bool {
+    match running_sidecar() {
+        Some((pid, _)) => {
+            stop_sidecar(pid);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Record requests from the sidecar's log like the app does, and attach the
+/// proxy's token counts to them every `USAGE_SYNC_INTERVAL`, until `running`
+/// is cleared
+fn start_usage_tracking(
+    dir: PathBuf,
+    running: Arc<AtomicBool>,
+    retention_days: u32,
+) -> Result<(), String> {
+    // Started here so a store that can't be opened stops the run
+    usage_recorder::recorder(&dir)?;
+    running.store(true, Ordering::SeqCst);
+    start_log_watcher(
+        dir.clone(),
+        running.clone(),
+        Arc::new(AtomicU64::new(0)),
+        retention_days,
+        |_| {},
+    );
+    std::thread::spawn(move || {
+        let mut last_sync = Instant::now();
+        while running.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_secs(1));
+            if last_sync.elapsed() < USAGE_SYNC_INTERVAL {
+                continue;
+            }
+            last_sync = Instant::now();
+            if let Some((_, port)) = running_sidecar() {
+                sync_usage_from_proxy_blocking(&dir, port);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Run CLIProxyAPI in the foreground until it is stopped, restarting it
+/// after crashes with the app's backoff policy. Config changes are picked
+/// up on each restart.
+pub fn run_foreground() -> Result<(), String> {
+    if let Some((pid, _)) = running_sidecar() {
+        return Err(format!("CLIProxyAPI is already running (PID {})", pid));
+    }
+    cleanup_stale_sidecar();
+    let tracking = Arc::new(AtomicBool::new(false));
+    start_usage_tracking(
+        get_proxypal_config_dir(),
+        tracking.clone(),
+        load_config().usage_retention_days,
+    )?;
+    let result = supervise();
+    tracking.store(false, Ordering::SeqCst);
+    usage_recorder::flush_all();
+    result
+}
+
+/// Spawn the sidecar and restart it after crashes until it is stopped
+fn supervise() -> Result<(), String> {
+    let mut supervisor = Supervisor::new(RestartPolicy::default());
+    loop {
+        let config = ensure_port(load_config())?;
+        let mut child = spawn_sidecar(&config, true)?;
+        let pid = child.id();
+        println!(
+            "[ProxyPal] CLIProxyAPI running on port {} (PID {})",
+            config.port, pid
+        );
+
+        let exit = child
+            .wait()
+            .map_err(|e| format!("Failed to wait for CLIProxyAPI: {}", e))?;
+        if !is_recorded_at(&get_sidecar_pid_path(), pid) {
+            println!("[ProxyPal] CLIProxyAPI stopped");
+            return Ok(());
+        }
+        match supervisor.record_exit(Instant::now()) {
+            ExitDecision::Restart { attempt, delay } => {
+                eprintln!(
+                    "[ProxyPal] CLIProxyAPI exited ({}), restart {} in {:?}",
+                    exit, attempt, delay
+                );
+                std::thread::sleep(delay);
+            }
+            ExitDecision::CrashLoop { exits } => {
+                let _ = std::fs::remove_file(get_sidecar_pid_path());
+                return Err(format!(
+                    "CLIProxyAPI exited {} times within {}s, giving up",
+                    exits,
+                    supervisor.policy().window.as_secs()
+                ));
+            }
+        }
+    }
+}
+
+/// config.json as JSON, secrets as vault references
+pub fn config_value() -> Result<Value, String> {
+    serde_json::to_value(load_config()).map_err(|e| e.to_string())
+}
+
+/// Look up a dotted camelCase path, e.g. "copilot.port"
+pub fn config_get(key: &str) -> Result<Value, String> {
+    let value = config_value()?;
+    key.split('.')
+        .try_fold(&value, |value, part| value.get(part))
+        .cloned()
+        .ok_or_else(|| format!("Unknown setting '{}'", key))
+}
+
+/// Whether `proxypal run` starts at login
+pub fn daemon_autostart() -> bool {
+    autostart::is_enabled(LoginItem::Daemon)
+}
+
+/// Start `proxypal run` at login, through a systemd user unit on Linux, or
+/// stop doing so
+pub fn set_daemon_autostart(enabled: bool) -> Result<(), String> {
+    autostart::set_enabled(LoginItem::Daemon, enabled)
+}
+
+/// Set a dotted camelCase path. `raw` is parsed as JSON when it can be,
+/// otherwise taken as a string. A running sidecar gets the new
+/// proxy-config.yaml through its file watcher. Returns whether it did.
+pub fn config_set(key: &str, raw: &str) -> Result<bool, String> {
+    let mut value = config_value()?;
+    let launch_at_login = load_config().launch_at_login;
+    let mut target = &mut value;
+    for part in key.split('.') {
+        target = target
+            .get_mut(part)
+            .ok_or_else(|| format!("Unknown setting '{}'", key))?;
+    }
+    *target = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+
+    let config: AppConfig =
+        serde_json::from_value(value).map_err(|e| format!("Invalid value for '{}': {}", key, e))?;
+    let validation = validate_config(&config, None);
+    if !validation.valid {
+        return Err(format!(
+            "Invalid configuration: {}",
+            summarize_errors(&validation)
+        ));
+    }
+    if config.launch_at_login != launch_at_login {
+        // Only the app knows its own executable; it syncs the item on start
+        println!("[ProxyPal] launchAtLogin takes effect the next time the app starts");
+    }
+    save_config_to_file(&config)?;
+
+    if running_sidecar().is_none() {
+        return Ok(false);
+    }
+    write_proxy_config_file(&config)?;
+    Ok(true)
+}
+
+/// Models the running proxy serves, for agent configs
+fn proxy_models(config: &AppConfig) -> Vec<AvailableModel> {
+    // config.json may hold a vault reference rather than the key
+    let api_key = match secrets::resolve_secret(&config.proxy_api_key) {
+        Ok(api_key) => api_key,
+        Err(e) => {
+            eprintln!("[ProxyPal] Can't ask the proxy for its models: {}", e);
+            return Vec::new();
+        }
+    };
+    let client = reqwest::blocking::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(10))
+        .build();
+    let response = client.ok().and_then(|client| {
+        client
+            .get(format!("http://127.0.0.1:{}/v1/models", config.port))
+            .header("Authorization", format!("Bearer {}", api_key))
+            .send()
+            .ok()
+    });
+    let Some(body) = response.and_then(|r| r.json::<Value>().ok()) else {
+        return Vec::new();
+    };
+    body["data"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| {
+                    Some(AvailableModel {
+                        id: m["id"].as_str()?.to_string(),
+                        owned_by: m["owned_by"].as_str().unwrap_or_default().to_string(),
+                        source: String::new(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Write an agent's config files, with the proxy's models when it is running
+pub fn configure(agent_id: &str) -> Result<Value, String> {
+    let config = load_config();
+    let models = proxy_models(&config);
+    if models.is_empty() {
+        eprintln!("[ProxyPal] No models from the proxy; start it first for a model list");
+    }
+    configure_agent(&config, agent_id.to_string(), models)
+}
+
+pub fn usage() -> Result<UsageStats, String> {
+    let dir = get_proxypal_config_dir();
+    match running_sidecar() {
+        Some((_, port)) => compute_usage_stats(&dir, true, port),
+        None => compute_usage_stats(&dir, false, load_config().port),
+    }
+}
+
+/// Credential files in CLIProxyAPI's auth directory, with per-provider counts
+pub fn auth_files() -> (Vec<AuthFile>, AuthStatus) {
+    let dir = get_cli_proxy_auth_dir();
+    let mut files: Vec<AuthFile> = std::fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let disabled = name.ends_with(".disabled");
+                    let provider = auth_file_provider(name.trim_end_matches(".disabled"))?;
+                    Some(AuthFile {
+                        name,
+                        provider: provider.to_string(),
+                        disabled,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_by(|a, b| (&a.provider, &a.name).cmp(&(&b.provider, &b.name)));
+    (files, scan_auth_dir(&dir))
+}
@@ -0,0 +1,65 @@
+//! CLIProxyAPI's auth directory: where it is and which credentials it holds.
+
+use crate::types::AuthStatus;
+
+/// CLIProxyAPI's auth directory
+pub fn get_cli_proxy_auth_dir() -> std::path::PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join(".cli-proxy-api")
+}
+
+/// Provider of a credential file, from CLIProxyAPI's naming patterns:
+/// - claude-{email}.json or anthropic-*.json
+/// - codex-{email}.json
+/// - gemini-{email}-{project}.json
+/// - qwen-{email}.json
+/// - iflow-{email}.json
+/// - vertex-{project_id}.json
+/// - antigravity-{email}.json
+pub fn auth_file_provider(filename: &str) -> Option<&'static str> {
+    let filename = filename.to_lowercase();
+    if !filename.ends_with(".json") {
+        return None;
+    }
+    const PREFIXES: &[(&str, &str)] = &[
+        ("claude-", "claude"),
+        ("anthropic-", "claude"),
+        ("codex-", "openai"),
+        ("gemini-", "gemini"),
+        ("qwen-", "qwen"),
+        ("iflow-", "iflow"),
+        ("vertex-", "vertex"),
+        ("kiro-", "kiro"),
+        ("antigravity-", "antigravity"),
+        ("kimi-", "kimi"),
+    ];
+    PREFIXES
+        .iter()
+        .find(|(prefix, _)| filename.starts_with(prefix))
+        .map(|(_, provider)| *provider)
+}
+
+/// Count the credential files in `auth_dir` per provider
+pub fn scan_auth_dir(auth_dir: &std::path::Path) -> AuthStatus {
+    let mut auth = AuthStatus::default();
+    let Ok(entries) = std::fs::read_dir(auth_dir) else {
+        return auth;
+    };
+    for entry in entries.flatten() {
+        let counter = match auth_file_provider(&entry.file_name().to_string_lossy()) {
+            Some("claude") => &mut auth.claude,
+            Some("openai") => &mut auth.openai,
+            Some("gemini") => &mut auth.gemini,
+            Some("qwen") => &mut auth.qwen,
+            Some("iflow") => &mut auth.iflow,
+            Some("vertex") => &mut auth.vertex,
+            Some("kiro") => &mut auth.kiro,
+            Some("antigravity") => &mut auth.antigravity,
+            Some("kimi") => &mut auth.kimi,
+            _ => continue,
+        };
+        *counter += 1;
+    }
+    auth
+}
@@ -0,0 +1,365 @@
+//! Starting ProxyPal when the user logs in.
+//!
+//! The app registers itself as an XDG autostart entry on Linux, a
+//! LaunchAgent on macOS and a value under the Run key on Windows. The
+//! headless daemon (`proxypal run`) gets a systemd user unit on Linux and the
+//! same LaunchAgent or Run key mechanism elsewhere.
+
+#[cfg(not(target_os = "windows"))]
+use std::path::Path;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoginItem {
+    /// The desktop app, which starts the proxy itself if `auto_start` is set
+    App,
+    /// `proxypal run`, the proxy without a window
+    Daemon,
+}
+
+impl LoginItem {
+    fn args(self) -> &'static [&'static str] {
+        match self {
+            LoginItem::App => &[],
+            LoginItem::Daemon => &["run"],
+        }
+    }
+}
+
+/// The binary a login item should start. Inside an AppImage the running
+/// executable lives in a temporary mount, so the image itself is used.
+fn executable() -> Result<PathBuf, String> {
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe().map_err(|e| format!("Could not locate the ProxyPal executable: {}", e))
+}
+
+/// Write `contents` to `path` unless it already holds exactly that
+#[cfg(not(target_os = "windows"))]
+fn write_if_changed(path: &Path, contents: &str) -> Result<(), String> {
+    if std::fs::read_to_string(path).is_ok_and(|current| current == contents) {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn remove_if_present(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove {}: {}", path.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+// ============================================
+// Linux: XDG autostart and systemd user units
+// ============================================
+
+#[cfg(target_os = "linux")]
+const UNIT_NAME: &str = "proxypal.service";
+
+#[cfg(target_os = "linux")]
+fn desktop_entry_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("autostart")
+        .join("proxypal.desktop")
+}
+
+#[cfg(target_os = "linux")]
+fn systemd_unit_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("systemd")
+        .join("user")
+        .join(UNIT_NAME)
+}
+
+/// Quote an argument for the Exec key of a desktop entry
+#[cfg(target_os = "linux")]
+fn quote_exec_arg(arg: &str) -> String {
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c));
+    if !needs_quotes {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    // The whole value is unescaped once more as a string
+    quoted.replace('\\', "\\\\")
+}
+
+#[cfg(target_os = "linux")]
+fn render_desktop_entry(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=ProxyPal\n\
+         Comment=Start ProxyPal at login\n\
+         Exec={}\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        quote_exec_arg(&exe.to_string_lossy())
+    )
+}
+
+/// Quote an argument for ExecStart, where `%` starts a specifier
+#[cfg(target_os = "linux")]
+fn quote_unit_arg(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(target_os = "linux")]
+fn render_systemd_unit(exe: &Path) -> String {
+    let mut exec = quote_unit_arg(&exe.to_string_lossy());
+    for arg in LoginItem::Daemon.args() {
+        exec.push(' ');
+        exec.push_str(arg);
+    }
+    format!(
+        "[Unit]\n\
+         Description=ProxyPal (CLIProxyAPI)\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exec
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn systemctl(args: &[&str]) -> Result<(), String> {
+    let output = crate::proxy::process::command("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run systemctl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "systemctl --user {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn is_enabled(item: LoginItem) -> bool {
+    match item {
+        LoginItem::App => desktop_entry_path().is_file(),
+        LoginItem::Daemon => systemd_unit_path().is_file(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_enabled(item: LoginItem, enabled: bool) -> Result<(), String> {
+    match (item, enabled) {
+        (LoginItem::App, true) => {
+            write_if_changed(&desktop_entry_path(), &render_desktop_entry(&executable()?))
+        }
+        (LoginItem::App, false) => remove_if_present(&desktop_entry_path()),
+        (LoginItem::Daemon, true) => {
+            write_if_changed(&systemd_unit_path(), &render_systemd_unit(&executable()?))?;
+            systemctl(&["daemon-reload"])?;
+            systemctl(&["enable", UNIT_NAME])
+        }
+        (LoginItem::Daemon, false) => {
+            if systemd_unit_path().is_file() {
+                systemctl(&["disable", UNIT_NAME])?;
+            }
+            remove_if_present(&systemd_unit_path())?;
+            systemctl(&["daemon-reload"])
+        }
+    }
+}
+
+// ============================================
+// macOS: LaunchAgents
+// ============================================
+
+#[cfg(target_os = "macos")]
+fn launch_agent_label(item: LoginItem) -> &'static str {
+    match item {
+        LoginItem::App => "com.proxypal.app",
+        LoginItem::Daemon => "com.proxypal.daemon",
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn launch_agent_path(item: LoginItem) -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("Library")
+        .join("LaunchAgents")
+        .join(format!("{}.plist", launch_agent_label(item)))
+}
+
+#[cfg(target_os = "macos")]
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(target_os = "macos")]
+fn render_launch_agent(item: LoginItem, exe: &Path) -> String {
+    let mut arguments = format!(
+        "        <string>{}</string>\n",
+        xml_escape(&exe.to_string_lossy())
+    );
+    for arg in item.args() {
+        arguments.push_str(&format!("        <string>{}</string>\n", xml_escape(arg)));
+    }
+    // The daemon is brought back after crashes, the app is not
+    let keep_alive = match item {
+        LoginItem::App => "",
+        LoginItem::Daemon => "    <key>KeepAlive</key>\n    <dict>\n        <key>SuccessfulExit</key>\n        <false/>\n    </dict>\n",
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n    \
+         <key>Label</key>\n    \
+         <string>{}</string>\n    \
+         <key>ProgramArguments</key>\n    \
+         <array>\n{}    </array>\n    \
+         <key>RunAtLoad</key>\n    \
+         <true/>\n{}\
+         </dict>\n\
+         </plist>\n",
+        launch_agent_label(item),
+        arguments,
+        keep_alive
+    )
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_enabled(item: LoginItem) -> bool {
+    launch_agent_path(item).is_file()
+}
+
+/// launchd loads the agent at the next login
+#[cfg(target_os = "macos")]
+pub fn set_enabled(item: LoginItem, enabled: bool) -> Result<(), String> {
+    let path = launch_agent_path(item);
+    if enabled {
+        write_if_changed(&path, &render_launch_agent(item, &executable()?))
+    } else {
+        remove_if_present(&path)
+    }
+}
+
+// ============================================
+// Windows: HKCU Run key
+// ============================================
+
+#[cfg(target_os = "windows")]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+#[cfg(target_os = "windows")]
+fn run_value_name(item: LoginItem) -> &'static str {
+    match item {
+        LoginItem::App => "ProxyPal",
+        LoginItem::Daemon => "ProxyPal Daemon",
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn reg(args: &[&str]) -> Result<bool, String> {
+    let output = crate::proxy::process::command("reg")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run reg: {}", e))?;
+    Ok(output.status.success())
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_enabled(item: LoginItem) -> bool {
+    reg(&["query", RUN_KEY, "/v", run_value_name(item)]).unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+pub fn set_enabled(item: LoginItem, enabled: bool) -> Result<(), String> {
+    let name = run_value_name(item);
+    if !enabled {
+        if is_enabled(item) && !reg(&["delete", RUN_KEY, "/v", name, "/f"])? {
+            return Err(format!("Failed to remove {} from the Run key", name));
+        }
+        return Ok(());
+    }
+    let mut command_line = format!("\"{}\"", executable()?.display());
+    for arg in item.args() {
+        command_line.push(' ');
+        command_line.push_str(arg);
+    }
+    if !reg(&[
+        "add",
+        RUN_KEY,
+        "/v",
+        name,
+        "/t",
+        "REG_SZ",
+        "/d",
+        &command_line,
+        "/f",
+    ])? {
+        return Err(format!("Failed to add {} to the Run key", name));
+    }
+    Ok(())
+}
+
+/// Make the app's login item match `launch_at_login`, refreshing the
+/// executable path if it moved since the item was written
+pub fn sync_app_login_item(launch_at_login: bool) {
+    if !launch_at_login && !is_enabled(LoginItem::App) {
+        return;
+    }
+    if let Err(e) = set_enabled(LoginItem::App, launch_at_login) {
+        eprintln!("[ProxyPal] Failed to update launch at login: {}", e);
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_items_quote_the_executable_and_run_the_daemon() {
+        let entry = render_desktop_entry(Path::new("/opt/Proxy Pal/proxypal"));
+        assert!(entry.contains("Exec=\"/opt/Proxy Pal/proxypal\"\n"));
+        let entry = render_desktop_entry(Path::new("/usr/bin/proxypal"));
+        assert!(entry.contains("Exec=/usr/bin/proxypal\n"));
+        assert_eq!(quote_exec_arg("/a $b"), "\"/a \\\\$b\"");
+
+        let unit = render_systemd_unit(Path::new("/home/me/100%/proxypal"));
+        assert!(unit.contains("ExecStart=\"/home/me/100%%/proxypal\" run\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Budgets: how much of each spending or token limit the current period has
+//! used, and what has already been done about it.
+//!
+//! Usage comes from the request rows of the usage stores, so a budget can't
+//! see further back than `usage_retention_days`. Which thresholds were
+//! announced and which accounts a budget paused is kept in
+//! budgets-state.json, so a restart doesn't repeat alerts or forget to
+//! resume accounts.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{get_budget_state_path, DEFAULT_INSTANCE_ID};
+use crate::types::{Budget, BudgetMetric, BudgetPeriod, BudgetScope};
+use crate::utils::detect_provider_from_model;
+
+/// Percentages of a budget that are announced
+pub const THRESHOLDS: [u8; 3] = [50, 80, 100];
+
+/// An auth file a budget disabled, in the auth dir of `instance`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PausedAccount {
+    pub instance: String,
+    pub file: String,
+}
+
+impl std::fmt::Display for PausedAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.instance == DEFAULT_INSTANCE_ID {
+            write!(f, "{}", self.file)
+        } else {
+            write!(f, "{}/{}", self.instance, self.file)
+        }
+    }
+}
+
+/// What was last done for a budget, within the period it names
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetState {
+    pub period: String,
+    /// Highest threshold announced, 0 for none
+    pub notified: u8,
+    /// Accounts disabled and not yet enabled again
+    pub paused: Vec<PausedAccount>,
+}
+
+/// What a check of one budget calls for
+#[derive(Debug, Default, PartialEq)]
+pub struct BudgetCheck {
+    /// A threshold reached since the last check
+    pub notify: Option<u8>,
+    /// Whether to pause the budget's accounts now
+    pub pause: bool,
+    /// Whether to enable the paused accounts again
+    pub resume: bool,
+}
+
+/// First day of the period containing `today`, and a label naming the period
+pub fn period_of(period: BudgetPeriod, today: NaiveDate) -> (NaiveDate, String) {
+    match period {
+        BudgetPeriod::Day => (today, today.format("%Y-%m-%d").to_string()),
+        BudgetPeriod::Week => {
+            let monday =
+                today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+            (monday, today.format("%G-W%V").to_string())
+        }
+        BudgetPeriod::Month => (
+            today.with_day(1).unwrap_or(today),
+            today.format("%Y-%m").to_string(),
+        ),
+    }
+}
+
+/// Start of the current period in Unix millis (local time), and its label
+pub fn current_period(period: BudgetPeriod) -> (u64, String) {
+    let now = chrono::Local::now();
+    let (first_day, label) = period_of(period, now.date_naive());
+    let start = first_day
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+        .unwrap_or(now);
+    (start.timestamp_millis() as u64, label)
+}
+
+/// The amount a budget counts out of a usage store's cost and tokens
+pub fn used_amount(budget: &Budget, cost: f64, tokens: u64) -> f64 {
+    match budget.metric {
+        BudgetMetric::Cost => cost,
+        BudgetMetric::Tokens => tokens as f64,
+    }
+}
+
+pub fn percent_used(budget: &Budget, used: f64) -> f64 {
+    if budget.limit > 0.0 {
+        used / budget.limit * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Compare a budget's usage with what was done before, updating `state`
+pub fn check(
+    budget: &Budget,
+    state: &mut BudgetState,
+    period: &str,
+    used: f64,
+) -> BudgetCheck {
+    let mut check = BudgetCheck::default();
+    if state.period != period {
+        // A new period starts from nothing, so paused accounts come back
+        // unless it is already over the limit
+        state.period = period.to_string();
+        state.notified = 0;
+    }
+
+    let percent = percent_used(budget, used);
+    let reached = THRESHOLDS
+        .iter()
+        .copied()
+        .filter(|threshold| percent >= *threshold as f64)
+        .max()
+        .unwrap_or(0);
+    if reached > state.notified {
+        check.notify = Some(reached);
+    }
+    // Also goes down when the limit is raised, so crossing again is announced
+    state.notified = reached;
+
+    // Paused accounts are only forgotten once they are enabled again
+    if reached >= 100 && budget.pause_accounts {
+        check.pause = state.paused.is_empty();
+    } else {
+        check.resume = !state.paused.is_empty();
+    }
+    check
+}
+
+/// Whether an auth file of `provider` serves requests the budget counts
+pub fn covers_account(budget: &Budget, provider: &str) -> bool {
+    let budget_provider = match budget.scope {
+        BudgetScope::Global => return true,
+        BudgetScope::Provider => budget.target.to_lowercase(),
+        BudgetScope::Model => detect_provider_from_model(&budget.target),
+    };
+    let provider = provider.to_lowercase();
+    // OpenAI models are served by Codex accounts
+    provider == budget_provider || (budget_provider == "openai" && provider == "codex")
+}
+
+/// "$4.10 of $5.00 today" or "1.2M of 2M tokens this week"
+pub fn describe_usage(budget: &Budget, used: f64) -> String {
+    let period = match budget.period {
+        BudgetPeriod::Day => "today",
+        BudgetPeriod::Week => "this week",
+        BudgetPeriod::Month => "this month",
+    };
+    match budget.metric {
+        BudgetMetric::Cost => format!("${:.2} of ${:.2} {}", used, budget.limit, period),
+        BudgetMetric::Tokens => format!(
+            "{} of {} tokens {}",
+            format_tokens(used),
+            format_tokens(budget.limit),
+            period
+        ),
+    }
+}
+
+fn format_tokens(tokens: f64) -> String {
+    if tokens >= 1_000_000.0 {
+        format!("{:.1}M", tokens / 1_000_000.0)
+    } else if tokens >= 1_000.0 {
+        format!("{:.1}K", tokens / 1_000.0)
+    } else {
+        format!("{}", tokens as u64)
+    }
+}
+
+pub fn load_state() -> HashMap<String, BudgetState> {
+    std::fs::read_to_string(get_budget_state_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_state(states: &HashMap<String, BudgetState>) -> Result<(), String> {
+    let path = get_budget_state_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(states).map_err(|e| e.to_string())?;
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, data)
+        .and_then(|_| std::fs::rename(&temp_path, &path))
+        .map_err(|e| format!("Failed to save budget state: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(limit: f64, pause_accounts: bool) -> Budget {
+        Budget {
+            id: "b".to_string(),
+            name: "Daily".to_string(),
+            enabled: true,
+            period: BudgetPeriod::Day,
+            metric: BudgetMetric::Cost,
+            limit,
+            scope: BudgetScope::Global,
+            target: String::new(),
+            pause_accounts,
+        }
+    }
+
+    #[test]
+    fn periods_start_on_the_first_day_and_monday() {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(); // A Saturday
+        assert_eq!(
+            period_of(BudgetPeriod::Day, day),
+            (day, "2026-10-17".to_string())
+        );
+        let (monday, label) = period_of(BudgetPeriod::Week, day);
+        assert_eq!(monday, NaiveDate::from_ymd_opt(2026, 10, 12).unwrap());
+        assert_eq!(label, "2026-W42");
+        let (first, label) = period_of(BudgetPeriod::Month, day);
+        assert_eq!(first, NaiveDate::from_ymd_opt(2026, 10, 1).unwrap());
+        assert_eq!(label, "2026-10");
+    }
+
+    #[test]
+    fn thresholds_are_announced_once_and_accounts_resume_next_period() {
+        let budget = budget(10.0, true);
+        let mut state = BudgetState::default();
+
+        assert_eq!(
+            check(&budget, &mut state, "d1", 1.0),
+            BudgetCheck::default()
+        );
+        assert_eq!(check(&budget, &mut state, "d1", 5.5).notify, Some(50));
+        assert_eq!(check(&budget, &mut state, "d1", 6.0).notify, None);
+        // Jumping past two thresholds announces the higher one
+        let hit = check(&budget, &mut state, "d1", 12.0);
+        assert_eq!(hit.notify, Some(100));
+        assert!(hit.pause);
+        state.paused = vec![PausedAccount {
+            instance: "team".to_string(),
+            file: "claude-a.json".to_string(),
+        }];
+        assert_eq!(state.paused[0].to_string(), "team/claude-a.json");
+        assert!(!check(&budget, &mut state, "d1", 13.0).pause);
+
+        let next_day = check(&budget, &mut state, "d2", 0.0);
+        assert!(next_day.resume);
+        assert_eq!(next_day.notify, None);
+        assert_eq!(state.notified, 0);
+        // Still asked for while enabling the accounts keeps failing
+        assert!(check(&budget, &mut state, "d2", 0.0).resume);
+    }
+
+    #[test]
+    fn provider_and_model_budgets_cover_their_accounts() {
+        let mut budget = budget(10.0, true);
+        assert!(covers_account(&budget, "gemini"));
+        budget.scope = BudgetScope::Provider;
+        budget.target = "Claude".to_string();
+        assert!(covers_account(&budget, "claude"));
+        assert!(!covers_account(&budget, "gemini"));
+        budget.scope = BudgetScope::Model;
+        budget.target = "gpt-5-codex".to_string();
+        assert!(covers_account(&budget, "codex"));
+        assert!(covers_account(&budget, "openai"));
+        assert!(!covers_account(&budget, "claude"));
+    }
+}
@@ -0,0 +1,92 @@
+//! Line diff for showing config changes in the UI.
+//!
+//! Config files are a few hundred lines at most, so a plain LCS table is
+//! fast enough and keeps us off an extra dependency.
+
+use crate::types::{DiffLine, DiffLineKind};
+
+/// Diff `old` against `new` line by line
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let line = |kind, text: &str| DiffLine {
+        kind,
+        text: text.to_string(),
+    };
+
+    let mut result = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            result.push(line(DiffLineKind::Equal, old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            result.push(line(DiffLineKind::Removed, old[i]));
+            i += 1;
+        } else {
+            result.push(line(DiffLineKind::Added, new[j]));
+            j += 1;
+        }
+    }
+    result.extend(old[i..].iter().map(|l| line(DiffLineKind::Removed, l)));
+    result.extend(new[j..].iter().map(|l| line(DiffLineKind::Added, l)));
+    result
+}
+
+/// Whether a diff contains any added or removed lines
+pub fn has_changes(lines: &[DiffLine]) -> bool {
+    lines.iter().any(|l| l.kind != DiffLineKind::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(lines: &[DiffLine]) -> Vec<(DiffLineKind, &str)> {
+        lines.iter().map(|l| (l.kind, l.text.as_str())).collect()
+    }
+
+    #[test]
+    fn changed_line_is_reported_as_removed_then_added() {
+        let lines = diff_lines(
+            "port: 8317\nrouting:\n  strategy: round-robin\n",
+            "port: 8317\nrouting:\n  strategy: fill-first\n",
+        );
+        assert_eq!(
+            kinds(&lines),
+            vec![
+                (DiffLineKind::Equal, "port: 8317"),
+                (DiffLineKind::Equal, "routing:"),
+                (DiffLineKind::Removed, "  strategy: round-robin"),
+                (DiffLineKind::Added, "  strategy: fill-first"),
+            ]
+        );
+        assert!(has_changes(&lines));
+    }
+
+    #[test]
+    fn empty_side_is_all_added_or_removed() {
+        let added = diff_lines("", "a\nb\n");
+        assert!(added.iter().all(|l| l.kind == DiffLineKind::Added));
+        assert_eq!(added.len(), 2);
+
+        let removed = diff_lines("a\n", "");
+        assert_eq!(kinds(&removed), vec![(DiffLineKind::Removed, "a")]);
+
+        assert!(!has_changes(&diff_lines("same\n", "same\n")));
+    }
+}
@@ -0,0 +1,1133 @@
+//! Usage store: requests and their rollups in an embedded SQLite database.
+//!
+//! Each proxy instance keeps usage.db in its directory. Every request the log
+//! watcher sees becomes a row in `requests` and bumps the rollups per day,
+//! hour, model and provider in the same transaction, so recording a request
+//! costs the same however much history there is. Request rows older than the
+//! retention period are pruned; daily rollups are kept for good.
+//!
+//! Only the directory's usage recorder writes (see `usage_recorder`); the rest
+//! of the app reads through read-only connections.
+//!
+//! history.json and aggregate.json from earlier versions are imported the
+//! first time a directory's store is opened, and kept as `*.imported`.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+use crate::types::{Aggregate, BudgetScope, ModelStats, RequestHistory, RequestLog, TimeSeriesPoint};
+use crate::helpers::pricing::{self, TokenCounts};
+
+/// Requests returned as history for the UI
+pub const HISTORY_LIMIT: usize = 500;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS requests (
+    id TEXT PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    tokens_in INTEGER,
+    tokens_out INTEGER,
+    tokens_cached INTEGER,
+    cost_usd REAL,
+    request_id TEXT
+);
+CREATE INDEX IF NOT EXISTS requests_by_time ON requests (timestamp);
+
+-- Management API usage entries already matched with a request
+CREATE TABLE IF NOT EXISTS token_details (
+    key TEXT PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    request TEXT NOT NULL
+);
+
+-- Requests and tokens per local day ('2024-05-01') or hour ('2024-05-01T13')
+CREATE TABLE IF NOT EXISTS series (
+    bucket TEXT NOT NULL,
+    label TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    tokens INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (bucket, label)
+) WITHOUT ROWID;
+
+-- Counters per model or provider
+CREATE TABLE IF NOT EXISTS breakdown (
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    success_count INTEGER NOT NULL DEFAULT 0,
+    tokens INTEGER NOT NULL DEFAULT 0,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cached_tokens INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (kind, name)
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS totals (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    created_at INTEGER NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    success_count INTEGER NOT NULL DEFAULT 0,
+    failure_count INTEGER NOT NULL DEFAULT 0,
+    tokens_in INTEGER NOT NULL DEFAULT 0,
+    tokens_out INTEGER NOT NULL DEFAULT 0,
+    tokens_cached INTEGER NOT NULL DEFAULT 0,
+    cost_usd REAL NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+const DAY: &str = "day";
+const HOUR: &str = "hour";
+const MODEL: &str = "model";
+const PROVIDER: &str = "provider";
+const LEGACY_IMPORTED: &str = "legacy-imported";
+
+/// How far apart a usage entry's start and a logged request's start may be,
+/// given log lines only have whole seconds
+const MATCH_WINDOW_MS: i64 = 3_000;
+
+/// Tokens the proxy's management API reports for one request
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenDetail {
+    /// Identifies the entry across syncs
+    pub key: String,
+    /// The proxy's request ID, when the entry carries one
+    pub request_id: Option<String>,
+    /// When the request started, in milliseconds
+    pub timestamp: u64,
+    pub model: String,
+    /// Provider the entry's source points to, over the one logged
+    pub provider: Option<String>,
+    pub tokens_in: u32,
+    pub tokens_out: u32,
+    pub tokens_cached: u32,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Day and hour labels of a request, in local time like the rest of the UI
+fn series_labels(timestamp: u64) -> (String, String) {
+    let time = chrono::DateTime::from_timestamp_millis(timestamp as i64)
+        .map(|t| t.with_timezone(&chrono::Local))
+        .unwrap_or_else(chrono::Local::now);
+    (
+        time.format("%Y-%m-%d").to_string(),
+        time.format("%Y-%m-%dT%H").to_string(),
+    )
+}
+
+/// "unknown" for a missing model or provider name
+fn name_or_unknown(name: &str) -> &str {
+    if name.is_empty() {
+        "unknown"
+    } else {
+        name
+    }
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Usage store error: {}", e)
+}
+
+pub struct UsageStore {
+    conn: Connection,
+}
+
+impl UsageStore {
+    /// The writable store in a proxy instance's directory, created and filled
+    /// from the legacy JSON files on first use
+    pub(super) fn open_in(dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let conn = Connection::open(dir.join("usage.db")).map_err(db_error)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .map_err(db_error)?;
+        // WAL lets the log watcher write while commands read
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_error)?;
+        conn.pragma_update(None, "synchronous", "NORMAL")
+            .map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        // Columns added since the table was first created
+        for (column, kind) in [("cost_usd", "REAL"), ("request_id", "TEXT")] {
+            if conn.prepare(&format!("SELECT {} FROM requests", column)).is_err() {
+                conn.execute_batch(&format!("ALTER TABLE requests ADD COLUMN {} {}", column, kind))
+                    .map_err(db_error)?;
+            }
+        }
+        // A log line read twice carries the same request ID and time. The ID is
+        // 8 random hex digits, so alone it repeats over a long history; lines
+        // without one are only told apart by `id`. Stores from before keyed
+        // requests on time and path, which merged same-second requests.
+        conn.execute_batch(
+            "DROP INDEX IF EXISTS requests_unique;
+             DROP INDEX IF EXISTS requests_by_request_id;
+             CREATE UNIQUE INDEX IF NOT EXISTS requests_by_request_id
+                 ON requests (request_id, timestamp) WHERE request_id IS NOT NULL;",
+        )
+        .map_err(db_error)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO totals (id, created_at) VALUES (1, ?1)",
+            params![now_millis() as i64],
+        )
+        .map_err(db_error)?;
+
+        let mut store = Self { conn };
+        store.import_legacy(dir)?;
+        Ok(store)
+    }
+
+    /// A read-only connection to a store the recorder has already created
+    pub(super) fn open_reader(dir: &Path) -> Result<Self, String> {
+        let conn = Connection::open_with_flags(
+            dir.join("usage.db"),
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(db_error)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .map_err(db_error)?;
+        Ok(Self { conn })
+    }
+
+    /// Run `f` in one transaction, committed only if it returns Ok. Writes
+    /// inside it use savepoints, so a failed one is undone on its own.
+    pub(super) fn in_transaction<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.conn
+            .execute_batch("BEGIN IMMEDIATE")
+            .map_err(db_error)?;
+        match f(self) {
+            Ok(value) => {
+                self.conn.execute_batch("COMMIT").map_err(db_error)?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(db_error)
+    }
+
+    /// Add a request and count it in the rollups. False if it was already
+    /// recorded, e.g. by both the log watcher and the UI.
+    pub(super) fn record_request(&mut self, request: &RequestLog) -> Result<bool, String> {
+        let tx = self.conn.savepoint().map_err(db_error)?;
+        let inserted = insert_request(&tx, request)?;
+        if inserted {
+            count_request(&tx, request)?;
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(inserted)
+    }
+
+    /// Give logged requests the tokens the proxy reported for them and add
+    /// those to the rollups. Entries matched before are skipped, and ones
+    /// whose request hasn't been logged yet are left for the next sync.
+    /// Returns how many were matched.
+    pub(super) fn attach_token_details(&mut self, details: &[TokenDetail]) -> Result<usize, String> {
+        let tx = self.conn.savepoint().map_err(db_error)?;
+        let mut matched = 0;
+        for detail in details {
+            let seen = tx
+                .query_row(
+                    "SELECT 1 FROM token_details WHERE key = ?1",
+                    [&detail.key],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(db_error)?
+                .is_some();
+            if seen {
+                continue;
+            }
+            let Some(request) = find_request_for(&tx, detail)? else {
+                continue;
+            };
+            attach_tokens(&tx, &request, detail)?;
+            tx.execute(
+                "INSERT INTO token_details (key, timestamp, request) VALUES (?1, ?2, ?3)",
+                params![detail.key, detail.timestamp as i64, request.id],
+            )
+            .map_err(db_error)?;
+            matched += 1;
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(matched)
+    }
+
+    /// Price the stored requests again after a pricing change and move the
+    /// total cost by the difference. Returns how many were repriced.
+    pub(super) fn recompute_costs(&mut self) -> Result<usize, String> {
+        let pricing = pricing::current();
+        let tx = self.conn.savepoint().map_err(db_error)?;
+        let priced = {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT id, provider, model, tokens_in, tokens_out, tokens_cached, cost_usd
+                     FROM requests WHERE tokens_in IS NOT NULL OR tokens_out IS NOT NULL",
+                )
+                .map_err(db_error)?;
+            let rows = stmt
+                .query_map([], |row| {
+                    let provider: String = row.get(1)?;
+                    let model: String = row.get(2)?;
+                    let tokens = TokenCounts {
+                        input: row.get::<_, Option<u32>>(3)?.unwrap_or(0) as u64,
+                        output: row.get::<_, Option<u32>>(4)?.unwrap_or(0) as u64,
+                        cache_read: row.get::<_, Option<u32>>(5)?.unwrap_or(0) as u64,
+                        cache_write: 0,
+                    };
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<f64>>(6)?.unwrap_or(0.0),
+                        pricing.cost(&provider, &model, tokens),
+                    ))
+                })
+                .map_err(db_error)?;
+            rows.collect::<Result<Vec<_>, _>>().map_err(db_error)?
+        };
+
+        let mut difference = 0.0;
+        for (id, old_cost, new_cost) in &priced {
+            difference += new_cost - old_cost;
+            tx.execute(
+                "UPDATE requests SET cost_usd = ?1 WHERE id = ?2",
+                params![new_cost, id],
+            )
+            .map_err(db_error)?;
+        }
+        tx.execute(
+            "UPDATE totals SET cost_usd = MAX(cost_usd + ?1, 0) WHERE id = 1",
+            [difference],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+        Ok(priced.len())
+    }
+
+    /// The most recent requests, oldest first
+    pub fn recent_requests(&self, limit: usize) -> Result<Vec<RequestLog>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, timestamp, provider, model, method, path, status, duration_ms,
+                        tokens_in, tokens_out, tokens_cached, cost_usd, request_id
+                 FROM requests ORDER BY timestamp DESC LIMIT ?1",
+            )
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map([limit as i64], |row| {
+                Ok(RequestLog {
+                    id: row.get(0)?,
+                    timestamp: row.get::<_, i64>(1)? as u64,
+                    provider: row.get(2)?,
+                    model: row.get(3)?,
+                    method: row.get(4)?,
+                    path: row.get(5)?,
+                    status: row.get(6)?,
+                    duration_ms: row.get::<_, i64>(7)? as u64,
+                    tokens_in: row.get(8)?,
+                    tokens_out: row.get(9)?,
+                    tokens_cached: row.get(10)?,
+                    cost_usd: row.get(11)?,
+                    request_id: row.get(12)?,
+                })
+            })
+            .map_err(db_error)?;
+        let mut requests = rows.collect::<Result<Vec<_>, _>>().map_err(db_error)?;
+        requests.reverse();
+        Ok(requests)
+    }
+
+    /// Cost and tokens of the stored requests since `since` (Unix millis),
+    /// optionally only those through one provider or for one model
+    pub fn usage_since(
+        &self,
+        since: u64,
+        scope: BudgetScope,
+        target: &str,
+    ) -> Result<(f64, u64), String> {
+        let filter = match scope {
+            BudgetScope::Global => "",
+            BudgetScope::Provider => "AND provider = ?2 COLLATE NOCASE",
+            BudgetScope::Model => "AND model = ?2 COLLATE NOCASE",
+        };
+        let sql = format!(
+            "SELECT COALESCE(SUM(cost_usd), 0),
+                    COALESCE(SUM(COALESCE(tokens_in, 0) + COALESCE(tokens_out, 0)), 0)
+             FROM requests WHERE timestamp >= ?1 {}",
+            filter
+        );
+        let map_row = |row: &rusqlite::Row| Ok((row.get::<_, f64>(0)?, row.get::<_, i64>(1)? as u64));
+        let result = if scope == BudgetScope::Global {
+            self.conn.query_row(&sql, [since as i64], map_row)
+        } else {
+            self.conn
+                .query_row(&sql, params![since as i64, target], map_row)
+        };
+        result.map_err(db_error)
+    }
+
+    /// Recent requests with all-time totals and the last two weeks of tokens
+    pub fn request_history(&self) -> Result<RequestHistory, String> {
+        let agg = self.load_recent_aggregate(14, 168)?;
+        Ok(RequestHistory {
+            requests: self.recent_requests(HISTORY_LIMIT)?,
+            total_tokens_in: agg.total_tokens_in,
+            total_tokens_out: agg.total_tokens_out,
+            total_tokens_cached: agg.total_tokens_cached,
+            total_cost_usd: agg.total_cost_usd,
+            tokens_by_day: agg.tokens_by_day,
+            tokens_by_hour: agg.tokens_by_hour,
+            total_request_count: agg.total_requests,
+            total_success_count: agg.total_success_count,
+        })
+    }
+
+    /// Forget individual requests; totals and rollups stay
+    pub(super) fn clear_requests(&self) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM requests", [])
+            .map(|_| ())
+            .map_err(db_error)
+    }
+
+    /// Totals, every series point and all breakdowns
+    pub fn load_aggregate(&self) -> Result<Aggregate, String> {
+        self.load_recent_aggregate(-1, -1)
+    }
+
+    /// Totals and breakdowns with only the latest `days` and `hours` of the
+    /// series (-1 for all of them)
+    pub fn load_recent_aggregate(&self, days: i64, hours: i64) -> Result<Aggregate, String> {
+        let mut agg = self
+            .conn
+            .query_row(
+                "SELECT created_at, requests, success_count, failure_count,
+                        tokens_in, tokens_out, tokens_cached, cost_usd
+                 FROM totals WHERE id = 1",
+                [],
+                |row| {
+                    Ok(Aggregate {
+                        created_at: row.get::<_, i64>(0)? as u64,
+                        total_requests: row.get::<_, i64>(1)? as u64,
+                        total_success_count: row.get::<_, i64>(2)? as u64,
+                        total_failure_count: row.get::<_, i64>(3)? as u64,
+                        total_tokens_in: row.get::<_, i64>(4)? as u64,
+                        total_tokens_out: row.get::<_, i64>(5)? as u64,
+                        total_tokens_cached: row.get::<_, i64>(6)? as u64,
+                        total_cost_usd: row.get(7)?,
+                        ..Aggregate::default()
+                    })
+                },
+            )
+            .map_err(db_error)?;
+
+        (agg.requests_by_day, agg.tokens_by_day) = self.series(DAY, days)?;
+        (agg.requests_by_hour, agg.tokens_by_hour) = self.series(HOUR, hours)?;
+        agg.model_stats = self.breakdown(MODEL)?;
+        agg.provider_stats = self.breakdown(PROVIDER)?;
+        Ok(agg)
+    }
+
+    /// Requests and tokens of the latest `limit` points, oldest first
+    fn series(
+        &self,
+        bucket: &str,
+        limit: i64,
+    ) -> Result<(Vec<TimeSeriesPoint>, Vec<TimeSeriesPoint>), String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT label, requests, tokens FROM series
+                 WHERE bucket = ?1 ORDER BY label DESC LIMIT ?2",
+            )
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map(params![bucket, limit], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                ))
+            })
+            .map_err(db_error)?;
+
+        let mut requests = Vec::new();
+        let mut tokens = Vec::new();
+        for row in rows {
+            let (label, request_count, token_count) = row.map_err(db_error)?;
+            requests.push(TimeSeriesPoint {
+                label: label.clone(),
+                value: request_count,
+            });
+            tokens.push(TimeSeriesPoint {
+                label,
+                value: token_count,
+            });
+        }
+        requests.reverse();
+        tokens.reverse();
+        Ok((requests, tokens))
+    }
+
+    fn breakdown(&self, kind: &str) -> Result<std::collections::HashMap<String, ModelStats>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT name, requests, success_count, tokens, input_tokens, output_tokens,
+                        cached_tokens
+                 FROM breakdown WHERE kind = ?1",
+            )
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map([kind], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    ModelStats {
+                        requests: row.get::<_, i64>(1)? as u64,
+                        success_count: row.get::<_, i64>(2)? as u64,
+                        tokens: row.get::<_, i64>(3)? as u64,
+                        input_tokens: row.get::<_, i64>(4)? as u64,
+                        output_tokens: row.get::<_, i64>(5)? as u64,
+                        cached_tokens: row.get::<_, i64>(6)? as u64,
+                    },
+                ))
+            })
+            .map_err(db_error)?;
+        rows.collect::<Result<_, _>>().map_err(db_error)
+    }
+
+    /// Overwrite the totals and every series point and breakdown present in
+    /// `agg`. Points it lacks are left alone.
+    pub(super) fn save_aggregate(&mut self, agg: &Aggregate) -> Result<(), String> {
+        let tx = self.conn.savepoint().map_err(db_error)?;
+        write_aggregate(&tx, agg)?;
+        tx.commit().map_err(db_error)
+    }
+
+    /// Drop requests and hourly points older than `retention_days`; 0 keeps
+    /// everything. Returns how many requests went.
+    pub(super) fn prune(&self, retention_days: u32) -> Result<usize, String> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now_millis().saturating_sub(retention_days as u64 * 24 * 60 * 60 * 1000);
+        let removed = self
+            .conn
+            .execute("DELETE FROM requests WHERE timestamp < ?1", [cutoff as i64])
+            .map_err(db_error)?;
+        self.conn
+            .execute(
+                "DELETE FROM token_details WHERE timestamp < ?1",
+                [cutoff as i64],
+            )
+            .map_err(db_error)?;
+        let (_, cutoff_hour) = series_labels(cutoff);
+        self.conn
+            .execute(
+                "DELETE FROM series WHERE bucket = ?1 AND label < ?2",
+                params![HOUR, cutoff_hour],
+            )
+            .map_err(db_error)?;
+        Ok(removed)
+    }
+
+    /// Move history.json and aggregate.json into the store, once
+    fn import_legacy(&mut self, dir: &Path) -> Result<(), String> {
+        if self.meta(LEGACY_IMPORTED)?.is_some() {
+            return Ok(());
+        }
+        let history_path = dir.join("history.json");
+        let aggregate_path = dir.join("aggregate.json");
+        let history: Option<RequestHistory> = read_json(&history_path);
+        let aggregate: Option<Aggregate> = read_json(&aggregate_path);
+
+        let tx = self.conn.transaction().map_err(db_error)?;
+        if let Some(agg) = &aggregate {
+            write_aggregate(&tx, agg)?;
+        }
+        if let Some(history) = &history {
+            for request in &history.requests {
+                // The aggregate already counts them; without one the
+                // requests are all there is to go on
+                if insert_request(&tx, request)? && aggregate.is_none() {
+                    count_request(&tx, request)?;
+                }
+            }
+            tx.execute(
+                "UPDATE totals SET tokens_in = MAX(tokens_in, ?1), tokens_out = MAX(tokens_out, ?2),
+                     tokens_cached = MAX(tokens_cached, ?3), cost_usd = MAX(cost_usd, ?4)
+                 WHERE id = 1",
+                params![
+                    history.total_tokens_in as i64,
+                    history.total_tokens_out as i64,
+                    history.total_tokens_cached as i64,
+                    history.total_cost_usd
+                ],
+            )
+            .map_err(db_error)?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![LEGACY_IMPORTED, now_millis().to_string()],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+
+        for path in [history_path, aggregate_path] {
+            if path.exists() {
+                let backup = PathBuf::from(format!("{}.imported", path.display()));
+                if let Err(e) = std::fs::rename(&path, &backup) {
+                    eprintln!("[ProxyPal] Failed to move {} aside: {}", path.display(), e);
+                }
+            }
+        }
+        if history.is_some() || aggregate.is_some() {
+            println!("[ProxyPal] Imported usage history into {}", dir.join("usage.db").display());
+        }
+        Ok(())
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let data = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn insert_request(conn: &Connection, request: &RequestLog) -> Result<bool, String> {
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO requests
+                 (id, timestamp, provider, model, method, path, status, duration_ms,
+                  tokens_in, tokens_out, tokens_cached, cost_usd, request_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                request.id,
+                request.timestamp as i64,
+                request.provider,
+                request.model,
+                request.method,
+                request.path,
+                request.status,
+                request.duration_ms as i64,
+                request.tokens_in,
+                request.tokens_out,
+                request.tokens_cached,
+                request_cost(request),
+                request.request_id,
+            ],
+        )
+        .map_err(db_error)?;
+    Ok(inserted > 0)
+}
+
+/// The cost of a request whose tokens are known
+fn request_cost(request: &RequestLog) -> Option<f64> {
+    if request.tokens_in.is_none() && request.tokens_out.is_none() {
+        return request.cost_usd;
+    }
+    Some(pricing::cost(
+        &request.provider,
+        &request.model,
+        TokenCounts {
+            input: request.tokens_in.unwrap_or(0) as u64,
+            output: request.tokens_out.unwrap_or(0) as u64,
+            cache_read: request.tokens_cached.unwrap_or(0) as u64,
+            cache_write: 0,
+        },
+    ))
+}
+
+/// A logged request still waiting for its tokens
+struct PendingRequest {
+    id: String,
+    timestamp: u64,
+    model: String,
+    provider: String,
+}
+
+/// The logged request a usage entry belongs to: the one with its request ID
+/// if it has one, otherwise the closest by start time with the same model.
+/// Log lines are written when a request ends, so its start is the line's
+/// time minus the duration.
+fn find_request_for(conn: &Connection, detail: &TokenDetail) -> Result<Option<PendingRequest>, String> {
+    let map_row = |row: &rusqlite::Row| {
+        Ok(PendingRequest {
+            id: row.get(0)?,
+            timestamp: row.get::<_, i64>(1)? as u64,
+            model: row.get(2)?,
+            provider: row.get(3)?,
+        })
+    };
+    if let Some(request_id) = &detail.request_id {
+        let found = conn
+            .query_row(
+                "SELECT id, timestamp, model, provider FROM requests
+                 WHERE request_id = ?1 AND tokens_in IS NULL
+                 ORDER BY ABS(timestamp - duration_ms - ?2) LIMIT 1",
+                params![request_id, detail.timestamp as i64],
+                map_row,
+            )
+            .optional()
+            .map_err(db_error)?;
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    // A model the log watcher couldn't tell is still a candidate
+    conn.query_row(
+        "SELECT id, timestamp, model, provider FROM requests
+         WHERE tokens_in IS NULL AND (model = ?1 OR model = 'unknown')
+             AND ABS(timestamp - duration_ms - ?2) <= ?3
+         ORDER BY model != ?1, ABS(timestamp - duration_ms - ?2) LIMIT 1",
+        params![detail.model, detail.timestamp as i64, MATCH_WINDOW_MS],
+        map_row,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+/// Count a request under the provider its usage entry names instead of the
+/// one guessed from the log line
+fn move_to_provider(conn: &Connection, request: &PendingRequest, provider: &str) -> Result<(), String> {
+    let success: i64 = conn
+        .query_row(
+            "SELECT status < 400 FROM requests WHERE id = ?1",
+            [&request.id],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    conn.execute(
+        "UPDATE requests SET provider = ?1 WHERE id = ?2",
+        params![provider, request.id],
+    )
+    .map_err(db_error)?;
+    conn.execute(
+        "UPDATE breakdown SET requests = MAX(requests - 1, 0),
+             success_count = MAX(success_count - ?1, 0)
+         WHERE kind = ?2 AND name = ?3",
+        params![success, PROVIDER, name_or_unknown(&request.provider)],
+    )
+    .map_err(db_error)?;
+    conn.execute(
+        "INSERT INTO breakdown (kind, name, requests, success_count) VALUES (?1, ?2, 1, ?3)
+         ON CONFLICT (kind, name) DO UPDATE
+         SET requests = requests + 1, success_count = success_count + excluded.success_count",
+        params![PROVIDER, provider, success],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Store a usage entry's tokens on its request and add them to the totals
+/// and rollups the request was counted in without any
+fn attach_tokens(conn: &Connection, request: &PendingRequest, detail: &TokenDetail) -> Result<(), String> {
+    let provider = match &detail.provider {
+        Some(provider) if *provider != request.provider => {
+            move_to_provider(conn, request, provider)?;
+            provider
+        }
+        _ => &request.provider,
+    };
+    let model = if request.model == "unknown" {
+        &detail.model
+    } else {
+        &request.model
+    };
+    let cost = pricing::cost(
+        provider,
+        model,
+        TokenCounts {
+            input: detail.tokens_in as u64,
+            output: detail.tokens_out as u64,
+            cache_read: detail.tokens_cached as u64,
+            cache_write: 0,
+        },
+    );
+    let tokens = detail.tokens_in as i64 + detail.tokens_out as i64;
+
+    conn.execute(
+        "UPDATE requests SET tokens_in = ?1, tokens_out = ?2, tokens_cached = ?3, cost_usd = ?4
+         WHERE id = ?5",
+        params![
+            detail.tokens_in,
+            detail.tokens_out,
+            detail.tokens_cached,
+            cost,
+            request.id
+        ],
+    )
+    .map_err(db_error)?;
+    conn.execute(
+        "UPDATE totals SET tokens_in = tokens_in + ?1, tokens_out = tokens_out + ?2,
+             tokens_cached = tokens_cached + ?3, cost_usd = cost_usd + ?4
+         WHERE id = 1",
+        params![detail.tokens_in, detail.tokens_out, detail.tokens_cached, cost],
+    )
+    .map_err(db_error)?;
+
+    let (day, hour) = series_labels(request.timestamp);
+    for (bucket, label) in [(DAY, day), (HOUR, hour)] {
+        conn.execute(
+            "UPDATE series SET tokens = tokens + ?1 WHERE bucket = ?2 AND label = ?3",
+            params![tokens, bucket, label],
+        )
+        .map_err(db_error)?;
+    }
+
+    for (kind, name) in [(MODEL, &request.model), (PROVIDER, provider)] {
+        conn.execute(
+            "UPDATE breakdown SET tokens = tokens + ?1, input_tokens = input_tokens + ?2,
+                 output_tokens = output_tokens + ?3, cached_tokens = cached_tokens + ?4
+             WHERE kind = ?5 AND name = ?6",
+            params![
+                tokens,
+                detail.tokens_in,
+                detail.tokens_out,
+                detail.tokens_cached,
+                kind,
+                name_or_unknown(name)
+            ],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Add one request to the totals and rollups
+fn count_request(conn: &Connection, request: &RequestLog) -> Result<(), String> {
+    let tokens_in = request.tokens_in.unwrap_or(0);
+    let tokens_out = request.tokens_out.unwrap_or(0);
+    let tokens_cached = request.tokens_cached.unwrap_or(0) as i64;
+    let tokens = tokens_in as i64 + tokens_out as i64;
+    let success = (request.status < 400) as i64;
+    let cost = request_cost(request).unwrap_or(0.0);
+
+    conn.execute(
+        "UPDATE totals SET requests = requests + 1, success_count = success_count + ?1,
+             failure_count = failure_count + 1 - ?1, tokens_in = tokens_in + ?2,
+             tokens_out = tokens_out + ?3, tokens_cached = tokens_cached + ?4,
+             cost_usd = cost_usd + ?5
+         WHERE id = 1",
+        params![success, tokens_in, tokens_out, tokens_cached, cost],
+    )
+    .map_err(db_error)?;
+
+    let (day, hour) = series_labels(request.timestamp);
+    for (bucket, label) in [(DAY, day), (HOUR, hour)] {
+        conn.execute(
+            "INSERT INTO series (bucket, label, requests, tokens) VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT (bucket, label) DO UPDATE
+             SET requests = requests + 1, tokens = tokens + excluded.tokens",
+            params![bucket, label, tokens],
+        )
+        .map_err(db_error)?;
+    }
+
+    for (kind, name) in [(MODEL, &request.model), (PROVIDER, &request.provider)] {
+        conn.execute(
+            "INSERT INTO breakdown
+                 (kind, name, requests, success_count, tokens, input_tokens, output_tokens,
+                  cached_tokens)
+             VALUES (?1, ?2, 1, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (kind, name) DO UPDATE
+             SET requests = requests + 1, success_count = success_count + excluded.success_count,
+                 tokens = tokens + excluded.tokens,
+                 input_tokens = input_tokens + excluded.input_tokens,
+                 output_tokens = output_tokens + excluded.output_tokens,
+                 cached_tokens = cached_tokens + excluded.cached_tokens",
+            params![
+                kind,
+                name_or_unknown(name),
+                success,
+                tokens,
+                tokens_in,
+                tokens_out,
+                tokens_cached
+            ],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+fn write_aggregate(conn: &Connection, agg: &Aggregate) -> Result<(), String> {
+    conn.execute(
+        "UPDATE totals SET created_at = ?1, requests = ?2, success_count = ?3,
+             failure_count = ?4, tokens_in = ?5, tokens_out = ?6, tokens_cached = ?7,
+             cost_usd = ?8
+         WHERE id = 1",
+        params![
+            agg.created_at as i64,
+            agg.total_requests as i64,
+            agg.total_success_count as i64,
+            agg.total_failure_count as i64,
+            agg.total_tokens_in as i64,
+            agg.total_tokens_out as i64,
+            agg.total_tokens_cached as i64,
+            agg.total_cost_usd
+        ],
+    )
+    .map_err(db_error)?;
+
+    let series = [
+        (DAY, "requests", &agg.requests_by_day),
+        (DAY, "tokens", &agg.tokens_by_day),
+        (HOUR, "requests", &agg.requests_by_hour),
+        (HOUR, "tokens", &agg.tokens_by_hour),
+    ];
+    for (bucket, column, points) in series {
+        let sql = format!(
+            "INSERT INTO series (bucket, label, {0}) VALUES (?1, ?2, ?3)
+             ON CONFLICT (bucket, label) DO UPDATE SET {0} = excluded.{0}",
+            column
+        );
+        let mut stmt = conn.prepare_cached(&sql).map_err(db_error)?;
+        for point in points {
+            stmt.execute(params![bucket, point.label, point.value as i64])
+                .map_err(db_error)?;
+        }
+    }
+
+    for (kind, stats) in [(MODEL, &agg.model_stats), (PROVIDER, &agg.provider_stats)] {
+        let mut stmt = conn
+            .prepare_cached(
+                "INSERT OR REPLACE INTO breakdown
+                     (kind, name, requests, success_count, tokens, input_tokens,
+                      output_tokens, cached_tokens)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )
+            .map_err(db_error)?;
+        for (name, s) in stats {
+            stmt.execute(params![
+                kind,
+                name_or_unknown(name),
+                s.requests as i64,
+                s.success_count as i64,
+                s.tokens as i64,
+                s.input_tokens as i64,
+                s.output_tokens as i64,
+                s.cached_tokens as i64
+            ])
+            .map_err(db_error)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{request, test_dir};
+    use std::fs;
+
+    fn detail(key: &str, request_id: Option<&str>, timestamp: u64) -> TokenDetail {
+        TokenDetail {
+            key: key.to_string(),
+            request_id: request_id.map(|id| id.to_string()),
+            timestamp,
+            model: "claude-sonnet-4".to_string(),
+            provider: None,
+            tokens_in: 1_000,
+            tokens_out: 500,
+            tokens_cached: 200,
+        }
+    }
+
+    #[test]
+    fn recording_a_request_updates_the_rollups_once() {
+        let dir = test_dir("usage-store");
+        let mut store = UsageStore::open_in(&dir).unwrap();
+        let now = now_millis();
+
+        assert!(store.record_request(&request("a", now, 200)).unwrap());
+        assert!(store.record_request(&request("b", now + 1, 500)).unwrap());
+        assert!(!store.record_request(&request("a", now, 200)).unwrap());
+
+        let agg = store.load_aggregate().unwrap();
+        assert_eq!(agg.total_requests, 2);
+        assert_eq!(agg.total_success_count, 1);
+        assert_eq!(agg.total_failure_count, 1);
+        assert_eq!(agg.total_tokens_in, 200);
+        let (today, _) = series_labels(now);
+        assert_eq!(agg.requests_by_day.last().unwrap().label, today);
+        assert_eq!(agg.tokens_by_day.last().unwrap().value, 240);
+        assert_eq!(agg.model_stats["claude-sonnet-4"].requests, 2);
+        assert_eq!(agg.provider_stats["claude"].success_count, 1);
+
+        let history = store.request_history().unwrap();
+        assert_eq!(history.requests.len(), 2);
+        assert_eq!(history.requests[0].id, "a");
+
+        // Clearing the list keeps the counts
+        store.clear_requests().unwrap();
+        assert!(store.recent_requests(10).unwrap().is_empty());
+        assert_eq!(store.load_aggregate().unwrap().total_requests, 2);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn requests_in_the_same_second_to_the_same_path_are_all_kept() {
+        let dir = test_dir("usage-same-second");
+        let mut store = UsageStore::open_in(&dir).unwrap();
+        // Log lines only have whole seconds
+        let second = now_millis() / 1_000 * 1_000;
+        let same_path = |id: &str, request_id: Option<&str>| RequestLog {
+            path: "/v1/messages".to_string(),
+            request_id: request_id.map(|id| id.to_string()),
+            ..request(id, second, 200)
+        };
+
+        assert!(store.record_request(&same_path("a", None)).unwrap());
+        assert!(store.record_request(&same_path("b", None)).unwrap());
+        assert!(store.record_request(&same_path("c", Some("aaaa1111"))).unwrap());
+        assert!(store.record_request(&same_path("d", Some("bbbb2222"))).unwrap());
+        // The same log line read again gets a new ID but keeps its request ID
+        assert!(!store.record_request(&same_path("e", Some("aaaa1111"))).unwrap());
+
+        assert_eq!(store.load_aggregate().unwrap().total_requests, 4);
+        assert_eq!(store.recent_requests(10).unwrap().len(), 4);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn token_details_are_matched_once_even_when_they_arrive_early() {
+        let dir = test_dir("usage-tokens");
+        let mut store = UsageStore::open_in(&dir).unwrap();
+        let start = now_millis() - 60_000;
+        // Logged when they end: 2s and 4s after starting, to the second
+        let logged = |id: &str, request_id: Option<&str>, ends: u64, duration_ms: u64| RequestLog {
+            timestamp: ends / 1000 * 1000,
+            duration_ms,
+            tokens_in: None,
+            tokens_out: None,
+            request_id: request_id.map(|id| id.to_string()),
+            ..request(id, 0, 200)
+        };
+        store
+            .record_request(&logged("a", Some("f803bb77"), start + 2_000, 2_000))
+            .unwrap();
+
+        let details = [
+            detail("1", Some("f803bb77"), start),
+            detail("2", None, start + 30_000),
+        ];
+        assert_eq!(store.attach_token_details(&details).unwrap(), 1);
+        let agg = store.load_aggregate().unwrap();
+        assert_eq!(agg.total_tokens_in, 1_000);
+        assert_eq!(agg.total_tokens_cached, 200);
+        assert_eq!(agg.model_stats["claude-sonnet-4"].output_tokens, 500);
+        assert_eq!(agg.tokens_by_day.last().unwrap().value, 1_500);
+
+        // The second request's log line comes in after the usage entry
+        store
+            .record_request(&logged("b", None, start + 34_000, 4_000))
+            .unwrap();
+        assert_eq!(store.attach_token_details(&details).unwrap(), 1);
+        assert_eq!(store.attach_token_details(&details).unwrap(), 0);
+
+        let requests = store.recent_requests(10).unwrap();
+        assert!(requests.iter().all(|r| r.tokens_in == Some(1_000)));
+        assert!(requests.iter().all(|r| r.cost_usd.is_some_and(|cost| cost > 0.0)));
+        let agg = store.load_aggregate().unwrap();
+        assert_eq!(agg.total_requests, 2);
+        assert_eq!(agg.total_tokens_in, 2_000);
+
+        // Repricing with unchanged prices leaves the total where it was
+        assert_eq!(store.recompute_costs().unwrap(), 2);
+        let repriced = store.load_aggregate().unwrap().total_cost_usd;
+        assert!((repriced - agg.total_cost_usd).abs() < 1e-9);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn retention_drops_old_requests_but_keeps_daily_counts() {
+        let dir = test_dir("usage-retention");
+        let mut store = UsageStore::open_in(&dir).unwrap();
+        let old = now_millis() - 40 * 24 * 60 * 60 * 1000;
+        store.record_request(&request("old", old, 200)).unwrap();
+        store.record_request(&request("new", now_millis(), 200)).unwrap();
+
+        assert_eq!(store.prune(0).unwrap(), 0);
+        assert_eq!(store.prune(30).unwrap(), 1);
+        let remaining = store.recent_requests(10).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, "new");
+
+        let agg = store.load_aggregate().unwrap();
+        assert_eq!(agg.total_requests, 2);
+        assert_eq!(agg.requests_by_day.len(), 2);
+        assert_eq!(agg.requests_by_hour.len(), 1);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn legacy_json_files_are_imported_once() {
+        let dir = test_dir("usage-import");
+        let history = RequestHistory {
+            requests: vec![request("a", 1_700_000_000_000, 200)],
+            total_tokens_in: 5_000,
+            ..RequestHistory::default()
+        };
+        let agg = Aggregate {
+            total_requests: 42,
+            total_success_count: 40,
+            requests_by_day: vec![TimeSeriesPoint {
+                label: "2023-11-14".to_string(),
+                value: 42,
+            }],
+            ..Aggregate::default()
+        };
+        fs::write(dir.join("history.json"), serde_json::to_string(&history).unwrap()).unwrap();
+        fs::write(dir.join("aggregate.json"), serde_json::to_string(&agg).unwrap()).unwrap();
+
+        let store = UsageStore::open_in(&dir).unwrap();
+        let imported = store.load_aggregate().unwrap();
+        // The aggregate already counts the history's requests
+        assert_eq!(imported.total_requests, 42);
+        assert_eq!(imported.total_tokens_in, 5_000);
+        assert_eq!(imported.requests_by_day[0].value, 42);
+        assert_eq!(store.recent_requests(10).unwrap().len(), 1);
+        assert!(!dir.join("history.json").exists());
+        assert!(dir.join("aggregate.json.imported").exists());
+        drop(store);
+
+        // A history.json showing up later is not imported again
+        fs::write(dir.join("history.json"), serde_json::to_string(&history).unwrap()).unwrap();
+        let store = UsageStore::open_in(&dir).unwrap();
+        assert_eq!(store.load_aggregate().unwrap().total_requests, 42);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
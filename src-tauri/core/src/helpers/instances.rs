@@ -0,0 +1,153 @@
+//! Storage for proxy instances besides the default one.
+//!
+//! instances.json lists them; each has a directory `instances/<id>/` with its
+//! own config.json, auth/ dir for CLIProxyAPI credentials, and the
+//! proxy-config.yaml, logs, PID file and usage files of a running sidecar.
+//! The default instance keeps using config.json and `~/.cli-proxy-api`.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    get_instances_path, load_config_from_path, save_config_to_path, AppConfig, DEFAULT_INSTANCE_ID,
+};
+use crate::proxy::config_yaml::DEFAULT_AUTH_DIR;
+use crate::types::ProxyInstanceDefinition;
+
+const MAX_INSTANCE_ID_LEN: usize = 32;
+
+/// A directory-safe id from a display name, unique among `existing`
+pub fn instance_id_for(name: &str, existing: &[String]) -> Result<String, String> {
+    let mut slug = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug
+        .trim_matches('-')
+        .chars()
+        .take(MAX_INSTANCE_ID_LEN)
+        .collect();
+    if slug.is_empty() {
+        return Err("Instance name needs at least one letter or digit".to_string());
+    }
+
+    let taken = |id: &str| id == DEFAULT_INSTANCE_ID || existing.iter().any(|e| e == id);
+    let mut id = slug.clone();
+    let mut n = 2;
+    while taken(&id) {
+        id = format!("{}-{}", slug, n);
+        n += 1;
+    }
+    Ok(id)
+}
+
+pub fn load_definitions_from(path: &Path) -> Vec<ProxyInstanceDefinition> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_definitions_to(
+    path: &Path,
+    definitions: &[ProxyInstanceDefinition],
+) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(definitions).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| format!("Failed to save proxy instances: {}", e))
+}
+
+pub fn load_definitions() -> Vec<ProxyInstanceDefinition> {
+    load_definitions_from(&get_instances_path())
+}
+
+pub fn save_definitions(definitions: &[ProxyInstanceDefinition]) -> Result<(), String> {
+    save_definitions_to(&get_instances_path(), definitions)
+}
+
+fn instance_config_path(dir: &Path) -> PathBuf {
+    dir.join("config.json")
+}
+
+/// The `auth-dir` CLIProxyAPI gets for an instance
+pub fn instance_auth_dir(id: &str, dir: &Path) -> String {
+    if id == DEFAULT_INSTANCE_ID {
+        return DEFAULT_AUTH_DIR.to_string();
+    }
+    dir.join("auth").to_string_lossy().to_string()
+}
+
+pub fn load_instance_config(dir: &Path) -> Result<AppConfig, String> {
+    let path = instance_config_path(dir);
+    if !path.exists() {
+        return Err(format!("{} is missing", path.display()));
+    }
+    load_config_from_path(&path).map_err(|e| e.message)
+}
+
+pub fn save_instance_config(dir: &Path, config: &AppConfig) -> Result<(), String> {
+    save_config_to_path(&instance_config_path(dir), config)
+}
+
+/// Lay out a new instance directory with `config` and an empty auth dir
+pub fn create_instance_dir(dir: &Path, config: &AppConfig) -> Result<(), String> {
+    std::fs::create_dir_all(dir.join("auth"))
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    save_instance_config(dir, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::fs;
+
+    #[test]
+    fn instance_ids_are_slugs_that_avoid_taken_ones() {
+        let existing = vec!["team".to_string(), "team-2".to_string()];
+        assert_eq!(
+            instance_id_for("Personal OAuth", &[]).unwrap(),
+            "personal-oauth"
+        );
+        assert_eq!(instance_id_for("Team", &existing).unwrap(), "team-3");
+        assert_eq!(instance_id_for("Default", &[]).unwrap(), "default-2");
+        assert_eq!(instance_id_for("../x", &[]).unwrap(), "x");
+        assert!(instance_id_for(" -- ", &[]).is_err());
+    }
+
+    #[test]
+    fn instance_dirs_hold_their_own_config_and_auth_dir() {
+        let dir = test_dir("instances");
+        let path = dir.join("instances.json");
+        assert!(load_definitions_from(&path).is_empty());
+
+        let definition = ProxyInstanceDefinition {
+            id: "team".to_string(),
+            name: "Team".to_string(),
+            created_at: 1,
+        };
+        save_definitions_to(&path, std::slice::from_ref(&definition)).unwrap();
+        assert_eq!(load_definitions_from(&path), vec![definition]);
+
+        let instance_dir = dir.join("instances").join("team");
+        let config = AppConfig {
+            port: 8318,
+            ..AppConfig::default()
+        };
+        create_instance_dir(&instance_dir, &config).unwrap();
+        assert_eq!(load_instance_config(&instance_dir).unwrap().port, 8318);
+        assert!(instance_dir.join("auth").is_dir());
+        assert_eq!(
+            instance_auth_dir("team", &instance_dir),
+            instance_dir.join("auth").to_string_lossy()
+        );
+        assert_eq!(
+            instance_auth_dir(DEFAULT_INSTANCE_ID, &dir),
+            "~/.cli-proxy-api"
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -2,15 +2,11 @@
 
 use regex::Regex;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
-use tauri::Emitter;
-
-use crate::helpers::history::{
-    load_aggregate, load_request_history, save_aggregate, save_request_history, update_model_stats,
-    update_provider_stats, update_timeseries,
-};
+use crate::helpers::usage_recorder;
 use crate::types::RequestLog;
 use crate::utils::{
     detect_provider_from_model, detect_provider_from_path, extract_model_from_path,
@@ -46,7 +42,7 @@ fn extract_timestamp_from_line(line: &str) -> Option<u64> {
             .map(|dt| {
                 dt.and_local_timezone(chrono::Local)
                     .earliest()
-                    .unwrap_or_else(|| chrono::Local::now())
+                    .unwrap_or_else(chrono::Local::now)
                     .timestamp_millis() as u64
             });
     }
@@ -185,6 +181,9 @@ fn parse_gin_log_line(
             tokens_in: None,
             tokens_out: None,
             tokens_cached: None,
+            cost_usd: None,
+            // Matched with the proxy's token counts on the next usage sync
+            request_id: (request_id != "--------").then_some(request_id),
         });
     }
 
@@ -205,7 +204,7 @@ fn parse_gin_log_line(
         .map(|dt| {
             dt.and_local_timezone(chrono::Local)
                 .earliest()
-                .unwrap_or_else(|| chrono::Local::now())
+                .unwrap_or_else(chrono::Local::now)
                 .timestamp_millis() as u64
         })
         .unwrap_or_else(|| {
@@ -249,21 +248,39 @@ fn parse_gin_log_line(
         tokens_in: None,     // Not available from GIN logs
         tokens_out: None,    // Not available from GIN logs
         tokens_cached: None, // Not available from GIN logs
+        cost_usd: None,
+        request_id: None,
     })
 }
 
-// Start watching the proxy log file for new entries
-pub(crate) fn start_log_watcher(
-    app_handle: tauri::AppHandle,
-    log_path: std::path::PathBuf,
+// Start watching the log file of the proxy instance in `dir` for new
+// entries. Requests go to the usage recorder for `dir` after the store is
+// pruned to `retention_days`, and to `on_request`, e.g. to show them live.
+// Runs until `running` is cleared; `request_counter` numbers the requests.
+pub fn start_log_watcher(
+    dir: PathBuf,
     running: Arc<AtomicBool>,
     request_counter: Arc<AtomicU64>,
+    retention_days: u32,
+    on_request: impl Fn(&RequestLog) + Send + 'static,
 ) {
+    let log_path = dir.join("logs").join("main.log");
     std::thread::spawn(move || {
         // Model cache to associate request IDs with model names from DEBUG lines
         let model_cache: std::sync::RwLock<std::collections::HashMap<String, String>> =
             std::sync::RwLock::new(std::collections::HashMap::new());
 
+        let recorder = match usage_recorder::recorder(&dir) {
+            Ok(recorder) => recorder,
+            Err(e) => {
+                eprintln!("[LogWatcher] {}", e);
+                return;
+            }
+        };
+        if let Err(e) = recorder.prune(retention_days) {
+            eprintln!("[LogWatcher] {}", e);
+        }
+
         // Wait for log file to exist
         let mut attempts = 0;
         while !log_path.exists() && attempts < 30 {
@@ -329,79 +346,11 @@ pub(crate) fn start_log_watcher(
             while reader.read_line(&mut line).unwrap_or(0) > 0 {
                 if let Some(request_log) = parse_gin_log_line(&line, &request_counter, &model_cache)
                 {
-                    // Emit to frontend for live display
-                    let _ = app_handle.emit("request-log", request_log.clone());
-
-                    // Persist to history (without token data for now)
-                    let mut history = load_request_history();
-
-                    // Check for duplicate by timestamp and path
-                    let is_duplicate = history.requests.iter().any(|r| {
-                        r.timestamp == request_log.timestamp && r.path == request_log.path
-                    });
-
-                    if !is_duplicate {
-                        // Load aggregate for cumulative stats
-                        let mut agg = load_aggregate();
-
-                        // Update aggregate counters
-                        agg.total_requests += 1;
-                        if request_log.status < 400 {
-                            agg.total_success_count += 1;
-                        } else {
-                            agg.total_failure_count += 1;
-                        }
-                        agg.total_tokens_in += request_log.tokens_in.unwrap_or(0) as u64;
-                        agg.total_tokens_out += request_log.tokens_out.unwrap_or(0) as u64;
-                        agg.total_tokens_cached += request_log.tokens_cached.unwrap_or(0) as u64;
-
-                        // Update time-series (today's date and current hour)
-                        let now = chrono::Local::now();
-                        let today = now.format("%Y-%m-%d").to_string();
-                        let hour_label = now.format("%Y-%m-%dT%H").to_string();
-
-                        // Update daily data
-                        update_timeseries(&mut agg.requests_by_day, &today, 1);
-                        let tokens = (request_log.tokens_in.unwrap_or(0)
-                            + request_log.tokens_out.unwrap_or(0))
-                            as u64;
-                        update_timeseries(&mut agg.tokens_by_day, &today, tokens);
-
-                        // Update hourly data (for Activity Patterns heatmap)
-                        update_timeseries(&mut agg.requests_by_hour, &hour_label, 1);
-                        update_timeseries(&mut agg.tokens_by_hour, &hour_label, tokens);
-
-                        // Trim hourly data to keep last 7 days worth (168 hours)
-                        const MAX_HOURLY_POINTS: usize = 168;
-                        if agg.requests_by_hour.len() > MAX_HOURLY_POINTS {
-                            agg.requests_by_hour = agg
-                                .requests_by_hour
-                                .split_off(agg.requests_by_hour.len() - MAX_HOURLY_POINTS);
-                        }
-                        if agg.tokens_by_hour.len() > MAX_HOURLY_POINTS {
-                            agg.tokens_by_hour = agg
-                                .tokens_by_hour
-                                .split_off(agg.tokens_by_hour.len() - MAX_HOURLY_POINTS);
-                        }
-
-                        // Update model/provider stats
-                        update_model_stats(&mut agg, &request_log);
-                        update_provider_stats(&mut agg, &request_log);
+                    on_request(&request_log);
 
-                        // Update history (keep only last 500 for UI display)
-                        history.requests.push(request_log);
-                        if history.requests.len() > 500 {
-                            history.requests =
-                                history.requests.split_off(history.requests.len() - 500);
-                        }
-
-                        // Save both files
-                        if let Err(e) = save_request_history(&history) {
-                            eprintln!("[LogWatcher] Failed to save history: {}", e);
-                        }
-                        if let Err(e) = save_aggregate(&agg) {
-                            eprintln!("[LogWatcher] Failed to save aggregate: {}", e);
-                        }
+                    // Persist to the usage store (without token data for now)
+                    if let Err(e) = recorder.record(request_log) {
+                        eprintln!("[LogWatcher] Failed to record request: {}", e);
                     }
                 }
                 line.clear();
@@ -0,0 +1,151 @@
+//! Config migration helpers.
+
+use serde_json::Value;
+
+use crate::config::CURRENT_CONFIG_VERSION;
+
+/// A single config.json upgrade step, applied to the raw JSON so fields that
+/// no longer exist on `AppConfig` can still be read
+type ConfigMigration = fn(&mut Value) -> Result<(), String>;
+
+/// Ordered migration chain keyed by the version each step upgrades from
+const CONFIG_MIGRATIONS: &[(u8, ConfigMigration)] = &[(1, migrate_v1_amp_openai_providers)];
+
+/// Version recorded in a raw config; files from before versioning count as 1
+pub fn config_version_of(value: &Value) -> u8 {
+    value
+        .get("configVersion")
+        .and_then(|v| v.as_u64())
+        .map(|v| v.min(u8::MAX as u64) as u8)
+        .unwrap_or(1)
+}
+
+/// Run every migration between the config's version and the current one.
+/// Returns the original version if any step ran.
+pub fn migrate_config_value(value: &mut Value) -> Result<Option<u8>, String> {
+    let from_version = config_version_of(value);
+    if from_version >= CURRENT_CONFIG_VERSION {
+        return Ok(None);
+    }
+    if !value.is_object() {
+        return Err("Config must be a JSON object".to_string());
+    }
+
+    let mut version = from_version;
+    while version < CURRENT_CONFIG_VERSION {
+        let (_, step) = CONFIG_MIGRATIONS
+            .iter()
+            .find(|(from, _)| *from == version)
+            .ok_or_else(|| format!("No migration from config version {}", version))?;
+        step(value).map_err(|e| {
+            format!(
+                "Config migration v{} -> v{} failed: {}",
+                version,
+                version + 1,
+                e
+            )
+        })?;
+        version += 1;
+        value["configVersion"] = Value::from(version);
+        eprintln!("[Migration] Config migrated to version {}", version);
+    }
+
+    Ok(Some(from_version))
+}
+
+/// v1 -> v2: single `ampOpenaiProvider` becomes the `ampOpenaiProviders` array
+fn migrate_v1_amp_openai_providers(value: &mut Value) -> Result<(), String> {
+    let obj = value.as_object_mut().ok_or("Config must be a JSON object")?;
+    let Some(old_provider) = obj.remove("ampOpenaiProvider") else {
+        return Ok(());
+    };
+    if !old_provider.is_object() {
+        return Ok(());
+    }
+
+    let providers = obj
+        .entry("ampOpenaiProviders")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if providers.is_null() {
+        *providers = Value::Array(Vec::new());
+    }
+    let providers = providers
+        .as_array_mut()
+        .ok_or("ampOpenaiProviders must be an array")?;
+    if !providers.is_empty() {
+        return Ok(());
+    }
+
+    let mut provider = old_provider;
+    let has_id = provider
+        .get("id")
+        .and_then(|id| id.as_str())
+        .is_some_and(|id| !id.is_empty());
+    if !has_id {
+        provider["id"] = Value::from(crate::types::amp::generate_uuid());
+    }
+    eprintln!(
+        "[Migration] Moving provider '{}' to ampOpenaiProviders",
+        provider.get("name").and_then(|n| n.as_str()).unwrap_or_default()
+    );
+    providers.push(provider);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    fn fixture(text: &str) -> Value {
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn migration_chain_covers_every_version() {
+        for version in 1..CURRENT_CONFIG_VERSION {
+            let steps = CONFIG_MIGRATIONS.iter().filter(|(from, _)| *from == version).count();
+            assert_eq!(steps, 1, "expected exactly one migration from v{}", version);
+        }
+    }
+
+    #[test]
+    fn v1_amp_openai_providers_matches_fixture() {
+        let mut value = fixture(include_str!("testdata/config_v1.json"));
+        migrate_v1_amp_openai_providers(&mut value).unwrap();
+        assert_eq!(value, fixture(include_str!("testdata/config_v1_to_v2.json")));
+    }
+
+    #[test]
+    fn v1_amp_openai_providers_generates_missing_id() {
+        let mut value = fixture(r#"{"ampOpenaiProvider": {"name": "Legacy", "baseUrl": "u", "apiKey": "k"}}"#);
+        migrate_v1_amp_openai_providers(&mut value).unwrap();
+        let id = value["ampOpenaiProviders"][0]["id"].as_str().unwrap();
+        assert!(!id.is_empty());
+    }
+
+    #[test]
+    fn v1_amp_openai_providers_keeps_existing_array() {
+        let mut value = fixture(
+            r#"{"ampOpenaiProvider": {"id": "old", "name": "Old", "baseUrl": "u", "apiKey": "k"},
+                "ampOpenaiProviders": [{"id": "new", "name": "New", "baseUrl": "u", "apiKey": "k"}]}"#,
+        );
+        migrate_v1_amp_openai_providers(&mut value).unwrap();
+        assert!(value.get("ampOpenaiProvider").is_none());
+        assert_eq!(value["ampOpenaiProviders"].as_array().unwrap().len(), 1);
+        assert_eq!(value["ampOpenaiProviders"][0]["id"], "new");
+    }
+
+    #[test]
+    fn full_chain_upgrades_v1_fixture_to_current_version() {
+        let mut value = fixture(include_str!("testdata/config_v1.json"));
+        assert_eq!(migrate_config_value(&mut value).unwrap(), Some(1));
+        assert_eq!(config_version_of(&value), CURRENT_CONFIG_VERSION);
+
+        let config: AppConfig = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(config.amp_openai_providers.len(), 1);
+
+        // Already current: nothing to do
+        assert_eq!(migrate_config_value(&mut value).unwrap(), None);
+    }
+}
@@ -0,0 +1,18 @@
+//! Internal helper modules.
+
+pub mod agent_config;
+pub mod auth_dir;
+pub mod autostart;
+pub mod budgets;
+pub mod diff;
+pub mod history;
+pub mod instances;
+pub mod log_watcher;
+pub mod migration;
+pub mod pricing;
+pub mod profiles;
+pub mod secrets;
+pub mod snapshots;
+pub mod usage_recorder;
+pub mod usage_stats;
+pub mod usage_sync;
@@ -0,0 +1,315 @@
+//! Model pricing for cost estimates.
+//!
+//! The catalog shipped in pricing_defaults.json can be overridden by the
+//! user's pricing.json. Each rule matches a model glob or /regex/ and may be
+//! limited to one provider. Rules limited to a provider are checked before
+//! the rest, so Copilot's requests stay free whatever the model costs
+//! elsewhere. Within each group the user's rules come before the defaults
+//! and the first match applies.
+
+use std::sync::{Arc, RwLock};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_pricing_path;
+use crate::types::{PricingRule, TokenRates};
+
+const DEFAULT_PRICING: &str = include_str!("pricing_defaults.json");
+
+/// Layout of pricing.json and the shipped defaults
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PricingFile {
+    rules: Vec<PricingRule>,
+}
+
+/// Tokens of one request. `input` is the whole prompt, cached parts included.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenCounts {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+}
+
+struct CompiledRule {
+    matcher: Regex,
+    provider: Option<String>,
+    rule: PricingRule,
+}
+
+pub struct Pricing {
+    /// In the order they are tried
+    rules: Vec<CompiledRule>,
+}
+
+lazy_static::lazy_static! {
+    static ref CURRENT: RwLock<Option<Arc<Pricing>>> = RwLock::new(None);
+}
+
+/// A case-insensitive regex for a whole model name from a glob, or from a
+/// pattern between slashes taken as a regex as-is
+fn compile_matcher(pattern: &str) -> Result<Regex, String> {
+    let source = match pattern
+        .strip_prefix('/')
+        .and_then(|inner| inner.strip_suffix('/'))
+    {
+        Some(regex) => regex.to_string(),
+        None => {
+            let mut source = String::from("^");
+            for c in pattern.chars() {
+                match c {
+                    '*' => source.push_str(".*"),
+                    '?' => source.push('.'),
+                    c => source.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            source.push('$');
+            source
+        }
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid model pattern '{}': {}", pattern, e))
+}
+
+fn validate_rates(pattern: &str, rates: &TokenRates) -> Result<(), String> {
+    let all = [
+        Some(rates.input),
+        Some(rates.output),
+        rates.cache_read,
+        rates.cache_write,
+    ];
+    if all
+        .into_iter()
+        .flatten()
+        .any(|rate| !rate.is_finite() || rate < 0.0)
+    {
+        return Err(format!("Rates for '{}' must be zero or more", pattern));
+    }
+    Ok(())
+}
+
+impl Pricing {
+    /// Pricing from the user's `overrides` and the shipped `defaults`
+    pub fn new(overrides: &[PricingRule], defaults: &[PricingRule]) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for provider_specific in [true, false] {
+            for rule in overrides.iter().chain(defaults) {
+                if rule.provider.is_some() != provider_specific {
+                    continue;
+                }
+                validate_rates(&rule.model, &rule.rates)?;
+                if let Some(tier) = &rule.long_context {
+                    validate_rates(&rule.model, &tier.rates)?;
+                }
+                rules.push(CompiledRule {
+                    matcher: compile_matcher(&rule.model)?,
+                    provider: rule.provider.as_ref().map(|p| p.to_lowercase()),
+                    rule: rule.clone(),
+                });
+            }
+        }
+        Ok(Self { rules })
+    }
+
+    fn rule_for(&self, provider: &str, model: &str) -> Option<&PricingRule> {
+        let provider = provider.to_lowercase();
+        self.rules
+            .iter()
+            .find(|compiled| {
+                compiled.provider.as_ref().is_none_or(|p| *p == provider)
+                    && compiled.matcher.is_match(model)
+            })
+            .map(|compiled| &compiled.rule)
+    }
+
+    /// Cost in USD of a request through `provider`; nothing if no rule
+    /// matches
+    pub fn cost(&self, provider: &str, model: &str, tokens: TokenCounts) -> f64 {
+        let Some(rule) = self.rule_for(provider, model) else {
+            return 0.0;
+        };
+        let rates = match &rule.long_context {
+            Some(tier) if tokens.input > tier.above_tokens => &tier.rates,
+            _ => &rule.rates,
+        };
+        let cache_read = tokens.cache_read.min(tokens.input);
+        let cache_write = tokens.cache_write.min(tokens.input - cache_read);
+        let uncached = tokens.input - cache_read - cache_write;
+        (uncached as f64 * rates.input
+            + cache_read as f64 * rates.cache_read.unwrap_or(rates.input)
+            + cache_write as f64 * rates.cache_write.unwrap_or(rates.input)
+            + tokens.output as f64 * rates.output)
+            / 1_000_000.0
+    }
+}
+
+/// The catalog shipped with ProxyPal
+pub fn default_rules() -> Vec<PricingRule> {
+    serde_json::from_str::<PricingFile>(DEFAULT_PRICING)
+        .map(|file| file.rules)
+        .unwrap_or_default()
+}
+
+/// The rules in the user's pricing.json, none if there isn't one
+pub fn load_overrides() -> Result<Vec<PricingRule>, String> {
+    let path = get_pricing_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str::<PricingFile>(&data)
+        .map(|file| file.rules)
+        .map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Read pricing.json again, e.g. after it was edited by hand
+pub fn reload() -> Result<Arc<Pricing>, String> {
+    let pricing = Arc::new(Pricing::new(&load_overrides()?, &default_rules())?);
+    *CURRENT.write().unwrap() = Some(pricing.clone());
+    Ok(pricing)
+}
+
+/// The pricing in effect, loaded on first use. A broken pricing.json falls
+/// back to the defaults.
+pub fn current() -> Arc<Pricing> {
+    if let Some(pricing) = CURRENT.read().unwrap().as_ref() {
+        return pricing.clone();
+    }
+    reload().unwrap_or_else(|e| {
+        eprintln!("[ProxyPal] {}; using the default pricing", e);
+        let pricing =
+            Arc::new(Pricing::new(&[], &default_rules()).unwrap_or(Pricing { rules: Vec::new() }));
+        *CURRENT.write().unwrap() = Some(pricing.clone());
+        pricing
+    })
+}
+
+/// Cost in USD of a request with the pricing in effect
+pub fn cost(provider: &str, model: &str, tokens: TokenCounts) -> f64 {
+    current().cost(provider, model, tokens)
+}
+
+/// Check and save the user's rules, then price with them
+pub fn save_overrides(rules: Vec<PricingRule>) -> Result<(), String> {
+    let pricing = Pricing::new(&rules, &default_rules())?;
+    let path = get_pricing_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(&PricingFile { rules })
+        .map_err(|e| format!("Failed to serialize pricing: {}", e))?;
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, data)
+        .and_then(|_| std::fs::rename(&temp_path, &path))
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    *CURRENT.write().unwrap() = Some(Arc::new(pricing));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(model: &str, provider: Option<&str>, input: f64, output: f64) -> PricingRule {
+        PricingRule {
+            model: model.to_string(),
+            provider: provider.map(|p| p.to_string()),
+            rates: TokenRates {
+                input,
+                output,
+                ..TokenRates::default()
+            },
+            long_context: None,
+        }
+    }
+
+    fn tokens(input: u64, output: u64) -> TokenCounts {
+        TokenCounts {
+            input,
+            output,
+            ..TokenCounts::default()
+        }
+    }
+
+    #[test]
+    fn default_catalog_parses_and_covers_every_model() {
+        let defaults = default_rules();
+        assert!(!defaults.is_empty());
+        let pricing = Pricing::new(&[], &defaults).unwrap();
+
+        // Per million tokens, below every long-context threshold
+        let rate =
+            |provider: &str, model: &str| pricing.cost(provider, model, tokens(100_000, 0)) * 10.0;
+        let expected = [
+            ("claude", "claude-sonnet-4-5-20250929", 3.0),
+            ("openai", "gpt-5-mini", 0.25),
+            ("openai", "gpt-5.1-codex", 1.25),
+            ("openai", "o4-mini", 1.1),
+            ("gemini", "gemini-2.5-flash-lite", 0.1),
+            ("copilot", "claude-opus-4.5", 0.0),
+            ("unknown", "some-new-model", 1.0),
+        ];
+        for (provider, model, per_million) in expected {
+            assert!(
+                (rate(provider, model) - per_million).abs() < 1e-9,
+                "{}",
+                model
+            );
+        }
+    }
+
+    #[test]
+    fn overrides_and_provider_rules_take_precedence() {
+        let defaults = vec![
+            rule("*", Some("copilot"), 0.0, 0.0),
+            rule("gpt-*", None, 1.0, 2.0),
+        ];
+        let overrides = vec![rule("/^gpt-4o(-mini)?$/", None, 5.0, 5.0)];
+        let pricing = Pricing::new(&overrides, &defaults).unwrap();
+
+        assert_eq!(pricing.cost("openai", "GPT-4o", tokens(1_000_000, 0)), 5.0);
+        assert_eq!(
+            pricing.cost("openai", "gpt-4o-audio", tokens(1_000_000, 0)),
+            1.0
+        );
+        assert_eq!(pricing.cost("copilot", "gpt-4o", tokens(1_000_000, 0)), 0.0);
+        assert_eq!(pricing.cost("openai", "claude", tokens(1_000_000, 0)), 0.0);
+
+        assert!(Pricing::new(&[rule("/(/", None, 1.0, 1.0)], &[]).is_err());
+        assert!(Pricing::new(&[rule("*", None, -1.0, 1.0)], &[]).is_err());
+    }
+
+    #[test]
+    fn cached_tokens_and_long_prompts_use_their_own_rates() {
+        let mut sonnet = rule("claude-sonnet-*", None, 3.0, 15.0);
+        sonnet.rates.cache_read = Some(0.3);
+        sonnet.long_context = Some(crate::types::LongContextTier {
+            above_tokens: 200_000,
+            rates: TokenRates {
+                input: 6.0,
+                output: 22.5,
+                cache_read: None,
+                cache_write: None,
+            },
+        });
+        let pricing = Pricing::new(&[sonnet], &[]).unwrap();
+
+        let cached = TokenCounts {
+            input: 100_000,
+            output: 10_000,
+            cache_read: 80_000,
+            cache_write: 0,
+        };
+        let expected = (20_000.0 * 3.0 + 80_000.0 * 0.3 + 10_000.0 * 15.0) / 1_000_000.0;
+        assert!((pricing.cost("claude", "claude-sonnet-4", cached) - expected).abs() < 1e-9);
+
+        // Over the threshold every token is billed at the tier's rates
+        let long = tokens(300_000, 1_000);
+        let expected = (300_000.0 * 6.0 + 1_000.0 * 22.5) / 1_000_000.0;
+        assert!((pricing.cost("claude", "claude-sonnet-4", long) - expected).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,223 @@
+//! Named configuration profile storage.
+//!
+//! Each profile is a full `AppConfig` saved as `profiles/<name>.json` next to
+//! config.json. config.json stays the live config; activating a profile copies
+//! it over config.json and records the name in active-profile.json.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    get_active_profile_path, get_profiles_dir, load_config_from_path, save_config_to_path,
+    AppConfig,
+};
+use crate::types::{ActiveProfile, ProfileInfo};
+
+const MAX_PROFILE_NAME_LEN: usize = 64;
+
+/// Profile names double as file names, so keep them to a safe character set
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    if name.len() > MAX_PROFILE_NAME_LEN {
+        return Err(format!(
+            "Profile name must be at most {} characters",
+            MAX_PROFILE_NAME_LEN
+        ));
+    }
+    if name != name.trim() {
+        return Err("Profile name cannot start or end with whitespace".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ' ')
+    {
+        return Err(
+            "Profile name may only contain letters, digits, spaces, '-' and '_'".to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn profile_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.json", name))
+}
+
+pub fn profile_exists_in(dir: &Path, name: &str) -> bool {
+    profile_path(dir, name).exists()
+}
+
+pub fn list_profiles_in(dir: &Path, active: Option<&str>) -> Vec<ProfileInfo> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut profiles: Vec<ProfileInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            if validate_profile_name(&name).is_err() {
+                return None;
+            }
+            let modified_at = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64);
+            Some(ProfileInfo {
+                active: active == Some(name.as_str()),
+                name,
+                modified_at,
+            })
+        })
+        .collect();
+
+    profiles.sort_by_key(|p| p.name.to_lowercase());
+    profiles
+}
+
+pub fn load_profile_from(dir: &Path, name: &str) -> Result<AppConfig, String> {
+    validate_profile_name(name)?;
+    let path = profile_path(dir, name);
+    if !path.exists() {
+        return Err(format!("Profile '{}' does not exist", name));
+    }
+    load_config_from_path(&path)
+        .map_err(|e| format!("Failed to load profile '{}': {}", name, e.message))
+}
+
+pub fn save_profile_to(dir: &Path, name: &str, config: &AppConfig) -> Result<(), String> {
+    validate_profile_name(name)?;
+    save_config_to_path(&profile_path(dir, name), config)
+}
+
+pub fn delete_profile_from(dir: &Path, name: &str) -> Result<(), String> {
+    validate_profile_name(name)?;
+    let path = profile_path(dir, name);
+    if !path.exists() {
+        return Err(format!("Profile '{}' does not exist", name));
+    }
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to delete profile '{}': {}", name, e))
+}
+
+pub fn load_active_profile_from(path: &Path) -> Option<String> {
+    let data = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str::<ActiveProfile>(&data).ok()?.name
+}
+
+pub fn save_active_profile_to(path: &Path, name: Option<&str>) -> Result<(), String> {
+    let marker = ActiveProfile {
+        name: name.map(|n| n.to_string()),
+    };
+    let data = serde_json::to_string_pretty(&marker).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| format!("Failed to save active profile: {}", e))
+}
+
+/// Take the profile's settings but keep app-level preferences (UI language,
+/// window behavior, login item, snapshot retention, control API) from the
+/// current config
+pub fn merge_profile_into(current: &AppConfig, profile: AppConfig) -> AppConfig {
+    AppConfig {
+        locale: current.locale.clone(),
+        sidebar_pinned: current.sidebar_pinned,
+        close_to_tray: current.close_to_tray,
+        launch_at_login: current.launch_at_login,
+        snapshot_retention: current.snapshot_retention.clone(),
+        control_api: current.control_api.clone(),
+        ..profile
+    }
+}
+
+pub fn list_profiles() -> Vec<ProfileInfo> {
+    let active = load_active_profile();
+    list_profiles_in(&get_profiles_dir(), active.as_deref())
+}
+
+pub fn load_active_profile() -> Option<String> {
+    load_active_profile_from(&get_active_profile_path())
+}
+
+pub fn save_active_profile(name: Option<&str>) -> Result<(), String> {
+    save_active_profile_to(&get_active_profile_path(), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::fs;
+
+    #[test]
+    fn validate_profile_name_rejects_path_characters() {
+        assert!(validate_profile_name("work").is_ok());
+        assert!(validate_profile_name("team A_2").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("../config").is_err());
+        assert!(validate_profile_name("a/b").is_err());
+        assert!(validate_profile_name(" padded").is_err());
+    }
+
+    #[test]
+    fn saved_profiles_are_listed_with_active_flag() {
+        let dir = test_dir("profiles-list");
+
+        let mut work = AppConfig::default();
+        work.routing_strategy = "round-robin".to_string();
+        let mut personal = AppConfig::default();
+        personal.routing_strategy = "fill-first".to_string();
+        save_profile_to(&dir, "work", &work).unwrap();
+        save_profile_to(&dir, "Personal", &personal).unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let profiles = list_profiles_in(&dir, Some("work"));
+        let names: Vec<_> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Personal", "work"]);
+        assert!(!profiles[0].active);
+        assert!(profiles[1].active);
+
+        let loaded = load_profile_from(&dir, "Personal").unwrap();
+        assert_eq!(loaded.routing_strategy, "fill-first");
+
+        delete_profile_from(&dir, "Personal").unwrap();
+        assert!(!profile_exists_in(&dir, "Personal"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn active_profile_marker_round_trips() {
+        let dir = test_dir("profiles-active");
+        let path = dir.join("active-profile.json");
+
+        assert_eq!(load_active_profile_from(&path), None);
+        save_active_profile_to(&path, Some("work")).unwrap();
+        assert_eq!(load_active_profile_from(&path).as_deref(), Some("work"));
+        save_active_profile_to(&path, None).unwrap();
+        assert_eq!(load_active_profile_from(&path), None);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn merge_profile_keeps_app_preferences() {
+        let mut current = AppConfig::default();
+        current.locale = "zh-CN".to_string();
+        current.close_to_tray = false;
+
+        let mut profile = AppConfig::default();
+        profile.locale = "en".to_string();
+        profile.routing_strategy = "fill-first".to_string();
+        profile.thinking_budget_mode = "high".to_string();
+
+        let merged = merge_profile_into(&current, profile);
+        assert_eq!(merged.locale, "zh-CN");
+        assert!(!merged.close_to_tray);
+        assert_eq!(merged.routing_strategy, "fill-first");
+        assert_eq!(merged.thinking_budget_mode, "high");
+    }
+}
@@ -0,0 +1,611 @@
+//! Encrypted-at-rest storage for API keys and tokens.
+//!
+//! Secrets live in secrets.vault, a JSON envelope around an XChaCha20-Poly1305
+//! encrypted map of name -> value. The key is either derived from a passphrase
+//! with Argon2id or read from a generated vault.key file. While the vault is
+//! enabled, config.json (and every profile, instance config and snapshot)
+//! stores `vault:<name>` references in place of the raw values; they are
+//! resolved only to write proxy-config.yaml and to talk to the sidecar and
+//! tunnels.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    get_config_path, get_instance_dir, get_profiles_dir, get_proxypal_config_dir,
+    get_vault_key_path, get_vault_path, load_config_from_path, remove_config_backups_in,
+    save_config_to_path, AppConfig,
+};
+use crate::helpers::instances::{load_definitions, load_instance_config, save_instance_config};
+use crate::helpers::profiles::{list_profiles, load_profile_from, save_profile_to};
+use crate::helpers::snapshots::seal_snapshots_in;
+use crate::types::{VaultMode, VaultStatus};
+
+/// Prefix marking a config value as a reference into the vault
+pub const SECRET_REF_PREFIX: &str = "vault:";
+
+const VAULT_VERSION: u8 = 1;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultFile {
+    version: u8,
+    mode: VaultMode,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// An unlocked vault. Dropping it forgets the key.
+pub struct Vault {
+    path: PathBuf,
+    mode: VaultMode,
+    salt: [u8; SALT_LEN],
+    key: [u8; KEY_LEN],
+    secrets: BTreeMap<String, String>,
+}
+
+/// Whether the vault is in use, and the unlocked vault if we have the key
+#[derive(Default)]
+struct VaultState {
+    enabled: Option<VaultMode>,
+    unlocked: Option<Vault>,
+}
+
+static VAULT: Mutex<VaultState> = Mutex::new(VaultState {
+    enabled: None,
+    unlocked: None,
+});
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn decode_fixed<const N: usize>(value: &str, what: &str) -> Result<[u8; N], String> {
+    let bytes = BASE64
+        .decode(value.trim())
+        .map_err(|e| format!("Invalid vault {}: {}", what, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("Invalid vault {} length", what))
+}
+
+fn derive_passphrase_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    let mut key = [0u8; KEY_LEN];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive vault key: {}", e))?;
+    Ok(key)
+}
+
+/// Read the key file, creating it with a random key if it doesn't exist yet
+fn load_or_create_key_file(path: &Path) -> Result<[u8; KEY_LEN], String> {
+    if path.exists() {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read vault key file: {}", e))?;
+        return decode_fixed(&data, "key file");
+    }
+    let key = random_bytes::<KEY_LEN>();
+    write_private_file(path, BASE64.encode(key).as_bytes())?;
+    Ok(key)
+}
+
+/// Write a file readable only by the current user (0600 on Unix), via a
+/// temp file and rename so readers never see a partial file
+pub fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let temp_path = path.with_extension("tmp");
+    {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&temp_path)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        std::io::Write::write_all(&mut file, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    // `mode` only applies on create, so tighten a temp file left over from before
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))?;
+    }
+    std::fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+impl Vault {
+    /// Start an empty vault. `secret` is the passphrase, or the key file path
+    /// in key-file mode.
+    pub fn create(path: &Path, mode: VaultMode, secret: &str) -> Result<Self, String> {
+        let salt = random_bytes::<SALT_LEN>();
+        let key = match mode {
+            VaultMode::Passphrase => {
+                if secret.is_empty() {
+                    return Err("Vault passphrase cannot be empty".to_string());
+                }
+                derive_passphrase_key(secret, &salt)?
+            }
+            VaultMode::KeyFile => load_or_create_key_file(Path::new(secret))?,
+        };
+        let vault = Self {
+            path: path.to_path_buf(),
+            mode,
+            salt,
+            key,
+            secrets: BTreeMap::new(),
+        };
+        vault.save()?;
+        Ok(vault)
+    }
+
+    pub fn read_mode(path: &Path) -> Result<VaultMode, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read secret vault: {}", e))?;
+        let file: VaultFile = serde_json::from_str(&data)
+            .map_err(|e| format!("Failed to parse secret vault: {}", e))?;
+        Ok(file.mode)
+    }
+
+    /// Decrypt the vault at `path`; `secret` is as for `create`
+    pub fn open(path: &Path, secret: &str) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read secret vault: {}", e))?;
+        let file: VaultFile = serde_json::from_str(&data)
+            .map_err(|e| format!("Failed to parse secret vault: {}", e))?;
+        if file.version != VAULT_VERSION {
+            return Err(format!("Unsupported secret vault version {}", file.version));
+        }
+
+        let salt = decode_fixed::<SALT_LEN>(&file.salt, "salt")?;
+        let nonce = decode_fixed::<NONCE_LEN>(&file.nonce, "nonce")?;
+        let ciphertext = BASE64
+            .decode(file.ciphertext.trim())
+            .map_err(|e| format!("Invalid vault ciphertext: {}", e))?;
+        let key = match file.mode {
+            VaultMode::Passphrase => derive_passphrase_key(secret, &salt)?,
+            VaultMode::KeyFile => {
+                let data = std::fs::read_to_string(secret)
+                    .map_err(|e| format!("Failed to read vault key file: {}", e))?;
+                decode_fixed::<KEY_LEN>(&data, "key file")?
+            }
+        };
+
+        let plaintext = XChaCha20Poly1305::new(&key.into())
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| match file.mode {
+                VaultMode::Passphrase => "Wrong passphrase or corrupted vault".to_string(),
+                VaultMode::KeyFile => "Vault key file doesn't match the vault".to_string(),
+            })?;
+        let secrets = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Failed to parse vault contents: {}", e))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            mode: file.mode,
+            salt,
+            key,
+            secrets,
+        })
+    }
+
+    /// Re-encrypt with a fresh nonce and write the vault file
+    pub fn save(&self) -> Result<(), String> {
+        let plaintext = serde_json::to_vec(&self.secrets).map_err(|e| e.to_string())?;
+        let nonce = random_bytes::<NONCE_LEN>();
+        let ciphertext = XChaCha20Poly1305::new(&self.key.into())
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|_| "Failed to encrypt secret vault".to_string())?;
+        let file = VaultFile {
+            version: VAULT_VERSION,
+            mode: self.mode,
+            salt: BASE64.encode(self.salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let data = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        write_private_file(&self.path, data.as_bytes())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.secrets.get(name).map(String::as_str)
+    }
+
+    /// Store `value` under `kind` and return its reference. An existing
+    /// entry with the same value is reused so repeated saves don't grow the vault.
+    pub fn store(&mut self, kind: &str, value: &str) -> String {
+        let prefix = format!("{}/", kind);
+        let existing = self
+            .secrets
+            .iter()
+            .find(|(name, stored)| name.starts_with(&prefix) && stored.as_str() == value)
+            .map(|(name, _)| name.clone());
+        let name = existing.unwrap_or_else(|| {
+            let name = format!("{}{}", prefix, &uuid::Uuid::new_v4().simple().to_string()[..8]);
+            self.secrets.insert(name.clone(), value.to_string());
+            name
+        });
+        format!("{}{}", SECRET_REF_PREFIX, name)
+    }
+}
+
+pub fn is_secret_ref(value: &str) -> bool {
+    value.starts_with(SECRET_REF_PREFIX)
+}
+
+/// Every secret-bearing field in the config, tagged with its vault kind
+fn secret_fields(config: &mut AppConfig) -> Vec<(&'static str, &mut String)> {
+    let mut fields: Vec<(&'static str, &mut String)> = vec![
+        ("proxy-password", &mut config.proxy_password),
+        ("amp-api-key", &mut config.amp_api_key),
+        ("management-key", &mut config.management_key),
+        ("copilot-github-token", &mut config.copilot.github_token),
+        ("control-api-token", &mut config.control_api.token),
+    ];
+    fields.extend(
+        config
+            .claude_api_keys
+            .iter_mut()
+            .map(|k| ("claude-api-key", &mut k.api_key)),
+    );
+    fields.extend(
+        config
+            .gemini_api_keys
+            .iter_mut()
+            .map(|k| ("gemini-api-key", &mut k.api_key)),
+    );
+    fields.extend(
+        config
+            .codex_api_keys
+            .iter_mut()
+            .map(|k| ("codex-api-key", &mut k.api_key)),
+    );
+    fields.extend(
+        config
+            .vertex_api_keys
+            .iter_mut()
+            .map(|k| ("vertex-api-key", &mut k.api_key)),
+    );
+    fields.extend(
+        config
+            .amp_openai_providers
+            .iter_mut()
+            .map(|p| ("openai-provider", &mut p.api_key)),
+    );
+    fields.extend(
+        config
+            .cloudflare_configs
+            .iter_mut()
+            .map(|c| ("cloudflare-tunnel-token", &mut c.tunnel_token)),
+    );
+    fields
+}
+
+/// Move plaintext secrets into the vault, leaving references behind.
+/// Returns how many values were moved.
+pub fn seal_config(config: &mut AppConfig, vault: &mut Vault) -> usize {
+    let mut moved = 0;
+    for (kind, field) in secret_fields(config) {
+        if field.is_empty() || is_secret_ref(field) {
+            continue;
+        }
+        *field = vault.store(kind, field);
+        moved += 1;
+    }
+    moved
+}
+
+pub fn has_plaintext_secrets(config: &AppConfig) -> bool {
+    let mut config = config.clone();
+    secret_fields(&mut config)
+        .iter()
+        .any(|(_, field)| !field.is_empty() && !is_secret_ref(field))
+}
+
+/// Replace references with their values
+pub fn resolve_config(config: &AppConfig, vault: Option<&Vault>) -> Result<AppConfig, String> {
+    let mut resolved = config.clone();
+    for (_, field) in secret_fields(&mut resolved) {
+        if let Some(name) = field.strip_prefix(SECRET_REF_PREFIX) {
+            let vault = vault.ok_or("Secret vault is locked; unlock it to use stored keys")?;
+            let value = vault
+                .get(name)
+                .ok_or_else(|| format!("Secret '{}' is missing from the vault", name))?;
+            *field = value.to_string();
+        }
+    }
+    Ok(resolved)
+}
+
+/// Resolve any references in `config` with the unlocked vault
+pub fn resolve_config_secrets(config: &AppConfig) -> Result<AppConfig, String> {
+    let state = VAULT.lock().unwrap();
+    resolve_config(config, state.unlocked.as_ref())
+}
+
+/// Resolve a single value that may be a reference
+pub fn resolve_secret(value: &str) -> Result<String, String> {
+    let Some(name) = value.strip_prefix(SECRET_REF_PREFIX) else {
+        return Ok(value.to_string());
+    };
+    let state = VAULT.lock().unwrap();
+    let vault = state
+        .unlocked
+        .as_ref()
+        .ok_or("Secret vault is locked; unlock it to use stored keys")?;
+    vault
+        .get(name)
+        .map(str::to_string)
+        .ok_or_else(|| format!("Secret '{}' is missing from the vault", name))
+}
+
+/// The config as it should be written to disk: with the vault enabled,
+/// plaintext secrets are sealed first. `None` means write it unchanged.
+pub fn seal_for_save(config: &AppConfig) -> Result<Option<AppConfig>, String> {
+    let mut state = VAULT.lock().unwrap();
+    if state.enabled.is_none() || !has_plaintext_secrets(config) {
+        return Ok(None);
+    }
+    let vault = state
+        .unlocked
+        .as_mut()
+        .ok_or("Secret vault is locked; unlock it before saving new keys")?;
+    let mut sealed = config.clone();
+    seal_config(&mut sealed, vault);
+    vault.save()?;
+    Ok(Some(sealed))
+}
+
+pub fn vault_status() -> VaultStatus {
+    let state = VAULT.lock().unwrap();
+    VaultStatus {
+        enabled: state.enabled.is_some(),
+        unlocked: state.unlocked.is_some(),
+        mode: state.enabled,
+        secret_count: state.unlocked.as_ref().map_or(0, |v| v.secrets.len()),
+    }
+}
+
+/// Detect an existing vault at startup and open it if it uses a key file
+pub fn init_vault() {
+    let path = get_vault_path();
+    if !path.exists() {
+        return;
+    }
+    let mode = match Vault::read_mode(&path) {
+        Ok(mode) => mode,
+        Err(e) => {
+            eprintln!("[ProxyPal] {}", e);
+            // Still treat it as enabled so plaintext is never written over it
+            VAULT.lock().unwrap().enabled = Some(VaultMode::Passphrase);
+            return;
+        }
+    };
+    let mut state = VAULT.lock().unwrap();
+    state.enabled = Some(mode);
+    if mode == VaultMode::KeyFile {
+        match Vault::open(&path, &get_vault_key_path().to_string_lossy()) {
+            Ok(vault) => state.unlocked = Some(vault),
+            Err(e) => eprintln!("[ProxyPal] Failed to unlock secret vault: {}", e),
+        }
+    }
+}
+
+/// Create the vault. Existing plaintext values are moved in by the caller
+/// with `migrate_plaintext_secrets` and `remove_plaintext_backups`.
+pub fn enable_vault(mode: VaultMode, passphrase: Option<&str>) -> Result<(), String> {
+    let path = get_vault_path();
+    if path.exists() {
+        return Err("Secret vault already exists".to_string());
+    }
+    let secret = match mode {
+        VaultMode::Passphrase => passphrase.unwrap_or_default().to_string(),
+        VaultMode::KeyFile => get_vault_key_path().to_string_lossy().to_string(),
+    };
+    let vault = Vault::create(&path, mode, &secret)?;
+    let mut state = VAULT.lock().unwrap();
+    state.enabled = Some(mode);
+    state.unlocked = Some(vault);
+    Ok(())
+}
+
+/// Move plaintext secrets from config.json, every profile, every instance's
+/// config.json and every snapshot into the unlocked vault. Returns how many
+/// files were rewritten.
+pub fn migrate_plaintext_secrets() -> Result<usize, String> {
+    if !vault_status().unlocked {
+        return Ok(0);
+    }
+    let mut rewritten = 0;
+
+    let config_path = get_config_path();
+    if config_path.exists() {
+        let config = load_config_from_path(&config_path).map_err(|e| e.message)?;
+        if has_plaintext_secrets(&config) {
+            save_config_to_path(&config_path, &config)?;
+            rewritten += 1;
+        }
+    }
+
+    let profiles_dir = get_profiles_dir();
+    for profile in list_profiles() {
+        let config = load_profile_from(&profiles_dir, &profile.name)?;
+        if has_plaintext_secrets(&config) {
+            save_profile_to(&profiles_dir, &profile.name, &config)?;
+            rewritten += 1;
+        }
+    }
+
+    for definition in load_definitions() {
+        let dir = get_instance_dir(&definition.id);
+        let Ok(config) = load_instance_config(&dir) else {
+            continue;
+        };
+        if has_plaintext_secrets(&config) {
+            save_instance_config(&dir, &config)?;
+            rewritten += 1;
+        }
+    }
+
+    {
+        let mut state = VAULT.lock().unwrap();
+        if let Some(vault) = state.unlocked.as_mut() {
+            let sealed = seal_snapshots_in(&get_proxypal_config_dir(), vault);
+            if sealed > 0 {
+                vault.save()?;
+                rewritten += sealed;
+            }
+        }
+    }
+
+    if rewritten > 0 {
+        println!("[ProxyPal] Moved plaintext secrets from {} file(s) into the vault", rewritten);
+    }
+    Ok(rewritten)
+}
+
+/// Delete config.json backups and quarantined copies, for the app and every
+/// instance, which can't be sealed. Run once when the vault is enabled.
+pub fn remove_plaintext_backups() -> usize {
+    let mut dirs = vec![get_proxypal_config_dir()];
+    dirs.extend(load_definitions().iter().map(|d| get_instance_dir(&d.id)));
+    let removed: Vec<PathBuf> = dirs.iter().flat_map(|dir| remove_config_backups_in(dir)).collect();
+    for path in &removed {
+        println!("[ProxyPal] Removed '{}', it held plaintext keys", path.display());
+    }
+    removed.len()
+}
+
+pub fn unlock_vault(passphrase: &str) -> Result<(), String> {
+    let mut state = VAULT.lock().unwrap();
+    let Some(mode) = state.enabled else {
+        return Err("Secret vault is not enabled".to_string());
+    };
+    let secret = match mode {
+        VaultMode::Passphrase => passphrase.to_string(),
+        VaultMode::KeyFile => get_vault_key_path().to_string_lossy().to_string(),
+    };
+    state.unlocked = Some(Vault::open(&get_vault_path(), &secret)?);
+    Ok(())
+}
+
+pub fn lock_vault() {
+    VAULT.lock().unwrap().unlocked = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use crate::types::ClaudeApiKey;
+    use std::fs;
+
+    fn config_with_secrets() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.amp_api_key = "amp-secret".to_string();
+        config.copilot.github_token = "ghu_token".to_string();
+        config.claude_api_keys.push(ClaudeApiKey {
+            api_key: "sk-ant-secret".to_string(),
+            base_url: None,
+            proxy_url: None,
+            headers: None,
+            models: None,
+            excluded_models: None,
+            prefix: None,
+        });
+        config
+    }
+
+    #[test]
+    fn passphrase_vault_round_trips_and_rejects_wrong_passphrase() {
+        let dir = test_dir("vault-passphrase");
+        let path = dir.join("secrets.vault");
+
+        let mut vault = Vault::create(&path, VaultMode::Passphrase, "correct horse").unwrap();
+        let reference = vault.store("amp-api-key", "amp-secret");
+        vault.save().unwrap();
+
+        let data = fs::read_to_string(&path).unwrap();
+        assert!(!data.contains("amp-secret"));
+        assert_eq!(Vault::read_mode(&path).unwrap(), VaultMode::Passphrase);
+
+        let reopened = Vault::open(&path, "correct horse").unwrap();
+        let name = reference.strip_prefix(SECRET_REF_PREFIX).unwrap();
+        assert_eq!(reopened.get(name), Some("amp-secret"));
+        assert!(Vault::open(&path, "wrong").is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn key_file_vault_creates_private_key_file() {
+        let dir = test_dir("vault-keyfile");
+        let path = dir.join("secrets.vault");
+        let key_path = dir.join("vault.key");
+
+        Vault::create(&path, VaultMode::KeyFile, &key_path.to_string_lossy()).unwrap();
+        assert!(key_path.exists());
+        assert!(Vault::open(&path, &key_path.to_string_lossy()).is_ok());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in [&path, &key_path] {
+                let mode = fs::metadata(file).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+        }
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn sealing_replaces_plaintext_with_references() {
+        let dir = test_dir("vault-seal");
+        let mut vault =
+            Vault::create(&dir.join("secrets.vault"), VaultMode::Passphrase, "pw").unwrap();
+
+        let plain = config_with_secrets();
+        let mut sealed = plain.clone();
+        // Default management key plus the three above
+        assert_eq!(seal_config(&mut sealed, &mut vault), 4);
+        assert!(!has_plaintext_secrets(&sealed));
+        assert!(sealed.amp_api_key.starts_with(SECRET_REF_PREFIX));
+        assert!(sealed.claude_api_keys[0].api_key.starts_with("vault:claude-api-key/"));
+        assert!(sealed.proxy_password.is_empty());
+
+        // Sealing the same values again reuses the entries
+        let mut again = plain.clone();
+        seal_config(&mut again, &mut vault);
+        assert_eq!(again.amp_api_key, sealed.amp_api_key);
+        assert_eq!(vault.secrets.len(), 4);
+
+        let resolved = resolve_config(&sealed, Some(&vault)).unwrap();
+        assert_eq!(resolved.amp_api_key, "amp-secret");
+        assert_eq!(resolved.claude_api_keys[0].api_key, "sk-ant-secret");
+        assert_eq!(resolved.management_key, plain.management_key);
+
+        assert!(resolve_config(&sealed, None).is_err());
+        assert!(resolve_config(&plain, None).is_ok());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
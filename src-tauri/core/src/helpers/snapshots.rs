@@ -0,0 +1,518 @@
+//! Rolling snapshots of the config files.
+//!
+//! Each snapshot is a directory under `snapshots/` holding copies of
+//! config.json, proxy-config.yaml and proxy-config-custom.yaml as they were
+//! before a save, plus a snapshot.json describing it. Saves that leave the
+//! files unchanged since the newest snapshot don't create a new one.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{get_proxypal_config_dir, AppConfig};
+use crate::helpers::diff::{diff_lines, has_changes};
+use crate::helpers::secrets::{
+    has_plaintext_secrets, seal_config, seal_for_save, write_private_file, Vault,
+};
+use crate::proxy::preview::{mask_json_secrets, mask_secrets};
+use crate::types::{ConfigSnapshot, SnapshotFileDiff, SnapshotRetention};
+
+/// Files captured in every snapshot, relative to the proxypal config dir
+pub const SNAPSHOT_FILES: &[&str] = &[
+    "config.json",
+    "proxy-config.yaml",
+    "proxy-config-custom.yaml",
+];
+
+const SNAPSHOT_META: &str = "snapshot.json";
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+fn snapshots_dir(config_dir: &Path) -> PathBuf {
+    config_dir.join("snapshots")
+}
+
+/// Contents of the tracked files that exist in `dir`
+fn read_tracked_files(dir: &Path) -> Vec<(&'static str, String)> {
+    SNAPSHOT_FILES
+        .iter()
+        .filter_map(|name| {
+            std::fs::read_to_string(dir.join(name))
+                .ok()
+                .map(|content| (*name, content))
+        })
+        .collect()
+}
+
+/// All snapshots, newest first
+pub fn list_snapshots_in(config_dir: &Path) -> Vec<ConfigSnapshot> {
+    let Ok(entries) = std::fs::read_dir(snapshots_dir(config_dir)) else {
+        return Vec::new();
+    };
+
+    let mut snapshots: Vec<ConfigSnapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let data = std::fs::read_to_string(entry.path().join(SNAPSHOT_META)).ok()?;
+            let snapshot: ConfigSnapshot = serde_json::from_str(&data).ok()?;
+            // Ignore metadata that doesn't match its directory
+            (entry.file_name().to_str() == Some(snapshot.id.as_str())).then_some(snapshot)
+        })
+        .collect();
+
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+    snapshots
+}
+
+fn find_snapshot(config_dir: &Path, id: &str) -> Result<ConfigSnapshot, String> {
+    list_snapshots_in(config_dir)
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("Snapshot '{}' does not exist", id))
+}
+
+/// Copy the current config files into a new snapshot, then apply retention.
+/// Returns `None` when there is nothing new to capture.
+pub fn create_snapshot_in(
+    config_dir: &Path,
+    reason: &str,
+    retention: &SnapshotRetention,
+) -> Result<Option<ConfigSnapshot>, String> {
+    let current = read_tracked_files(config_dir);
+    if current.is_empty() {
+        return Ok(None);
+    }
+
+    let existing = list_snapshots_in(config_dir);
+    if let Some(latest) = existing.first() {
+        if read_tracked_files(&snapshots_dir(config_dir).join(&latest.id)) == current {
+            return Ok(None);
+        }
+    }
+
+    let now = chrono::Local::now();
+    let base_id = now.format("%Y%m%d-%H%M%S-%3f").to_string();
+    let mut id = base_id.clone();
+    let mut suffix = 1;
+    while snapshots_dir(config_dir).join(&id).exists() {
+        id = format!("{}-{}", base_id, suffix);
+        suffix += 1;
+    }
+
+    let dir = snapshots_dir(config_dir).join(&id);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create snapshot dir '{}': {}", dir.display(), e))?;
+    for (name, content) in &current {
+        // proxy-config.yaml holds resolved secrets, so keep copies private
+        write_private_file(&dir.join(name), content.as_bytes())
+            .map_err(|e| format!("Failed to write snapshot of {}: {}", name, e))?;
+    }
+
+    let snapshot = ConfigSnapshot {
+        id,
+        created_at: now.timestamp_millis() as u64,
+        reason: reason.to_string(),
+        files: current.iter().map(|(name, _)| name.to_string()).collect(),
+    };
+    let meta = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(SNAPSHOT_META), meta)
+        .map_err(|e| format!("Failed to write snapshot metadata: {}", e))?;
+
+    prune_snapshots_in(config_dir, retention, snapshot.created_at);
+    Ok(Some(snapshot))
+}
+
+/// Move plaintext secrets in every snapshot's config.json into `vault`.
+/// A copy that doesn't read as a current config can't be sealed, so its
+/// snapshot is deleted. Returns how many snapshots were changed.
+pub fn seal_snapshots_in(config_dir: &Path, vault: &mut Vault) -> usize {
+    let mut changed = 0;
+    for snapshot in list_snapshots_in(config_dir) {
+        let dir = snapshots_dir(config_dir).join(&snapshot.id);
+        let path = dir.join("config.json");
+        let Ok(data) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let sealed = serde_json::from_str::<AppConfig>(&data)
+            .map_err(|e| e.to_string())
+            .and_then(|mut config| {
+                if !has_plaintext_secrets(&config) {
+                    return Ok(None);
+                }
+                seal_config(&mut config, vault);
+                serde_json::to_string_pretty(&config)
+                    .map(Some)
+                    .map_err(|e| e.to_string())
+            });
+        let result = match sealed {
+            Ok(None) => continue,
+            Ok(Some(data)) => write_private_file(&path, data.as_bytes()),
+            Err(e) => {
+                eprintln!(
+                    "[ProxyPal] Removing snapshot '{}', its config.json can't be sealed: {}",
+                    snapshot.id, e
+                );
+                std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())
+            }
+        };
+        match result {
+            Ok(()) => changed += 1,
+            Err(e) => eprintln!("[ProxyPal] Failed to seal snapshot '{}': {}", snapshot.id, e),
+        }
+    }
+    changed
+}
+
+/// Drop snapshots beyond the count limit or older than the age limit.
+/// The newest snapshot is always kept.
+fn prune_snapshots_in(config_dir: &Path, retention: &SnapshotRetention, now_millis: u64) {
+    let max_age = u64::from(retention.max_age_days) * DAY_MILLIS;
+    for (index, snapshot) in list_snapshots_in(config_dir).iter().enumerate().skip(1) {
+        let over_count = retention.max_count > 0 && index >= retention.max_count as usize;
+        let too_old =
+            retention.max_age_days > 0 && now_millis.saturating_sub(snapshot.created_at) > max_age;
+        if over_count || too_old {
+            let dir = snapshots_dir(config_dir).join(&snapshot.id);
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                eprintln!(
+                    "[ProxyPal] Failed to remove old snapshot '{}': {}",
+                    snapshot.id, e
+                );
+            }
+        }
+    }
+}
+
+/// A tracked file with its secrets masked, for showing in the UI
+fn masked(name: &str, content: &str) -> String {
+    if name.ends_with(".json") {
+        mask_json_secrets(content)
+    } else {
+        mask_secrets(content)
+    }
+}
+
+/// Diff each file in the snapshot against the file on disk now, with
+/// secrets masked on both sides
+pub fn diff_snapshot_in(config_dir: &Path, id: &str) -> Result<Vec<SnapshotFileDiff>, String> {
+    let snapshot = find_snapshot(config_dir, id)?;
+    let snapshot_files = read_tracked_files(&snapshots_dir(config_dir).join(&snapshot.id));
+    let current_files = read_tracked_files(config_dir);
+    let content_of = |files: &[(&str, String)], name: &str| {
+        files
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, content)| content.clone())
+    };
+
+    Ok(SNAPSHOT_FILES
+        .iter()
+        .filter_map(|name| {
+            let old = content_of(&snapshot_files, name);
+            let new = content_of(&current_files, name);
+            if old.is_none() && new.is_none() {
+                return None;
+            }
+            // Compared unmasked, so a changed short key still counts
+            let lines = diff_lines(
+                &masked(name, old.as_deref().unwrap_or_default()),
+                &masked(name, new.as_deref().unwrap_or_default()),
+            );
+            Some(SnapshotFileDiff {
+                file: name.to_string(),
+                changed: old != new || has_changes(&lines),
+                lines,
+            })
+        })
+        .collect())
+}
+
+/// Put the snapshot's files back in place. The current files are snapshotted
+/// first so a restore can itself be undone. Tracked files that didn't exist
+/// when the snapshot was taken are removed.
+pub fn restore_snapshot_in(
+    config_dir: &Path,
+    id: &str,
+    retention: &SnapshotRetention,
+) -> Result<ConfigSnapshot, String> {
+    let snapshot = find_snapshot(config_dir, id)?;
+    // Read everything up front; pruning below may delete an old snapshot
+    let mut files = read_tracked_files(&snapshots_dir(config_dir).join(&snapshot.id));
+    if let Some((_, content)) = files.iter_mut().find(|(name, _)| *name == "config.json") {
+        serde_json::from_str::<serde_json::Value>(content)
+            .map_err(|e| format!("Snapshot '{}' has an unreadable config.json: {}", id, e))?;
+        // Taken before the vault was enabled: don't put plaintext keys back
+        if let Ok(config) = serde_json::from_str::<AppConfig>(content) {
+            if let Some(sealed) = seal_for_save(&config)? {
+                *content = serde_json::to_string_pretty(&sealed).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    create_snapshot_in(config_dir, "before-restore", retention)?;
+
+    for name in SNAPSHOT_FILES {
+        let path = config_dir.join(name);
+        match files.iter().find(|(n, _)| n == name) {
+            Some((_, content)) => {
+                write_private_file(&path, content.as_bytes())
+                    .map_err(|e| format!("Failed to restore {}: {}", name, e))?;
+            }
+            None if path.exists() => {
+                std::fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to remove {}: {}", name, e))?;
+            }
+            None => {}
+        }
+    }
+
+    eprintln!("[ProxyPal] Restored config snapshot '{}'", id);
+    Ok(snapshot)
+}
+
+/// Snapshot the live config files before they are overwritten. Failures are
+/// logged rather than returned so a full disk never blocks saving.
+pub fn snapshot_config(reason: &str, retention: &SnapshotRetention) {
+    match create_snapshot_in(&get_proxypal_config_dir(), reason, retention) {
+        Ok(Some(snapshot)) => eprintln!(
+            "[ProxyPal] Created config snapshot '{}' ({})",
+            snapshot.id, reason
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!("[ProxyPal] Failed to snapshot config: {}", e),
+    }
+}
+
+pub fn list_snapshots() -> Vec<ConfigSnapshot> {
+    list_snapshots_in(&get_proxypal_config_dir())
+}
+
+pub fn diff_snapshot(id: &str) -> Result<Vec<SnapshotFileDiff>, String> {
+    diff_snapshot_in(&get_proxypal_config_dir(), id)
+}
+
+pub fn restore_snapshot(
+    id: &str,
+    retention: &SnapshotRetention,
+) -> Result<ConfigSnapshot, String> {
+    restore_snapshot_in(&get_proxypal_config_dir(), id, retention)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::fs;
+
+    #[test]
+    fn unchanged_files_do_not_create_duplicate_snapshots() {
+        let dir = test_dir("snapshots-dedupe");
+        let retention = SnapshotRetention::default();
+
+        assert_eq!(create_snapshot_in(&dir, "save-config", &retention).unwrap(), None);
+
+        fs::write(dir.join("config.json"), "{\"port\": 8317}").unwrap();
+        fs::write(dir.join("proxy-config.yaml"), "port: 8317\n").unwrap();
+        let first = create_snapshot_in(&dir, "save-config", &retention)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.files, vec!["config.json", "proxy-config.yaml"]);
+        assert_eq!(create_snapshot_in(&dir, "save-config", &retention).unwrap(), None);
+
+        fs::write(dir.join("proxy-config.yaml"), "port: 9000\n").unwrap();
+        assert!(create_snapshot_in(&dir, "save-config-yaml", &retention)
+            .unwrap()
+            .is_some());
+
+        let listed = list_snapshots_in(&dir);
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].reason, "save-config-yaml");
+        assert_eq!(listed[1], first);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn retention_limits_count_and_age() {
+        let dir = test_dir("snapshots-retention");
+        let unlimited = SnapshotRetention {
+            max_count: 0,
+            max_age_days: 0,
+        };
+        for port in 0..4 {
+            fs::write(dir.join("config.json"), format!("{{\"port\": {}}}", port)).unwrap();
+            create_snapshot_in(&dir, "save-config", &unlimited).unwrap();
+        }
+        assert_eq!(list_snapshots_in(&dir).len(), 4);
+
+        let by_count = SnapshotRetention {
+            max_count: 3,
+            max_age_days: 0,
+        };
+        let newest = list_snapshots_in(&dir)[0].clone();
+        prune_snapshots_in(&dir, &by_count, newest.created_at);
+        assert_eq!(list_snapshots_in(&dir).len(), 3);
+
+        // Everything is older than a day from this clock, but the newest stays
+        let by_age = SnapshotRetention {
+            max_count: 0,
+            max_age_days: 1,
+        };
+        prune_snapshots_in(&dir, &by_age, newest.created_at + 2 * DAY_MILLIS);
+        assert_eq!(list_snapshots_in(&dir), vec![newest]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn restore_puts_files_back_and_can_be_undone() {
+        let dir = test_dir("snapshots-restore");
+        let retention = SnapshotRetention::default();
+
+        fs::write(dir.join("config.json"), "{\"port\": 8317}\n").unwrap();
+        fs::write(dir.join("proxy-config.yaml"), "routing:\n  strategy: round-robin\n").unwrap();
+        let good = create_snapshot_in(&dir, "save-config", &retention)
+            .unwrap()
+            .unwrap();
+
+        // A broken raw YAML edit plus a new custom file
+        fs::write(dir.join("proxy-config.yaml"), "routing: [oops\n").unwrap();
+        fs::write(dir.join("proxy-config-custom.yaml"), "debug: true\n").unwrap();
+
+        let diffs = diff_snapshot_in(&dir, &good.id).unwrap();
+        let files: Vec<_> = diffs.iter().map(|d| (d.file.as_str(), d.changed)).collect();
+        assert_eq!(
+            files,
+            vec![
+                ("config.json", false),
+                ("proxy-config.yaml", true),
+                ("proxy-config-custom.yaml", true),
+            ]
+        );
+
+        restore_snapshot_in(&dir, &good.id, &retention).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("proxy-config.yaml")).unwrap(),
+            "routing:\n  strategy: round-robin\n"
+        );
+        assert!(!dir.join("proxy-config-custom.yaml").exists());
+
+        let latest = list_snapshots_in(&dir)[0].clone();
+        assert_eq!(latest.reason, "before-restore");
+        restore_snapshot_in(&dir, &latest.id, &retention).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("proxy-config-custom.yaml")).unwrap(),
+            "debug: true\n"
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn restored_config_plans_its_proxy_changes() {
+        use crate::config::{load_config_from_path, save_config_to_path, AppConfig};
+        use crate::proxy::config_yaml::build_proxy_config_yaml;
+        use crate::proxy::reload::plan_config_change;
+
+        let dir = test_dir("snapshots-reload");
+        let retention = SnapshotRetention::default();
+        let config_path = dir.join("config.json");
+
+        let mut snapshotted = AppConfig::default();
+        snapshotted.request_retry = 3;
+        snapshotted.routing_strategy = "fill-first".to_string();
+        save_config_to_path(&config_path, &snapshotted).unwrap();
+        let snapshot = create_snapshot_in(&dir, "save-config", &retention)
+            .unwrap()
+            .unwrap();
+
+        let current = AppConfig::default();
+        save_config_to_path(&config_path, &current).unwrap();
+        let loaded = build_proxy_config_yaml(&current, &dir).unwrap().yaml;
+
+        restore_snapshot_in(&dir, &snapshot.id, &retention).unwrap();
+        let restored = load_config_from_path(&config_path).unwrap();
+        let (plan, _) = plan_config_change(Some(&loaded), &current, &restored, &dir).unwrap();
+        assert_eq!(plan.management, vec!["request-retry"]);
+        assert_eq!(plan.file_reload, vec!["routing"]);
+        assert!(plan.restart.is_empty());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn diffs_do_not_show_secrets() {
+        let dir = test_dir("snapshots-masked");
+        let retention = SnapshotRetention::default();
+
+        fs::write(
+            dir.join("config.json"),
+            "{\n  \"managementKey\": \"old-management-secret\"\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("proxy-config.yaml"), "api-keys:\n- old-local-api-key\n").unwrap();
+        let snapshot = create_snapshot_in(&dir, "save-config", &retention)
+            .unwrap()
+            .unwrap();
+        fs::write(
+            dir.join("config.json"),
+            "{\n  \"managementKey\": \"new-management-secret\"\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("proxy-config.yaml"), "api-keys:\n- new-local-api-key\n").unwrap();
+
+        let diffs = diff_snapshot_in(&dir, &snapshot.id).unwrap();
+        let shown = serde_json::to_string(&diffs).unwrap();
+        for secret in [
+            "old-management-secret",
+            "new-management-secret",
+            "old-local-api-key",
+            "new-local-api-key",
+        ] {
+            assert!(!shown.contains(secret), "{} leaked into the diff", secret);
+        }
+        assert!(diffs.iter().all(|d| d.changed));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn enabling_the_vault_seals_snapshot_configs() {
+        use crate::config::save_config_to_path;
+        use crate::types::VaultMode;
+
+        let dir = test_dir("snapshots-seal");
+        let retention = SnapshotRetention::default();
+        let mut config = AppConfig::default();
+        config.amp_api_key = "amp-secret".to_string();
+        save_config_to_path(&dir.join("config.json"), &config).unwrap();
+        let sealable = create_snapshot_in(&dir, "save-config", &retention)
+            .unwrap()
+            .unwrap();
+        fs::write(dir.join("config.json"), "{\"port\": \"not a number\"}").unwrap();
+        let unreadable = create_snapshot_in(&dir, "save-config", &retention)
+            .unwrap()
+            .unwrap();
+
+        let mut vault =
+            Vault::create(&dir.join("secrets.vault"), VaultMode::Passphrase, "pw").unwrap();
+        assert_eq!(seal_snapshots_in(&dir, &mut vault), 2);
+        assert_eq!(list_snapshots_in(&dir), vec![sealable.clone()]);
+        let sealed =
+            fs::read_to_string(snapshots_dir(&dir).join(&sealable.id).join("config.json"))
+                .unwrap();
+        assert!(!sealed.contains("amp-secret"));
+        assert!(sealed.contains("vault:amp-api-key/"));
+        assert!(!snapshots_dir(&dir).join(&unreadable.id).exists());
+
+        // Nothing left to seal
+        assert_eq!(seal_snapshots_in(&dir, &mut vault), 0);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unknown_snapshot_ids_are_rejected() {
+        let dir = test_dir("snapshots-unknown");
+        assert!(diff_snapshot_in(&dir, "../config.json").is_err());
+        assert!(restore_snapshot_in(&dir, "missing", &SnapshotRetention::default()).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
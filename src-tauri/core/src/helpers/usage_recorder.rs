@@ -0,0 +1,289 @@
+//! Single writer for each usage store.
+//!
+//! The log watcher, the proxy usage sync and the UI all produce usage
+//! updates. Instead of each of them writing usage.db on its own, they send
+//! events to the recorder for the store's directory. Its thread owns the only
+//! writing connection and applies whatever has queued up in one transaction,
+//! so a batch lands whole or not at all, and a read-modify-write of the
+//! totals can't interleave with another one.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::helpers::history::{TokenDetail, UsageStore};
+use crate::types::{Aggregate, RequestLog};
+
+/// How long the recorder waits for more events before committing a batch
+const BATCH_WINDOW: Duration = Duration::from_millis(200);
+/// Events applied in one transaction at most
+const MAX_BATCH: usize = 256;
+
+/// A change to the stored totals, series and breakdowns
+pub type AggregateUpdate = Box<dyn FnOnce(&mut Aggregate) + Send>;
+
+enum UsageEvent {
+    Request(RequestLog),
+    TokenDetails(Vec<TokenDetail>),
+    UpdateAggregate(AggregateUpdate),
+    ClearRequests,
+    Prune(u32),
+    /// Answered with how many requests were repriced, once committed
+    RecomputeCosts(mpsc::Sender<Result<usize, String>>),
+    /// Answered once every earlier event is committed
+    Flush(mpsc::Sender<Result<(), String>>),
+}
+
+struct RecorderHandle {
+    sender: mpsc::Sender<UsageEvent>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+lazy_static::lazy_static! {
+    static ref RECORDERS: Mutex<HashMap<PathBuf, RecorderHandle>> = Mutex::new(HashMap::new());
+}
+
+/// Sends usage events to the writer of one directory's store
+#[derive(Clone)]
+pub struct UsageRecorder {
+    dir: PathBuf,
+    sender: mpsc::Sender<UsageEvent>,
+}
+
+/// The recorder for the store in `dir`, started on first use
+pub fn recorder(dir: &Path) -> Result<UsageRecorder, String> {
+    let mut recorders = RECORDERS.lock().unwrap();
+    if let Some(handle) = recorders.get(dir) {
+        if !handle.thread.is_finished() {
+            return Ok(UsageRecorder {
+                dir: dir.to_path_buf(),
+                sender: handle.sender.clone(),
+            });
+        }
+    }
+
+    // Opened here so a store that can't be opened is reported to the caller
+    let store = UsageStore::open_in(dir)?;
+    let (sender, receiver) = mpsc::channel();
+    let label = dir.display().to_string();
+    let thread = std::thread::Builder::new()
+        .name("usage-recorder".to_string())
+        .spawn(move || run(store, receiver, &label))
+        .map_err(|e| format!("Failed to start the usage recorder: {}", e))?;
+    recorders.insert(
+        dir.to_path_buf(),
+        RecorderHandle {
+            sender: sender.clone(),
+            thread,
+        },
+    );
+    Ok(UsageRecorder {
+        dir: dir.to_path_buf(),
+        sender,
+    })
+}
+
+/// A read-only view of the store in `dir`
+pub fn reader(dir: &Path) -> Result<UsageStore, String> {
+    // The recorder creates the schema and imports legacy files first
+    recorder(dir)?;
+    UsageStore::open_reader(dir)
+}
+
+/// Commit what is queued for `dir` and stop its recorder, e.g. before the
+/// directory is removed
+pub fn close(dir: &Path) {
+    let handle = RECORDERS.lock().unwrap().remove(dir);
+    if let Some(handle) = handle {
+        drop(handle.sender);
+        let _ = handle.thread.join();
+    }
+}
+
+/// Commit every recorder's queued events; called before the app exits
+pub fn flush_all() {
+    let senders: Vec<_> = RECORDERS
+        .lock()
+        .unwrap()
+        .values()
+        .map(|handle| handle.sender.clone())
+        .collect();
+    for sender in senders {
+        let (reply, done) = mpsc::channel();
+        if sender.send(UsageEvent::Flush(reply)).is_ok() {
+            let _ = done.recv_timeout(Duration::from_secs(5));
+        }
+    }
+}
+
+impl UsageRecorder {
+    fn send(&self, event: UsageEvent) -> Result<(), String> {
+        self.sender.send(event).map_err(|_| {
+            // The thread is gone; the next recorder() call starts a new one
+            format!("Usage recorder for {} has stopped", self.dir.display())
+        })
+    }
+
+    /// Queue a request; one that is already stored is skipped
+    pub fn record(&self, request: RequestLog) -> Result<(), String> {
+        self.send(UsageEvent::Request(request))
+    }
+
+    /// Queue the proxy's per-request token counts, to be matched with the
+    /// logged requests
+    pub fn attach_tokens(&self, details: Vec<TokenDetail>) -> Result<(), String> {
+        self.send(UsageEvent::TokenDetails(details))
+    }
+
+    /// Queue a change to the stored aggregate. `update` sees the aggregate
+    /// with every earlier event applied.
+    pub fn update_aggregate(
+        &self,
+        update: impl FnOnce(&mut Aggregate) + Send + 'static,
+    ) -> Result<(), String> {
+        self.send(UsageEvent::UpdateAggregate(Box::new(update)))
+    }
+
+    /// Queue forgetting individual requests; totals and rollups stay
+    pub fn clear_requests(&self) -> Result<(), String> {
+        self.send(UsageEvent::ClearRequests)
+    }
+
+    /// Queue dropping requests older than `retention_days` (0 keeps all)
+    pub fn prune(&self, retention_days: u32) -> Result<(), String> {
+        self.send(UsageEvent::Prune(retention_days))
+    }
+
+    /// Price the stored requests again with the current pricing and wait
+    /// for it, returning how many were repriced
+    pub fn recompute_costs(&self) -> Result<usize, String> {
+        let (reply, done) = mpsc::channel();
+        self.send(UsageEvent::RecomputeCosts(reply))?;
+        done.recv()
+            .map_err(|_| format!("Usage recorder for {} has stopped", self.dir.display()))?
+    }
+
+    /// Wait until everything queued so far is committed, returning the
+    /// error of the batch it went in, if any
+    pub fn flush(&self) -> Result<(), String> {
+        let (reply, done) = mpsc::channel();
+        self.send(UsageEvent::Flush(reply))?;
+        done.recv()
+            .map_err(|_| format!("Usage recorder for {} has stopped", self.dir.display()))?
+    }
+}
+
+/// The recorder thread: collect a batch, apply it in one transaction, repeat
+/// until every sender is gone
+fn run(mut store: UsageStore, receiver: mpsc::Receiver<UsageEvent>, label: &str) {
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + BATCH_WINDOW;
+        while batch.len() < MAX_BATCH
+            && !matches!(
+                batch.last(),
+                Some(UsageEvent::Flush(_) | UsageEvent::RecomputeCosts(_))
+            )
+        {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(wait) {
+                Ok(event) => batch.push(event),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        let mut replies = Vec::new();
+        let mut repriced = Vec::new();
+        let result = store.in_transaction(|store| {
+            for event in batch {
+                let applied = match event {
+                    UsageEvent::Request(request) => store.record_request(&request).map(|_| ()),
+                    UsageEvent::TokenDetails(details) => {
+                        store.attach_token_details(&details).map(|_| ())
+                    }
+                    UsageEvent::UpdateAggregate(update) => {
+                        store.load_aggregate().and_then(|mut agg| {
+                            update(&mut agg);
+                            store.save_aggregate(&agg)
+                        })
+                    }
+                    UsageEvent::ClearRequests => store.clear_requests(),
+                    UsageEvent::Prune(retention_days) => {
+                        store.prune(retention_days).map(|removed| {
+                            if removed > 0 {
+                                println!(
+                                    "[UsageRecorder] Pruned {} requests older than {} days",
+                                    removed, retention_days
+                                );
+                            }
+                        })
+                    }
+                    UsageEvent::RecomputeCosts(reply) => {
+                        let count = store.recompute_costs();
+                        let applied = count.as_ref().map(|_| ()).map_err(|e| e.clone());
+                        repriced.push((reply, count));
+                        applied
+                    }
+                    UsageEvent::Flush(reply) => {
+                        replies.push(reply);
+                        Ok(())
+                    }
+                };
+                // A bad event is undone by its savepoint; the rest still land
+                if let Err(e) = applied {
+                    eprintln!("[UsageRecorder] {}: {}", label, e);
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = &result {
+            eprintln!("[UsageRecorder] {}: batch not saved: {}", label, e);
+        }
+        for reply in replies {
+            let _ = reply.send(result.clone());
+        }
+        for (reply, count) in repriced {
+            let _ = reply.send(result.clone().and(count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{request, test_dir};
+    use std::fs;
+
+    #[test]
+    fn concurrent_producers_do_not_lose_counts() {
+        let dir = test_dir("usage-recorder");
+        let producers: Vec<_> = (0..8)
+            .map(|t| {
+                let recorder = recorder(&dir).unwrap();
+                std::thread::spawn(move || {
+                    for i in 0..50 {
+                        recorder
+                            .record(request(&format!("{}-{}", t, i), 1_700_000_000_000, 200))
+                            .unwrap();
+                        recorder
+                            .update_aggregate(|agg| agg.total_tokens_cached += 1)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        recorder(&dir).unwrap().flush().unwrap();
+
+        let agg = reader(&dir).unwrap().load_aggregate().unwrap();
+        assert_eq!(agg.total_requests, 400);
+        assert_eq!(agg.total_tokens_cached, 400);
+
+        close(&dir);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
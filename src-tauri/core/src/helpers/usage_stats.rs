@@ -0,0 +1,292 @@
+//! Usage statistics for the dashboard and `proxypal usage`: the usage
+//! store's totals, with per-model tokens from the running proxy.
+
+use crate::helpers::usage_recorder;
+use crate::helpers::usage_sync::sync_usage_from_proxy_blocking;
+use crate::types::{ModelUsage, ProviderUsage, TimeSeriesPoint, UsageStats};
+
+// Live usage data from Go backend
+#[allow(dead_code)] // Fields deserialized from JSON, used indirectly
+struct LiveUsageData {
+    total_tokens: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    cached_tokens: u64,
+    model_tokens: std::collections::HashMap<String, u64>,
+    model_token_breakdown: std::collections::HashMap<String, (u64, u64, u64)>, // (input, output, cached)
+    tokens_by_hour: Vec<TimeSeriesPoint>,
+}
+
+// Fetch live usage stats from Go backend (blocking version for sync context)
+fn fetch_live_usage_stats_blocking(dir: &std::path::Path, port: u16) -> Option<LiveUsageData> {
+    let url = format!("http://127.0.0.1:{}/v0/management/usage", port);
+    let client = reqwest::blocking::Client::new();
+
+    let response = client
+        .get(&url)
+        .header("X-Management-Key", &crate::get_instance_management_key(dir).ok()?)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .ok()?;
+
+    let json: serde_json::Value = response.json().ok()?;
+
+    // Parse the response structure:
+    // { "usage": { "total_tokens": N, "apis": { "api-name": { "models": { "model": { "total_tokens": N, "details": [...] } } } } } }
+    let usage = json.get("usage")?;
+
+    let total_tokens = usage.get("total_tokens")?.as_u64().unwrap_or(0);
+
+    // Extract input/output/cached tokens from the detailed data
+    let mut input_tokens = 0u64;
+    let mut output_tokens = 0u64;
+    let mut cached_tokens = 0u64;
+    let mut model_tokens: std::collections::HashMap<String, u64> =
+        std::collections::HashMap::new();
+    let mut model_token_breakdown: std::collections::HashMap<String, (u64, u64, u64)> =
+        std::collections::HashMap::new();
+
+    if let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) {
+        for (_api_name, api_data) in apis {
+            if let Some(models) = api_data.get("models").and_then(|v| v.as_object()) {
+                for (model_name, model_data) in models {
+                    let model_total = model_data
+                        .get("total_tokens")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0);
+                    *model_tokens.entry(model_name.clone()).or_insert(0) += model_total;
+
+                    // Sum up input/output/cached from details per model
+                    let mut model_input = 0u64;
+                    let mut model_output = 0u64;
+                    let mut model_cached = 0u64;
+
+                    if let Some(details) = model_data.get("details").and_then(|v| v.as_array()) {
+                        for detail in details {
+                            if let Some(tokens) = detail.get("tokens") {
+                                let inp = tokens
+                                    .get("input_tokens")
+                                    .and_then(|v| v.as_u64())
+                                    .unwrap_or(0);
+                                let out = tokens
+                                    .get("output_tokens")
+                                    .and_then(|v| v.as_u64())
+                                    .unwrap_or(0);
+                                let cached = tokens
+                                    .get("cached_tokens")
+                                    .and_then(|v| v.as_u64())
+                                    .unwrap_or(0);
+
+                                input_tokens += inp;
+                                output_tokens += out;
+                                cached_tokens += cached;
+
+                                model_input += inp;
+                                model_output += out;
+                                model_cached += cached;
+                            }
+                        }
+                    }
+
+                    // Store per-model breakdown
+                    let entry = model_token_breakdown
+                        .entry(model_name.clone())
+                        .or_insert((0, 0, 0));
+                    entry.0 += model_input;
+                    entry.1 += model_output;
+                    entry.2 += model_cached;
+                }
+            }
+        }
+    }
+
+    Some(LiveUsageData {
+        total_tokens,
+        input_tokens,
+        output_tokens,
+        cached_tokens,
+        model_tokens,
+        model_token_breakdown,
+        tokens_by_hour: {
+            // Parse tokens_by_hour from Go backend: { "HH": value, ... }
+            let mut result = Vec::new();
+            if let Some(tbh) = usage
+                .get("tokens_by_hour")
+                .and_then(|v| v.as_object())
+            {
+                let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+                for (hour, value) in tbh {
+                    if let Some(v) = value.as_u64() {
+                        // Convert "HH" format to "YYYY-MM-DDTHH" format
+                        let label = format!("{}T{}", today, hour);
+                        result.push(TimeSeriesPoint { label, value: v });
+                    }
+                }
+                result.sort_by(|a, b| a.label.cmp(&b.label));
+            }
+            result
+        },
+    })
+}
+
+/// Usage statistics from the usage store in `dir`, topped up from the proxy
+/// on `port` when it is running
+pub fn compute_usage_stats(
+    dir: &std::path::Path,
+    is_running: bool,
+    port: u16,
+) -> Result<UsageStats, String> {
+
+    // Sync from proxy first if running (this updates aggregate with latest data from CLIProxyAPI)
+    if is_running {
+        sync_usage_from_proxy_blocking(dir, port);
+    }
+
+    // Now query the updated totals, the last 14 days and the last 168 hours
+    let agg = usage_recorder::reader(dir)?.load_recent_aggregate(14, 168)?;
+
+    // Try to fetch live data from Go backend if proxy is running
+    // Live data provides per-model breakdowns for the current session
+    let live_data = if is_running {
+        fetch_live_usage_stats_blocking(dir, port)
+    } else {
+        None
+    };
+
+    // Use aggregate as the source of truth for all-time totals (preserved across restarts).
+    // Live data only supplements per-model breakdowns for the current session.
+    let total_tokens = agg.total_tokens_in + agg.total_tokens_out;
+    let input_tokens = agg.total_tokens_in;
+    let output_tokens = agg.total_tokens_out;
+    let cached_tokens = agg.total_tokens_cached;
+    let (model_tokens, model_token_breakdown) = if let Some(ref live) = live_data {
+        (
+            live.model_tokens.clone(),
+            live.model_token_breakdown.clone(),
+        )
+    } else {
+        // Build model token breakdown from aggregate stats
+        let agg_model_tokens: std::collections::HashMap<String, u64> = agg
+            .model_stats
+            .iter()
+            .map(|(k, v)| (k.clone(), v.tokens))
+            .collect();
+        let agg_model_breakdown: std::collections::HashMap<String, (u64, u64, u64)> = agg
+            .model_stats
+            .iter()
+            .map(|(k, v)| (k.clone(), (v.input_tokens, v.output_tokens, v.cached_tokens)))
+            .collect();
+        (agg_model_tokens, agg_model_breakdown)
+    };
+
+    // If no data yet, return defaults
+    if agg.total_requests == 0 {
+        return Ok(UsageStats::default());
+    }
+
+    // Use aggregate as primary source of truth for all-time stats
+    let total_requests = agg.total_requests;
+    let success_count = agg.total_success_count;
+    let failure_count = agg.total_failure_count;
+
+    // Calculate today's stats from aggregate time-series
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let requests_today = agg
+        .requests_by_day
+        .iter()
+        .find(|p| p.label == today)
+        .map(|p| p.value)
+        .unwrap_or(0);
+
+    // Get today's tokens from live data or aggregate
+    let tokens_today = if let Some(ref live) = live_data {
+        live.total_tokens // Use live total as "today" since it's current session
+    } else {
+        agg.tokens_by_day
+            .iter()
+            .find(|p| p.label == today)
+            .map(|p| p.value)
+            .unwrap_or(0)
+    };
+
+    // Build model stats - merge aggregate with live token data
+    let mut models: Vec<ModelUsage> = agg
+        .model_stats
+        .iter()
+        .filter(|(model, _)| *model != "unknown" && !model.is_empty())
+        .map(|(model, stats)| {
+            let tokens = model_tokens.get(model).copied().unwrap_or(stats.tokens);
+            let (input, output, cached) = model_token_breakdown
+                .get(model)
+                .copied()
+                .unwrap_or((0, 0, 0));
+            ModelUsage {
+                model: model.clone(),
+                requests: stats.requests,
+                tokens,
+                input_tokens: input,
+                output_tokens: output,
+                cached_tokens: cached,
+            }
+        })
+        .collect();
+
+    // Add any models from live data that aren't in aggregate
+    for (model, tokens) in &model_tokens {
+        if !models.iter().any(|m| &m.model == model) && model != "unknown" && !model.is_empty() {
+            let (input, output, cached) = model_token_breakdown
+                .get(model)
+                .copied()
+                .unwrap_or((0, 0, 0));
+            models.push(ModelUsage {
+                model: model.clone(),
+                requests: 0, // Will be updated from aggregate
+                tokens: *tokens,
+                input_tokens: input,
+                output_tokens: output,
+                cached_tokens: cached,
+            });
+        }
+    }
+    models.sort_by_key(|m| std::cmp::Reverse(m.requests));
+
+    // Build provider stats from aggregate
+    let mut providers: Vec<ProviderUsage> = agg
+        .provider_stats
+        .iter()
+        .filter(|(provider, _)| *provider != "unknown" && !provider.is_empty())
+        .map(|(provider, stats)| ProviderUsage {
+            provider: provider.clone(),
+            requests: stats.requests,
+            tokens: stats.tokens,
+        })
+        .collect();
+    providers.sort_by_key(|p| std::cmp::Reverse(p.requests));
+
+    let requests_by_day = agg.requests_by_day.clone();
+    let tokens_by_day = agg.tokens_by_day.clone();
+    // Hourly data feeds the Activity Patterns heatmap
+    let requests_by_hour = agg.requests_by_hour.clone();
+    let tokens_by_hour = match &live_data {
+        Some(live) if agg.tokens_by_hour.is_empty() => live.tokens_by_hour.clone(),
+        _ => agg.tokens_by_hour.clone(),
+    };
+
+    Ok(UsageStats {
+        total_requests,
+        success_count,
+        failure_count,
+        total_tokens,
+        input_tokens,
+        output_tokens,
+        cached_tokens,
+        requests_today,
+        tokens_today,
+        models,
+        providers,
+        requests_by_day,
+        tokens_by_day,
+        requests_by_hour,
+        tokens_by_hour,
+    })
+}
@@ -0,0 +1,389 @@
+//! Syncing the proxy's management usage report into a usage store.
+//!
+//! Tokens and cost are only ever added per request, when a usage entry is
+//! matched with the request the log watcher recorded (see
+//! `UsageStore::attach_token_details`). The report's own token sums cover
+//! entries whose log line hasn't arrived yet, so they are not merged: the
+//! entry would be counted again once it is matched. Request counts, which
+//! the logs can miss, are taken from the report when they are higher.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::helpers::history::TokenDetail;
+use crate::helpers::usage_recorder;
+use crate::types::TimeSeriesPoint;
+use crate::utils::detect_provider_from_usage_source;
+
+/// The per-request entries of a management usage report, for matching with
+/// the requests the log watcher recorded
+pub fn token_details(usage: &Value) -> Vec<TokenDetail> {
+    let mut details = Vec::new();
+    let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) else {
+        return details;
+    };
+    for api_data in apis.values() {
+        let Some(models) = api_data.get("models").and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for (model_name, model_data) in models {
+            let Some(entries) = model_data.get("details").and_then(|v| v.as_array()) else {
+                continue;
+            };
+            for entry in entries {
+                let timestamp = match entry.get("timestamp") {
+                    Some(Value::String(text)) => chrono::DateTime::parse_from_rfc3339(text)
+                        .map(|t| t.timestamp_millis() as u64)
+                        .ok(),
+                    Some(value) => value.as_u64(),
+                    None => None,
+                };
+                let (Some(timestamp), Some(tokens)) = (timestamp, entry.get("tokens")) else {
+                    continue;
+                };
+                let count =
+                    |key: &str| tokens.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+                let request_id = entry
+                    .get("request_id")
+                    .and_then(|v| v.as_str())
+                    .filter(|id| !id.is_empty())
+                    .map(|id| id.to_string());
+                let source = entry
+                    .get("source")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                // Entries have no ID of their own; these fields tell them apart
+                let key = format!(
+                    "{}|{}|{}|{}|{}",
+                    entry.get("timestamp").unwrap_or(&Value::Null),
+                    model_name,
+                    source,
+                    entry.get("auth_index").unwrap_or(&Value::Null),
+                    tokens
+                );
+                details.push(TokenDetail {
+                    key,
+                    request_id,
+                    timestamp,
+                    model: model_name.clone(),
+                    provider: detect_provider_from_usage_source(source),
+                    tokens_in: count("input_tokens"),
+                    tokens_out: count("output_tokens"),
+                    tokens_cached: count("cached_tokens"),
+                });
+            }
+        }
+    }
+    details
+}
+
+/// A `{ label: count }` series of the report, with "HH" hours made into
+/// "YYYY-MM-DDTHH" labels for today
+fn series(usage: &Value, key: &str) -> Vec<TimeSeriesPoint> {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let mut points: Vec<TimeSeriesPoint> = usage
+        .get(key)
+        .and_then(|v| v.as_object())
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|(label, value)| {
+                    let label = if label.len() == 2 {
+                        format!("{}T{}", today, label)
+                    } else {
+                        label.clone()
+                    };
+                    value.as_u64().map(|value| TimeSeriesPoint { label, value })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    points.sort_by(|a, b| a.label.cmp(&b.label));
+    points
+}
+
+/// Requests per model in the report
+fn model_requests(usage: &Value) -> HashMap<String, u64> {
+    let mut requests = HashMap::new();
+    let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) else {
+        return requests;
+    };
+    for api_data in apis.values() {
+        let Some(models) = api_data.get("models").and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for (model_name, model_data) in models {
+            *requests.entry(model_name.clone()).or_insert(0) += model_data
+                .get("total_requests")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+        }
+    }
+    requests
+}
+
+/// Merge a management usage report (the `usage` field of the response) into
+/// the store in `dir`, and wait until it is written
+pub fn apply_proxy_usage(dir: &Path, usage: &Value) -> Result<(), String> {
+    let requests_by_day = series(usage, "requests_by_day");
+    let requests_by_hour = series(usage, "requests_by_hour");
+    let model_requests = model_requests(usage);
+
+    let recorder = usage_recorder::recorder(dir)?;
+    recorder.attach_tokens(token_details(usage))?;
+    recorder.update_aggregate(move |agg| {
+        // Either side can miss requests, so the higher count wins
+        for (points, synced) in [
+            (&mut agg.requests_by_day, &requests_by_day),
+            (&mut agg.requests_by_hour, &requests_by_hour),
+        ] {
+            for point in synced {
+                match points.iter_mut().find(|p| p.label == point.label) {
+                    Some(existing) => existing.value = existing.value.max(point.value),
+                    None => points.push(point.clone()),
+                }
+            }
+            points.sort_by(|a, b| a.label.cmp(&b.label));
+        }
+
+        let mut synced_requests = 0;
+        for (model_name, requests) in model_requests {
+            synced_requests += requests;
+            let stats = agg.model_stats.entry(model_name).or_default();
+            stats.requests = stats.requests.max(requests);
+        }
+        let synced_by_day: u64 = requests_by_day.iter().map(|p| p.value).sum();
+        agg.total_requests = agg.total_requests.max(synced_requests).max(synced_by_day);
+    })?;
+    recorder.flush()
+}
+
+/// Fetch the management usage report from the proxy on `port` and apply
+/// it to the store in `dir`, logging what goes wrong
+pub fn sync_usage_from_proxy_blocking(dir: &std::path::Path, port: u16) {
+    let url = format!("http://127.0.0.1:{}/v0/management/usage", port);
+    let client = reqwest::blocking::Client::new();
+    let management_key = match crate::get_instance_management_key(dir) {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("[usage] sync_usage_from_proxy_blocking: {}", e);
+            return;
+        }
+    };
+
+    let response = match client
+        .get(&url)
+        .header("X-Management-Key", &management_key)
+        .timeout(std::time::Duration::from_secs(5))
+        .send()
+    {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("[usage] sync_usage_from_proxy_blocking: request failed: {}", e);
+            return;
+        }
+    };
+
+    if !response.status().is_success() {
+        eprintln!(
+            "[usage] sync_usage_from_proxy_blocking: HTTP {}",
+            response.status()
+        );
+        return;
+    }
+
+    let body: serde_json::Value = match response.json() {
+        Ok(j) => j,
+        Err(e) => {
+            eprintln!("[usage] sync_usage_from_proxy_blocking: parse error: {}", e);
+            return;
+        }
+    };
+
+    let usage = match body.get("usage") {
+        Some(u) => u,
+        None => {
+            eprintln!("[usage] sync_usage_from_proxy_blocking: missing 'usage' field");
+            return;
+        }
+    };
+
+    if let Err(e) = apply_proxy_usage(dir, usage) {
+        eprintln!("[usage] sync_usage_from_proxy_blocking: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{request, test_dir};
+    use crate::types::RequestLog;
+
+    /// A request the log watcher recorded before its usage entry was matched
+    fn logged(request_id: &str, timestamp: u64) -> RequestLog {
+        RequestLog {
+            tokens_in: None,
+            tokens_out: None,
+            request_id: Some(request_id.to_string()),
+            ..request(request_id, timestamp, 200)
+        }
+    }
+
+    #[test]
+    fn entries_logged_late_are_counted_once() {
+        let dir = test_dir("usage-sync");
+        let now = chrono::Local::now();
+        let start = now.timestamp_millis() as u64 - 10_000;
+        let entry = |request_id: &str, at: u64| {
+            serde_json::json!({
+                "timestamp": chrono::DateTime::from_timestamp_millis(at as i64).unwrap().to_rfc3339(),
+                "request_id": request_id,
+                "tokens": { "input_tokens": 1_000, "output_tokens": 500 },
+            })
+        };
+        // The proxy's own sums count both requests
+        let usage = serde_json::json!({
+            "apis": { "/v1/messages": { "models": { "claude-sonnet-4": {
+                "total_requests": 2,
+                "total_tokens": 3_000,
+                "details": [entry("aaaa1111", start), entry("bbbb2222", start + 2_000)],
+            } } } },
+            "tokens_by_day": { now.format("%Y-%m-%d").to_string(): 3_000 },
+            "requests_by_day": { now.format("%Y-%m-%d").to_string(): 2 },
+        });
+
+        let recorder = usage_recorder::recorder(&dir).unwrap();
+        recorder.record(logged("aaaa1111", start + 1_000)).unwrap();
+        apply_proxy_usage(&dir, &usage).unwrap();
+        let first = usage_recorder::reader(&dir)
+            .unwrap()
+            .load_aggregate()
+            .unwrap();
+        assert_eq!(first.total_tokens_in, 1_000);
+
+        // The second request's log line only arrives after the first sync
+        recorder.record(logged("bbbb2222", start + 3_000)).unwrap();
+        apply_proxy_usage(&dir, &usage).unwrap();
+        let store = usage_recorder::reader(&dir).unwrap();
+        let agg = store.load_aggregate().unwrap();
+        assert_eq!(agg.total_tokens_in, 2_000);
+        assert_eq!(agg.total_tokens_out, 1_000);
+        assert_eq!(agg.tokens_by_day.last().unwrap().value, 3_000);
+        assert_eq!(agg.model_stats["claude-sonnet-4"].input_tokens, 2_000);
+        let request_costs: f64 = store
+            .recent_requests(10)
+            .unwrap()
+            .iter()
+            .filter_map(|r| r.cost_usd)
+            .sum();
+        assert!(request_costs > 0.0);
+        assert!((agg.total_cost_usd - request_costs).abs() < 1e-9);
+
+        drop((store, recorder));
+        usage_recorder::close(&dir);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn report_counts_only_raise_logged_counts() {
+        let dir = test_dir("usage-sync-counts");
+        let now = chrono::Local::now();
+        let start = now.timestamp_millis() as u64 - 10_000;
+        let report = |requests: u64| {
+            serde_json::json!({
+                "apis": { "/v1/messages": { "models": { "claude-sonnet-4": {
+                    "total_requests": requests,
+                } } } },
+                "requests_by_day": { now.format("%Y-%m-%d").to_string(): requests },
+            })
+        };
+
+        let recorder = usage_recorder::recorder(&dir).unwrap();
+        recorder.record(logged("aaaa1111", start)).unwrap();
+        recorder.record(logged("bbbb2222", start + 1_000)).unwrap();
+        recorder
+            .record(RequestLog {
+                status: 500,
+                ..logged("cccc3333", start + 2_000)
+            })
+            .unwrap();
+
+        // A report that missed a request doesn't lower what the logs counted
+        apply_proxy_usage(&dir, &report(2)).unwrap();
+        let reader = usage_recorder::reader(&dir).unwrap();
+        let agg = reader.load_aggregate().unwrap();
+        assert_eq!(agg.model_stats["claude-sonnet-4"].requests, 3);
+        assert_eq!(agg.model_stats["claude-sonnet-4"].success_count, 2);
+        assert_eq!(agg.requests_by_day.last().unwrap().value, 3);
+        assert_eq!(agg.total_requests, 3);
+
+        // One that saw more raises the counts, but not the successes
+        apply_proxy_usage(&dir, &report(5)).unwrap();
+        let agg = reader.load_aggregate().unwrap();
+        assert_eq!(agg.model_stats["claude-sonnet-4"].requests, 5);
+        assert_eq!(agg.model_stats["claude-sonnet-4"].success_count, 2);
+        assert_eq!(agg.requests_by_day.last().unwrap().value, 5);
+        assert_eq!(agg.total_requests, 5);
+        assert_eq!(agg.total_success_count, 2);
+
+        drop((reader, recorder));
+        usage_recorder::close(&dir);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn copilot_entries_are_attributed_and_priced_free() {
+        let dir = test_dir("usage-sync-copilot");
+        let start = chrono::Local::now().timestamp_millis() as u64 - 10_000;
+        let entry = |request_id: &str, source: &str| {
+            serde_json::json!({
+                "timestamp": start,
+                "request_id": request_id,
+                "source": source,
+                "tokens": { "input_tokens": 100_000, "output_tokens": 10_000 },
+            })
+        };
+        let usage = serde_json::json!({
+            "apis": { "sk-client": { "models": { "gpt-4o": {
+                "total_requests": 2,
+                "details": [
+                    entry("aaaa1111", crate::proxy::config_yaml::COPILOT_API_KEY),
+                    entry("bbbb2222", "sk-openai-account"),
+                ],
+            } } } },
+        });
+        // Both log lines look like OpenAI traffic
+        let gpt = |request_id: &str, at: u64| RequestLog {
+            model: "gpt-4o".to_string(),
+            provider: crate::utils::detect_provider_from_model("gpt-4o"),
+            path: "/v1/chat/completions".to_string(),
+            ..logged(request_id, at)
+        };
+
+        let recorder = usage_recorder::recorder(&dir).unwrap();
+        recorder.record(gpt("aaaa1111", start + 1_000)).unwrap();
+        recorder.record(gpt("bbbb2222", start + 2_000)).unwrap();
+        apply_proxy_usage(&dir, &usage).unwrap();
+
+        let store = usage_recorder::reader(&dir).unwrap();
+        let requests = store.recent_requests(10).unwrap();
+        let copilot = requests.iter().find(|r| r.id == "aaaa1111").unwrap();
+        let openai = requests.iter().find(|r| r.id == "bbbb2222").unwrap();
+        assert_eq!(copilot.provider, "copilot");
+        assert_eq!(copilot.cost_usd, Some(0.0));
+        assert_eq!(openai.provider, "openai");
+        assert!(openai.cost_usd.is_some_and(|cost| cost > 0.0));
+
+        let agg = store.load_aggregate().unwrap();
+        assert_eq!(agg.provider_stats["copilot"].requests, 1);
+        assert_eq!(agg.provider_stats["copilot"].input_tokens, 100_000);
+        assert_eq!(agg.provider_stats["openai"].requests, 1);
+        assert!((agg.total_cost_usd - openai.cost_usd.unwrap()).abs() < 1e-9);
+
+        drop((store, recorder));
+        usage_recorder::close(&dir);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,57 @@
+//! ProxyPal without the GUI.
+//!
+//! Config generation, sidecar ownership and supervision, usage tracking,
+//! auth scanning and agent setup, shared by the Tauri app and the `proxypal`
+//! CLI. Nothing here depends on Tauri, so the CLI builds and runs on a
+//! machine without GTK or WebKit.
+
+// Tests build configs as `default()` plus the fields they care about
+#![cfg_attr(test, allow(clippy::field_reassign_with_default))]
+
+pub mod cli;
+pub mod config;
+pub mod headless;
+pub mod helpers;
+pub mod proxy;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod types;
+pub mod utils;
+
+use std::path::Path;
+
+use crate::config::load_config;
+
+/// Get management key from config (used for internal proxy API calls).
+/// Fails while the key is in a locked vault; its reference isn't a key.
+pub fn get_management_key() -> Result<String, String> {
+    helpers::secrets::resolve_secret(&load_config().management_key)
+}
+
+/// `get_management_key` for the proxy instance whose config.json is in `dir`.
+/// Each instance writes its own key into its proxy-config.yaml; the default
+/// instance's dir holds the app's config.json.
+pub fn get_instance_management_key(dir: &Path) -> Result<String, String> {
+    let config = config::load_config_from_path(&dir.join("config.json")).map_err(|e| e.message)?;
+    helpers::secrets::resolve_secret(&config.management_key)
+}
+
+// GPT-5 base models that support reasoning level suffixes (single source of truth)
+// Used by both backend (proxy config generation) and frontend (Settings UI)
+pub const GPT5_BASE_MODELS: &[&str] = &[
+    "gpt-5",
+    "gpt-5-mini",
+    "gpt-5-codex",
+    "gpt-5-codex-mini",
+    "gpt-5.1",
+    "gpt-5.1-codex",
+    "gpt-5.1-codex-mini",
+    "gpt-5.1-codex-max",
+    "gpt-5.2",
+    "gpt-5.2-codex",
+    "gpt-5.3-codex",
+    "gpt-5.3-codex-spark",
+];
+
+// GPT-5 reasoning level suffixes
+pub const GPT5_REASONING_SUFFIXES: &[&str] = &["minimal", "low", "medium", "high", "xhigh"];
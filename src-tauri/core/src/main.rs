@@ -0,0 +1,6 @@
+//! `proxypal`: ProxyPal from the command line, without the GUI.
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(proxypal_core::cli::main(args));
+}
@@ -0,0 +1,520 @@
+//! CLIProxyAPI binaries installed at runtime.
+//!
+//! The bundled sidecar is fixed at build time. Other versions can be installed
+//! from a release archive into `sidecars/<version>/`, each with an
+//! `install.json` describing where it came from. An archive is only unpacked
+//! once its SHA-256 matches the release's checksum manifest (goreleaser's
+//! `checksums.txt`: `<sha256>  <file name>` per line). The manifest is
+//! usually fetched from the same place as the archive, so the check catches
+//! corrupt or truncated downloads, not a tampered release.
+//! `sidecars/active.json` pins the version `start_proxy` launches; without
+//! it the bundled sidecar runs.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::proxy::process::command;
+use crate::types::SidecarBinary;
+
+const INSTALL_FILE: &str = "install.json";
+const ACTIVE_FILE: &str = "active.json";
+/// Manifest published next to the archives of a release
+pub const MANIFEST_NAME: &str = "checksums.txt";
+
+/// Executable names used by CLIProxyAPI and CLIProxyAPIPlus archives
+const ARCHIVE_BINARY_NAMES: &[&str] = &[
+    "cli-proxy-api-plus",
+    "CLIProxyAPIPlus",
+    "CLIProxyAPI",
+    "cli-proxy-api",
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ActiveFile {
+    version: Option<String>,
+}
+
+/// File name of an installed binary
+fn binary_file_name() -> &'static str {
+    if cfg!(windows) {
+        "cli-proxy-api.exe"
+    } else {
+        "cli-proxy-api"
+    }
+}
+
+/// Versions become directory names, so keep them to plain characters
+fn validate_version(version: &str) -> Result<(), String> {
+    let valid = !version.is_empty()
+        && !version.starts_with('.')
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid sidecar version '{}'", version))
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Checksums by file name from a `checksums.txt`
+pub fn parse_manifest(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let hash = fields.next()?;
+            // sha256sum marks binary mode with a leading '*'
+            let name = fields.next()?.trim_start_matches('*');
+            let is_hash = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+            is_hash.then(|| (name.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+/// The version in a release asset name, e.g. "6.7.18-0" from
+/// `CLIProxyAPIPlus_6.7.18-0_linux_amd64.tar.gz`
+pub fn version_from_asset_name(name: &str) -> Option<String> {
+    let mut parts = name.split('_');
+    parts.next()?;
+    let version = parts.next()?.trim_start_matches('v');
+    version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then(|| version.to_string())
+}
+
+fn read_install(dir: &Path) -> Option<SidecarBinary> {
+    let data = std::fs::read_to_string(dir.join(INSTALL_FILE)).ok()?;
+    let mut binary: SidecarBinary = serde_json::from_str(&data).ok()?;
+    let path = dir.join(binary_file_name());
+    if !path.is_file() {
+        return None;
+    }
+    binary.path = path.to_string_lossy().to_string();
+    Some(binary)
+}
+
+/// The pinned version, if any
+pub fn active_version(sidecars_dir: &Path) -> Option<String> {
+    let data = std::fs::read_to_string(sidecars_dir.join(ACTIVE_FILE)).ok()?;
+    serde_json::from_str::<ActiveFile>(&data).ok()?.version
+}
+
+/// Pin `version`, or go back to the bundled sidecar with None
+pub fn set_active_version(sidecars_dir: &Path, version: Option<&str>) -> Result<(), String> {
+    if let Some(version) = version {
+        validate_version(version)?;
+        if read_install(&sidecars_dir.join(version)).is_none() {
+            return Err(format!("CLIProxyAPI {} is not installed", version));
+        }
+    }
+    std::fs::create_dir_all(sidecars_dir)
+        .map_err(|e| format!("Failed to create sidecars directory: {}", e))?;
+    let data = serde_json::to_string_pretty(&ActiveFile {
+        version: version.map(str::to_string),
+    })
+    .map_err(|e| e.to_string())?;
+    std::fs::write(sidecars_dir.join(ACTIVE_FILE), data)
+        .map_err(|e| format!("Failed to save active sidecar: {}", e))
+}
+
+/// Executable of the pinned version. None means the bundled sidecar, also
+/// when the pinned version has gone missing.
+pub fn active_binary(sidecars_dir: &Path) -> Option<PathBuf> {
+    let version = active_version(sidecars_dir)?;
+    validate_version(&version).ok()?;
+    match read_install(&sidecars_dir.join(&version)) {
+        Some(binary) => Some(PathBuf::from(binary.path)),
+        None => {
+            eprintln!(
+                "[ProxyPal] Pinned CLIProxyAPI {} is missing, using the bundled sidecar",
+                version
+            );
+            None
+        }
+    }
+}
+
+/// Run `binary --help` and make sure it exits cleanly within 5s, i.e. that
+/// it is an executable for this machine that starts at all
+pub fn probe_binary(binary: &Path) -> Result<(), String> {
+    let mut child = command(&binary.to_string_lossy())
+        .arg("--help")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| format!("it can't be run: {}", e))?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => return Err(format!("it exited with {}", status)),
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err("it did not exit within 5s".to_string());
+            }
+            None => std::thread::sleep(Duration::from_millis(50)),
+        }
+    }
+}
+
+/// Installed versions, newest install first
+pub fn installed_versions(sidecars_dir: &Path) -> Vec<SidecarBinary> {
+    let Ok(entries) = std::fs::read_dir(sidecars_dir) else {
+        return Vec::new();
+    };
+    let active = active_version(sidecars_dir);
+    let mut binaries: Vec<SidecarBinary> = entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| read_install(&e.path()))
+        .map(|mut binary| {
+            binary.active = active.as_deref() == Some(binary.version.as_str());
+            binary
+        })
+        .collect();
+    binaries.sort_by(|a, b| b.installed_at.cmp(&a.installed_at));
+    binaries
+}
+
+/// Reject files that are clearly not an executable for any platform
+fn validate_executable(bytes: &[u8]) -> Result<(), String> {
+    let known = [
+        &[0x7f, b'E', b'L', b'F'][..], // ELF
+        &[0xcf, 0xfa, 0xed, 0xfe][..], // Mach-O 64-bit
+        &[0xca, 0xfe, 0xba, 0xbe][..], // Mach-O universal
+        &[b'M', b'Z'][..],             // PE
+    ];
+    if known.iter().any(|magic| bytes.starts_with(magic)) {
+        Ok(())
+    } else {
+        Err("The file in the archive is not an executable".to_string())
+    }
+}
+
+fn unpack(archive: &Path, dest: &Path) -> Result<(), String> {
+    let name = archive.to_string_lossy().to_lowercase();
+    let archive_arg = archive.to_string_lossy().to_string();
+    let dest_arg = dest.to_string_lossy().to_string();
+    let mut cmd = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let mut cmd = command("tar");
+        cmd.args(["-xzf", &archive_arg, "-C", &dest_arg]);
+        cmd
+    } else if name.ends_with(".zip") && cfg!(windows) {
+        // bsdtar ships with Windows 10+ and reads zip archives
+        let mut cmd = command("tar");
+        cmd.args(["-xf", &archive_arg, "-C", &dest_arg]);
+        cmd
+    } else if name.ends_with(".zip") {
+        let mut cmd = command("unzip");
+        cmd.args(["-o", "-q", &archive_arg, "-d", &dest_arg]);
+        cmd
+    } else {
+        return Err(format!(
+            "Unsupported archive '{}', expected .tar.gz or .zip",
+            archive.display()
+        ));
+    };
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to unpack archive: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to unpack archive: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn find_binary(dir: &Path) -> Option<PathBuf> {
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_binary(&path) {
+                return Some(found);
+            }
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let stem = name.strip_suffix(".exe").unwrap_or(&name);
+        if ARCHIVE_BINARY_NAMES.contains(&stem) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// What to install: the archive bytes plus where they came from
+pub struct ArchiveSource<'a> {
+    pub bytes: &'a [u8],
+    pub asset_name: &'a str,
+    pub source: &'a str,
+}
+
+/// Verify `archive` against `expected_sha256`, unpack it and install its
+/// binary as `version` (taken from the asset name when None). An existing
+/// install of the same version is replaced.
+pub fn install_archive(
+    sidecars_dir: &Path,
+    archive: ArchiveSource<'_>,
+    expected_sha256: &str,
+    version: Option<&str>,
+) -> Result<SidecarBinary, String> {
+    let sha256 = sha256_hex(archive.bytes);
+    if !sha256.eq_ignore_ascii_case(expected_sha256.trim()) {
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            archive.asset_name, expected_sha256, sha256
+        ));
+    }
+
+    let version = match version {
+        Some(version) => version.trim_start_matches('v').to_string(),
+        None => version_from_asset_name(archive.asset_name).ok_or_else(|| {
+            format!(
+                "Can't tell the version from '{}'; please specify it",
+                archive.asset_name
+            )
+        })?,
+    };
+    validate_version(&version)?;
+
+    let staging = sidecars_dir.join(format!(".staging-{}", crate::types::amp::generate_uuid()));
+    std::fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+    let result = install_from_staging(sidecars_dir, &staging, &archive, &sha256, &version);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn install_from_staging(
+    sidecars_dir: &Path,
+    staging: &Path,
+    archive: &ArchiveSource<'_>,
+    sha256: &str,
+    version: &str,
+) -> Result<SidecarBinary, String> {
+    let archive_path = staging.join(archive.asset_name);
+    std::fs::write(&archive_path, archive.bytes)
+        .map_err(|e| format!("Failed to save archive: {}", e))?;
+    let unpacked = staging.join("unpacked");
+    std::fs::create_dir_all(&unpacked).map_err(|e| e.to_string())?;
+    unpack(&archive_path, &unpacked)?;
+
+    let found = find_binary(&unpacked)
+        .ok_or_else(|| format!("No CLIProxyAPI binary in {}", archive.asset_name))?;
+    let bytes = std::fs::read(&found).map_err(|e| e.to_string())?;
+    validate_executable(&bytes)?;
+
+    // Build the version dir in staging, so a failed install leaves the one
+    // already in place untouched
+    let version_dir = sidecars_dir.join(version);
+    let staged_dir = staging.join("version");
+    std::fs::create_dir_all(&staged_dir)
+        .map_err(|e| format!("Failed to create {}: {}", staged_dir.display(), e))?;
+    let staged_binary = staged_dir.join(binary_file_name());
+    std::fs::write(&staged_binary, &bytes).map_err(|e| format!("Failed to install binary: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged_binary, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make binary executable: {}", e))?;
+    }
+
+    let binary = SidecarBinary {
+        version: version.to_string(),
+        path: version_dir.join(binary_file_name()).to_string_lossy().to_string(),
+        sha256: sha256.to_string(),
+        source: archive.source.to_string(),
+        installed_at: chrono::Utc::now().to_rfc3339(),
+        active: active_version(sidecars_dir).as_deref() == Some(version),
+    };
+    let data = serde_json::to_string_pretty(&binary).map_err(|e| e.to_string())?;
+    std::fs::write(staged_dir.join(INSTALL_FILE), data)
+        .map_err(|e| format!("Failed to write {}: {}", INSTALL_FILE, e))?;
+
+    // A reinstall moves the old dir into staging first, which the caller
+    // removes, and puts it back if the new one can't take its place
+    let previous = staging.join("previous");
+    let replacing = version_dir.exists();
+    if replacing {
+        std::fs::rename(&version_dir, &previous)
+            .map_err(|e| format!("Failed to replace {}: {}", version_dir.display(), e))?;
+    }
+    if let Err(e) = std::fs::rename(&staged_dir, &version_dir) {
+        if replacing {
+            let _ = std::fs::rename(&previous, &version_dir);
+        }
+        return Err(format!("Failed to install {}: {}", version_dir.display(), e));
+    }
+    println!(
+        "[ProxyPal] Installed CLIProxyAPI {} from {}",
+        version, archive.source
+    );
+    Ok(binary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::fs;
+
+    #[test]
+    fn manifest_and_asset_names_are_parsed() {
+        let hash = "a".repeat(64);
+        let manifest = format!(
+            "{}  CLIProxyAPIPlus_6.7.18-0_linux_amd64.tar.gz\n{} *CLIProxyAPIPlus_6.7.18-0_windows_amd64.zip\nnot a checksum line\n",
+            hash,
+            "B".repeat(64)
+        );
+        let checksums = parse_manifest(&manifest);
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(
+            checksums["CLIProxyAPIPlus_6.7.18-0_linux_amd64.tar.gz"],
+            hash
+        );
+        assert_eq!(
+            checksums["CLIProxyAPIPlus_6.7.18-0_windows_amd64.zip"],
+            "b".repeat(64)
+        );
+
+        assert_eq!(
+            version_from_asset_name("CLIProxyAPIPlus_6.7.18-0_linux_amd64.tar.gz"),
+            Some("6.7.18-0".to_string())
+        );
+        assert_eq!(
+            version_from_asset_name("CLIProxyAPI_v6.6.80_darwin_arm64.tar.gz"),
+            Some("6.6.80".to_string())
+        );
+        assert_eq!(version_from_asset_name("cli-proxy-api.tar.gz"), None);
+        assert!(validate_version("../escape").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn verified_archive_installs_and_can_be_pinned() {
+        let dir = test_dir("sidecars");
+        let sidecars = dir.join("sidecars");
+
+        // A release-shaped archive holding a fake ELF binary
+        let content = dir.join("content");
+        fs::create_dir_all(&content).unwrap();
+        fs::write(content.join("cli-proxy-api-plus"), b"\x7fELF fake binary").unwrap();
+        fs::write(content.join("README.md"), "docs").unwrap();
+        let asset_name = "CLIProxyAPIPlus_6.7.20-1_linux_amd64.tar.gz";
+        let archive_path = dir.join(asset_name);
+        let status = std::process::Command::new("tar")
+            .arg("-czf")
+            .arg(&archive_path)
+            .arg("-C")
+            .arg(&content)
+            .arg(".")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let bytes = fs::read(&archive_path).unwrap();
+        let source = ArchiveSource {
+            bytes: &bytes,
+            asset_name,
+            source: "test",
+        };
+
+        let wrong = install_archive(&sidecars, source, &"0".repeat(64), None);
+        assert!(wrong.unwrap_err().contains("Checksum mismatch"));
+        assert!(installed_versions(&sidecars).is_empty());
+
+        let source = ArchiveSource {
+            bytes: &bytes,
+            asset_name,
+            source: "test",
+        };
+        let installed = install_archive(&sidecars, source, &sha256_hex(&bytes), None).unwrap();
+        assert_eq!(installed.version, "6.7.20-1");
+        assert_eq!(fs::read(&installed.path).unwrap(), b"\x7fELF fake binary");
+        assert_eq!(active_binary(&sidecars), None);
+
+        // A failed reinstall leaves the installed version alone
+        fs::remove_file(content.join("cli-proxy-api-plus")).unwrap();
+        let empty_path = dir.join("empty.tar.gz");
+        let status = std::process::Command::new("tar")
+            .arg("-czf")
+            .arg(&empty_path)
+            .arg("-C")
+            .arg(&content)
+            .arg(".")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let empty = fs::read(&empty_path).unwrap();
+        let source = ArchiveSource {
+            bytes: &empty,
+            asset_name,
+            source: "test",
+        };
+        let failed = install_archive(&sidecars, source, &sha256_hex(&empty), None);
+        assert!(failed.unwrap_err().contains("No CLIProxyAPI binary"));
+        assert_eq!(fs::read(&installed.path).unwrap(), b"\x7fELF fake binary");
+
+        // A successful one replaces it and leaves no staging behind
+        let source = ArchiveSource {
+            bytes: &bytes,
+            asset_name,
+            source: "test",
+        };
+        install_archive(&sidecars, source, &sha256_hex(&bytes), None).unwrap();
+        assert_eq!(installed_versions(&sidecars).len(), 1);
+        assert_eq!(fs::read_dir(&sidecars).unwrap().count(), 1);
+
+        set_active_version(&sidecars, Some("6.7.20-1")).unwrap();
+        assert_eq!(
+            active_binary(&sidecars),
+            Some(PathBuf::from(&installed.path))
+        );
+        let listed = installed_versions(&sidecars);
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].active);
+        assert!(set_active_version(&sidecars, Some("1.0.0")).is_err());
+
+        set_active_version(&sidecars, None).unwrap();
+        assert_eq!(active_binary(&sidecars), None);
+
+        // The fake binary can't run on any machine
+        assert!(probe_binary(&PathBuf::from(&installed.path)).is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn probe_needs_a_clean_exit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = test_dir("sidecar-probe");
+        let script = |name: &str, body: &str| {
+            let path = dir.join(name);
+            fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        };
+        assert!(probe_binary(&script("good", "exit 0")).is_ok());
+        let failed = probe_binary(&script("bad", "exit 3")).unwrap_err();
+        assert!(failed.contains("exited"), "{}", failed);
+        assert!(probe_binary(&dir.join("missing")).is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,146 @@
+//! CLIProxyAPI version detection and the features each version has.
+//!
+//! The version comes from the `X-CPA-Version` header on management responses
+//! or, for builds that don't send it, from the startup banner
+//! (`CLIProxyAPI Version: 6.7.18, Commit: ...`). Everything that depends on a
+//! sidecar feature reads the resulting `SidecarCapabilities` from AppState.
+//! An undetected version is treated as current, matching the bundled binary.
+
+use std::fmt;
+
+use crate::types::{SidecarCapabilities, SidecarLogFormat};
+
+/// Header CLIProxyAPI adds to management API responses
+pub const VERSION_HEADER: &str = "x-cpa-version";
+
+/// Oldest sidecar ProxyPal is tested against
+pub const MINIMUM_VERSION: SidecarVersion = SidecarVersion::new(6, 6, 72);
+/// `/api/auth/status`
+const AUTH_STATUS_API: SidecarVersion = SidecarVersion::new(6, 6, 72);
+/// `PATCH /v0/management/auth-files/status`
+const AUTH_FILE_STATUS_API: SidecarVersion = SidecarVersion::new(6, 7, 18);
+/// Bracketed `[timestamp] [level]` log lines. Not pinned to a release, so it
+/// only decides which format the log parser tries first.
+const BRACKETED_LOGS: SidecarVersion = SidecarVersion::new(6, 0, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SidecarVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl SidecarVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse "6.7.18", "v6.7.18" or "6.7.18-2" (CLIProxyAPIPlus build suffix).
+    /// Missing minor/patch count as 0.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().trim_start_matches(['v', 'V']);
+        let core = text.split(['-', '+', ' ', ',']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u32>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for SidecarVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The version from a startup banner line, e.g.
+/// `CLIProxyAPI Version: 6.7.18, Commit: abc123, BuiltAt: ...`
+pub fn version_from_banner<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    lines.into_iter().find_map(|line| {
+        let (_, rest) = line.split_once("Version:")?;
+        let version = rest.trim().split([',', ' ']).next()?;
+        SidecarVersion::parse(version).map(|_| version.to_string())
+    })
+}
+
+/// Capabilities for a detected version string, or for an unknown one
+pub fn capabilities_for(version: Option<&str>) -> SidecarCapabilities {
+    let parsed = version.and_then(SidecarVersion::parse);
+    let at_least = |min: SidecarVersion| parsed.is_none_or(|v| v >= min);
+    SidecarCapabilities {
+        version: version.map(str::to_string),
+        minimum_version: MINIMUM_VERSION.to_string(),
+        outdated: !at_least(MINIMUM_VERSION),
+        auth_file_status_api: at_least(AUTH_FILE_STATUS_API),
+        auth_status_api: at_least(AUTH_STATUS_API),
+        log_format: match parsed {
+            None => SidecarLogFormat::Unknown,
+            Some(v) if v >= BRACKETED_LOGS => SidecarLogFormat::Bracketed,
+            Some(_) => SidecarLogFormat::Iso,
+        },
+    }
+}
+
+impl Default for SidecarCapabilities {
+    fn default() -> Self {
+        capabilities_for(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_parse_and_compare() {
+        assert_eq!(
+            SidecarVersion::parse("v6.7.18"),
+            Some(SidecarVersion::new(6, 7, 18))
+        );
+        assert_eq!(
+            SidecarVersion::parse("6.7.18-2"),
+            Some(SidecarVersion::new(6, 7, 18))
+        );
+        assert_eq!(
+            SidecarVersion::parse("6.8"),
+            Some(SidecarVersion::new(6, 8, 0))
+        );
+        assert_eq!(SidecarVersion::parse("dev"), None);
+        assert!(SidecarVersion::new(6, 10, 0) > SidecarVersion::new(6, 9, 99));
+
+        let banner = [
+            "starting server",
+            "CLIProxyAPI Version: 6.7.18-1, Commit: abc123, BuiltAt: 2025-12-01",
+        ];
+        assert_eq!(version_from_banner(banner), Some("6.7.18-1".to_string()));
+        assert_eq!(version_from_banner(["Version: unknown"]), None);
+    }
+
+    #[test]
+    fn capabilities_follow_the_version() {
+        let current = capabilities_for(Some("6.7.20"));
+        assert!(!current.outdated);
+        assert!(current.auth_file_status_api && current.auth_status_api);
+        assert_eq!(current.log_format, SidecarLogFormat::Bracketed);
+
+        let middle = capabilities_for(Some("6.7.0"));
+        assert!(!middle.outdated);
+        assert!(!middle.auth_file_status_api);
+        assert!(middle.auth_status_api);
+
+        let old = capabilities_for(Some("5.9.1"));
+        assert!(old.outdated);
+        assert!(!old.auth_status_api);
+        assert_eq!(old.log_format, SidecarLogFormat::Iso);
+
+        let unknown = capabilities_for(None);
+        assert!(!unknown.outdated && unknown.auth_file_status_api);
+        assert_eq!(unknown.log_format, SidecarLogFormat::Unknown);
+        assert_eq!(unknown.minimum_version, "6.6.72");
+    }
+}
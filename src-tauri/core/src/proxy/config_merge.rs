@@ -0,0 +1,203 @@
+//! Deep merge of proxy-config-custom.yaml into the generated proxy config.
+//!
+//! Maps are merged key by key, lists are combined according to the
+//! configured `ListMergeStrategy`, and any generated scalar the custom file
+//! overrides is reported back as a `MergeConflict`.
+
+use serde_yaml::Value;
+
+use crate::types::{CustomConfigMergeSettings, ListMergeStrategy, MergeConflict};
+
+/// Merge `custom` into `base` in place, returning every overridden value.
+pub fn merge_custom_config(
+    base: &mut Value,
+    custom: Value,
+    settings: &CustomConfigMergeSettings,
+) -> Vec<MergeConflict> {
+    let mut conflicts = Vec::new();
+    merge_value(base, custom, "", settings, &mut conflicts);
+    conflicts
+}
+
+fn merge_value(
+    base: &mut Value,
+    custom: Value,
+    path: &str,
+    settings: &CustomConfigMergeSettings,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    match (base, custom) {
+        (Value::Mapping(base_map), Value::Mapping(custom_map)) => {
+            for (key, value) in custom_map {
+                let child_path = join_path(path, &key);
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value, &child_path, settings, conflicts),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base_seq), Value::Sequence(custom_seq)) => {
+            match settings.strategy_for(path) {
+                ListMergeStrategy::Append => {
+                    for item in custom_seq {
+                        if !base_seq.contains(&item) {
+                            base_seq.push(item);
+                        }
+                    }
+                }
+                ListMergeStrategy::Prepend => {
+                    let mut merged: Vec<Value> = custom_seq;
+                    for item in base_seq.drain(..) {
+                        if !merged.contains(&item) {
+                            merged.push(item);
+                        }
+                    }
+                    *base_seq = merged;
+                }
+                ListMergeStrategy::Replace => {
+                    if *base_seq != custom_seq {
+                        conflicts.push(MergeConflict {
+                            path: path.to_string(),
+                            generated: render_value(&Value::Sequence(base_seq.clone())),
+                            custom: render_value(&Value::Sequence(custom_seq.clone())),
+                        });
+                    }
+                    *base_seq = custom_seq;
+                }
+            }
+        }
+        (base, custom) => {
+            if *base != custom {
+                conflicts.push(MergeConflict {
+                    path: path.to_string(),
+                    generated: render_value(base),
+                    custom: render_value(&custom),
+                });
+            }
+            *base = custom;
+        }
+    }
+}
+
+fn join_path(parent: &str, key: &Value) -> String {
+    let key = match key {
+        Value::String(s) => s.clone(),
+        other => render_value(other),
+    };
+    if parent.is_empty() {
+        key
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+fn render_value(value: &Value) -> String {
+    serde_yaml::to_string(value)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ListMergeStrategy;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn nested_maps_are_merged_without_duplicate_keys() {
+        let mut base = yaml("routing:\n  strategy: round-robin\nport: 8317\n");
+        let conflicts = merge_custom_config(
+            &mut base,
+            yaml("routing:\n  sticky: true\n"),
+            &CustomConfigMergeSettings::default(),
+        );
+
+        assert!(conflicts.is_empty());
+        assert_eq!(base, yaml("routing:\n  strategy: round-robin\n  sticky: true\nport: 8317\n"));
+    }
+
+    #[test]
+    fn overridden_scalars_are_reported_as_conflicts() {
+        let mut base = yaml("routing:\n  strategy: round-robin\n");
+        let conflicts = merge_custom_config(
+            &mut base,
+            yaml("routing:\n  strategy: fill-first\n"),
+            &CustomConfigMergeSettings::default(),
+        );
+
+        assert_eq!(base, yaml("routing:\n  strategy: fill-first\n"));
+        assert_eq!(
+            conflicts,
+            vec![MergeConflict {
+                path: "routing.strategy".to_string(),
+                generated: "round-robin".to_string(),
+                custom: "fill-first".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn identical_scalars_are_not_conflicts() {
+        let mut base = yaml("debug: false\n");
+        let conflicts = merge_custom_config(
+            &mut base,
+            yaml("debug: false\n"),
+            &CustomConfigMergeSettings::default(),
+        );
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn lists_are_appended_by_default_without_duplicates() {
+        let mut base = yaml("api-keys:\n  - proxypal-local\n");
+        let conflicts = merge_custom_config(
+            &mut base,
+            yaml("api-keys:\n  - proxypal-local\n  - team-key\n"),
+            &CustomConfigMergeSettings::default(),
+        );
+
+        assert!(conflicts.is_empty());
+        assert_eq!(base, yaml("api-keys:\n  - proxypal-local\n  - team-key\n"));
+    }
+
+    #[test]
+    fn path_strategy_overrides_default_list_strategy() {
+        let mut settings = CustomConfigMergeSettings::default();
+        settings
+            .path_strategies
+            .insert("payload.default".to_string(), ListMergeStrategy::Replace);
+        settings
+            .path_strategies
+            .insert("api-keys".to_string(), ListMergeStrategy::Prepend);
+
+        let mut base = yaml("api-keys: [a]\npayload:\n  default: [x, y]\n");
+        let conflicts = merge_custom_config(
+            &mut base,
+            yaml("api-keys: [b]\npayload:\n  default: [z]\n"),
+            &settings,
+        );
+
+        assert_eq!(base, yaml("api-keys: [b, a]\npayload:\n  default: [z]\n"));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "payload.default");
+    }
+
+    #[test]
+    fn type_mismatch_uses_custom_value_and_reports_conflict() {
+        let mut base = yaml("proxy-url: http://a\n");
+        let conflicts = merge_custom_config(
+            &mut base,
+            yaml("proxy-url:\n  nested: true\n"),
+            &CustomConfigMergeSettings::default(),
+        );
+
+        assert_eq!(base, yaml("proxy-url:\n  nested: true\n"));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "proxy-url");
+    }
+}
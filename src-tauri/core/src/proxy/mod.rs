@@ -0,0 +1,14 @@
+//! Proxy-specific helpers (config generation, log watcher, etc.).
+
+pub mod binaries;
+pub mod capabilities;
+pub mod config_merge;
+pub mod config_yaml;
+pub mod preview;
+pub mod output;
+pub mod ports;
+pub mod process;
+pub mod reload;
+pub mod supervisor;
+pub mod system_proxy;
+pub mod validation;
@@ -0,0 +1,241 @@
+//! Captured stdout/stderr of the CLIProxyAPI sidecar.
+//!
+//! Lines are kept in a bounded ring buffer so the UI can page through them
+//! and start failures can quote them. Optionally they are also appended to a
+//! size-rotated file next to config.json, for packaged builds where the
+//! console isn't visible.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::types::{SidecarOutputLine, SidecarStream};
+
+/// Lines kept in memory
+const BUFFER_LINES: usize = 2000;
+/// Size at which the output file is rotated
+const FILE_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept besides the current one (.1 is the newest)
+const FILE_KEEP: usize = 3;
+/// Lines quoted when the sidecar fails to start
+pub const START_FAILURE_LINES: usize = 50;
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn write_line(&mut self, text: &str) -> Result<(), String> {
+        if self.size + text.len() as u64 > FILE_MAX_BYTES && self.size > 0 {
+            self.rotate()?;
+        }
+        self.file
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write sidecar output: {}", e))?;
+        self.size += text.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        let _ = std::fs::remove_file(self.rotated_path(FILE_KEEP));
+        for index in (1..FILE_KEEP).rev() {
+            let _ = std::fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+        }
+        std::fs::rename(&self.path, self.rotated_path(1))
+            .map_err(|e| format!("Failed to rotate {}: {}", self.path.display(), e))?;
+        *self = Self::open(&self.path)?;
+        Ok(())
+    }
+}
+
+pub struct SidecarOutput {
+    capacity: usize,
+    lines: VecDeque<SidecarOutputLine>,
+    last_seq: u64,
+    file: Option<RotatingFile>,
+}
+
+impl Default for SidecarOutput {
+    fn default() -> Self {
+        Self::with_capacity(BUFFER_LINES)
+    }
+}
+
+impl SidecarOutput {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::with_capacity(capacity),
+            last_seq: 0,
+            file: None,
+        }
+    }
+
+    /// Also append output to `path`, or stop doing so with None
+    pub fn set_file(&mut self, path: Option<&Path>) -> Result<(), String> {
+        match path {
+            Some(path) if self.file.as_ref().map(|f| f.path.as_path()) == Some(path) => Ok(()),
+            Some(path) => {
+                self.file = Some(RotatingFile::open(path)?);
+                Ok(())
+            }
+            None => {
+                self.file = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Sequence number of the newest line, 0 before any output
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    pub fn push(&mut self, stream: SidecarStream, text: &str) -> Option<SidecarOutputLine> {
+        let text = text.trim_end_matches(['\r', '\n']);
+        if text.is_empty() {
+            return None;
+        }
+        let now = chrono::Utc::now();
+        self.last_seq += 1;
+        let line = SidecarOutputLine {
+            seq: self.last_seq,
+            timestamp: now.timestamp_millis() as u64,
+            stream,
+            line: text.to_string(),
+        };
+
+        if let Some(file) = self.file.as_mut() {
+            let entry = format!("{} {}\n", now.to_rfc3339(), format_line(&line));
+            if let Err(e) = file.write_line(&entry) {
+                eprintln!("[ProxyPal] {}", e);
+                self.file = None;
+            }
+        }
+
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.clone());
+        Some(line)
+    }
+
+    /// Lines after `since` (a sequence number), at most the newest `limit`
+    pub fn query(&self, since: Option<u64>, limit: Option<usize>) -> Vec<SidecarOutputLine> {
+        let since = since.unwrap_or(0);
+        let matching = self.lines.iter().filter(|l| l.seq > since);
+        let count = matching.clone().count();
+        let skip = limit.map_or(0, |limit| count.saturating_sub(limit));
+        matching.skip(skip).cloned().collect()
+    }
+
+    /// The last `limit` lines after `since`, as text for an error message
+    pub fn tail_text(&self, since: u64, limit: usize) -> Vec<String> {
+        self.query(Some(since), Some(limit))
+            .iter()
+            .map(format_line)
+            .collect()
+    }
+}
+
+fn format_line(line: &SidecarOutputLine) -> String {
+    let stream = match line.stream {
+        SidecarStream::Stdout => "stdout",
+        SidecarStream::Stderr => "stderr",
+    };
+    format!("[{}] {}", stream, line.line)
+}
+
+/// Append captured output to a start-failure message
+pub fn with_output(message: String, tail: &[String]) -> String {
+    if tail.is_empty() {
+        return message;
+    }
+    format!(
+        "{}\n\nLast output from CLIProxyAPI:\n{}",
+        message,
+        tail.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::fs;
+
+    #[test]
+    fn ring_buffer_keeps_newest_lines_and_filters() {
+        let mut output = SidecarOutput::with_capacity(3);
+        assert!(output.push(SidecarStream::Stdout, "\n").is_none());
+        for i in 1..=5 {
+            let stream = if i % 2 == 0 {
+                SidecarStream::Stderr
+            } else {
+                SidecarStream::Stdout
+            };
+            output.push(stream, &format!("line {}\n", i));
+        }
+        assert_eq!(output.last_seq(), 5);
+
+        let all = output.query(None, None);
+        assert_eq!(all.iter().map(|l| l.seq).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(all[1].stream, SidecarStream::Stderr);
+        assert_eq!(all[1].line, "line 4");
+
+        assert_eq!(output.query(Some(4), None).len(), 1);
+        assert_eq!(output.query(None, Some(2))[0].seq, 4);
+        assert_eq!(
+            output.tail_text(3, 10),
+            vec!["[stderr] line 4", "[stdout] line 5"]
+        );
+        assert_eq!(
+            with_output("Failed".to_string(), &output.tail_text(4, 10)),
+            "Failed\n\nLast output from CLIProxyAPI:\n[stdout] line 5"
+        );
+    }
+
+    #[test]
+    fn output_file_rotates() {
+        let dir = test_dir("sidecar-output");
+        let path = dir.join("sidecar-output.log");
+        let mut output = SidecarOutput::default();
+        output.set_file(Some(&path)).unwrap();
+        output.push(SidecarStream::Stdout, "before rotation");
+
+        // Pretend the file is already full so the next line rotates it
+        output.file.as_mut().unwrap().size = FILE_MAX_BYTES;
+        output.push(SidecarStream::Stderr, "after rotation");
+
+        let rotated = fs::read_to_string(dir.join("sidecar-output.log.1")).unwrap();
+        assert!(rotated.ends_with("[stdout] before rotation\n"));
+        let current = fs::read_to_string(&path).unwrap();
+        assert!(current.ends_with("[stderr] after rotation\n"));
+        assert_eq!(current.lines().count(), 1);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
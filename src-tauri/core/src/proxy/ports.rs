@@ -0,0 +1,185 @@
+//! Free-port selection and carrying a port change over to everything that
+//! has the proxy port baked in.
+//!
+//! Agent configs written by `configure_cli_agent` contain the endpoint as a
+//! plain URL, so they are updated by rewriting `127.0.0.1:<old>` and
+//! `localhost:<old>` in place rather than regenerating them, which would need
+//! the model list and would drop the user's own edits.
+
+use std::net::TcpListener;
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::config::AppConfig;
+
+/// How far past the configured port to look for a free one
+const PORT_SEARCH_RANGE: u16 = 100;
+
+/// Agent config files that may contain the proxy endpoint, relative to home
+const AGENT_CONFIG_FILES: &[&str] = &[
+    ".codex/config.toml",
+    ".config/opencode/opencode.json",
+    ".factory/config.json",
+    ".config/amp/settings.json",
+    ".claude/settings.json",
+    ".claude/proxypal-models.md",
+];
+
+/// Whether nothing is listening on `port`, locally or on all interfaces
+pub fn is_port_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok() && TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// The first free port after `start`, skipping `reserved` (e.g. the Copilot port)
+pub fn find_free_port(start: u16, reserved: &[u16]) -> Option<u16> {
+    (1..=PORT_SEARCH_RANGE)
+        .filter_map(|offset| start.checked_add(offset))
+        .find(|port| !reserved.contains(port) && is_port_free(*port))
+}
+
+/// Replace local endpoints on `old` with `new`. None if nothing matched.
+pub fn rewrite_port(text: &str, old: u16, new: u16) -> Option<String> {
+    let pattern = Regex::new(&format!(r"(://(?:127\.0\.0\.1|localhost)):{}\b", old)).ok()?;
+    if !pattern.is_match(text) {
+        return None;
+    }
+    Some(pattern.replace_all(text, format!("${{1}}:{}", new)).to_string())
+}
+
+/// Rewrite the agent config files under `home`. Returns the updated files and
+/// any errors, one per file.
+pub fn propagate_port_to_agents(home: &Path, old: u16, new: u16) -> (Vec<String>, Vec<String>) {
+    let mut updated = Vec::new();
+    let mut errors = Vec::new();
+    for relative in AGENT_CONFIG_FILES {
+        let path = home.join(relative);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let Some(rewritten) = rewrite_port(&content, old, new) else {
+            continue;
+        };
+        match replace_file(&path, &rewritten) {
+            Ok(()) => updated.push(path.to_string_lossy().to_string()),
+            Err(e) => errors.push(format!("Failed to update {}: {}", path.display(), e)),
+        }
+    }
+    (updated, errors)
+}
+
+/// Write `content` to a temp file next to `path` and rename it over `path`,
+/// so an agent never reads a half-written config. Keeps the file's permissions.
+fn replace_file(path: &Path, content: &str) -> std::io::Result<()> {
+    let temp_path = path.with_extension("proxypal-tmp");
+    let written = std::fs::write(&temp_path, content)
+        .and_then(|_| std::fs::set_permissions(&temp_path, std::fs::metadata(path)?.permissions()))
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    written
+}
+
+/// Move the proxy port in `config` from `old` to `new`, along with tunnels
+/// that forwarded the old port. Returns the ids of the tunnels that changed.
+pub fn apply_port_change(config: &mut AppConfig, old: u16, new: u16) -> Vec<String> {
+    config.port = new;
+
+    let mut tunnels = Vec::new();
+    for ssh in config.ssh_configs.iter_mut().filter(|c| c.local_port == old) {
+        ssh.local_port = new;
+        tunnels.push(ssh.id.clone());
+    }
+    for cf in config.cloudflare_configs.iter_mut().filter(|c| c.local_port == old) {
+        cf.local_port = new;
+        tunnels.push(cf.id.clone());
+    }
+    tunnels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use crate::types::{CloudflareConfig, SshConfig};
+    use std::fs;
+
+    #[test]
+    fn rewrite_port_only_touches_local_endpoints() {
+        let text = r#"base_url = "http://127.0.0.1:8317/v1"
+amp = "http://localhost:8317"
+other = "http://localhost:83170"
+remote = "http://example.com:8317"
+"#;
+        let rewritten = rewrite_port(text, 8317, 8318).unwrap();
+        assert!(rewritten.contains("http://127.0.0.1:8318/v1"));
+        assert!(rewritten.contains("http://localhost:8318\""));
+        assert!(rewritten.contains("localhost:83170"));
+        assert!(rewritten.contains("example.com:8317"));
+        assert_eq!(rewrite_port("no endpoints here", 8317, 8318), None);
+    }
+
+    #[test]
+    fn agent_files_and_tunnels_follow_the_new_port() {
+        let home = test_dir("port-propagation");
+        fs::create_dir_all(home.join(".codex")).unwrap();
+        fs::create_dir_all(home.join(".claude")).unwrap();
+        fs::write(
+            home.join(".codex/config.toml"),
+            "base_url = \"http://127.0.0.1:8317/v1\"\n",
+        )
+        .unwrap();
+        fs::write(
+            home.join(".claude/settings.json"),
+            r#"{"env": {"ANTHROPIC_BASE_URL": "http://127.0.0.1:9999"}}"#,
+        )
+        .unwrap();
+
+        let (updated, errors) = propagate_port_to_agents(&home, 8317, 8320);
+        assert!(errors.is_empty());
+        assert_eq!(updated.len(), 1);
+        assert!(updated[0].ends_with("config.toml"));
+        assert!(fs::read_to_string(home.join(".codex/config.toml"))
+            .unwrap()
+            .contains("127.0.0.1:8320"));
+        assert!(!home.join(".codex/config.proxypal-tmp").exists());
+
+        let mut config = AppConfig::default();
+        config.ssh_configs.push(SshConfig {
+            id: "ssh-1".to_string(),
+            host: "example.com".to_string(),
+            port: 22,
+            username: "me".to_string(),
+            key_file: None,
+            remote_port: 9000,
+            local_port: 8317,
+            enabled: true,
+        });
+        config.cloudflare_configs.push(CloudflareConfig {
+            id: "cf-1".to_string(),
+            name: "other".to_string(),
+            tunnel_token: String::new(),
+            local_port: 3000,
+            enabled: false,
+        });
+        assert_eq!(apply_port_change(&mut config, 8317, 8320), vec!["ssh-1"]);
+        assert_eq!(config.port, 8320);
+        assert_eq!(config.ssh_configs[0].local_port, 8320);
+        assert_eq!(config.cloudflare_configs[0].local_port, 3000);
+
+        let _ = fs::remove_dir_all(home);
+    }
+
+    #[test]
+    fn find_free_port_skips_reserved_and_busy_ports() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let busy = listener.local_addr().unwrap().port();
+        let start = busy - 1;
+        let found = find_free_port(start, &[]).unwrap();
+        assert_ne!(found, busy);
+        assert!(found > start);
+        let reserved = find_free_port(start, &[found]).unwrap();
+        assert_ne!(reserved, found);
+    }
+}
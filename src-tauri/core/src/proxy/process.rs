@@ -0,0 +1,285 @@
+//! Ownership of the CLIProxyAPI sidecar process.
+//!
+//! Every sidecar ProxyPal spawns is recorded in cli-proxy-api.pid together
+//! with its process name. Cleanup only signals that PID, and only while it
+//! still carries the recorded name, so a CLIProxyAPI someone started by hand
+//! (or a recycled PID) is never touched. Stopping forgets the PID before it
+//! sends SIGTERM and waits out a grace period before SIGKILL, so whoever
+//! supervises the process (the app or `proxypal run`) can tell a stop from a
+//! crash by whether the PID file still names it.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_sidecar_pid_path;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// How long the sidecar gets to exit after SIGTERM
+pub const STOP_GRACE: Duration = Duration::from_secs(3);
+
+const SIDECAR_NAME: &str = "cli-proxy-api";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PidRecord {
+    pid: u32,
+    port: u16,
+    name: String,
+}
+
+/// A std Command that doesn't flash a console window on Windows
+pub fn command(program: &str) -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(program);
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+fn read_pid_record(path: &Path) -> Option<PidRecord> {
+    let data = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn write_pid_record(path: &Path, record: &PidRecord) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(record).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| format!("Failed to write PID file: {}", e))
+}
+
+/// Name of the running process with this PID, without any directory
+pub fn process_name(pid: u32) -> Option<String> {
+    #[cfg(unix)]
+    let name = {
+        let output = command("ps")
+            .args(["-p", &pid.to_string(), "-o", "comm="])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    #[cfg(windows)]
+    let name = {
+        let output = command("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .output()
+            .ok()?;
+        parse_tasklist_name(&String::from_utf8_lossy(&output.stdout)).unwrap_or_default()
+    };
+
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().to_string();
+    Some(name).filter(|n| !n.is_empty())
+}
+
+/// First column of `tasklist /FO CSV /NH`, e.g. `"cli-proxy-api.exe","1234",...`
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_tasklist_name(output: &str) -> Option<String> {
+    let line = output.lines().find(|l| l.starts_with('"'))?;
+    line.split("\",\"")
+        .next()
+        .map(|name| name.trim_matches('"').to_string())
+}
+
+fn is_alive(pid: u32) -> bool {
+    process_name(pid).is_some()
+}
+
+fn send_terminate(pid: u32) {
+    #[cfg(unix)]
+    let _ = command("kill").args(["-TERM", &pid.to_string()]).output();
+    #[cfg(windows)]
+    let _ = command("taskkill").args(["/PID", &pid.to_string()]).output();
+}
+
+fn send_kill(pid: u32) {
+    #[cfg(unix)]
+    let _ = command("kill").args(["-KILL", &pid.to_string()]).output();
+    #[cfg(windows)]
+    let _ = command("taskkill").args(["/F", "/PID", &pid.to_string()]).output();
+}
+
+/// SIGTERM, wait up to `grace` for the process to exit, then SIGKILL.
+/// Blocks; call from a blocking task in async code.
+pub fn terminate_gracefully(pid: u32, grace: Duration) {
+    send_terminate(pid);
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        if !is_alive(pid) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    if is_alive(pid) {
+        eprintln!("[ProxyPal] PID {} ignored SIGTERM, killing it", pid);
+        send_kill(pid);
+    }
+}
+
+/// Remember the sidecar we just spawned
+pub fn record_sidecar(pid: u32, port: u16) {
+    record_sidecar_at(&get_sidecar_pid_path(), pid, port);
+}
+
+/// `record_sidecar` with the PID file of a proxy instance
+pub fn record_sidecar_at(path: &Path, pid: u32, port: u16) {
+    let record = PidRecord {
+        pid,
+        port,
+        name: process_name(pid).unwrap_or_else(|| SIDECAR_NAME.to_string()),
+    };
+    if let Err(e) = write_pid_record(path, &record) {
+        eprintln!("[ProxyPal] {}", e);
+    }
+}
+
+/// The recorded sidecar as (PID, port), if it is still running
+pub fn running_sidecar() -> Option<(u32, u16)> {
+    running_sidecar_at(&get_sidecar_pid_path())
+}
+
+/// `running_sidecar` with the PID file of a proxy instance
+pub fn running_sidecar_at(path: &Path) -> Option<(u32, u16)> {
+    let record = read_pid_record(path)?;
+    let name = process_name(record.pid)?;
+    (name == record.name).then_some((record.pid, record.port))
+}
+
+/// Whether the PID file still names `pid`. False once the sidecar was
+/// stopped on purpose, by whichever process did it.
+pub fn is_recorded_at(path: &Path, pid: u32) -> bool {
+    read_pid_record(path).is_some_and(|r| r.pid == pid)
+}
+
+/// Stop the sidecar with this PID and forget it
+pub fn stop_sidecar(pid: u32) {
+    stop_sidecar_at(&get_sidecar_pid_path(), pid);
+}
+
+/// `stop_sidecar` with the PID file of a proxy instance
+pub fn stop_sidecar_at(path: &Path, pid: u32) {
+    println!("[ProxyPal] Stopping CLIProxyAPI (PID {})", pid);
+    // Forget it first: its supervisor sees the exit right away
+    if is_recorded_at(path, pid) {
+        let _ = std::fs::remove_file(path);
+    }
+    terminate_gracefully(pid, STOP_GRACE);
+}
+
+/// Stop a sidecar left behind by an earlier ProxyPal run, if the recorded
+/// PID is still that sidecar
+pub fn cleanup_stale_sidecar() {
+    cleanup_stale_sidecar_at(&get_sidecar_pid_path());
+}
+
+pub fn cleanup_stale_sidecar_at(path: &Path) {
+    let Some(record) = read_pid_record(path) else {
+        return;
+    };
+    match process_name(record.pid) {
+        Some(name) if name == record.name => {
+            println!(
+                "[ProxyPal] Stopping leftover CLIProxyAPI from a previous run (PID {}, port {})",
+                record.pid, record.port
+            );
+            let _ = std::fs::remove_file(path);
+            terminate_gracefully(record.pid, STOP_GRACE);
+        }
+        Some(name) => println!(
+            "[ProxyPal] PID {} from the old PID file is now '{}', leaving it alone",
+            record.pid, name
+        ),
+        None => {}
+    }
+    let _ = std::fs::remove_file(path);
+}
+
+/// PID of the process listening on `port` in `netstat -ano` output
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_netstat_listener(output: &str, port: u16) -> Option<u32> {
+    let suffix = format!(":{}", port);
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["TCP", local, _, "LISTENING", pid] if local.ends_with(&suffix) => pid.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// The process listening on `port`, as (PID, name)
+pub fn port_owner(port: u16) -> Option<(u32, String)> {
+    #[cfg(unix)]
+    let pid = {
+        let output = command("lsof")
+            .args(["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-t"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.trim().parse::<u32>().ok())?
+    };
+    #[cfg(windows)]
+    let pid = {
+        let output = command("netstat").args(["-ano", "-p", "TCP"]).output().ok()?;
+        parse_netstat_listener(&String::from_utf8_lossy(&output.stdout), port)?
+    };
+
+    let name = process_name(pid).unwrap_or_else(|| "unknown".to_string());
+    Some((pid, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::fs;
+
+    #[test]
+    fn stale_pid_file_for_another_process_is_removed_without_signalling() {
+        let dir = test_dir("pidfile");
+        let path = dir.join("cli-proxy-api.pid");
+
+        // Our own PID under a different name stands in for a recycled PID;
+        // if it were signalled the test process would die
+        let record = PidRecord {
+            pid: std::process::id(),
+            port: 8317,
+            name: "not-the-sidecar".to_string(),
+        };
+        write_pid_record(&path, &record).unwrap();
+        assert_eq!(read_pid_record(&path), Some(record));
+
+        assert!(is_recorded_at(&path, std::process::id()));
+        assert!(running_sidecar_at(&path).is_none());
+
+        cleanup_stale_sidecar_at(&path);
+        assert!(!path.exists());
+        assert!(!is_recorded_at(&path, std::process::id()));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn windows_tool_output_is_parsed() {
+        let netstat = "\
+Active Connections
+
+  Proto  Local Address          Foreign Address        State           PID
+  TCP    0.0.0.0:135            0.0.0.0:0              LISTENING       1100
+  TCP    127.0.0.1:8317         127.0.0.1:52100        ESTABLISHED     4242
+  TCP    127.0.0.1:8317         0.0.0.0:0              LISTENING       4242
+  TCP    127.0.0.1:18317        0.0.0.0:0              LISTENING       5151
+";
+        assert_eq!(parse_netstat_listener(netstat, 8317), Some(4242));
+        assert_eq!(parse_netstat_listener(netstat, 9000), None);
+
+        let tasklist = "\"cli-proxy-api.exe\",\"4242\",\"Console\",\"1\",\"25,000 K\"\r\n";
+        assert_eq!(parse_tasklist_name(tasklist), Some("cli-proxy-api.exe".to_string()));
+        assert_eq!(parse_tasklist_name("INFO: No tasks are running."), None);
+    }
+}
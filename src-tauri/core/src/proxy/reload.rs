@@ -0,0 +1,226 @@
+//! Decide how a config change reaches a running CLIProxyAPI.
+//!
+//! Both configs are compared as rendered proxy-config.yaml, so anything
+//! proxy-config-custom.yaml overrides is compared too. CLIProxyAPI watches its
+//! config file and reloads nearly everything in place; a few scalars also have
+//! Management API endpoints that apply immediately. Only settings read once at
+//! startup need the sidecar restarted, which drops in-flight streams.
+
+use std::path::Path;
+
+use serde_yaml::Value;
+
+use crate::config::AppConfig;
+use crate::proxy::config_yaml::build_proxy_config_yaml;
+use crate::types::ReloadPath;
+
+/// YAML paths with a `/v0/management/<endpoint>` PUT, as (path, endpoint)
+pub const MANAGEMENT_FIELDS: &[(&str, &str)] = &[
+    ("debug", "debug"),
+    ("usage-statistics-enabled", "usage-statistics-enabled"),
+    ("logging-to-file", "logging-to-file"),
+    ("request-retry", "request-retry"),
+    ("max-retry-interval", "max-retry-interval"),
+    ("proxy-url", "proxy-url"),
+    ("quota-exceeded.switch-project", "quota-exceeded/switch-project"),
+    (
+        "quota-exceeded.switch-preview-model",
+        "quota-exceeded/switch-preview-model",
+    ),
+    ("request-log", "request-log"),
+    ("ws-auth", "ws-auth"),
+    ("ampcode.force-model-mappings", "ampcode/force-model-mappings"),
+];
+
+/// Top-level keys CLIProxyAPI only reads when it starts
+const RESTART_FIELDS: &[&str] = &["port", "auth-dir", "commercial-mode"];
+
+/// Changed settings grouped by how they get applied
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReloadPlan {
+    pub management: Vec<String>,
+    pub file_reload: Vec<String>,
+    pub restart: Vec<String>,
+}
+
+impl ReloadPlan {
+    /// The most disruptive path this plan needs
+    pub fn path(&self) -> ReloadPath {
+        if !self.restart.is_empty() {
+            ReloadPath::Restart
+        } else if !self.file_reload.is_empty() {
+            ReloadPath::FileReload
+        } else if !self.management.is_empty() {
+            ReloadPath::Management
+        } else {
+            ReloadPath::None
+        }
+    }
+}
+
+/// Look up a dotted path, e.g. "quota-exceeded.switch-project"
+pub fn value_at<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(root, |value, key| value.as_mapping()?.get(key))
+}
+
+fn remove_at(root: &mut Value, path: &str) {
+    let Some((parent, key)) = path.rsplit_once('.') else {
+        if let Some(map) = root.as_mapping_mut() {
+            map.remove(path);
+        }
+        return;
+    };
+    let mut current = root;
+    for part in parent.split('.') {
+        match current.as_mapping_mut().and_then(|m| m.get_mut(part)) {
+            Some(next) => current = next,
+            None => return,
+        }
+    }
+    if let Some(map) = current.as_mapping_mut() {
+        map.remove(key);
+    }
+}
+
+/// Compare the YAML the sidecar has loaded with the YAML we'd write now
+pub fn plan_reload(old: &Value, new: &Value) -> ReloadPlan {
+    let mut plan = ReloadPlan::default();
+
+    let mut keys: Vec<&Value> = Vec::new();
+    for map in [new.as_mapping(), old.as_mapping()].into_iter().flatten() {
+        for key in map.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+
+    for key in keys {
+        let Some(name) = key.as_str() else {
+            continue;
+        };
+        let old_value = value_at(old, name).cloned().unwrap_or(Value::Null);
+        let new_value = value_at(new, name).cloned().unwrap_or(Value::Null);
+        if old_value == new_value {
+            continue;
+        }
+        if RESTART_FIELDS.contains(&name) {
+            plan.restart.push(name.to_string());
+            continue;
+        }
+
+        // Strip the fields the Management API covers; whatever still differs
+        // has to go through the file watcher
+        let mut old_rest = Value::Mapping([(key.clone(), old_value)].into_iter().collect());
+        let mut new_rest = Value::Mapping([(key.clone(), new_value)].into_iter().collect());
+        for (path, _) in MANAGEMENT_FIELDS {
+            let covered = *path == name || path.starts_with(&format!("{}.", name));
+            if !covered {
+                continue;
+            }
+            if value_at(&old_rest, path) != value_at(&new_rest, path) {
+                plan.management.push(path.to_string());
+            }
+            remove_at(&mut old_rest, path);
+            remove_at(&mut new_rest, path);
+        }
+        if old_rest != new_rest {
+            plan.file_reload.push(name.to_string());
+        }
+    }
+
+    plan
+}
+
+fn parse_proxy_yaml(yaml: &str) -> Result<Value, String> {
+    serde_yaml::from_str(yaml).map_err(|e| format!("Failed to parse proxy config: {}", e))
+}
+
+/// Plan moving the sidecar from the YAML it `loaded` (`old` rendered when
+/// that's unknown) to `new`. Also returns `new` rendered, for the values to
+/// push.
+pub fn plan_config_change(
+    loaded: Option<&str>,
+    old: &AppConfig,
+    new: &AppConfig,
+    config_dir: &Path,
+) -> Result<(ReloadPlan, Value), String> {
+    let old_value = match loaded.and_then(|yaml| parse_proxy_yaml(yaml).ok()) {
+        Some(value) => value,
+        None => parse_proxy_yaml(&build_proxy_config_yaml(old, config_dir)?.yaml)?,
+    };
+    let new_value = parse_proxy_yaml(&build_proxy_config_yaml(new, config_dir)?.yaml)?;
+    Ok((plan_reload(&old_value, &new_value), new_value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::proxy::config_yaml::build_proxy_config_document;
+
+    fn document(config: &AppConfig) -> Value {
+        serde_yaml::to_value(build_proxy_config_document(config)).unwrap()
+    }
+
+    #[test]
+    fn unchanged_config_needs_nothing() {
+        let config = AppConfig::default();
+        let plan = plan_reload(&document(&config), &document(&config));
+        assert_eq!(plan, ReloadPlan::default());
+        assert_eq!(plan.path(), ReloadPath::None);
+    }
+
+    #[test]
+    fn management_fields_are_applied_live() {
+        let old = AppConfig::default();
+        let mut new = old.clone();
+        new.debug = true;
+        new.quota_switch_project = true;
+        new.force_model_mappings = !old.force_model_mappings;
+        new.locale = "zh-CN".to_string(); // Not part of proxy-config.yaml
+
+        let plan = plan_reload(&document(&old), &document(&new));
+        assert_eq!(
+            plan.management,
+            vec![
+                "debug",
+                "quota-exceeded.switch-project",
+                "ampcode.force-model-mappings"
+            ]
+        );
+        assert!(plan.file_reload.is_empty());
+        assert_eq!(plan.path(), ReloadPath::Management);
+    }
+
+    #[test]
+    fn other_changes_use_file_reload_and_port_needs_restart() {
+        let old = AppConfig::default();
+        let mut new = old.clone();
+        new.routing_strategy = "fill-first".to_string();
+        new.amp_api_key = "amp-key".to_string();
+        new.force_model_mappings = !old.force_model_mappings;
+
+        let plan = plan_reload(&document(&old), &document(&new));
+        assert_eq!(plan.management, vec!["ampcode.force-model-mappings"]);
+        assert_eq!(plan.file_reload, vec!["routing", "ampcode"]);
+        assert_eq!(plan.path(), ReloadPath::FileReload);
+
+        new.port = 9000;
+        let plan = plan_reload(&document(&old), &document(&new));
+        assert_eq!(plan.restart, vec!["port"]);
+        assert_eq!(plan.path(), ReloadPath::Restart);
+    }
+
+    #[test]
+    fn removed_keys_are_detected() {
+        let mut old = AppConfig::default();
+        old.proxy_url = "http://proxy.local:8080".to_string();
+        let new = AppConfig::default();
+
+        let plan = plan_reload(&document(&old), &document(&new));
+        assert_eq!(plan.management, vec!["proxy-url"]);
+        assert_eq!(value_at(&document(&new), "proxy-url"), None);
+    }
+}
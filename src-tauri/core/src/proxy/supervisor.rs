@@ -0,0 +1,226 @@
+//! Restart policy for the CLIProxyAPI sidecar.
+//!
+//! Every unexpected exit is recorded and answered with a restart after an
+//! exponentially growing delay. Once too many exits land inside the window the
+//! sidecar is considered crash-looping and left stopped, with the last stderr
+//! lines kept as the explanation. Each launch gets a generation number so exits
+//! we caused ourselves (stop, restart, config reload) are told apart from crashes.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Stderr lines kept for the crash-loop report
+const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    pub max_exits: usize, // Exits within `window` that count as a crash loop
+    pub window: Duration,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_exits: 5,
+            window: Duration::from_secs(60),
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// What to do about an unexpected exit
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExitDecision {
+    Restart { attempt: u32, delay: Duration },
+    CrashLoop { exits: usize },
+}
+
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    policy: RestartPolicy,
+    generation: u64,
+    abandoned: Option<u64>,
+    exits: VecDeque<Instant>,
+    stderr_tail: VecDeque<String>,
+}
+
+impl Supervisor {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn policy(&self) -> &RestartPolicy {
+        &self.policy
+    }
+
+    /// Start tracking a new sidecar process. Exits of earlier ones are expected.
+    pub fn begin_launch(&mut self) -> u64 {
+        self.generation += 1;
+        self.stderr_tail.clear();
+        self.generation
+    }
+
+    /// The current process is being stopped on purpose; cancels pending restarts
+    pub fn stop(&mut self) {
+        self.generation += 1;
+    }
+
+    /// The launch of `generation` failed and its process is being stopped. Its
+    /// exit is expected, but unlike `stop` a pending restart may try again.
+    pub fn abandon(&mut self, generation: u64) {
+        self.abandoned = Some(generation);
+    }
+
+    /// The process from `generation` exited by itself. Returns the token the
+    /// restart holds, or None if the exit was expected.
+    pub fn mark_exited(&mut self, generation: u64) -> Option<u64> {
+        if !self.is_current(generation) || self.abandoned == Some(generation) {
+            return None;
+        }
+        self.generation += 1;
+        Some(self.generation)
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation == generation
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Forget earlier crashes, e.g. when the user starts the proxy by hand
+    pub fn reset(&mut self) {
+        self.exits.clear();
+    }
+
+    pub fn push_stderr(&mut self, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return;
+        }
+        if self.stderr_tail.len() == STDERR_TAIL_LINES {
+            self.stderr_tail.pop_front();
+        }
+        self.stderr_tail.push_back(line.to_string());
+    }
+
+    pub fn stderr_tail(&self) -> Vec<String> {
+        self.stderr_tail.iter().cloned().collect()
+    }
+
+    pub fn record_exit(&mut self, now: Instant) -> ExitDecision {
+        while let Some(first) = self.exits.front() {
+            if now.duration_since(*first) > self.policy.window {
+                self.exits.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.exits.push_back(now);
+
+        let exits = self.exits.len();
+        if exits >= self.policy.max_exits {
+            return ExitDecision::CrashLoop { exits };
+        }
+        let attempt = exits as u32;
+        let delay = self
+            .policy
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.policy.max_delay);
+        ExitDecision::Restart { attempt, delay }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            max_exits: 4,
+            window: Duration::from_secs(10),
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(3),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_until_capped_then_detects_crash_loop() {
+        let mut supervisor = Supervisor::new(policy());
+        let start = Instant::now();
+
+        let delays: Vec<_> = (0..3)
+            .map(|i| supervisor.record_exit(start + Duration::from_secs(i)))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                ExitDecision::Restart { attempt: 1, delay: Duration::from_secs(1) },
+                ExitDecision::Restart { attempt: 2, delay: Duration::from_secs(2) },
+                ExitDecision::Restart { attempt: 3, delay: Duration::from_secs(3) },
+            ]
+        );
+        assert_eq!(
+            supervisor.record_exit(start + Duration::from_secs(3)),
+            ExitDecision::CrashLoop { exits: 4 }
+        );
+    }
+
+    #[test]
+    fn exits_outside_the_window_are_forgotten() {
+        let mut supervisor = Supervisor::new(policy());
+        let start = Instant::now();
+        for i in 0..3 {
+            supervisor.record_exit(start + Duration::from_secs(i));
+        }
+        assert_eq!(
+            supervisor.record_exit(start + Duration::from_secs(12)),
+            ExitDecision::Restart { attempt: 2, delay: Duration::from_secs(2) }
+        );
+
+        supervisor.reset();
+        assert_eq!(
+            supervisor.record_exit(start + Duration::from_secs(13)),
+            ExitDecision::Restart { attempt: 1, delay: Duration::from_secs(1) }
+        );
+    }
+
+    #[test]
+    fn generations_and_stderr_tail() {
+        let mut supervisor = Supervisor::default();
+        let first = supervisor.begin_launch();
+        for i in 0..25 {
+            supervisor.push_stderr(&format!("line {}\n", i));
+        }
+        supervisor.push_stderr("  ");
+        let tail = supervisor.stderr_tail();
+        assert_eq!(tail.len(), STDERR_TAIL_LINES);
+        assert_eq!(tail.first().map(String::as_str), Some("line 5"));
+        assert_eq!(tail.last().map(String::as_str), Some("line 24"));
+
+        assert!(supervisor.is_current(first));
+        supervisor.stop();
+        assert!(!supervisor.is_current(first));
+        assert_eq!(supervisor.mark_exited(first), None);
+
+        let second = supervisor.begin_launch();
+        assert!(supervisor.stderr_tail().is_empty());
+        let token = supervisor.mark_exited(second).unwrap();
+        assert!(supervisor.is_current(token));
+        assert_eq!(supervisor.mark_exited(second), None);
+
+        // An abandoned launch's exit is expected but leaves its generation current
+        let third = supervisor.begin_launch();
+        supervisor.abandon(third);
+        assert_eq!(supervisor.mark_exited(third), None);
+        assert!(supervisor.is_current(third));
+    }
+}
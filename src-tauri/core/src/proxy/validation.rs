@@ -0,0 +1,644 @@
+//! Pre-flight checks on AppConfig before it is turned into proxy-config.yaml.
+//!
+//! CLIProxyAPI only reports a bad config by failing to come up, so anything
+//! we can catch here saves the user a 5 s readiness timeout and a vague error.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::config::AppConfig;
+use crate::proxy::config_yaml::build_openai_compat_section;
+use crate::types::{ConfigDiagnostic, ConfigValidation, DiagnosticSeverity};
+
+/// Proxy schemes CLIProxyAPI can dial out through
+const SUPPORTED_PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+struct Diagnostics(Vec<ConfigDiagnostic>);
+
+impl Diagnostics {
+    fn push(&mut self, severity: DiagnosticSeverity, code: &str, field: String, message: String) {
+        self.0.push(ConfigDiagnostic {
+            severity,
+            code: code.to_string(),
+            field,
+            message,
+        });
+    }
+
+    fn error(&mut self, code: &str, field: String, message: String) {
+        self.push(DiagnosticSeverity::Error, code, field, message);
+    }
+
+    fn warning(&mut self, code: &str, field: String, message: String) {
+        self.push(DiagnosticSeverity::Warning, code, field, message);
+    }
+}
+
+/// Check `config` for problems. `live_models` is the running proxy's model
+/// list, or `configured_models` before a launch; Amp mapping targets are only
+/// checked when it is given.
+pub fn validate_config(config: &AppConfig, live_models: Option<&[String]>) -> ConfigValidation {
+    let mut diagnostics = Diagnostics(Vec::new());
+
+    check_ports(config, &mut diagnostics);
+    check_api_keys(config, &mut diagnostics);
+    check_urls(config, &mut diagnostics);
+    check_model_aliases(config, &mut diagnostics);
+    check_amp_mappings(config, live_models, &mut diagnostics);
+
+    let diagnostics = diagnostics.0;
+    ConfigValidation {
+        valid: diagnostics
+            .iter()
+            .all(|d| d.severity != DiagnosticSeverity::Error),
+        diagnostics,
+    }
+}
+
+fn check_ports(config: &AppConfig, diagnostics: &mut Diagnostics) {
+    if config.port == 0 {
+        diagnostics.error(
+            "invalid-port",
+            "port".to_string(),
+            "Proxy port must be between 1 and 65535".to_string(),
+        );
+    } else if config.port < 1024 {
+        diagnostics.warning(
+            "privileged-port",
+            "port".to_string(),
+            format!(
+                "Port {} is below 1024 and usually needs administrator rights",
+                config.port
+            ),
+        );
+    }
+
+    if config.copilot.enabled {
+        if config.copilot.port == 0 {
+            diagnostics.error(
+                "invalid-port",
+                "copilot.port".to_string(),
+                "Copilot port must be between 1 and 65535".to_string(),
+            );
+        } else if config.copilot.port == config.port {
+            diagnostics.error(
+                "port-conflict",
+                "copilot.port".to_string(),
+                format!(
+                    "Copilot and the proxy are both configured to use port {}",
+                    config.port
+                ),
+            );
+        }
+    }
+
+    if config.control_api.enabled {
+        let port = config.control_api.port;
+        let taken_by = if port == config.port {
+            Some("the proxy")
+        } else if config.copilot.enabled && port == config.copilot.port {
+            Some("Copilot")
+        } else {
+            None
+        };
+        if port == 0 {
+            diagnostics.error(
+                "invalid-port",
+                "controlApi.port".to_string(),
+                "Control API port must be between 1 and 65535".to_string(),
+            );
+        } else if let Some(taken_by) = taken_by {
+            diagnostics.error(
+                "port-conflict",
+                "controlApi.port".to_string(),
+                format!(
+                    "The control API and {} are both configured to use port {}",
+                    taken_by, port
+                ),
+            );
+        }
+    }
+}
+
+fn check_api_keys(config: &AppConfig, diagnostics: &mut Diagnostics) {
+    let keyed: [(&str, Vec<&str>); 4] = [
+        (
+            "claudeApiKeys",
+            config.claude_api_keys.iter().map(|k| k.api_key.as_str()).collect(),
+        ),
+        (
+            "geminiApiKeys",
+            config.gemini_api_keys.iter().map(|k| k.api_key.as_str()).collect(),
+        ),
+        (
+            "codexApiKeys",
+            config.codex_api_keys.iter().map(|k| k.api_key.as_str()).collect(),
+        ),
+        (
+            "vertexApiKeys",
+            config.vertex_api_keys.iter().map(|k| k.api_key.as_str()).collect(),
+        ),
+    ];
+    for (section, keys) in keyed {
+        for (i, key) in keys.iter().enumerate() {
+            if key.trim().is_empty() {
+                diagnostics.error(
+                    "empty-api-key",
+                    format!("{}[{}].apiKey", section, i),
+                    "API key is empty".to_string(),
+                );
+            }
+        }
+    }
+
+    for (i, provider) in config.amp_openai_providers.iter().enumerate() {
+        if provider.api_key.trim().is_empty() {
+            // build_openai_compat_section drops these silently
+            diagnostics.warning(
+                "empty-api-key",
+                format!("ampOpenaiProviders[{}].apiKey", i),
+                format!(
+                    "Provider '{}' has no API key and will be skipped",
+                    provider.name
+                ),
+            );
+        }
+    }
+
+    if config.management_key.trim().is_empty() {
+        diagnostics.error(
+            "empty-management-key",
+            "managementKey".to_string(),
+            "Management key is empty; ProxyPal can't talk to the proxy without it".to_string(),
+        );
+    }
+    if config.proxy_api_key.trim().is_empty() {
+        diagnostics.warning(
+            "empty-proxy-api-key",
+            "proxyApiKey".to_string(),
+            "Proxy API key is empty, so clients can't authenticate".to_string(),
+        );
+    }
+}
+
+fn check_base_url(field: String, url: &str, diagnostics: &mut Diagnostics) {
+    match url::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => {}
+        Ok(parsed) if !matches!(parsed.scheme(), "http" | "https") => diagnostics.error(
+            "invalid-base-url",
+            field,
+            format!("Base URL '{}' must use http or https", url),
+        ),
+        Ok(_) => diagnostics.error(
+            "invalid-base-url",
+            field,
+            format!("Base URL '{}' has no host", url),
+        ),
+        Err(e) => diagnostics.error(
+            "invalid-base-url",
+            field,
+            format!("Base URL '{}' is not a valid URL: {}", url, e),
+        ),
+    }
+}
+
+fn check_proxy_url(field: String, url: &str, diagnostics: &mut Diagnostics) {
+    match url::Url::parse(url) {
+        Ok(parsed) if SUPPORTED_PROXY_SCHEMES.contains(&parsed.scheme()) => {
+            if !parsed.has_host() {
+                diagnostics.error(
+                    "invalid-proxy-url",
+                    field,
+                    format!("Proxy URL '{}' has no host", url),
+                );
+            }
+        }
+        Ok(parsed) => diagnostics.error(
+            "unsupported-proxy-scheme",
+            field,
+            format!(
+                "Proxy scheme '{}' is not supported; use one of {}",
+                parsed.scheme(),
+                SUPPORTED_PROXY_SCHEMES.join(", ")
+            ),
+        ),
+        Err(e) => diagnostics.error(
+            "invalid-proxy-url",
+            field,
+            format!("Proxy URL '{}' is not a valid URL: {}", url, e),
+        ),
+    }
+}
+
+fn non_blank(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|v| !v.trim().is_empty())
+}
+
+fn check_urls(config: &AppConfig, diagnostics: &mut Diagnostics) {
+    // The system proxy is resolved at start time and isn't ours to validate
+    if !config.use_system_proxy && !config.proxy_url.trim().is_empty() {
+        check_proxy_url("proxyUrl".to_string(), config.proxy_url.trim(), diagnostics);
+    }
+
+    for (i, key) in config.claude_api_keys.iter().enumerate() {
+        if let Some(url) = non_blank(&key.base_url) {
+            check_base_url(format!("claudeApiKeys[{}].baseUrl", i), url, diagnostics);
+        }
+        if let Some(url) = non_blank(&key.proxy_url) {
+            check_proxy_url(format!("claudeApiKeys[{}].proxyUrl", i), url, diagnostics);
+        }
+    }
+    for (i, key) in config.gemini_api_keys.iter().enumerate() {
+        if let Some(url) = non_blank(&key.base_url) {
+            check_base_url(format!("geminiApiKeys[{}].baseUrl", i), url, diagnostics);
+        }
+        if let Some(url) = non_blank(&key.proxy_url) {
+            check_proxy_url(format!("geminiApiKeys[{}].proxyUrl", i), url, diagnostics);
+        }
+    }
+    for (i, key) in config.codex_api_keys.iter().enumerate() {
+        if let Some(url) = non_blank(&key.base_url) {
+            check_base_url(format!("codexApiKeys[{}].baseUrl", i), url, diagnostics);
+        }
+        if let Some(url) = non_blank(&key.proxy_url) {
+            check_proxy_url(format!("codexApiKeys[{}].proxyUrl", i), url, diagnostics);
+        }
+    }
+    for (i, key) in config.vertex_api_keys.iter().enumerate() {
+        if let Some(url) = non_blank(&key.base_url) {
+            check_base_url(format!("vertexApiKeys[{}].baseUrl", i), url, diagnostics);
+        }
+    }
+    for (i, provider) in config.amp_openai_providers.iter().enumerate() {
+        let field = format!("ampOpenaiProviders[{}].baseUrl", i);
+        if provider.base_url.trim().is_empty() {
+            diagnostics.warning(
+                "invalid-base-url",
+                field,
+                format!(
+                    "Provider '{}' has no base URL and will be skipped",
+                    provider.name
+                ),
+            );
+        } else {
+            check_base_url(field, provider.base_url.trim(), diagnostics);
+        }
+    }
+}
+
+/// The model name a client sends, as the proxy will expose it
+fn exposed_name(prefix: &Option<String>, name: &str) -> String {
+    match prefix.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(prefix) => format!("{}/{}", prefix, name),
+        None => name.to_string(),
+    }
+}
+
+/// Models the config itself declares: Claude API key aliases, openai-compat
+/// provider models and, with Copilot on, Copilot's list. OAuth accounts serve
+/// more, so a target missing here only gets a warning.
+pub fn configured_models(config: &AppConfig) -> Vec<String> {
+    let mut models: Vec<String> = config
+        .claude_api_keys
+        .iter()
+        .flat_map(|key| {
+            key.models.iter().flatten().map(move |model| {
+                let alias = model
+                    .alias
+                    .as_deref()
+                    .filter(|a| !a.is_empty())
+                    .unwrap_or(&model.name);
+                exposed_name(&key.prefix, alias)
+            })
+        })
+        .collect();
+    for entry in build_openai_compat_section(config) {
+        models.extend(entry.models.into_iter().map(|m| {
+            if m.alias.is_empty() {
+                m.name
+            } else {
+                m.alias
+            }
+        }));
+    }
+    models
+}
+
+fn check_model_aliases(config: &AppConfig, diagnostics: &mut Diagnostics) {
+    // exposed name -> (provider, field) of first declaration
+    let mut seen: HashMap<String, (String, String)> = HashMap::new();
+    let mut declare = |name: String, provider: String, field: String, diagnostics: &mut Diagnostics| {
+        if name.is_empty() {
+            return;
+        }
+        match seen.get(&name) {
+            // Several keys for the same provider serving one model is load balancing
+            Some((first_provider, _)) if *first_provider == provider => {}
+            Some((first_provider, first_field)) => diagnostics.warning(
+                "duplicate-model-alias",
+                field,
+                format!(
+                    "Model '{}' is also provided by {} ({}); requests may go to either",
+                    name, first_provider, first_field
+                ),
+            ),
+            None => {
+                seen.insert(name, (provider, field));
+            }
+        }
+    };
+
+    for (i, key) in config.claude_api_keys.iter().enumerate() {
+        for (j, model) in key.models.iter().flatten().enumerate() {
+            let alias = model
+                .alias
+                .as_deref()
+                .filter(|a| !a.is_empty())
+                .unwrap_or(&model.name);
+            declare(
+                exposed_name(&key.prefix, alias),
+                "Claude API keys".to_string(),
+                format!("claudeApiKeys[{}].models[{}]", i, j),
+                diagnostics,
+            );
+        }
+    }
+
+    for (i, provider) in config.amp_openai_providers.iter().enumerate() {
+        let mut in_provider = HashSet::new();
+        for (j, model) in provider.models.iter().enumerate() {
+            let alias = if model.alias.is_empty() {
+                &model.name
+            } else {
+                &model.alias
+            };
+            let field = format!("ampOpenaiProviders[{}].models[{}]", i, j);
+            if !alias.is_empty() && !in_provider.insert(alias.clone()) {
+                diagnostics.error(
+                    "duplicate-model-alias",
+                    field,
+                    format!(
+                        "Provider '{}' declares model alias '{}' more than once",
+                        provider.name, alias
+                    ),
+                );
+                continue;
+            }
+            declare(
+                alias.clone(),
+                format!("provider '{}'", provider.name),
+                field,
+                diagnostics,
+            );
+        }
+    }
+}
+
+fn check_amp_mappings(
+    config: &AppConfig,
+    live_models: Option<&[String]>,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut mapped = HashSet::new();
+    for (i, mapping) in config.amp_model_mappings.iter().enumerate() {
+        if !mapping.enabled {
+            continue;
+        }
+        let field = format!("ampModelMappings[{}]", i);
+        if mapping.name.trim().is_empty() || mapping.alias.trim().is_empty() {
+            diagnostics.error(
+                "incomplete-amp-mapping",
+                field,
+                "Amp model mapping needs both a source and a target model".to_string(),
+            );
+            continue;
+        }
+        if !mapped.insert(mapping.name.as_str()) {
+            diagnostics.error(
+                "duplicate-amp-mapping",
+                field,
+                format!("Amp model '{}' is mapped more than once", mapping.name),
+            );
+            continue;
+        }
+        if let Some(models) = live_models {
+            if !models.iter().any(|m| m == &mapping.alias) {
+                diagnostics.warning(
+                    "unknown-amp-target",
+                    field,
+                    format!(
+                        "Amp model '{}' maps to '{}', which no provider is known to serve",
+                        mapping.name, mapping.alias
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// One-line summary of the errors, for `start_proxy`'s error string
+pub fn summarize_errors(validation: &ConfigValidation) -> String {
+    validation
+        .errors()
+        .map(|d| format!("{}: {}", d.field, d.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AmpModelMapping, AmpOpenAIModel, AmpOpenAIProvider, ClaudeApiKey, ModelMapping};
+
+    fn codes(validation: &ConfigValidation) -> Vec<(DiagnosticSeverity, &str, &str)> {
+        validation
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.code.as_str(), d.field.as_str()))
+            .collect()
+    }
+
+    fn claude_key(api_key: &str) -> ClaudeApiKey {
+        ClaudeApiKey {
+            api_key: api_key.to_string(),
+            base_url: None,
+            proxy_url: None,
+            headers: None,
+            models: None,
+            excluded_models: None,
+            prefix: None,
+        }
+    }
+
+    fn openai_provider(name: &str, models: &[(&str, &str)]) -> AmpOpenAIProvider {
+        AmpOpenAIProvider {
+            id: name.to_string(),
+            name: name.to_string(),
+            base_url: "https://api.example.com/v1".to_string(),
+            api_key: "sk-test".to_string(),
+            models: models
+                .iter()
+                .map(|(name, alias)| AmpOpenAIModel {
+                    name: name.to_string(),
+                    alias: alias.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        let validation = validate_config(&AppConfig::default(), None);
+        assert!(validation.valid);
+        assert!(validation.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn ports_are_checked() {
+        let mut config = AppConfig::default();
+        config.port = 0;
+        config.copilot.enabled = true;
+        config.copilot.port = 0;
+        let validation = validate_config(&config, None);
+        assert!(!validation.valid);
+        assert_eq!(
+            codes(&validation),
+            vec![
+                (DiagnosticSeverity::Error, "invalid-port", "port"),
+                (DiagnosticSeverity::Error, "invalid-port", "copilot.port"),
+            ]
+        );
+
+        config.port = 80;
+        config.copilot.port = 80;
+        config.control_api.enabled = true;
+        config.control_api.port = 80;
+        assert_eq!(
+            codes(&validate_config(&config, None)),
+            vec![
+                (DiagnosticSeverity::Warning, "privileged-port", "port"),
+                (DiagnosticSeverity::Error, "port-conflict", "copilot.port"),
+                (DiagnosticSeverity::Error, "port-conflict", "controlApi.port"),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_keys_and_bad_urls_are_errors() {
+        let mut config = AppConfig::default();
+        let mut key = claude_key(" ");
+        key.base_url = Some("api.anthropic.com".to_string());
+        key.proxy_url = Some("ftp://proxy:21".to_string());
+        config.claude_api_keys.push(key);
+        config.proxy_url = "socks5://127.0.0.1:1080".to_string();
+
+        let validation = validate_config(&config, None);
+        assert!(!validation.valid);
+        assert_eq!(
+            codes(&validation),
+            vec![
+                (DiagnosticSeverity::Error, "empty-api-key", "claudeApiKeys[0].apiKey"),
+                (DiagnosticSeverity::Error, "invalid-base-url", "claudeApiKeys[0].baseUrl"),
+                (
+                    DiagnosticSeverity::Error,
+                    "unsupported-proxy-scheme",
+                    "claudeApiKeys[0].proxyUrl"
+                ),
+            ]
+        );
+        assert!(summarize_errors(&validation).starts_with("claudeApiKeys[0].apiKey: API key is empty"));
+    }
+
+    #[test]
+    fn duplicate_aliases_across_providers_are_warnings() {
+        let mut config = AppConfig::default();
+        let mut first = claude_key("sk-1");
+        first.models = Some(vec![ModelMapping {
+            name: "claude-sonnet-4-5".to_string(),
+            alias: Some("sonnet".to_string()),
+        }]);
+        let mut second = first.clone();
+        second.api_key = "sk-2".to_string();
+        config.claude_api_keys = vec![first, second];
+        config.amp_openai_providers = vec![
+            openai_provider("zai", &[("glm-4.6", "sonnet")]),
+            openai_provider("dup", &[("a", "same"), ("b", "same")]),
+        ];
+
+        let validation = validate_config(&config, None);
+        assert_eq!(
+            codes(&validation),
+            vec![
+                (
+                    DiagnosticSeverity::Warning,
+                    "duplicate-model-alias",
+                    "ampOpenaiProviders[0].models[0]"
+                ),
+                (
+                    DiagnosticSeverity::Error,
+                    "duplicate-model-alias",
+                    "ampOpenaiProviders[1].models[1]"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn amp_targets_are_checked_against_live_models() {
+        let mut config = AppConfig::default();
+        let mapping = |name: &str, alias: &str| AmpModelMapping {
+            name: name.to_string(),
+            alias: alias.to_string(),
+            enabled: true,
+            fork: false,
+        };
+        config.amp_model_mappings = vec![
+            mapping("claude-opus-4-5", "gpt-5"),
+            mapping("claude-haiku-4-5", "missing-model"),
+            mapping("claude-opus-4-5", "gpt-5-mini"),
+        ];
+
+        let offline = validate_config(&config, None);
+        assert_eq!(
+            codes(&offline),
+            vec![(DiagnosticSeverity::Error, "duplicate-amp-mapping", "ampModelMappings[2]")]
+        );
+
+        let live = vec!["gpt-5".to_string()];
+        let online = validate_config(&config, Some(&live));
+        assert_eq!(
+            codes(&online),
+            vec![
+                (DiagnosticSeverity::Warning, "unknown-amp-target", "ampModelMappings[1]"),
+                (DiagnosticSeverity::Error, "duplicate-amp-mapping", "ampModelMappings[2]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_models_cover_aliases_providers_and_copilot() {
+        let mut config = AppConfig::default();
+        config.amp_openai_providers = vec![openai_provider("zen", &[("glm-4.6", "glm")])];
+        assert_eq!(configured_models(&config), vec!["glm".to_string()]);
+
+        config.copilot.enabled = true;
+        config.amp_model_mappings = vec![
+            AmpModelMapping {
+                name: "claude-opus-4-5".to_string(),
+                alias: "gpt-4.1".to_string(),
+                enabled: true,
+                fork: false,
+            },
+            AmpModelMapping {
+                name: "claude-haiku-4-5".to_string(),
+                alias: "glm-4.6".to_string(),
+                enabled: true,
+                fork: false,
+            },
+        ];
+        let validation = validate_config(&config, Some(&configured_models(&config)));
+        assert_eq!(
+            codes(&validation),
+            vec![(DiagnosticSeverity::Warning, "unknown-amp-target", "ampModelMappings[1]")]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Fixtures shared by the unit tests here and, through the `test-support`
+//! feature, the app's.
+
+use std::path::PathBuf;
+
+use crate::types::amp::generate_uuid;
+use crate::types::RequestLog;
+
+/// A fresh directory under the system temp dir; tests remove it themselves
+pub fn test_dir(prefix: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("proxypal-{}-{}", prefix, generate_uuid()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A logged Claude request with a few tokens and no request id
+pub fn request(id: &str, timestamp: u64, status: u16) -> RequestLog {
+    RequestLog {
+        id: id.to_string(),
+        timestamp,
+        provider: "claude".to_string(),
+        model: "claude-sonnet-4".to_string(),
+        method: "POST".to_string(),
+        path: format!("/v1/messages?{}", id),
+        status,
+        duration_ms: 120,
+        tokens_in: Some(100),
+        tokens_out: Some(20),
+        tokens_cached: None,
+        cost_usd: None,
+        request_id: None,
+    }
+}
@@ -35,6 +35,6 @@ pub struct AmpOpenAIProvider {
     pub models: Vec<AmpOpenAIModel>,
 }
 
-pub(crate) fn generate_uuid() -> String {
+pub fn generate_uuid() -> String {
     uuid::Uuid::new_v4().to_string()
 }
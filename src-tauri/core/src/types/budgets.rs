@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Day,
+    Week, // Starting on Monday
+    Month,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetMetric {
+    Cost,   // USD
+    Tokens, // Input plus output
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetScope {
+    #[default]
+    Global,
+    Provider,
+    Model,
+}
+
+// A spending or token limit, as listed in config.json
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Budget {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_budget_enabled")]
+    pub enabled: bool,
+    pub period: BudgetPeriod,
+    pub metric: BudgetMetric,
+    pub limit: f64,
+    #[serde(default)]
+    pub scope: BudgetScope,
+    #[serde(default)]
+    pub target: String, // Provider or model name for those scopes
+    #[serde(default)]
+    pub pause_accounts: bool, // Disable the matching auth files at 100% until the period ends
+}
+
+fn default_budget_enabled() -> bool {
+    true
+}
+
+// How much of a budget the current period has used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub id: String,
+    pub name: String,
+    pub period_start: u64, // Unix millis
+    pub used: f64,
+    pub limit: f64,
+    pub percent: f64,
+    pub paused_accounts: Vec<String>,
+}
+
+// Payload of `budget-exceeded`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetAlert {
+    pub id: String,
+    pub name: String,
+    pub used: f64,
+    pub limit: f64,
+    pub metric: BudgetMetric,
+    pub period: BudgetPeriod,
+    pub paused_accounts: Vec<String>,
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+// ProxyPal's own localhost API for scripts and editor extensions
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_control_api_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub token: String, // Bearer token, generated when the API is first enabled
+}
+
+fn default_control_api_port() -> u16 {
+    8320
+}
+
+impl Default for ControlApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_control_api_port(),
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlApiStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    pub url: String,
+    pub token: String,
+    pub error: Option<String>, // Why the server isn't listening, if it should be
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use super::ProxyStatus;
+
+// A proxy instance besides the default one, as listed in instances.json
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyInstanceDefinition {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64, // Unix millis
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyInstanceInfo {
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    pub dir: String, // Holds its config.json, proxy-config.yaml, logs and usage files
+    pub auth_dir: String, // Credential files CLIProxyAPI loads for this instance
+    pub status: ProxyStatus,
+}
+
+// Payload of `proxy-instance-status-changed`, sent for every instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceStatusEvent {
+    pub id: String,
+    pub status: ProxyStatus,
+}
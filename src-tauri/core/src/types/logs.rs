@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+// Log entry structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub message: String,
+}
+
+/// Which sidecar pipe a line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SidecarStream {
+    Stdout,
+    Stderr,
+}
+
+/// One line of CLIProxyAPI output, as captured by ProxyPal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarOutputLine {
+    pub seq: u64, // Increases by one per line; pass the last seen value as `since`
+    pub timestamp: u64, // Unix millis
+    pub stream: SidecarStream,
+    pub line: String,
+}
@@ -3,14 +3,22 @@ pub mod amp;
 pub mod api_keys;
 pub mod auth;
 pub mod auth_files;
+pub mod budgets;
+pub mod control_api;
 pub mod copilot;
 pub mod health;
+pub mod instances;
 pub mod logs;
 pub mod models;
+pub mod pricing;
+pub mod profiles;
 pub mod proxy;
 pub mod quota;
+pub mod secrets;
 pub mod settings;
+pub mod snapshots;
 pub mod usage;
+pub mod validation;
 
 pub mod ssh;
 pub mod cloudflare;
@@ -20,14 +28,22 @@ pub use amp::*;
 pub use api_keys::*;
 pub use auth::*;
 pub use auth_files::*;
+pub use budgets::*;
+pub use control_api::*;
 pub use copilot::*;
 pub use health::*;
+pub use instances::*;
 pub use logs::*;
 pub use models::*;
+pub use pricing::*;
+pub use profiles::*;
 pub use proxy::*;
 pub use quota::*;
+pub use secrets::*;
 pub use settings::*;
+pub use snapshots::*;
 pub use usage::*;
+pub use validation::*;
 pub use ssh::*;
 #[allow(unused_imports)]
 pub use cloudflare::*;
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+// USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenRates {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>, // Defaults to the input rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>, // Defaults to the input rate
+}
+
+// Rates for requests whose prompt is larger than `above_tokens`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LongContextTier {
+    pub above_tokens: u64,
+    #[serde(flatten)]
+    pub rates: TokenRates,
+}
+
+// One entry of pricing.json. `model` is a glob such as "claude-*-opus-*",
+// or a regex between slashes such as "/^o[134](-mini)?$/".
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingRule {
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>, // Only requests through this provider
+    #[serde(flatten)]
+    pub rates: TokenRates,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_context: Option<LongContextTier>,
+}
+
+// The shipped defaults and the user's pricing.json, which takes precedence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingTables {
+    pub defaults: Vec<PricingRule>,
+    pub overrides: Vec<PricingRule>,
+    pub overrides_path: String,
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+// Named AppConfig profile stored under the proxypal config dir
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInfo {
+    pub name: String,
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<u64>,
+}
+
+// Marker for which profile config.json was last activated from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveProfile {
+    #[serde(default)]
+    pub name: Option<String>,
+}
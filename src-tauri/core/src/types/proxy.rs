@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::DiffLine;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyStatus {
+    pub running: bool,
+    pub port: u16,
+    pub endpoint: String,
+    pub restart_count: u32, // Automatic restarts after the sidecar exited unexpectedly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>, // Why the supervisor gave up, cleared on the next start
+}
+
+impl Default for ProxyStatus {
+    fn default() -> Self {
+        Self {
+            running: false,
+            port: 8317,
+            endpoint: "http://localhost:8317/v1".to_string(),
+            restart_count: 0,
+            last_error: None,
+        }
+    }
+}
+
+/// Payload of `proxy-restarted`, emitted once a crashed sidecar is back up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyRestartedEvent {
+    pub attempt: u32, // Restarts since the last stable run
+    pub restart_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub delay_ms: u64,
+}
+
+/// Payload of `proxy-crash-loop`, emitted when the supervisor stops restarting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyCrashLoopEvent {
+    pub message: String,
+    pub exits: usize,
+    pub window_secs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub stderr_tail: Vec<String>,
+}
+
+/// How a list in proxy-config-custom.yaml combines with the generated list at the same path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListMergeStrategy {
+    #[default]
+    Append,
+    Prepend,
+    Replace,
+}
+
+/// Settings for merging proxy-config-custom.yaml into the generated config
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomConfigMergeSettings {
+    #[serde(default)]
+    pub list_strategy: ListMergeStrategy,
+    /// Per-path overrides keyed by dotted YAML path, e.g. "payload.default"
+    #[serde(default)]
+    pub path_strategies: HashMap<String, ListMergeStrategy>,
+}
+
+impl CustomConfigMergeSettings {
+    pub fn strategy_for(&self, path: &str) -> ListMergeStrategy {
+        self.path_strategies
+            .get(path)
+            .copied()
+            .unwrap_or(self.list_strategy)
+    }
+}
+
+/// A generated value that proxy-config-custom.yaml overrode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub path: String,
+    pub generated: String,
+    pub custom: String,
+}
+
+/// What the preview was diffed against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyConfigBaseline {
+    Running, // The YAML the running sidecar loaded
+    File,    // proxy-config.yaml on disk; the proxy isn't running
+    None,    // No proxy-config.yaml has been written yet
+}
+
+/// proxy-config.yaml as it would be generated, diffed against the current one.
+/// Secrets are masked in both the YAML and the diff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfigPreview {
+    pub yaml: String,
+    pub baseline: ProxyConfigBaseline,
+    pub changed: bool,
+    pub diff: Vec<DiffLine>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// How a config change was applied to the running proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReloadPath {
+    None,       // Nothing the proxy uses changed, or it isn't running
+    Management, // Pushed through /v0/management endpoints only
+    FileReload, // Picked up by CLIProxyAPI's config file watcher
+    Restart,    // Sidecar restarted
+}
+
+/// Which settings changed and how each reached the proxy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadReport {
+    pub path: ReloadPath,
+    pub management_fields: Vec<String>,
+    pub file_reload_fields: Vec<String>,
+    pub restart_fields: Vec<String>,
+}
+
+/// Where a proxy port change was carried over to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortChangeReport {
+    pub old_port: u16,
+    pub new_port: u16,
+    pub updated_files: Vec<String>, // Agent config files rewritten to the new port
+    pub updated_tunnels: Vec<String>, // SSH and Cloudflare tunnels now forwarding the new port
+    pub errors: Vec<String>,
+}
+
+/// Log line layout written by the sidecar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SidecarLogFormat {
+    /// `[2025-12-02 22:12:52] [info] [gin_logger.go:58] message`
+    Bracketed,
+    /// `2024-01-15T10:30:45.123Z [INFO] message`, from older releases
+    Iso,
+    /// Version not detected; bracketed is tried first
+    #[default]
+    Unknown,
+}
+
+/// What the running CLIProxyAPI supports, derived from its version.
+/// Commands branch on these flags instead of probing endpoints.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarCapabilities {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>, // None until detected, or if detection failed
+    pub minimum_version: String,
+    pub outdated: bool, // Older than minimum_version; the UI shows an upgrade warning
+    pub auth_file_status_api: bool, // PATCH auth-files/status (v6.7.18+), else files are renamed
+    pub auth_status_api: bool, // GET /api/auth/status (v6.6.72+)
+    pub log_format: SidecarLogFormat,
+}
+
+/// A CLIProxyAPI version installed into the sidecars directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarBinary {
+    pub version: String,
+    pub path: String,
+    pub sha256: String, // Of the release archive it came from
+    pub source: String, // URL or local path of that archive
+    pub installed_at: String, // RFC 3339
+    #[serde(default)]
+    pub active: bool, // Launched instead of the bundled sidecar
+}
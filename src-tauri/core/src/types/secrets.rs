@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+// How the secret vault key is obtained
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum VaultMode {
+    Passphrase, // Derived from a passphrase the user enters after each launch
+    KeyFile,    // Random key stored next to the vault, unlocked automatically
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<VaultMode>,
+    pub secret_count: usize,
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+// How many config snapshots to keep; 0 disables that limit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotRetention {
+    #[serde(default = "default_snapshot_max_count")]
+    pub max_count: u32,
+    #[serde(default = "default_snapshot_max_age_days")]
+    pub max_age_days: u32,
+}
+
+fn default_snapshot_max_count() -> u32 {
+    20
+}
+
+fn default_snapshot_max_age_days() -> u32 {
+    30
+}
+
+impl Default for SnapshotRetention {
+    fn default() -> Self {
+        Self {
+            max_count: default_snapshot_max_count(),
+            max_age_days: default_snapshot_max_age_days(),
+        }
+    }
+}
+
+// Point-in-time copy of config.json and the proxy YAML files
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSnapshot {
+    pub id: String,
+    pub created_at: u64, // Unix millis
+    pub reason: String,  // What triggered it, e.g. "save-config"
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+    Equal,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+// Line diff of one snapshot file against the file on disk now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFileDiff {
+    pub file: String,
+    pub changed: bool,
+    pub lines: Vec<DiffLine>,
+}
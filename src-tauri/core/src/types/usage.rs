@@ -14,6 +14,10 @@ pub struct RequestLog {
     pub tokens_in: Option<u32>,
     pub tokens_out: Option<u32>,
     pub tokens_cached: Option<u32>,
+    /// Estimated from the tokens once they are known
+    pub cost_usd: Option<f64>,
+    /// The proxy's own ID for the request, from its log line
+    pub request_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
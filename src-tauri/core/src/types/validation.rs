@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,   // The proxy would fail to start or misbehave
+    Warning, // Starts fine, but probably not what the user meant
+}
+
+// One problem found in AppConfig, pointing at the offending field
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub code: String,  // Stable identifier, e.g. "invalid-base-url"
+    pub field: String, // camelCase path, e.g. "claudeApiKeys[0].baseUrl"
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigValidation {
+    pub valid: bool, // No errors; warnings are allowed
+    pub diagnostics: Vec<ConfigDiagnostic>,
+}
+
+impl ConfigValidation {
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Warning)
+    }
+}
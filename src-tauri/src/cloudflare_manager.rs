@@ -6,6 +6,7 @@ use tauri::{AppHandle, Emitter};
 use tokio::process::Command;
 use tokio::sync::Notify;
 
+use crate::events;
 use crate::types::cloudflare::CloudflareConfig;
 
 /// Find cloudflared binary path - checks common installation locations
@@ -111,7 +112,7 @@ impl CloudflareManager {
         let config_clone = config.clone();
         
         let emit_status = move |status: &str, msg: Option<String>, url: Option<String>| {
-            let _ = app.emit("cloudflare-status-changed", CloudflareStatusUpdate {
+            let _ = app.emit(events::CLOUDFLARE_STATUS_CHANGED, CloudflareStatusUpdate {
                 id: config_clone.id.clone(),
                 status: status.to_string(),
                 message: msg,
@@ -133,6 +134,14 @@ impl CloudflareManager {
                 return;
             }
             let cloudflared_bin = cloudflared_path.unwrap();
+
+            let tunnel_token = match crate::helpers::secrets::resolve_secret(&config.tunnel_token) {
+                Ok(token) => token,
+                Err(e) => {
+                    emit_status_clone("error", Some(e), None);
+                    return;
+                }
+            };
             
             let mut retry_count = 0;
             const MAX_RETRIES: u32 = 3;
@@ -146,7 +155,7 @@ impl CloudflareManager {
                 // cloudflared tunnel --url http://localhost:<port>
                 let mut cmd = Command::new(&cloudflared_bin);
                 
-                if config.tunnel_token.is_empty() {
+                if tunnel_token.is_empty() {
                     // Quick tunnel mode - expose local port directly
                     cmd.arg("tunnel");
                     cmd.arg("--url");
@@ -157,7 +166,7 @@ impl CloudflareManager {
                     cmd.arg("tunnel");
                     cmd.arg("run");
                     cmd.arg("--token");
-                    cmd.arg(&config.tunnel_token);
+                    cmd.arg(&tunnel_token);
                 }
 
                 emit_status_clone("connecting", Some(format!("Connecting to port {}...", config.local_port)), None);
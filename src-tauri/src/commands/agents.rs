@@ -3,6 +3,7 @@
 //! Extracted from lib.rs — handles detection and configuration of CLI agents
 //! (Claude Code, Codex, Gemini CLI, etc.) and IDE tools (Cursor, Continue, etc.).
 
+use crate::helpers::agent_config::configure_agent;
 use crate::state::AppState;
 use crate::types::{AgentStatus, AvailableModel, DetectedTool};
 #[cfg(target_os = "windows")]
@@ -311,729 +312,8 @@ pub async fn configure_cli_agent(
     agent_id: String,
     models: Vec<AvailableModel>,
 ) -> Result<serde_json::Value, String> {
-    let (port, endpoint, endpoint_v1) = {
-        let config = state.config.lock().unwrap();
-        let port = config.port;
-        let endpoint = format!("http://127.0.0.1:{}", port);
-        let endpoint_v1 = format!("{}/v1", endpoint);
-        (port, endpoint, endpoint_v1)
-    }; // Mutex guard dropped here
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-
-    // Precompute thinking/reasoning config for opencode
-    let (thinking_budget, reasoning_effort) = {
-        let config = state.config.lock().unwrap();
-        let mode = if config.thinking_budget_mode.is_empty() {
-            "medium"
-        } else {
-            &config.thinking_budget_mode
-        };
-        let custom = if config.thinking_budget_custom == 0 {
-            16000
-        } else {
-            config.thinking_budget_custom
-        };
-        let budget: u64 = match mode {
-            "low" => 2048,
-            "medium" => 8192,
-            "high" => 32768,
-            "custom" => custom as u64,
-            _ => 8192,
-        };
-        let effort = if config.reasoning_effort_level.is_empty() {
-            "medium".to_string()
-        } else {
-            config.reasoning_effort_level.clone()
-        };
-        (budget, effort)
-    };
-
-    match agent_id.as_str() {
-        "claude-code" => configure_claude_code_agent(&home, &endpoint, &models),
-
-        "codex" => {
-            // Create ~/.codex directory
-            let codex_dir = home.join(".codex");
-            std::fs::create_dir_all(&codex_dir).map_err(|e| e.to_string())?;
-
-            // Write config.toml
-            let config_content = format!(
-                r#"# ProxyPal - Codex Configuration
-model_provider = "cliproxyapi"
-model = "gpt-5-codex"
-model_reasoning_effort = "high"
-
-[model_providers.cliproxyapi]
-name = "cliproxyapi"
-base_url = "{}/v1"
-wire_api = "responses"
-"#,
-                endpoint
-            );
-
-            let config_path = codex_dir.join("config.toml");
-            std::fs::write(&config_path, &config_content).map_err(|e| e.to_string())?;
-
-            // Write auth.json
-            let auth_content = r#"{
-  "OPENAI_API_KEY": "proxypal-local"
-}"#;
-            let auth_path = codex_dir.join("auth.json");
-            std::fs::write(&auth_path, auth_content).map_err(|e| e.to_string())?;
-
-            Ok(serde_json::json!({
-                "success": true,
-                "configType": "file",
-                "configPath": config_path.to_string_lossy(),
-                "authPath": auth_path.to_string_lossy(),
-                "instructions": "Codex has been configured. Run 'codex' to start using it."
-            }))
-        }
-
-        "gemini-cli" => {
-            // Generate shell config for Gemini CLI
-            let shell_config = format!(
-                r#"# ProxyPal - Gemini CLI Configuration
-# Option 1: OAuth mode (local only)
-export CODE_ASSIST_ENDPOINT="{}"
-
-# Option 2: API Key mode (works with any IP/domain)
-# export GOOGLE_GEMINI_BASE_URL="{}"
-# export GEMINI_API_KEY="proxypal-local"
-"#,
-                endpoint, endpoint
-            );
-
-            Ok(serde_json::json!({
-                "success": true,
-                "configType": "env",
-                "shellConfig": shell_config,
-                "instructions": "Add the above to your ~/.bashrc, ~/.zshrc, or shell config file, then restart your terminal."
-            }))
-        }
-
-        "factory-droid" => configure_factory_droid_agent(&home, &endpoint, &models),
-
-        "amp-cli" => configure_amp_cli_agent(&home, port),
-
-        "opencode" => configure_opencode_agent(
-            &home,
-            &endpoint,
-            &endpoint_v1,
-            &models,
-            thinking_budget,
-            &reasoning_effort,
-        ),
-
-        _ => Err(format!("Unknown agent: {}", agent_id)),
-    }
-}
-
-fn configure_claude_code_agent(
-    home: &std::path::Path,
-    endpoint: &str,
-    models: &[AvailableModel],
-) -> Result<serde_json::Value, String> {
-    // Write config to ~/.claude/settings.json (Claude Code's config file)
-    let config_dir = home.join(".claude");
-    std::fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
-    let config_path = config_dir.join("settings.json");
-
-    // Find best models for each tier from available models
-    // Priority: Claude > Gemini-Claude > Gemini > GPT
-    let find_model = |patterns: &[&str]| -> Option<String> {
-        for pattern in patterns {
-            if let Some(m) = models.iter().find(|m| m.id.contains(pattern)) {
-                return Some(m.id.clone());
-            }
-        }
-        None
-    };
-
-    // Opus tier: claude-opus > gpt-5(high)
-    let opus_model = find_model(&["claude-opus-4", "claude-opus", "gpt-5"])
-        .unwrap_or_else(|| "claude-opus-4-1-20250805".to_string());
-
-    // Sonnet tier: claude-sonnet-4-5 > gpt-5
-    let sonnet_model =
-        find_model(&["claude-sonnet-4-5", "claude-sonnet-4", "claude-sonnet", "gpt-5"])
-            .unwrap_or_else(|| "claude-sonnet-4-5-20250929".to_string());
-
-    // Haiku tier: claude-haiku > gemini-claude-sonnet > gemini-2.5-flash > gpt-5(minimal)
-    let haiku_model = find_model(&[
-        "claude-3-5-haiku",
-        "claude-haiku",
-        "gemini-claude-sonnet-4-5",
-        "gemini-2.5-flash",
-        "gpt-5",
-    ])
-    .unwrap_or_else(|| "claude-3-5-haiku-20241022".to_string());
-
-    // Build env config for Claude Code settings.json
-    let env_config = serde_json::json!({
-        "ANTHROPIC_BASE_URL": endpoint,
-        "ANTHROPIC_AUTH_TOKEN": "proxypal-local",
-        "ANTHROPIC_MODEL": sonnet_model,
-        "ANTHROPIC_DEFAULT_OPUS_MODEL": opus_model,
-        "ANTHROPIC_DEFAULT_SONNET_MODEL": sonnet_model,
-        "ANTHROPIC_DEFAULT_HAIKU_MODEL": haiku_model
-    });
-
-    // If config exists, merge with existing (preserve other settings)
-    let final_config = if config_path.exists() {
-        if let Ok(existing) = std::fs::read_to_string(&config_path) {
-            if let Ok(mut existing_json) = serde_json::from_str::<serde_json::Value>(&existing) {
-                // Merge env into existing config
-                if let Some(env) = existing_json.get_mut("env") {
-                    if let Some(obj) = env.as_object_mut() {
-                        // Update ProxyPal-related env vars
-                        obj.insert(
-                            "ANTHROPIC_BASE_URL".to_string(),
-                            env_config["ANTHROPIC_BASE_URL"].clone(),
-                        );
-                        obj.insert(
-                            "ANTHROPIC_AUTH_TOKEN".to_string(),
-                            env_config["ANTHROPIC_AUTH_TOKEN"].clone(),
-                        );
-                        obj.insert(
-                            "ANTHROPIC_MODEL".to_string(),
-                            env_config["ANTHROPIC_MODEL"].clone(),
-                        );
-                        obj.insert(
-                            "ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(),
-                            env_config["ANTHROPIC_DEFAULT_OPUS_MODEL"].clone(),
-                        );
-                        obj.insert(
-                            "ANTHROPIC_DEFAULT_SONNET_MODEL".to_string(),
-                            env_config["ANTHROPIC_DEFAULT_SONNET_MODEL"].clone(),
-                        );
-                        obj.insert(
-                            "ANTHROPIC_DEFAULT_HAIKU_MODEL".to_string(),
-                            env_config["ANTHROPIC_DEFAULT_HAIKU_MODEL"].clone(),
-                        );
-                    }
-                } else {
-                    existing_json["env"] = env_config;
-                }
-                existing_json
-            } else {
-                serde_json::json!({ "env": env_config })
-            }
-        } else {
-            serde_json::json!({ "env": env_config })
-        }
-    } else {
-        serde_json::json!({ "env": env_config })
-    };
-
-    let config_str = serde_json::to_string_pretty(&final_config).map_err(|e| e.to_string())?;
-    std::fs::write(&config_path, &config_str).map_err(|e| e.to_string())?;
-
-    // Create a reference file with all available model options from each provider
-    let reference_path = config_dir.join("proxypal-models.md");
-    let reference_content = format!(
-        r#"# ProxyPal Model Reference for Claude Code
-
-Edit your `~/.claude/settings.json` and replace the model values in the `env` section.
-
-## Current Configuration
-```json
-"ANTHROPIC_BASE_URL": "{}",
-"ANTHROPIC_AUTH_TOKEN": "proxypal-local",
-"ANTHROPIC_MODEL": "{}",
-"ANTHROPIC_DEFAULT_OPUS_MODEL": "{}",
-"ANTHROPIC_DEFAULT_SONNET_MODEL": "{}",
-"ANTHROPIC_DEFAULT_HAIKU_MODEL": "{}"
-```
-
-## Available Models by Provider
-
-### Claude (Anthropic)
-| Tier | Model ID |
-|------|----------|
-| Opus | `claude-opus-4-1-20250805`, `claude-opus-4-5-20251101` |
-| Sonnet | `claude-sonnet-4-5-20250929`, `claude-sonnet-4-20250514` |
-| Haiku | `claude-3-5-haiku-20241022` |
-
-### Gemini via Antigravity (with extended thinking)
-| Tier | Model ID |
-|------|----------|
-| Opus | `claude-opus-4-5-thinking` |
-| Sonnet | `claude-sonnet-4-5-thinking`, `claude-sonnet-4-5` |
-| Haiku | `gemini-2.5-flash`, `gemini-2.5-flash-lite` |
-
-### Gemini (Google)
-| Tier | Model ID |
-|------|----------|
-| Opus | `gemini-2.5-pro` |
-| Sonnet | `gemini-2.5-flash` |
-| Haiku | `gemini-2.5-flash-lite` |
-
-### Vertex AI (Google Cloud)
-| Tier | Model ID |
-|------|----------|
-| Opus | `gemini-2.5-pro`, `gemini-3-pro-preview` |
-| Sonnet | `gemini-2.5-flash`, `gemini-3-pro-image-preview` |
-| Haiku | `gemini-2.5-flash-lite` |
-
-> **Note**: Vertex AI uses Google Cloud service account authentication.
-> Import your service account JSON in ProxyPal to use these models.
-
-### OpenAI GPT-5
-| Tier | Model ID |
-|------|----------|
-| Opus | `gpt-5(high)`, `gpt-5` |
-| Sonnet | `gpt-5(medium)`, `gpt-5-codex` |
-| Haiku | `gpt-5(minimal)`, `gpt-5(low)` |
-
-### Qwen
-| Tier | Model ID |
-|------|----------|
-| Opus | `qwen3-coder-plus`, `qwen3-max` |
-| Sonnet | `qwen3-coder-plus` |
-| Haiku | `qwen3-coder-flash`, `qwen3-235b-a22b-instruct` |
-
-### iFlow
-| Tier | Model ID |
-|------|----------|
-| Opus | `qwen3-max` |
-| Sonnet | `qwen3-coder-plus` |
-| Haiku | `qwen3-235b-a22b-instruct` |
-
-## Example Configurations
-
-### Use Gemini Antigravity (with thinking)
-```json
-"ANTHROPIC_DEFAULT_OPUS_MODEL": "claude-opus-4-5-thinking",
-"ANTHROPIC_DEFAULT_SONNET_MODEL": "claude-sonnet-4-5-thinking",
-"ANTHROPIC_DEFAULT_HAIKU_MODEL": "gemini-2.5-flash"
-```
-
-### Use OpenAI GPT-5
-```json
-"ANTHROPIC_DEFAULT_OPUS_MODEL": "gpt-5(high)",
-"ANTHROPIC_DEFAULT_SONNET_MODEL": "gpt-5(medium)",
-"ANTHROPIC_DEFAULT_HAIKU_MODEL": "gpt-5(minimal)"
-```
-
-### Use Qwen
-```json
-"ANTHROPIC_DEFAULT_OPUS_MODEL": "qwen3-coder-plus",
-"ANTHROPIC_DEFAULT_SONNET_MODEL": "qwen3-coder-plus",
-"ANTHROPIC_DEFAULT_HAIKU_MODEL": "qwen3-coder-flash"
-```
-
----
-Generated by ProxyPal. Run `claude` to start using Claude Code.
-"#,
-        endpoint, sonnet_model, opus_model, sonnet_model, haiku_model
-    );
-
-    std::fs::write(&reference_path, &reference_content).map_err(|e| e.to_string())?;
-
-    Ok(serde_json::json!({
-        "success": true,
-        "configType": "config",
-        "configPath": config_path.to_string_lossy(),
-        "modelsConfigured": models.len(),
-        "instructions": format!("ProxyPal configured for Claude Code. See {} for all available model options from different providers.", reference_path.to_string_lossy())
-    }))
-}
-
-fn configure_factory_droid_agent(
-    home: &std::path::Path,
-    endpoint: &str,
-    models: &[AvailableModel],
-) -> Result<serde_json::Value, String> {
-    // Create ~/.factory directory
-    let factory_dir = home.join(".factory");
-    std::fs::create_dir_all(&factory_dir).map_err(|e| e.to_string())?;
-
-    // Build dynamic custom_models array from available models
-    let proxypal_models: Vec<serde_json::Value> = models
-        .iter()
-        .map(|m| {
-            let (base_url, provider) = match m.owned_by.as_str() {
-                "anthropic" => (endpoint.to_string(), "anthropic"),
-                _ => (format!("{}/v1", endpoint), "openai"),
-            };
-
-            // Add source indicator to model display name for clarity
-            let display_name = match m.source.as_str() {
-                "vertex" => format!("{} [Vertex]", m.id),
-                "vertex+gemini-api" => format!("{} [Vertex+API]", m.id),
-                "copilot" => format!("{} [Copilot]", m.id),
-                _ => m.id.clone(),
-            };
-
-            serde_json::json!({
-                "model": m.id,
-                "model_display_name": display_name,
-                "base_url": base_url,
-                "api_key": "proxypal-local",
-                "provider": provider
-            })
-        })
-        .collect();
-
-    let config_path = factory_dir.join("config.json");
-
-    // Merge with existing config to preserve user's other custom_models
-    let final_config = if config_path.exists() {
-        if let Ok(existing) = std::fs::read_to_string(&config_path) {
-            if let Ok(mut existing_json) = serde_json::from_str::<serde_json::Value>(&existing) {
-                // Get existing custom_models, filter out proxypal entries, then add new ones
-                let mut merged_models: Vec<serde_json::Value> = Vec::new();
-
-                // Keep existing models that are NOT from proxypal (don't have proxypal-local api_key)
-                if let Some(existing_models) =
-                    existing_json.get("custom_models").and_then(|v| v.as_array())
-                {
-                    for model in existing_models {
-                        let is_proxypal = model
-                            .get("api_key")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s == "proxypal-local")
-                            .unwrap_or(false);
-                        if !is_proxypal {
-                            merged_models.push(model.clone());
-                        }
-                    }
-                }
-
-                // Add all proxypal models
-                merged_models.extend(proxypal_models);
-
-                // Update the custom_models field
-                existing_json["custom_models"] = serde_json::json!(merged_models);
-                existing_json
-            } else {
-                // Existing file is not valid JSON, create new
-                serde_json::json!({ "custom_models": proxypal_models })
-            }
-        } else {
-            // Can't read file, create new
-            serde_json::json!({ "custom_models": proxypal_models })
-        }
-    } else {
-        // No existing config, create new
-        serde_json::json!({ "custom_models": proxypal_models })
-    };
-
-    let config_str = serde_json::to_string_pretty(&final_config).map_err(|e| e.to_string())?;
-    std::fs::write(&config_path, &config_str).map_err(|e| e.to_string())?;
-
-    Ok(serde_json::json!({
-        "success": true,
-        "configType": "file",
-        "configPath": config_path.to_string_lossy(),
-        "modelsConfigured": models.len(),
-        "instructions": "Factory Droid has been configured. Run 'droid' or 'factory' to start using it."
-    }))
-}
-
-fn configure_amp_cli_agent(
-    home: &std::path::Path,
-    port: u16,
-) -> Result<serde_json::Value, String> {
-    // Create ~/.config/amp directory
-    let amp_dir = home.join(".config/amp");
-    std::fs::create_dir_all(&amp_dir).map_err(|e| e.to_string())?;
-
-    // Amp CLI requires localhost URL (not 127.0.0.1) per CLIProxyAPI docs
-    // See: https://help.router-for.me/agent-client/amp-cli.html
-    let amp_endpoint = format!("http://localhost:{}", port);
-
-    // NOTE: Model mappings are configured in CLIProxyAPI's config.yaml (proxy-config.yaml),
-    // NOT in Amp's settings.json. Amp CLI doesn't support amp.modelMapping setting.
-    // The mappings in ProxyPal settings are written to CLIProxyAPI config when proxy starts.
-    // See: https://help.router-for.me/agent-client/amp-cli.html#model-fallback-behavior
-
-    // ProxyPal settings to add/update (only valid Amp CLI settings)
-    let proxypal_settings = serde_json::json!({
-        // Core proxy URL - routes all Amp traffic through CLIProxyAPI
-        "amp.url": amp_endpoint,
-
-        // API key for authentication with the proxy
-        // This matches the api-keys in CLIProxyAPI config
-        "amp.apiKey": "proxypal-local",
-
-        // Enable extended thinking for Claude models
-        "amp.anthropic.thinking.enabled": true,
-
-        // Enable TODOs tracking
-        "amp.todos.enabled": true,
-
-        // Git commit settings - add Amp thread link and co-author
-        "amp.git.commit.ampThread.enabled": true,
-        "amp.git.commit.coauthor.enabled": true,
-
-        // Tool timeout (5 minutes)
-        "amp.tools.stopTimeout": 300,
-
-        // Auto-update mode
-        "amp.updates.mode": "auto"
-    });
-
-    let config_path = amp_dir.join("settings.json");
-
-    // Merge with existing config to preserve user's other settings
-    let final_config = if config_path.exists() {
-        if let Ok(existing) = std::fs::read_to_string(&config_path) {
-            if let Ok(mut existing_json) = serde_json::from_str::<serde_json::Value>(&existing) {
-                // Merge proxypal settings into existing config
-                if let Some(existing_obj) = existing_json.as_object_mut() {
-                    if let Some(new_obj) = proxypal_settings.as_object() {
-                        for (key, value) in new_obj {
-                            existing_obj.insert(key.clone(), value.clone());
-                        }
-                    }
-                    // Remove invalid amp.modelMapping key if it exists
-                    // Model mappings should be in CLIProxyAPI config, not Amp settings
-                    existing_obj.remove("amp.modelMapping");
-                }
-                existing_json
-            } else {
-                // Existing file is not valid JSON, create new
-                proxypal_settings
-            }
-        } else {
-            // Can't read file, create new
-            proxypal_settings
-        }
-    } else {
-        // No existing config, create new
-        proxypal_settings
-    };
-
-    let settings_content = serde_json::to_string_pretty(&final_config).map_err(|e| e.to_string())?;
-    std::fs::write(&config_path, &settings_content).map_err(|e| e.to_string())?;
-
-    // Also provide env var option and API key instructions
-    let shell_config = format!(
-        r#"# ProxyPal - Amp CLI Configuration (alternative to settings.json)
-export AMP_URL="{}"
-export AMP_API_KEY="proxypal-local"
-
-# For Amp cloud features, get your API key from https://ampcode.com/settings
-# and add it to ProxyPal Settings > Amp CLI Integration > Amp API Key
-"#,
-        amp_endpoint
-    );
-
-    Ok(serde_json::json!({
-        "success": true,
-        "configType": "both",
-        "configPath": config_path.to_string_lossy(),
-        "shellConfig": shell_config,
-        "instructions": "Amp CLI has been configured. Run 'amp' to start using it. The API key 'proxypal-local' is pre-configured for local proxy access."
-    }))
-}
-
-fn configure_opencode_agent(
-    home: &std::path::Path,
-    _endpoint: &str,
-    endpoint_v1: &str,
-    models: &[AvailableModel],
-    thinking_budget: u64,
-    reasoning_effort: &str,
-) -> Result<serde_json::Value, String> {
-    let config_dir = home.join(".config/opencode");
-    std::fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
-    let config_path = config_dir.join("opencode.json");
-
-    // Build dynamic models object from available models
-    // OpenCode needs model configs with name and limits
-    let mut models_obj = serde_json::Map::new();
-
-    for m in models {
-        let (context_limit, output_limit) =
-            crate::commands::models::get_model_limits(&m.id, &m.owned_by, &m.source);
-        let display_name =
-            crate::commands::models::get_model_display_name(&m.id, &m.owned_by, &m.source);
-        // Enable reasoning display for models with "-thinking" suffix
-        let is_thinking_model = m.id.ends_with("-thinking");
-        // Check if this is a GPT-5.x model (Codex reasoning models)
-        let is_gpt5_model = m.id.starts_with("gpt-5");
-        // Check if this is a Gemini 3 model (native thinking support)
-        let is_gemini3_model = (m.id.starts_with("gemini-3-") || m.id.starts_with("gemini-3.1-"))
-            && !m.id.contains("image");
-        // Check if this is a Qwen3 or DeepSeek model with thinking support
-        let is_qwen3_thinking = m.id.contains("qwen3") && m.id.contains("thinking");
-        let is_deepseek_thinking = m.id.contains("deepseek") && m.id.contains("thinking");
-        // Use user's configured thinking budget
-        let min_thinking_output: u64 = thinking_budget + 8192; // thinking + 8K buffer for response
-        let effective_output_limit = if is_thinking_model
-            || is_gemini3_model
-            || is_qwen3_thinking
-            || is_deepseek_thinking
-        {
-            std::cmp::max(output_limit, min_thinking_output)
-        } else {
-            output_limit
-        };
-
-        // Determine modalities based on model capabilities
-        // Multimodal models support text + image + pdf input
-        let is_multimodal = m.id.starts_with("gemini-claude-")
-            || m.id.starts_with("gemini-2.5-")
-            || (m.id.starts_with("gemini-3-") && !m.id.contains("image"))
-            || (m.id.starts_with("gemini-3.1-") && !m.id.contains("image"))
-            || m.id.starts_with("gpt-4o")
-            || m.id.starts_with("gpt-4.1")
-            || m.id.starts_with("gpt-5")
-            || m.id.starts_with("o1")
-            || m.id.starts_with("o3")
-            || m.id.starts_with("o4")
-            || m.id.starts_with("claude-")
-            || m.id.starts_with("copilot-gpt-4");
-
-        let mut model_config = serde_json::json!({
-            "name": display_name,
-            "limit": { "context": context_limit, "output": effective_output_limit }
-        });
-
-        // Add modalities for multimodal models
-        if is_multimodal {
-            model_config["modalities"] = serde_json::json!({
-                "input": ["text", "image", "pdf"],
-                "output": ["text"]
-            });
-        }
-
-        // Map thinking budget mode to thinking level string for Gemini 3 variants
-        let thinking_level_from_budget = match thinking_budget {
-            0..=2048 => "low",
-            2049..=16383 => "medium",
-            _ => "high",
-        };
-
-        if is_thinking_model || is_qwen3_thinking || is_deepseek_thinking {
-            // Enable extended thinking
-            model_config["reasoning"] = serde_json::json!(true);
-            // Check if this is a Claude/Qwen3/DeepSeek thinking model (uses thinking.budgetTokens)
-            // vs OpenAI o-series (uses reasoningEffort)
-            let is_budget_thinking = (m.id.contains("claude")
-                || m.id.contains("qwen3")
-                || m.id.contains("deepseek"))
-                && m.id.contains("thinking");
-            if is_budget_thinking {
-                // Add variants for gemini-claude-*-thinking models
-                let low_budget = 8192u64;
-                let max_budget = 32768u64;
-                model_config["variants"] = serde_json::json!({
-                    "low": {
-                        "thinkingConfig": {
-                            "thinkingBudget": low_budget
-                        }
-                    },
-                    "max": {
-                        "thinkingConfig": {
-                            "thinkingBudget": max_budget
-                        }
-                    }
-                });
-                model_config["options"] = serde_json::json!({
-                    "thinking": {
-                        "type": "enabled",
-                        "budgetTokens": thinking_budget
-                    }
-                });
-            } else {
-                // OpenAI o-series models use reasoningEffort
-                model_config["options"] = serde_json::json!({
-                    "reasoningEffort": "high"
-                });
-            }
-        } else if is_gemini3_model {
-            // Gemini 3 models use generationConfig.thinkingConfig
-            model_config["reasoning"] = serde_json::json!(true);
-
-            // Add variants for Gemini 3 models based on user's thinking budget
-            model_config["variants"] = serde_json::json!({
-                "low": {
-                    "thinkingLevel": "low"
-                },
-                "medium": {
-                    "thinkingLevel": "medium"
-                },
-                "high": {
-                    "thinkingLevel": "high"
-                }
-            });
-
-            model_config["options"] = serde_json::json!({
-                "generationConfig": {
-                    "thinkingConfig": {
-                        "thinkingLevel": thinking_level_from_budget,
-                        "includeThoughts": true
-                    }
-                }
-            });
-        } else if is_gpt5_model && reasoning_effort != "none" {
-            // Add reasoning effort for GPT-5.x models (Codex)
-            model_config["reasoning"] = serde_json::json!(true);
-            model_config["options"] = serde_json::json!({
-                "reasoningEffort": reasoning_effort
-            });
-        }
-        models_obj.insert(m.id.clone(), model_config);
-    }
-
-    // Create or update opencode.json with proxypal provider
-    // Use @ai-sdk/anthropic for native Anthropic API (better for Claude models with thinking)
-    let opencode_config = serde_json::json!({
-        "$schema": "https://opencode.ai/config.json",
-        "provider": {
-            "proxypal": {
-                "npm": "@ai-sdk/anthropic",
-                "name": "ProxyPal",
-                "options": {
-                    "baseURL": endpoint_v1,
-                    "apiKey": "proxypal-local",
-                    "includeUsage": true
-                },
-                "models": models_obj
-            }
-        }
-    });
-
-    // If config exists, merge with existing
-    let final_config = if config_path.exists() {
-        if let Ok(existing) = std::fs::read_to_string(&config_path) {
-            if let Ok(mut existing_json) = serde_json::from_str::<serde_json::Value>(&existing) {
-                // Merge provider into existing config
-                if let Some(providers) = existing_json.get_mut("provider") {
-                    if let Some(obj) = providers.as_object_mut() {
-                        obj.insert(
-                            "proxypal".to_string(),
-                            opencode_config["provider"]["proxypal"].clone(),
-                        );
-                    }
-                } else {
-                    existing_json["provider"] = opencode_config["provider"].clone();
-                }
-                existing_json
-            } else {
-                opencode_config
-            }
-        } else {
-            opencode_config
-        }
-    } else {
-        opencode_config
-    };
-
-    let config_str = serde_json::to_string_pretty(&final_config).map_err(|e| e.to_string())?;
-    std::fs::write(&config_path, &config_str).map_err(|e| e.to_string())?;
-
-    Ok(serde_json::json!({
-        "success": true,
-        "configType": "config",
-        "configPath": config_path.to_string_lossy(),
-        "modelsConfigured": models.len(),
-        "instructions": "ProxyPal provider added to OpenCode. Run 'opencode' and use /models to select a model (e.g., proxypal/gemini-2.5-pro). OpenCode uses AI SDK (ai-sdk.dev) and models.dev registry."
-    }))
+    let config = state.config.lock().unwrap().clone();
+    configure_agent(&config, agent_id, models)
 }
 
 // Get shell profile path
@@ -59,7 +59,7 @@ pub async fn get_gemini_api_keys(state: State<'_, AppState>) -> Result<Vec<Gemin
     let client = crate::build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to fetch Gemini API keys: {}", e))?;
@@ -82,7 +82,7 @@ pub async fn set_gemini_api_keys(state: State<'_, AppState>, keys: Vec<GeminiApi
     
     let response = client
         .put(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .json(&body)
         .send()
         .await
@@ -133,7 +133,7 @@ pub async fn get_claude_api_keys(state: State<'_, AppState>) -> Result<Vec<Claud
     let client = crate::build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to fetch Claude API keys: {}", e))?;
@@ -156,7 +156,7 @@ pub async fn set_claude_api_keys(state: State<'_, AppState>, keys: Vec<ClaudeApi
     
     let response = client
         .put(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .json(&body)
         .send()
         .await
@@ -207,7 +207,7 @@ pub async fn get_codex_api_keys(state: State<'_, AppState>) -> Result<Vec<CodexA
     let client = crate::build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to fetch Codex API keys: {}", e))?;
@@ -230,7 +230,7 @@ pub async fn set_codex_api_keys(state: State<'_, AppState>, keys: Vec<CodexApiKe
     
     let response = client
         .put(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .json(&body)
         .send()
         .await
@@ -281,7 +281,7 @@ pub async fn get_vertex_api_keys(state: State<'_, AppState>) -> Result<Vec<Verte
     let client = crate::build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to fetch Vertex API keys: {}", e))?;
@@ -304,7 +304,7 @@ pub async fn set_vertex_api_keys(state: State<'_, AppState>, keys: Vec<VertexApi
     
     let response = client
         .put(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .json(&body)
         .send()
         .await
@@ -348,7 +348,7 @@ pub async fn get_openai_compatible_providers(state: State<'_, AppState>) -> Resu
     let client = crate::build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to fetch OpenAI-compatible providers: {}", e))?;
@@ -371,7 +371,7 @@ pub async fn set_openai_compatible_providers(state: State<'_, AppState>, provide
     
     let response = client
         .put(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .json(&body)
         .send()
         .await
@@ -3,6 +3,9 @@
 //! Extracted from lib.rs — handles authentication status, OAuth flows,
 //! provider connection/disconnection, and credential management.
 
+use crate::commands::proxy::management_target;
+use crate::events;
+use crate::helpers::auth_dir::{get_cli_proxy_auth_dir, scan_auth_dir};
 use crate::state::AppState;
 use crate::types::{AuthStatus, OAuthState};
 use serde::{Deserialize, Serialize};
@@ -36,12 +39,11 @@ pub fn get_auth_status(state: State<AppState>) -> AuthStatus {
 pub async fn get_oauth_url(
     state: State<'_, AppState>,
     provider: String,
+    instance_id: Option<String>,
 ) -> Result<OAuthUrlResponse, String> {
-    // Get proxy port from config
-    let port = {
-        let config = state.config.lock().unwrap();
-        config.port
-    };
+    // The instance whose sidecar runs the login and stores the credential
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
 
     // Kiro uses a web UI page directly, not a JSON API endpoint
     // Return the URL directly without making an HTTP request
@@ -98,7 +100,7 @@ pub async fn get_oauth_url(
     let client = crate::build_management_client();
     let response = client
         .get(&endpoint)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to get OAuth URL: {}. Is the proxy running?", e))?;
@@ -142,12 +144,11 @@ pub async fn get_oauth_url(
 pub async fn get_device_code(
     state: State<'_, AppState>,
     provider: String,
+    instance_id: Option<String>,
 ) -> Result<DeviceCodeResponse, String> {
-    // Get the proxy port from config
-    let port = {
-        let config = state.config.lock().map_err(|e| e.to_string())?;
-        config.port
-    };
+    // The instance whose sidecar runs the login and stores the credential
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
 
     // Build endpoint WITHOUT ?is_webui=true to trigger device-code flow
     let endpoint = match provider.as_str() {
@@ -165,7 +166,7 @@ pub async fn get_device_code(
     let client = crate::build_management_client();
     let response = client
         .get(&endpoint)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to get device code: {}. Is the proxy running?", e))?;
@@ -234,12 +235,11 @@ pub async fn open_oauth(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
     provider: String,
+    instance_id: Option<String>,
 ) -> Result<String, String> {
-    // Get proxy port from config
-    let port = {
-        let config = state.config.lock().unwrap();
-        config.port
-    };
+    // The instance whose sidecar runs the login and stores the credential
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
 
     // For Kiro, open the Web OAuth UI directly in CLIProxyAPIPlus
     if provider == "kiro" {
@@ -296,7 +296,7 @@ pub async fn open_oauth(
     let client = crate::build_management_client();
     let response = client
         .get(&endpoint)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to get OAuth URL: {}. Is the proxy running?", e))?;
@@ -343,11 +343,10 @@ pub async fn open_oauth(
 pub async fn poll_oauth_status(
     state: State<'_, AppState>,
     oauth_state: String,
+    instance_id: Option<String>,
 ) -> Result<bool, String> {
-    let port = {
-        let config = state.config.lock().unwrap();
-        config.port
-    };
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
 
     let endpoint = format!(
         "http://localhost:{}/v0/management/get-auth-status?state={}",
@@ -357,7 +356,7 @@ pub async fn poll_oauth_status(
     let client = crate::build_management_client();
     let response = client
         .get(&endpoint)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to poll OAuth status: {}", e))?;
@@ -381,50 +380,7 @@ pub async fn refresh_auth_status(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<AuthStatus, String> {
-    // Check CLIProxyAPI's auth directory for credentials
-    let auth_dir = dirs::home_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join(".cli-proxy-api");
-
-    let mut new_auth = AuthStatus::default();
-
-    // Scan auth directory for credential files and count them per provider
-    if let Ok(entries) = std::fs::read_dir(&auth_dir) {
-        for entry in entries.flatten() {
-            let filename = entry.file_name().to_string_lossy().to_lowercase();
-
-            // CLIProxyAPI naming patterns:
-            // - claude-{email}.json or anthropic-*.json
-            // - codex-{email}.json
-            // - gemini-{email}-{project}.json
-            // - qwen-{email}.json
-            // - iflow-{email}.json
-            // - vertex-{project_id}.json
-            // - antigravity-{email}.json
-
-            if filename.ends_with(".json") {
-                if filename.starts_with("claude-") || filename.starts_with("anthropic-") {
-                    new_auth.claude += 1;
-                } else if filename.starts_with("codex-") {
-                    new_auth.openai += 1;
-                } else if filename.starts_with("gemini-") {
-                    new_auth.gemini += 1;
-                } else if filename.starts_with("qwen-") {
-                    new_auth.qwen += 1;
-                } else if filename.starts_with("iflow-") {
-                    new_auth.iflow += 1;
-                } else if filename.starts_with("vertex-") {
-                    new_auth.vertex += 1;
-                } else if filename.starts_with("kiro-") {
-                    new_auth.kiro += 1;
-                } else if filename.starts_with("antigravity-") {
-                    new_auth.antigravity += 1;
-                } else if filename.starts_with("kimi-") {
-                    new_auth.kimi += 1;
-                }
-            }
-        }
-    }
+    let new_auth = scan_auth_dir(&get_cli_proxy_auth_dir());
 
     // Update state
     {
@@ -436,7 +392,7 @@ pub async fn refresh_auth_status(
     crate::save_auth_to_file(&new_auth)?;
 
     // Emit auth status update
-    let _ = app.emit("auth-status-changed", new_auth.clone());
+    let _ = app.emit(events::AUTH_STATUS_CHANGED, new_auth.clone());
 
     Ok(new_auth)
 }
@@ -478,22 +434,24 @@ pub async fn complete_oauth(
         *pending = None;
 
         // Emit auth status update
-        let _ = app.emit("auth-status-changed", auth.clone());
+        let _ = app.emit(events::AUTH_STATUS_CHANGED, auth.clone());
 
         Ok(auth.clone())
     }
 }
 
+/// Delete the provider's credential files from the instance's auth dir.
+/// Another instance's accounts don't count towards the app's auth status,
+/// so for one the remaining accounts are rescanned and returned.
 #[tauri::command]
 pub async fn disconnect_provider(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
     provider: String,
+    instance_id: Option<String>,
 ) -> Result<AuthStatus, String> {
-    // Delete credential files from ~/.cli-proxy-api/ for this provider
-    let auth_dir = dirs::home_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join(".cli-proxy-api");
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let auth_dir = instance.auth_dir();
 
     if auth_dir.exists() {
         if let Ok(entries) = std::fs::read_dir(&auth_dir) {
@@ -525,6 +483,10 @@ pub async fn disconnect_provider(
         }
     }
 
+    if !instance.is_default() {
+        return Ok(scan_auth_dir(&auth_dir));
+    }
+
     let mut auth = state.auth_status.lock().unwrap();
 
     match provider.as_str() {
@@ -544,7 +506,7 @@ pub async fn disconnect_provider(
     crate::save_auth_to_file(&auth)?;
 
     // Emit auth status update
-    let _ = app.emit("auth-status-changed", auth.clone());
+    let _ = app.emit(events::AUTH_STATUS_CHANGED, auth.clone());
 
     Ok(auth.clone())
 }
@@ -1,26 +1,35 @@
 //! Auth Files Management - via Management API
 
-use crate::state::AppState;
+use std::path::Path;
+
+use crate::commands::proxy::management_target;
+use crate::helpers::auth_dir::auth_file_provider;
+use crate::state::{AppState, ProxyInstance};
 use crate::types::{self, AuthFile};
-use crate::{build_management_client, get_management_key, get_management_url};
+use crate::{build_management_client, get_management_url};
+use proxypal_core::get_instance_management_key;
 use tauri::State;
 
-// Get all auth files
+// Get all auth files of an instance, the default one when none is given
 #[tauri::command]
-pub async fn get_auth_files(state: State<'_, AppState>) -> Result<Vec<AuthFile>, String> {
-    let port = state.config.lock().unwrap().port;
+pub async fn get_auth_files(
+    state: State<'_, AppState>,
+    instance_id: Option<String>,
+) -> Result<Vec<AuthFile>, String> {
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
     let url = get_management_url(port, "auth-files");
     
     // 1. Fetch active files from Management API
     let mut files: Vec<AuthFile> = Vec::new();
     
     // Only try to fetch if proxy is running
-    let proxy_running = state.proxy_status.lock().unwrap().running;
+    let proxy_running = instance.proxy_status.lock().unwrap().running;
     if proxy_running {
         let client = build_management_client();
         match client
             .get(&url)
-            .header("X-Management-Key", &get_management_key())
+            .header("X-Management-Key", &management_key)
             .send()
             .await 
         {
@@ -60,10 +69,8 @@ pub async fn get_auth_files(state: State<'_, AppState>) -> Result<Vec<AuthFile>,
         }
     }
     
-    // 2. Scan for disabled files (.json.disabled) in auth directory
-    let auth_dir = dirs::home_dir()
-        .ok_or("Could not find home directory")?
-        .join(".cli-proxy-api");
+    // 2. Scan for disabled files (.json.disabled) in the instance's auth directory
+    let auth_dir = instance.auth_dir();
         
     if auth_dir.exists() {
         if let Ok(entries) = std::fs::read_dir(&auth_dir) {
@@ -124,8 +131,14 @@ pub async fn get_auth_files(state: State<'_, AppState>) -> Result<Vec<AuthFile>,
 
 // Upload auth file
 #[tauri::command]
-pub async fn upload_auth_file(state: State<'_, AppState>, file_path: String, provider: String) -> Result<(), String> {
-    let port = state.config.lock().unwrap().port;
+pub async fn upload_auth_file(
+    state: State<'_, AppState>,
+    file_path: String,
+    provider: String,
+    instance_id: Option<String>,
+) -> Result<(), String> {
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
     let url = get_management_url(port, "auth-files");
     
     // Read file content
@@ -154,7 +167,7 @@ pub async fn upload_auth_file(state: State<'_, AppState>, file_path: String, pro
     
     let response = client
         .post(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &management_key)
         .multipart(form)
         .send()
         .await
@@ -171,11 +184,16 @@ pub async fn upload_auth_file(state: State<'_, AppState>, file_path: String, pro
 
 // Delete auth file
 #[tauri::command]
-pub async fn delete_auth_file(state: State<'_, AppState>, file_id: String) -> Result<(), String> {
+pub async fn delete_auth_file(
+    state: State<'_, AppState>,
+    file_id: String,
+    instance_id: Option<String>,
+) -> Result<(), String> {
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
+
     // Check if it's a disabled file first (file_id matches filename without extension usually)
-    let auth_dir = dirs::home_dir()
-        .ok_or("Could not find home directory")?
-        .join(".cli-proxy-api");
+    let auth_dir = instance.auth_dir();
         
     let disabled_path = auth_dir.join(format!("{}.json.disabled", file_id));
     if disabled_path.exists() {
@@ -185,13 +203,12 @@ pub async fn delete_auth_file(state: State<'_, AppState>, file_id: String) -> Re
     }
 
     // Otherwise try to delete via API
-    let port = state.config.lock().unwrap().port;
     let url = format!("{}?name={}", get_management_url(port, "auth-files"), file_id);
     
     let client = build_management_client();
     let response = client
         .delete(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to delete auth file: {}", e))?;
@@ -206,86 +223,126 @@ pub async fn delete_auth_file(state: State<'_, AppState>, file_id: String) -> Re
 }
 
 // Toggle auth file enabled/disabled via management API (CLIProxyAPI v6.7.18+)
-// Fallback: manually rename file to .json.disabled if API returns 404
+// Older sidecars have no such endpoint, so the file is renamed to .json.disabled instead
 #[tauri::command]
 pub async fn toggle_auth_file(
 	state: State<'_, AppState>,
 	file_name: String,
 	disabled: bool,
+	instance_id: Option<String>,
+) -> Result<(), String> {
+	let instance = state.instance_or_default(instance_id.as_deref())?;
+	set_auth_file_disabled(&instance, &file_name, disabled).await
+}
+
+// Disable or enable an auth file of one instance. Goes through that instance's
+// management API when its sidecar is running and has the endpoint, and renames
+// the file in the instance's auth dir otherwise, or when it was renamed before
+pub(crate) async fn set_auth_file_disabled(
+	instance: &ProxyInstance,
+	file_name: &str,
+	disabled: bool,
 ) -> Result<(), String> {
-	let port = {
-		let config = state.config.lock().unwrap();
-		config.port
+	let auth_dir = instance.auth_dir();
+	let (running, port) = {
+		let status = instance.proxy_status.lock().unwrap();
+		(status.running, status.port)
 	};
+	let renamed = auth_dir.join(format!("{}.disabled", file_name)).exists();
+	if !running
+		|| (!disabled && renamed)
+		|| !instance.sidecar_capabilities.lock().unwrap().auth_file_status_api
+	{
+		return rename_auth_file(&auth_dir, file_name, disabled);
+	}
 
-	// Use the new PATCH endpoint from CLIProxyAPI v6.7.18
 	// Endpoint: PATCH /v0/management/auth-files/status
 	// Body: { "name": "filename.json", "disabled": true/false }
 	let url = get_management_url(port, "auth-files/status");
 
 	let client = build_management_client();
-	let response_res = client
+	let response = client
 		.patch(&url)
-		.header("X-Management-Key", &get_management_key())
+		.header("X-Management-Key", &get_instance_management_key(&instance.dir)?)
 		.json(&serde_json::json!({
 			"name": file_name,
 			"disabled": disabled
 		}))
 		.send()
-		.await;
+		.await
+		.map_err(|e| format!("Failed to toggle auth file: {}", e))?;
 
-	match response_res {
-		Ok(response) if response.status().is_success() => Ok(()),
-		Ok(response) if response.status().as_u16() == 404 => {
-			// API not found (old version), fallback to manual file renaming
-			let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-			let auth_dir = home_dir.join(".cli-proxy-api");
+	if !response.status().is_success() {
+		let status = response.status();
+		let error_text = response.text().await.unwrap_or_default();
+		return Err(format!(
+			"Failed to toggle auth file: {} - {}",
+			status, error_text
+		));
+	}
 
-			let current_name = if !disabled {
-				format!("{}.disabled", file_name)
-			} else {
-				file_name.clone()
-			};
+	Ok(())
+}
 
-			let new_name = if disabled {
-				format!("{}.disabled", file_name)
-			} else {
-				file_name.clone()
-			};
+// Disable an auth file by renaming it to .json.disabled (and back), for sidecars
+// without the auth-files/status endpoint
+fn rename_auth_file(auth_dir: &Path, file_name: &str, disabled: bool) -> Result<(), String> {
+	let disabled_name = format!("{}.disabled", file_name);
+	let (current_name, new_name) = if disabled {
+		(file_name, disabled_name.as_str())
+	} else {
+		(disabled_name.as_str(), file_name)
+	};
 
-			let current_path = auth_dir.join(&current_name);
-			let new_path = auth_dir.join(&new_name);
+	let current_path = auth_dir.join(current_name);
+	let new_path = auth_dir.join(new_name);
 
-			if current_path.exists() {
-				std::fs::rename(&current_path, &new_path)
-					.map_err(|e| format!("Manual toggle failed: {}", e))?;
-				Ok(())
-			} else {
-				Err(format!("Auth file not found: {:?}", current_path))
-			}
-		}
-		Ok(response) => {
-			let status = response.status();
-			let error_text = response.text().await.unwrap_or_default();
-			Err(format!(
-				"Failed to toggle auth file: {} - {}",
-				status, error_text
-			))
-		}
-		Err(e) => Err(format!("Failed to toggle auth file: {}", e)),
+	if current_path.exists() {
+		std::fs::rename(&current_path, &new_path)
+			.map_err(|e| format!("Manual toggle failed: {}", e))?;
+		Ok(())
+	} else {
+		Err(format!("Auth file not found: {:?}", current_path))
 	}
 }
 
+// Names and providers of the credential files in `auth_dir` that are neither
+// renamed aside nor disabled through the management API
+pub(crate) fn enabled_auth_files(auth_dir: &Path) -> Vec<(String, &'static str)> {
+	let Ok(entries) = std::fs::read_dir(auth_dir) else {
+		return Vec::new();
+	};
+	entries
+		.flatten()
+		.filter_map(|entry| {
+			let name = entry.file_name().to_string_lossy().to_string();
+			let provider = auth_file_provider(&name)?;
+			let disabled = std::fs::read_to_string(entry.path())
+				.ok()
+				.and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+				.and_then(|json| json.get("disabled").and_then(|v| v.as_bool()))
+				.unwrap_or(false);
+			(!disabled).then_some((name, provider))
+		})
+		.collect()
+}
+
 // Download auth file - returns path to temp file
 #[tauri::command]
-pub async fn download_auth_file(state: State<'_, AppState>, _file_id: String, filename: String) -> Result<String, String> {
-    let port = state.config.lock().unwrap().port;
+pub async fn download_auth_file(
+    state: State<'_, AppState>,
+    _file_id: String,
+    filename: String,
+    instance_id: Option<String>,
+) -> Result<String, String> {
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
     let url = format!("{}?name={}", get_management_url(port, "auth-files/download"), filename);
     
     let client = build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to download auth file: {}", e))?;
@@ -311,14 +368,18 @@ pub async fn download_auth_file(state: State<'_, AppState>, _file_id: String, fi
 
 // Delete all auth files
 #[tauri::command]
-pub async fn delete_all_auth_files(state: State<'_, AppState>) -> Result<(), String> {
-    let port = state.config.lock().unwrap().port;
+pub async fn delete_all_auth_files(
+    state: State<'_, AppState>,
+    instance_id: Option<String>,
+) -> Result<(), String> {
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
     let url = format!("{}?all=true", get_management_url(port, "auth-files"));
     
     let client = build_management_client();
     let response = client
         .delete(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to delete all auth files: {}", e))?;
@@ -338,32 +399,41 @@ pub async fn delete_all_auth_files(state: State<'_, AppState>) -> Result<(), Str
 
 // Verify auth status from CLIProxyAPI's /api/auth/status endpoint
 #[tauri::command]
-pub async fn verify_proxy_auth_status(state: State<'_, AppState>) -> Result<types::ProxyAuthStatus, String> {
-    let port = state.config.lock().unwrap().port;
-    
+pub async fn verify_proxy_auth_status(
+    state: State<'_, AppState>,
+    instance_id: Option<String>,
+) -> Result<types::ProxyAuthStatus, String> {
+    let instance = state.instance_or_default(instance_id.as_deref())?;
+    let (port, management_key) = management_target(&state, &instance)?;
+
     // Check if proxy is running first
-    let proxy_running = state.proxy_status.lock().unwrap().running;
+    let proxy_running = instance.proxy_status.lock().unwrap().running;
     if !proxy_running {
         return Ok(types::ProxyAuthStatus::default());
     }
+
+    // The endpoint doesn't exist before CLIProxyAPI v6.6.72
+    if !instance.sidecar_capabilities.lock().unwrap().auth_status_api {
+        return Ok(types::ProxyAuthStatus {
+            status: "unsupported".to_string(),
+            providers: types::ProxyAuthProviders::default(),
+        });
+    }
     
-    // The new endpoint in CLIProxyAPI v6.6.72+ is /api/auth/status
     let url = format!("http://127.0.0.1:{}/api/auth/status", port);
     
     let client = build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &management_key)
         .send()
         .await
         .map_err(|e| format!("Failed to verify auth status: {}", e))?;
     
     if !response.status().is_success() {
-        // Fallback: endpoint might not exist in older CLIProxyAPI versions
-        return Ok(types::ProxyAuthStatus {
-            status: "unsupported".to_string(),
-            providers: types::ProxyAuthProviders::default(),
-        });
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to verify auth status: {} - {}", status, text));
     }
     
     let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
@@ -0,0 +1,212 @@
+//! Budget commands and the monitor that announces budgets reaching their
+//! thresholds.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::auth_files::{enabled_auth_files, set_auth_file_disabled};
+use crate::events;
+use crate::helpers::budgets::{self, PausedAccount};
+use crate::helpers::usage_recorder;
+use crate::state::AppState;
+use crate::types::{Budget, BudgetAlert, BudgetStatus};
+
+/// How often the monitor checks the budgets
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+fn instance_dirs(state: &AppState) -> Vec<PathBuf> {
+    state
+        .all_instances()
+        .iter()
+        .map(|instance| instance.dir.clone())
+        .collect()
+}
+
+/// What a budget has used since `since`, across every instance
+async fn budget_usage(dirs: Vec<PathBuf>, budget: Budget, since: u64) -> Result<f64, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut used = 0.0;
+        for dir in &dirs {
+            let (cost, tokens) =
+                usage_recorder::reader(dir)?.usage_since(since, budget.scope, &budget.target)?;
+            used += budgets::used_amount(&budget, cost, tokens);
+        }
+        Ok(used)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_budget_status(state: State<'_, AppState>) -> Result<Vec<BudgetStatus>, String> {
+    let budget_list = state.config.lock().unwrap().budgets.clone();
+    let dirs = instance_dirs(&state);
+    let states = budgets::load_state();
+    let mut statuses = Vec::new();
+    for budget in budget_list {
+        let (period_start, _) = budgets::current_period(budget.period);
+        let used = budget_usage(dirs.clone(), budget.clone(), period_start).await?;
+        // Also those a past period left paused because enabling them failed
+        let paused_accounts = states
+            .get(&budget.id)
+            .map(|s| s.paused.iter().map(|account| account.to_string()).collect())
+            .unwrap_or_default();
+        statuses.push(BudgetStatus {
+            percent: budgets::percent_used(&budget, used),
+            id: budget.id,
+            name: budget.name,
+            period_start,
+            used,
+            limit: budget.limit,
+            paused_accounts,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Disable the enabled auth files a budget covers, in every instance since
+/// usage is counted across all of them. Returns those disabled.
+async fn pause_accounts(app: &AppHandle, budget: &Budget) -> Vec<PausedAccount> {
+    let mut paused = Vec::new();
+    for instance in app.state::<AppState>().all_instances() {
+        for (file, provider) in enabled_auth_files(&instance.auth_dir()) {
+            if !budgets::covers_account(budget, provider) {
+                continue;
+            }
+            let account = PausedAccount {
+                instance: instance.id.clone(),
+                file,
+            };
+            match set_auth_file_disabled(&instance, &account.file, true).await {
+                Ok(()) => paused.push(account),
+                Err(e) => eprintln!("[Budgets] Failed to pause {}: {}", account, e),
+            }
+        }
+    }
+    if !paused.is_empty() {
+        let names: Vec<String> = paused.iter().map(|account| account.to_string()).collect();
+        println!("[Budgets] '{}' paused {}", budget.name, names.join(", "));
+    }
+    paused
+}
+
+/// Enable paused accounts again. Those that fail, e.g. because the proxy is
+/// stopped, stay in `paused` for the next check.
+async fn resume_accounts(app: &AppHandle, paused: &mut Vec<PausedAccount>) {
+    let state = app.state::<AppState>();
+    let mut remaining = Vec::new();
+    for account in paused.drain(..) {
+        // Its auth dir went with the instance
+        let Ok(instance) = state.instance(&account.instance) else {
+            continue;
+        };
+        match set_auth_file_disabled(&instance, &account.file, false).await {
+            Ok(()) => println!("[Budgets] Resumed {}", account),
+            Err(e) => {
+                eprintln!("[Budgets] Failed to resume {}: {}", account, e);
+                remaining.push(account);
+            }
+        }
+    }
+    *paused = remaining;
+}
+
+fn notify(app: &AppHandle, budget: &Budget, threshold: u8, used: f64) {
+    use tauri_plugin_notification::NotificationExt;
+    let title = if threshold >= 100 {
+        format!("Budget '{}' reached", budget.name)
+    } else {
+        format!("Budget '{}' at {}%", budget.name, threshold)
+    };
+    let _ = app
+        .notification()
+        .builder()
+        .title(&title)
+        .body(budgets::describe_usage(budget, used))
+        .show();
+}
+
+async fn check_budgets(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let budget_list: Vec<Budget> = state
+        .config
+        .lock()
+        .unwrap()
+        .budgets
+        .iter()
+        .filter(|budget| budget.enabled)
+        .cloned()
+        .collect();
+    let dirs = instance_dirs(&state);
+    let mut states = budgets::load_state();
+    let before = states.clone();
+
+    // Budgets removed or turned off let their accounts go
+    let gone: Vec<String> = states
+        .keys()
+        .filter(|id| !budget_list.iter().any(|budget| &budget.id == *id))
+        .cloned()
+        .collect();
+    for id in gone {
+        if let Some(old) = states.get_mut(&id) {
+            resume_accounts(app, &mut old.paused).await;
+            if old.paused.is_empty() {
+                states.remove(&id);
+            }
+        }
+    }
+
+    for budget in &budget_list {
+        let (period_start, period) = budgets::current_period(budget.period);
+        let used = budget_usage(dirs.clone(), budget.clone(), period_start).await?;
+        let entry = states.entry(budget.id.clone()).or_default();
+        let check = budgets::check(budget, entry, &period, used);
+
+        if check.resume {
+            resume_accounts(app, &mut entry.paused).await;
+        }
+        if check.pause {
+            entry.paused = pause_accounts(app, budget).await;
+        }
+        if let Some(threshold) = check.notify {
+            notify(app, budget, threshold, used);
+            if threshold >= 100 {
+                let _ = app.emit(
+                    events::BUDGET_EXCEEDED,
+                    BudgetAlert {
+                        id: budget.id.clone(),
+                        name: budget.name.clone(),
+                        used,
+                        limit: budget.limit,
+                        metric: budget.metric,
+                        period: budget.period,
+                        paused_accounts: entry
+                            .paused
+                            .iter()
+                            .map(|account| account.to_string())
+                            .collect(),
+                    },
+                );
+            }
+        }
+    }
+
+    if states != before {
+        budgets::save_state(&states)?;
+    }
+    Ok(())
+}
+
+/// Check the budgets now and every minute after
+pub fn start_budget_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = check_budgets(&app).await {
+                eprintln!("[Budgets] {}", e);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
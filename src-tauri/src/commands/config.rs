@@ -2,11 +2,19 @@
 
 #[cfg(test)]
 use crate::config::save_config_to_path;
-use crate::config::{save_config_to_file, AppConfig};
+use crate::commands::control_api::apply_control_api_change;
+use crate::commands::proxy::{propagate_port_change, reconcile_proxy_config};
+use crate::config::{save_config_to_file, AppConfig, ConfigLoadError};
+use crate::helpers::autostart::{self, LoginItem};
+use crate::helpers::secrets::write_private_file;
+use crate::helpers::snapshots::snapshot_config;
+use crate::proxy::ports::apply_port_change;
+use crate::proxy::validation;
 use crate::state::AppState;
+use crate::types::{ConfigValidation, ReloadReport};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tauri::State;
+use tauri::{AppHandle, State};
 
 #[tauri::command]
 pub fn get_config(state: State<AppState>) -> AppConfig {
@@ -30,7 +38,11 @@ pub fn get_config(state: State<AppState>) -> AppConfig {
 }
 
 #[tauri::command]
-pub fn save_config(state: State<AppState>, config: AppConfig) -> Result<(), String> {
+pub fn save_config(
+    app: AppHandle,
+    state: State<AppState>,
+    config: AppConfig,
+) -> Result<(), String> {
     // Debug: Log provider models before save
     eprintln!(
         "[ProxyPal Debug] Saving {} custom providers",
@@ -48,10 +60,37 @@ pub fn save_config(state: State<AppState>, config: AppConfig) -> Result<(), Stri
         }
     }
 
+    // Install or remove the login item when the setting is toggled
+    let launch_at_login = state.config.lock().unwrap().launch_at_login;
+    if config.launch_at_login != launch_at_login {
+        autostart::set_enabled(LoginItem::App, config.launch_at_login)?;
+    }
+
+    // Tunnels forwarding the old port move with it, as in apply_config
+    let (old_port, old_control_api) = {
+        let old = state.config.lock().unwrap();
+        (old.port, old.control_api.clone())
+    };
+    let mut config = config;
+    let new_port = config.port;
+    let tunnels = if new_port != old_port {
+        apply_port_change(&mut config, old_port, new_port)
+    } else {
+        Vec::new()
+    };
+
+    snapshot_config("save-config", &config.snapshot_retention);
     persist_config(&config)?;
+    // The routing edit above is hot-reloaded by the sidecar
+    if let Ok(yaml) = fs::read_to_string(proxy_config_path()) {
+        *state.default_instance().loaded_proxy_config.lock().unwrap() = Some(yaml);
+    }
 
-    let mut current_config = state.config.lock().unwrap();
-    *current_config = config.clone();
+    *state.config.lock().unwrap() = config.clone();
+    if new_port != old_port {
+        propagate_port_change(&app, &config, old_port, &tunnels);
+    }
+    apply_control_api_change(&app, &old_control_api, &config.control_api);
 
     eprintln!("[ProxyPal Debug] Config saved successfully");
     Ok(())
@@ -103,8 +142,8 @@ fn update_proxy_config_yaml_at_path(
 
     existing_yaml = set_routing_strategy(&existing_yaml, &app_config.routing_strategy);
 
-    std::fs::write(proxy_config_path, existing_yaml)
-        .map_err(|e| format!("Failed to write proxy config: {}", e))
+    // It carries the proxy's API keys
+    write_private_file(proxy_config_path, existing_yaml.as_bytes())
 }
 
 fn set_routing_strategy(existing_yaml: &str, strategy: &str) -> String {
@@ -163,7 +202,10 @@ pub fn get_config_yaml() -> Result<String, String> {
 }
 
 #[tauri::command]
-pub fn save_config_yaml(yaml: String) -> Result<(), String> {
+pub fn save_config_yaml(state: State<AppState>, yaml: String) -> Result<(), String> {
+    let retention = state.config.lock().unwrap().snapshot_retention.clone();
+    snapshot_config("save-config-yaml", &retention);
+
     let config_dir = dirs::config_dir()
         .unwrap_or_else(|| std::path::PathBuf::from("."))
         .join("proxypal");
@@ -172,7 +214,76 @@ pub fn save_config_yaml(yaml: String) -> Result<(), String> {
     // Save directly to main config file
     // Note: This will be overwritten on next proxy restart
     let config_path = config_dir.join("proxy-config.yaml");
-    fs::write(&config_path, yaml).map_err(|e| format!("Failed to save config YAML: {}", e))
+    write_private_file(&config_path, yaml.as_bytes())
+        .map_err(|e| format!("Failed to save config YAML: {}", e))?;
+    *state.default_instance().loaded_proxy_config.lock().unwrap() = Some(yaml);
+    Ok(())
+}
+
+/// Save `config` and apply it to the running proxy, restarting only when a
+/// changed setting can't be reloaded in place
+#[tauri::command]
+pub async fn apply_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    config: AppConfig,
+) -> Result<ReloadReport, String> {
+    if state.default_instance().proxy_status.lock().unwrap().running {
+        let validation = validation::validate_config(&config, None);
+        if !validation.valid {
+            return Err(format!(
+                "Invalid configuration: {}",
+                validation::summarize_errors(&validation)
+            ));
+        }
+    }
+
+    let old = state.config.lock().unwrap().clone();
+    // Tunnels forwarding the old port move with it
+    let mut config = config;
+    let new_port = config.port;
+    let tunnels = if new_port != old.port {
+        apply_port_change(&mut config, old.port, new_port)
+    } else {
+        Vec::new()
+    };
+
+    snapshot_config("save-config", &config.snapshot_retention);
+    save_config_to_file(&config)?;
+    *state.config.lock().unwrap() = config.clone();
+    apply_control_api_change(&app, &old.control_api, &config.control_api);
+
+    let report = reconcile_proxy_config(&app, &state, &old, &config).await?;
+    if new_port != old.port {
+        propagate_port_change(&app, &config, old.port, &tunnels);
+    }
+    Ok(report)
+}
+
+/// Error from the last config.json load, e.g. a corrupt file that was moved aside
+#[tauri::command]
+pub fn get_config_load_error() -> Option<ConfigLoadError> {
+    crate::config::last_config_load_error()
+}
+
+/// Check a config for problems before it is saved or the proxy started. When
+/// the proxy is running, Amp mappings are also checked against its models.
+#[tauri::command]
+pub async fn validate_config(
+    state: State<'_, AppState>,
+    config: Option<AppConfig>,
+) -> Result<ConfigValidation, String> {
+    let config = config.unwrap_or_else(|| state.config.lock().unwrap().clone());
+    let running = state.default_instance().proxy_status.lock().unwrap().running;
+    let live_models = if running {
+        crate::commands::models::get_available_models(state.clone())
+            .await
+            .ok()
+            .map(|models| models.into_iter().map(|m| m.id).collect::<Vec<_>>())
+    } else {
+        None
+    };
+    Ok(validation::validate_config(&config, live_models.as_deref()))
 }
 
 #[tauri::command]
@@ -191,13 +302,7 @@ pub fn reload_config(state: State<AppState>) -> Result<AppConfig, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::amp::generate_uuid;
-
-    fn test_dir(prefix: &str) -> PathBuf {
-        let dir = std::env::temp_dir().join(format!("proxypal-{}-{}", prefix, generate_uuid()));
-        fs::create_dir_all(&dir).unwrap();
-        dir
-    }
+    use proxypal_core::test_support::test_dir;
 
     #[test]
     fn update_proxy_config_yaml_sets_routing_strategy() {
@@ -211,6 +316,12 @@ mod tests {
 
         let content = fs::read_to_string(&yaml_path).unwrap();
         assert!(content.contains("strategy: \"least-connections\""));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&yaml_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
 
         let _ = fs::remove_dir_all(dir);
     }
@@ -0,0 +1,99 @@
+//! Settings for the localhost control API (see `control_api`).
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::config::save_config_to_file;
+use crate::control_api::{generate_token, ControlApi};
+use crate::helpers::secrets::resolve_secret;
+use crate::proxy::validation::validate_config;
+use crate::state::AppState;
+use crate::types::{ControlApiConfig, ControlApiStatus, DiagnosticSeverity};
+
+fn status_of(state: &State<'_, AppState>, control_api: &State<'_, ControlApi>) -> ControlApiStatus {
+    let config = state.config.lock().unwrap().control_api.clone();
+    let running_port = control_api.running_port();
+    let port = running_port.unwrap_or(config.port);
+    ControlApiStatus {
+        enabled: config.enabled,
+        running: running_port.is_some(),
+        port,
+        url: format!("http://127.0.0.1:{}", port),
+        // A locked vault shows no token rather than failing the whole status
+        token: resolve_secret(&config.token).unwrap_or_default(),
+        error: control_api.last_error(),
+    }
+}
+
+/// Restart or stop the server when a saved config changed its settings.
+/// A failure is kept for the status rather than failing the save.
+pub(crate) fn apply_control_api_change(
+    app: &AppHandle,
+    old: &ControlApiConfig,
+    new: &ControlApiConfig,
+) {
+    if old == new {
+        return;
+    }
+    if let Err(e) = app.state::<ControlApi>().apply(app, new) {
+        eprintln!("[ProxyPal] Control API: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_control_api_status(
+    state: State<'_, AppState>,
+    control_api: State<'_, ControlApi>,
+) -> ControlApiStatus {
+    status_of(&state, &control_api)
+}
+
+/// Turn the control API on or off, optionally on a new port. A token is
+/// generated the first time it is enabled.
+#[tauri::command]
+pub fn set_control_api(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    control_api: State<'_, ControlApi>,
+    enabled: bool,
+    port: Option<u16>,
+) -> Result<ControlApiStatus, String> {
+    let settings = {
+        let mut config = state.config.lock().unwrap();
+        let mut updated = config.clone();
+        updated.control_api.enabled = enabled;
+        if let Some(port) = port {
+            updated.control_api.port = port;
+        }
+        if enabled && updated.control_api.token.is_empty() {
+            updated.control_api.token = generate_token();
+        }
+        let validation = validate_config(&updated, None);
+        if let Some(error) = validation.diagnostics.iter().find(|d| {
+            d.severity == DiagnosticSeverity::Error && d.field.starts_with("controlApi")
+        }) {
+            return Err(error.message.clone());
+        }
+        save_config_to_file(&updated)?;
+        *config = updated;
+        config.control_api.clone()
+    };
+    control_api.apply(&app, &settings)?;
+    Ok(status_of(&state, &control_api))
+}
+
+/// Replace the token; clients using the old one are rejected from now on
+#[tauri::command]
+pub fn regenerate_control_api_token(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    control_api: State<'_, ControlApi>,
+) -> Result<ControlApiStatus, String> {
+    let settings = {
+        let mut config = state.config.lock().unwrap();
+        config.control_api.token = generate_token();
+        save_config_to_file(&config)?;
+        config.control_api.clone()
+    };
+    control_api.apply(&app, &settings)?;
+    Ok(status_of(&state, &control_api))
+}
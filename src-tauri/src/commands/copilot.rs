@@ -1,3 +1,7 @@
+use crate::commands::proxy::reconcile_proxy_config;
+use crate::config::save_config_to_file;
+use crate::events;
+use crate::proxy::ports::{find_free_port, is_port_free};
 use crate::state::AppState;
 use crate::types::{CopilotApiDetection, CopilotApiInstallResult, CopilotStatus};
 use tauri::{Emitter, Manager, State};
@@ -17,8 +21,8 @@ pub async fn start_copilot(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<CopilotStatus, String> {
-    let config = state.config.lock().unwrap().clone();
-    let port = config.copilot.port;
+    let mut config = state.config.lock().unwrap().clone();
+    let mut port = config.copilot.port;
     
     // Check if copilot is enabled
     if !config.copilot.enabled {
@@ -44,7 +48,7 @@ pub async fn start_copilot(
                 status.authenticated = true;
                 status.clone()
             };
-            let _ = app.emit("copilot-status-changed", new_status.clone());
+            let _ = app.emit(events::COPILOT_STATUS_CHANGED, new_status.clone());
             return Ok(new_status);
         }
     }
@@ -59,6 +63,20 @@ pub async fn start_copilot(
     
     // Small delay to let port be released
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+    // Something that isn't a healthy copilot-api holds the port. Move to a free
+    // one if allowed; the proxy's Copilot openai-compat entry follows it.
+    if config.auto_select_port && !is_port_free(port) {
+        let free_port = find_free_port(port, &[config.port])
+            .ok_or_else(|| format!("Port {} is in use and no free port was found", port))?;
+        println!("[copilot] Port {} is in use, switching to port {}", port, free_port);
+        let old = config.clone();
+        config.copilot.port = free_port;
+        save_config_to_file(&config)?;
+        *state.config.lock().unwrap() = config.clone();
+        reconcile_proxy_config(&app, &state, &old, &config).await?;
+        port = free_port;
+    }
     
     // Check if copilot-api is installed globally (faster startup)
     let detection = detect_copilot_api(app.clone()).await?;
@@ -147,7 +165,7 @@ pub async fn start_copilot(
     // Add GitHub token if specified (for direct authentication)
     if !config.copilot.github_token.is_empty() {
         args.push("--github-token".to_string());
-        args.push(config.copilot.github_token.clone());
+        args.push(crate::helpers::secrets::resolve_secret(&config.copilot.github_token)?);
     }
     
     // Add rate limit if specified
@@ -203,7 +221,7 @@ pub async fn start_copilot(
                         if let Some(state) = app_handle.try_state::<AppState>() {
                             let mut status = state.copilot_status.lock().unwrap();
                             status.authenticated = true;
-                            let _ = app_handle.emit("copilot-status-changed", status.clone());
+                            let _ = app_handle.emit(events::COPILOT_STATUS_CHANGED, status.clone());
                             println!("[copilot] ✓ Authenticated via stdout detection");
                         }
                     }
@@ -211,7 +229,7 @@ pub async fn start_copilot(
                     // Check for auth URL in output
                     if text.contains("https://github.com/login/device") || text.contains("device code") {
                         // Emit auth required event
-                        let _ = app_handle.emit("copilot-auth-required", text.to_string());
+                        let _ = app_handle.emit(events::COPILOT_AUTH_REQUIRED, text.to_string());
                         println!("[copilot] Auth required - device code flow initiated");
                     }
                 }
@@ -226,7 +244,7 @@ pub async fn start_copilot(
                         if let Some(state) = app_handle.try_state::<AppState>() {
                             let mut status = state.copilot_status.lock().unwrap();
                             status.authenticated = true;
-                            let _ = app_handle.emit("copilot-status-changed", status.clone());
+                            let _ = app_handle.emit(events::COPILOT_STATUS_CHANGED, status.clone());
                             println!("[copilot] ✓ Authenticated via stderr detection");
                         }
                     }
@@ -238,7 +256,7 @@ pub async fn start_copilot(
                         let mut status = state.copilot_status.lock().unwrap();
                         status.running = false;
                         status.authenticated = false;
-                        let _ = app_handle.emit("copilot-status-changed", status.clone());
+                        let _ = app_handle.emit(events::COPILOT_STATUS_CHANGED, status.clone());
                     }
                     break;
                 }
@@ -261,7 +279,7 @@ pub async fn start_copilot(
             if status.authenticated {
                 println!("[copilot] ✓ Ready via stdout detection at {:.1}s", (i + 1) as f32 * 0.5);
                 let status_clone = status.clone();
-                let _ = app.emit("copilot-status-changed", status_clone.clone());
+                let _ = app.emit(events::COPILOT_STATUS_CHANGED, status_clone.clone());
                 return Ok(status_clone);
             }
             if !status.running {
@@ -283,7 +301,7 @@ pub async fn start_copilot(
                     status.authenticated = true;
                     status.clone()
                 };
-                let _ = app.emit("copilot-status-changed", new_status.clone());
+                let _ = app.emit(events::COPILOT_STATUS_CHANGED, new_status.clone());
                 return Ok(new_status);
             }
         }
@@ -293,7 +311,7 @@ pub async fn start_copilot(
     // The background task will continue polling and emit status updates
     let initial_status = state.copilot_status.lock().unwrap().clone();
     println!("[copilot] Returning after 8s wait: running={}, authenticated={}", initial_status.running, initial_status.authenticated);
-    let _ = app.emit("copilot-status-changed", initial_status.clone());
+    let _ = app.emit(events::COPILOT_STATUS_CHANGED, initial_status.clone());
     
     // Spawn background task to poll for authentication
     // This runs independently and emits status updates as authentication completes
@@ -336,7 +354,7 @@ pub async fn start_copilot(
                             status.authenticated = true;
                             status.clone()
                         };
-                        let _ = app_handle.emit("copilot-status-changed", new_status);
+                        let _ = app_handle.emit(events::COPILOT_STATUS_CHANGED, new_status);
                     }
                     return;
                 }
@@ -384,7 +402,7 @@ pub async fn stop_copilot(
     };
     
     // Emit status update
-    let _ = app.emit("copilot-status-changed", new_status.clone());
+    let _ = app.emit(events::COPILOT_STATUS_CHANGED, new_status.clone());
     
     Ok(new_status)
 }
@@ -9,8 +9,8 @@ use crate::types::{ProviderHealth, HealthStatus};
 pub async fn check_provider_health(state: State<'_, AppState>) -> Result<ProviderHealth, String> {
     let (port, proxy_running, proxy_api_key) = {
         let config = state.config.lock().unwrap();
-        let status = state.proxy_status.lock().unwrap();
-        (config.port, status.running, config.proxy_api_key.clone())
+        let running = state.default_instance().proxy_status.lock().unwrap().running;
+        (config.port, running, config.proxy_api_key.clone())
     };
     
     let auth_status = state.auth_status.lock().unwrap().clone();
@@ -0,0 +1,229 @@
+//! Commands for proxy instances besides the default one.
+//!
+//! The un-scoped proxy commands keep driving the default instance; these take
+//! an instance id and work on any of them.
+
+use std::sync::Arc;
+use tauri::{AppHandle, State};
+
+use crate::commands::proxy::{instance_config, refresh_adopted_status, start_instance, stop_instance};
+use crate::helpers::usage_stats::compute_usage_stats;
+use crate::config::{get_instance_dir, AppConfig, DEFAULT_INSTANCE_ID};
+use crate::helpers::usage_recorder;
+use crate::helpers::instances::{
+    create_instance_dir, instance_auth_dir, instance_id_for, load_definitions, save_definitions,
+    save_instance_config,
+};
+use crate::proxy::ports::find_free_port;
+use crate::proxy::validation::{summarize_errors, validate_config};
+use crate::state::{AppState, ProxyInstance};
+use crate::types::{
+    ControlApiConfig, ProxyInstanceDefinition, ProxyInstanceInfo, ProxyStatus, RequestHistory,
+    UsageStats,
+};
+
+fn instance_info(instance: &ProxyInstance, name: &str) -> ProxyInstanceInfo {
+    refresh_adopted_status(instance);
+    ProxyInstanceInfo {
+        id: instance.id.clone(),
+        name: name.to_string(),
+        is_default: instance.is_default(),
+        dir: instance.dir.to_string_lossy().to_string(),
+        auth_dir: instance_auth_dir(&instance.id, &instance.dir),
+        status: instance.proxy_status.lock().unwrap().clone(),
+    }
+}
+
+/// An instance other than the default, which has its own commands
+fn extra_instance(state: &AppState, id: &str) -> Result<Arc<ProxyInstance>, String> {
+    if id == DEFAULT_INSTANCE_ID {
+        return Err("Use the regular proxy commands for the default instance".to_string());
+    }
+    state.instance(id)
+}
+
+/// All instances, the default one first
+#[tauri::command]
+pub fn list_proxy_instances(state: State<'_, AppState>) -> Vec<ProxyInstanceInfo> {
+    let mut instances = vec![instance_info(&state.default_instance(), "Default")];
+    for definition in load_definitions() {
+        if let Ok(instance) = state.instance(&definition.id) {
+            instances.push(instance_info(&instance, &definition.name));
+        }
+    }
+    instances
+}
+
+/// Add an instance with its own directory and auth dir. Without a port the
+/// first free one above those already taken is used.
+#[tauri::command]
+pub fn create_proxy_instance(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    port: Option<u16>,
+    copy_current_config: bool,
+) -> Result<ProxyInstanceInfo, String> {
+    let mut definitions = load_definitions();
+    let existing: Vec<String> = definitions.iter().map(|d| d.id.clone()).collect();
+    let id = instance_id_for(&name, &existing)?;
+
+    let mut config = if copy_current_config {
+        let mut config = state.config.lock().unwrap().clone();
+        // The control API belongs to the app, not to a proxy
+        config.control_api = ControlApiConfig::default();
+        config
+    } else {
+        AppConfig::default()
+    };
+    let taken: Vec<u16> = state
+        .all_instances()
+        .iter()
+        .map(|instance| instance_config(&state, instance).map(|c| c.port))
+        .collect::<Result<_, _>>()?;
+    config.port = match port {
+        Some(port) if taken.contains(&port) => {
+            return Err(format!("Port {} is already used by another instance", port));
+        }
+        Some(port) => port,
+        None => {
+            let start = taken
+                .iter()
+                .copied()
+                .max()
+                .unwrap_or(config.port)
+                .saturating_add(1);
+            find_free_port(start, &taken).ok_or("No free port for the new instance")?
+        }
+    };
+
+    let dir = get_instance_dir(&id);
+    create_instance_dir(&dir, &config)?;
+    definitions.push(ProxyInstanceDefinition {
+        id: id.clone(),
+        name: name.trim().to_string(),
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    });
+    save_definitions(&definitions)?;
+
+    let instance = Arc::new(ProxyInstance::new(&id, dir));
+    state
+        .instances
+        .lock()
+        .unwrap()
+        .insert(id.clone(), instance.clone());
+    println!(
+        "[ProxyPal] Created proxy instance {} on port {}",
+        id, config.port
+    );
+    crate::refresh_tray_menu(&app);
+
+    Ok(instance_info(&instance, name.trim()))
+}
+
+/// Stop an instance and remove it together with its directory, including
+/// the credentials in its auth dir
+#[tauri::command]
+pub async fn delete_proxy_instance(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let instance = extra_instance(&state, &id)?;
+    stop_instance(app.clone(), instance.clone()).await?;
+
+    let mut definitions = load_definitions();
+    definitions.retain(|d| d.id != id);
+    save_definitions(&definitions)?;
+    state.instances.lock().unwrap().remove(&id);
+
+    // Waits for the stopped log watcher to let go of the recorder
+    let dir = instance.dir.clone();
+    let _ = tauri::async_runtime::spawn_blocking(move || usage_recorder::close(&dir)).await;
+
+    if instance.dir.exists() {
+        std::fs::remove_dir_all(&instance.dir)
+            .map_err(|e| format!("Failed to remove {}: {}", instance.dir.display(), e))?;
+    }
+    println!("[ProxyPal] Deleted proxy instance {}", id);
+    crate::refresh_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn start_proxy_instance(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<ProxyStatus, String> {
+    let instance = state.instance(&id)?;
+    start_instance(app, instance).await
+}
+
+#[tauri::command]
+pub async fn stop_proxy_instance(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<ProxyStatus, String> {
+    let instance = state.instance(&id)?;
+    stop_instance(app, instance).await
+}
+
+#[tauri::command]
+pub fn get_instance_config(state: State<'_, AppState>, id: String) -> Result<AppConfig, String> {
+    let instance = extra_instance(&state, &id)?;
+    instance_config(&state, &instance)
+}
+
+/// Save an instance's config, restarting it if it is running so the sidecar
+/// picks up the new port and settings
+#[tauri::command]
+pub async fn save_proxy_instance_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    config: AppConfig,
+) -> Result<ProxyStatus, String> {
+    let instance = extra_instance(&state, &id)?;
+    let validation = validate_config(&config, None);
+    if !validation.valid {
+        return Err(format!(
+            "Invalid configuration: {}",
+            summarize_errors(&validation)
+        ));
+    }
+    save_instance_config(&instance.dir, &config)?;
+
+    let status = instance.proxy_status.lock().unwrap().clone();
+    if !status.running {
+        return Ok(status);
+    }
+    stop_instance(app.clone(), instance.clone()).await?;
+    start_instance(app, instance).await
+}
+
+#[tauri::command]
+pub fn get_instance_usage_stats(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<UsageStats, String> {
+    let instance = state.instance(&id)?;
+    let (is_running, port) = {
+        let status = instance.proxy_status.lock().unwrap();
+        (status.running, status.port)
+    };
+    compute_usage_stats(&instance.dir, is_running, port)
+}
+
+#[tauri::command]
+pub fn get_instance_request_history(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<RequestHistory, String> {
+    let instance = state.instance(&id)?;
+    usage_recorder::reader(&instance.dir)?.request_history()
+}
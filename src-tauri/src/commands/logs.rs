@@ -1,7 +1,7 @@
 //! Log viewer commands and helpers.
 
 use crate::state::AppState;
-use crate::types::LogEntry;
+use crate::types::{LogEntry, SidecarLogFormat, SidecarOutputLine};
 use crate::{build_management_client, get_management_key, get_management_url};
 use serde::Deserialize;
 use tauri::State;
@@ -27,13 +27,14 @@ pub async fn get_logs(
     lines: Option<u32>,
 ) -> Result<Vec<LogEntry>, String> {
     let port = state.config.lock().unwrap().port;
+    let format = state.default_instance().sidecar_capabilities.lock().unwrap().log_format;
     let lines_param = lines.unwrap_or(500);
     let url = format!("{}?lines={}", get_management_url(port, "logs"), lines_param);
 
     let client = build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to get logs: {}", e))?;
@@ -55,22 +56,67 @@ pub async fn get_logs(
         .lines
         .iter()
         .filter(|line| !line.is_empty())
-        .map(|line| parse_log_line(line))
+        .map(|line| parse_log_line(line, format))
         .collect();
 
     Ok(entries)
 }
 
+/// Captured CLIProxyAPI stdout/stderr: lines after sequence number `since`,
+/// at most the newest `limit`
+#[tauri::command]
+pub fn get_sidecar_output(
+    state: State<'_, AppState>,
+    since: Option<u64>,
+    limit: Option<usize>,
+) -> Vec<SidecarOutputLine> {
+    state.default_instance().sidecar_output.lock().unwrap().query(since, limit)
+}
+
 // Parse a log line into a LogEntry struct
 // Expected formats from CLIProxyAPI:
 // - "[2025-12-02 22:12:52] [info] [gin_logger.go:58] message"
 // - "[2025-12-02 22:12:52] [info] message"
-// - "2024-01-15T10:30:45.123Z [INFO] message"
-fn parse_log_line(line: &str) -> LogEntry {
+// - "2024-01-15T10:30:45.123Z [INFO] message" (before v6)
+// The format expected for the sidecar's version is tried first and the other
+// one after it, since the version cutoff is only approximate.
+fn parse_log_line(line: &str, format: SidecarLogFormat) -> LogEntry {
     let line = line.trim();
 
-    // Format: [timestamp] [level] [source] message
-    // or: [timestamp] [level] message
+    let parsed = match format {
+        SidecarLogFormat::Iso => parse_iso_line(line).or_else(|| parse_bracketed_line(line)),
+        SidecarLogFormat::Bracketed | SidecarLogFormat::Unknown => {
+            parse_bracketed_line(line).or_else(|| parse_iso_line(line))
+        }
+    };
+    if let Some(entry) = parsed {
+        return entry;
+    }
+
+    // Try "LEVEL: message" format
+    for level in &["ERROR", "WARN", "INFO", "DEBUG", "TRACE"] {
+        if line.to_uppercase().starts_with(level) {
+            let rest = &line[level.len()..];
+            if rest.starts_with(':') || rest.starts_with(' ') {
+                return LogEntry {
+                    timestamp: String::new(),
+                    level: level.to_string(),
+                    message: rest.trim_start_matches(|c| c == ':' || c == ' ').to_string(),
+                };
+            }
+        }
+    }
+
+    // Default: plain text as INFO
+    LogEntry {
+        timestamp: String::new(),
+        level: "INFO".to_string(),
+        message: line.to_string(),
+    }
+}
+
+// [timestamp] [level] [source] message, or [timestamp] [level] message
+fn parse_bracketed_line(line: &str) -> Option<LogEntry> {
     if line.starts_with('[') {
         let mut parts = Vec::new();
         let mut current_start = 0;
@@ -95,15 +141,18 @@ fn parse_log_line(line: &str) -> LogEntry {
             let timestamp = parts[0].to_string();
             let level = parts[1].to_uppercase();
 
-            return LogEntry {
+            return Some(LogEntry {
                 timestamp,
                 level: normalize_log_level(&level),
                 message: message.to_string(),
-            };
+            });
         }
     }
+    None
+}
 
-    // Try ISO timestamp format: "2024-01-15T10:30:45.123Z [INFO] message"
+// 2024-01-15T10:30:45.123Z [INFO] message
+fn parse_iso_line(line: &str) -> Option<LogEntry> {
     if line.len() > 20 && (line.chars().nth(4) == Some('-') || line.chars().nth(10) == Some('T')) {
         if let Some(bracket_start) = line.find('[') {
             if let Some(bracket_end) = line[bracket_start..].find(']') {
@@ -111,35 +160,15 @@ fn parse_log_line(line: &str) -> LogEntry {
                 let level = line[bracket_start + 1..bracket_start + bracket_end].to_string();
                 let message = line[bracket_start + bracket_end + 1..].trim().to_string();
 
-                return LogEntry {
+                return Some(LogEntry {
                     timestamp,
                     level: normalize_log_level(&level),
                     message,
-                };
-            }
-        }
-    }
-
-    // Try "LEVEL: message" format
-    for level in &["ERROR", "WARN", "INFO", "DEBUG", "TRACE"] {
-        if line.to_uppercase().starts_with(level) {
-            let rest = &line[level.len()..];
-            if rest.starts_with(':') || rest.starts_with(' ') {
-                return LogEntry {
-                    timestamp: String::new(),
-                    level: level.to_string(),
-                    message: rest.trim_start_matches(|c| c == ':' || c == ' ').to_string(),
-                };
+                });
             }
         }
     }
-
-    // Default: plain text as INFO
-    LogEntry {
-        timestamp: String::new(),
-        level: "INFO".to_string(),
-        message: line.to_string(),
-    }
+    None
 }
 
 // Normalize log level to standard format
@@ -163,7 +192,7 @@ pub async fn clear_logs(state: State<'_, AppState>) -> Result<(), String> {
     let client = build_management_client();
     let response = client
         .delete(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to clear logs: {}", e))?;
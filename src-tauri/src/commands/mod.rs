@@ -4,14 +4,22 @@ pub mod agents;
 pub mod api_keys;
 pub mod auth;
 pub mod auth_files;
+pub mod budgets;
 pub mod config;
+pub mod control_api;
 pub mod cloudflare;
 pub mod copilot;
 pub mod health;
+pub mod instances;
 pub mod logs;
 pub mod models;
+pub mod pricing;
+pub mod profiles;
 pub mod proxy;
 pub mod quota;
+pub mod secrets;
 pub mod settings;
+pub mod sidecar;
+pub mod snapshots;
 pub mod ssh;
 pub mod usage;
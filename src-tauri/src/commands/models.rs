@@ -1,4 +1,5 @@
 use crate::config::save_config_to_file;
+use crate::helpers::secrets::resolve_secret;
 use crate::state::AppState;
 use crate::types::{AvailableModel, ProviderTestResult};
 use serde::Deserialize;
@@ -24,7 +25,7 @@ pub fn get_gpt_reasoning_models() -> Vec<String> {
 #[tauri::command]
 pub async fn get_available_models(state: State<'_, AppState>) -> Result<Vec<AvailableModel>, String> {
     let config = state.config.lock().unwrap().clone();
-    let proxy_running = state.proxy_status.lock().unwrap().running;
+    let proxy_running = state.default_instance().proxy_status.lock().unwrap().running;
     
     if !proxy_running {
         return Ok(vec![]);
@@ -54,7 +55,8 @@ pub async fn get_available_models(state: State<'_, AppState>) -> Result<Vec<Avai
             // Connection error - proxy might have crashed
             // Update state to reflect proxy is not running
             {
-                let mut status = state.proxy_status.lock().unwrap();
+                let instance = state.default_instance();
+                let mut status = instance.proxy_status.lock().unwrap();
                 status.running = false;
             }
             return Err(format!("Proxy not responding. Please restart the proxy. ({})", e));
@@ -196,6 +198,8 @@ pub async fn test_openai_provider(base_url: String, api_key: String) -> Result<P
             models_found: None,
         });
     }
+    // A key the user didn't replace arrives as its vault reference
+    let api_key = resolve_secret(&api_key)?;
     
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(10))
@@ -386,117 +390,6 @@ pub async fn fetch_openai_compatible_models(state: State<'_, AppState>) -> Resul
     Ok(results)
 }
 
-// Get model context and output limits
-pub(crate) fn get_model_limits(model_id: &str, owned_by: &str, source: &str) -> (u64, u64) {
-    // Return (context_limit, output_limit)
-    // First check model_id patterns (handles Antigravity Claude models like claude-opus-4-5-thinking)
-    let model_lower = model_id.to_lowercase();
-    
-    // Claude models (direct or via Antigravity)
-    if model_lower.contains("claude") {
-        // Claude 4.5 models: 200K context, 64K output
-        // Claude 3.5 haiku: 200K context, 8K output
-        if model_lower.contains("3-5-haiku") || model_lower.contains("3-haiku") {
-            return (200000, 8192);
-        } else {
-            // sonnet-4-5, opus-4-5, haiku-4-5, and other Claude 4.x models
-            return (200000, 64000);
-        }
-    }
-    
-    // Gemini models
-    if model_lower.contains("gemini") {
-        // Gemini 2.5 models: 1M context, 65K output
-        return (1000000, 65536);
-    }
-    
-    // GPT/OpenAI models
-    if model_lower.contains("gpt") || model_lower.starts_with("o1") || model_lower.starts_with("o3") {
-        // o1, o3 reasoning models: 200K context, 100K output
-        if model_lower.contains("o3") || model_lower.contains("o1") {
-            return (200000, 100000);
-        } else if model_lower.contains("gpt-5") || model_lower.contains("gpt5") {
-            // GPT-5 via Copilot: 128K context (Copilot limit)
-            // GPT-5 via ChatGPT/ProxyPal: 400K context
-            if source == "copilot" {
-                return (128000, 32768);
-            } else {
-                return (400000, 32768);
-            }
-        } else {
-            // gpt-4o, gpt-4o-mini, gpt-4.1: 128K context, 16K output
-            return (128000, 16384);
-        }
-    }
-    
-    // Qwen models
-    if model_lower.contains("qwen") {
-        // Qwen3 Coder Plus: 1M context
-        if model_lower.contains("coder") {
-            return (1000000, 65536);
-        } else {
-            // Qwen3 models: 262K context (max), 65K output
-            return (262144, 65536);
-        }
-    }
-    
-    // DeepSeek models
-    if model_lower.contains("deepseek") {
-        // deepseek-reasoner: 128K output, deepseek-chat: 8K output
-        if model_lower.contains("reasoner") || model_lower.contains("r1") {
-            return (128000, 128000);
-        } else {
-            return (128000, 8192);
-        }
-    }
-    
-    // Fallback to owned_by for any remaining models
-    match owned_by {
-        "anthropic" => (200000, 64000),
-        "google" => (1000000, 65536),
-        "openai" => (128000, 16384),
-        "qwen" => (262144, 65536),
-        "deepseek" => (128000, 8192),
-        _ => (128000, 16384) // safe defaults
-    }
-}
-
-// Get display name for a model
-pub(crate) fn get_model_display_name(model_id: &str, owned_by: &str, source: &str) -> String {
-    // Convert model ID to human-readable name
-    let base_name = model_id
-        .replace("-", " ")
-        .replace(".", " ")
-        .split_whitespace()
-        .map(|word| {
-            let mut chars: Vec<char> = word.chars().collect();
-            if !chars.is_empty() {
-                chars[0] = chars[0].to_uppercase().next().unwrap_or(chars[0]);
-            }
-            chars.into_iter().collect::<String>()
-        })
-        .collect::<Vec<String>>()
-        .join(" ");
-    
-    // Add provider prefix for clarity
-    let name = match owned_by {
-        "copilot" => format!("Copilot {}", base_name),
-        "anthropic" => format!("{}", base_name),
-        "google" => format!("{}", base_name),
-        "openai" => format!("{}", base_name),
-        "qwen" => format!("{}", base_name),
-        _ => base_name
-    };
-    
-    // Add source indicator for Vertex AI and other special sources
-    match source {
-        "vertex" => format!("{} [Vertex]", name),
-        "vertex+gemini-api" => format!("{} [Vertex+API]", name),
-        "copilot" => format!("{} [Copilot]", name),
-        _ => name
-    }
-}
-
 #[tauri::command]
 pub async fn set_claude_code_model(model_type: String, model_name: String) -> Result<(), String> {
     let home = dirs::home_dir().ok_or("Could not find home directory")?;
@@ -546,7 +439,7 @@ pub async fn get_force_model_mappings(state: State<'_, AppState>) -> Result<bool
     let client = crate::build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to get force model mappings: {}", e))?;
@@ -568,7 +461,7 @@ pub async fn set_force_model_mappings(state: State<'_, AppState>, value: bool) -
     let client = crate::build_management_client();
     let response = client
         .put(&url)
-        .header("X-Management-Key", &crate::get_management_key())
+        .header("X-Management-Key", &crate::get_management_key()?)
         .json(&serde_json::json!({ "value": value }))
         .send()
         .await
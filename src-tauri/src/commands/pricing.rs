@@ -0,0 +1,46 @@
+//! Pricing commands: the model price catalog and repricing stored usage.
+
+use tauri::State;
+
+use crate::config::get_pricing_path;
+use crate::helpers::{pricing, usage_recorder};
+use crate::state::AppState;
+use crate::types::{PricingRule, PricingTables};
+
+#[tauri::command]
+pub fn get_pricing() -> Result<PricingTables, String> {
+    Ok(PricingTables {
+        defaults: pricing::default_rules(),
+        overrides: pricing::load_overrides()?,
+        overrides_path: get_pricing_path().to_string_lossy().to_string(),
+    })
+}
+
+/// Replace the user's pricing rules. Stored costs keep their old prices
+/// until `recompute_costs` runs.
+#[tauri::command]
+pub fn save_pricing_overrides(rules: Vec<PricingRule>) -> Result<(), String> {
+    pricing::save_overrides(rules)
+}
+
+/// Reprice every instance's stored requests with pricing.json as it is now
+/// on disk. Returns how many requests were repriced.
+#[tauri::command]
+pub async fn recompute_costs(state: State<'_, AppState>) -> Result<usize, String> {
+    pricing::reload()?;
+    let dirs: Vec<_> = state
+        .all_instances()
+        .iter()
+        .map(|instance| instance.dir.clone())
+        .collect();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut repriced = 0;
+        for dir in dirs {
+            repriced += usage_recorder::recorder(&dir)?.recompute_costs()?;
+        }
+        println!("[ProxyPal] Repriced {} stored requests", repriced);
+        Ok(repriced)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
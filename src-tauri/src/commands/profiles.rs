@@ -0,0 +1,106 @@
+//! Named configuration profile commands.
+
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::proxy::reconcile_proxy_config;
+use crate::config::{get_profiles_dir, save_config_to_file, AppConfig};
+use crate::events;
+use crate::helpers::profiles::{
+    delete_profile_from, list_profiles, load_active_profile, load_profile_from,
+    merge_profile_into, profile_exists_in, save_active_profile, save_profile_to,
+    validate_profile_name,
+};
+use crate::helpers::snapshots::snapshot_config;
+use crate::state::AppState;
+use crate::types::ProfileInfo;
+
+#[tauri::command]
+pub fn get_profiles() -> Vec<ProfileInfo> {
+    list_profiles()
+}
+
+/// Save the current config as a new profile
+#[tauri::command]
+pub fn create_profile(
+    app: AppHandle,
+    state: State<AppState>,
+    name: String,
+) -> Result<Vec<ProfileInfo>, String> {
+    let dir = get_profiles_dir();
+    validate_profile_name(&name)?;
+    if profile_exists_in(&dir, &name) {
+        return Err(format!("Profile '{}' already exists", name));
+    }
+
+    let config = state.config.lock().unwrap().clone();
+    save_profile_to(&dir, &name, &config)?;
+
+    // The first profile mirrors config.json, so treat it as the active one
+    if load_active_profile().is_none() {
+        save_active_profile(Some(&name))?;
+    }
+
+    crate::refresh_tray_menu(&app);
+    Ok(list_profiles())
+}
+
+#[tauri::command]
+pub fn clone_profile(
+    app: AppHandle,
+    source: String,
+    name: String,
+) -> Result<Vec<ProfileInfo>, String> {
+    let dir = get_profiles_dir();
+    validate_profile_name(&name)?;
+    if profile_exists_in(&dir, &name) {
+        return Err(format!("Profile '{}' already exists", name));
+    }
+
+    let config = load_profile_from(&dir, &source)?;
+    save_profile_to(&dir, &name, &config)?;
+
+    crate::refresh_tray_menu(&app);
+    Ok(list_profiles())
+}
+
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, name: String) -> Result<Vec<ProfileInfo>, String> {
+    if load_active_profile().as_deref() == Some(name.as_str()) {
+        return Err("Cannot delete the active profile. Activate another profile first.".to_string());
+    }
+    delete_profile_from(&get_profiles_dir(), &name)?;
+
+    crate::refresh_tray_menu(&app);
+    Ok(list_profiles())
+}
+
+/// Switch config.json to the named profile and apply it to the running proxy.
+/// Unsaved changes to the outgoing profile are written back to it first.
+#[tauri::command]
+pub async fn activate_profile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<AppConfig, String> {
+    let dir = get_profiles_dir();
+    let profile = load_profile_from(&dir, &name)?;
+    let current = state.config.lock().unwrap().clone();
+
+    if let Some(previous) = load_active_profile() {
+        if previous != name && profile_exists_in(&dir, &previous) {
+            save_profile_to(&dir, &previous, &current)?;
+        }
+    }
+
+    let next = merge_profile_into(&current, profile);
+    snapshot_config("activate-profile", &current.snapshot_retention);
+    save_config_to_file(&next)?;
+    *state.config.lock().unwrap() = next.clone();
+    save_active_profile(Some(&name))?;
+
+    reconcile_proxy_config(&app, &state, &current, &next).await?;
+
+    crate::refresh_tray_menu(&app);
+    let _ = app.emit(events::PROFILE_ACTIVATED, &name);
+    Ok(next)
+}
@@ -1,624 +1,812 @@
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use tauri::{Emitter, Manager, State};
 use tauri_plugin_shell::ShellExt;
 
-use crate::config::AppConfig;
-use crate::state::AppState;
-use crate::types::ProxyStatus;
+use crate::cloudflare_manager::CloudflareManager;
+use crate::config::{get_sidecars_dir, save_config_to_file, AppConfig};
+use crate::events;
+use crate::ssh_manager::SshManager;
+use crate::state::{AppState, ProxyInstance};
+use crate::types::{
+    InstanceStatusEvent, PortChangeReport, ProxyConfigBaseline, ProxyConfigPreview, ProxyCrashLoopEvent,
+    ProxyRestartedEvent, ProxyStatus, ReloadPath, ReloadReport, SidecarCapabilities,
+    SidecarStream,
+};
+use crate::helpers::instances::{instance_auth_dir, load_instance_config, save_instance_config};
 use crate::helpers::log_watcher::start_log_watcher;
-use crate::get_management_key;
-use crate::GPT5_BASE_MODELS;
-use crate::GPT5_REASONING_SUFFIXES;
-
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
-#[cfg(target_os = "windows")]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
-
-use env_proxy;
-use sysproxy::Sysproxy;
-use url::Url;
-
-const DEFAULT_PROXY_CHECK_URL: &str = "https://example.com";
-
-fn env_proxy_for_url(target_url: &str) -> Option<String> {
-    let parsed = Url::parse(target_url).ok()?;
-    let proxy = env_proxy::for_url(&parsed);
-    let (host, port) = proxy.host_port()?;
-    Some(format!("http://{}:{}", host, port))
-}
-
-fn normalize_system_proxy(host: &str, port: u16) -> String {
-    let protocol = if host.to_ascii_lowercase().contains("socks") {
-        "socks5"
-    } else {
-        "http"
-    };
-    format!("{}://{}:{}", protocol, host, port)
-}
+use crate::helpers::secrets::resolve_secret;
+use crate::helpers::snapshots::snapshot_config;
+use crate::proxy::config_yaml::write_proxy_config_file_in;
+use crate::proxy::binaries::active_binary;
+use crate::proxy::capabilities::{capabilities_for, version_from_banner, VERSION_HEADER};
+use crate::proxy::output::{with_output, START_FAILURE_LINES};
+use crate::proxy::ports::{apply_port_change, find_free_port, is_port_free, propagate_port_to_agents};
+use crate::proxy::preview::build_proxy_config_preview;
+use crate::proxy::process::{
+    cleanup_stale_sidecar_at, is_recorded_at, port_owner, record_sidecar_at, running_sidecar_at,
+    stop_sidecar_at,
+};
+use crate::proxy::reload::{plan_config_change, value_at, MANAGEMENT_FIELDS};
+use crate::proxy::supervisor::ExitDecision;
+use crate::proxy::system_proxy;
+use crate::proxy::validation::{configured_models, summarize_errors, validate_config};
 
 #[tauri::command]
 pub fn get_system_proxy() -> Result<Option<String>, String> {
-    // 1. Check environment variables first (common in Linux/Dev environments)
-    // We use a neutral URL to avoid region-specific assumptions.
-    if let Some(proxy) = env_proxy_for_url(DEFAULT_PROXY_CHECK_URL) {
-        return Ok(Some(proxy));
-    }
+    system_proxy::get_system_proxy()
+}
+
+/// Regenerate proxy-config.yaml from AppConfig, merging user customizations.
+/// A running CLIProxyAPI picks the new file up through its config file watcher.
+pub(crate) fn write_proxy_config(
+    app: &tauri::AppHandle,
+    config: &AppConfig,
+) -> Result<std::path::PathBuf, String> {
+    let instance = app.state::<AppState>().default_instance();
+    write_instance_proxy_config(app, &instance, config)
+}
 
-    // 2. Check OS-level system proxy settings
-    let sys_proxy = Sysproxy::get_system_proxy();
+/// `write_proxy_config` for any proxy instance, into its directory
+pub(crate) fn write_instance_proxy_config(
+    app: &tauri::AppHandle,
+    instance: &ProxyInstance,
+    config: &AppConfig,
+) -> Result<std::path::PathBuf, String> {
+    let auth_dir = instance_auth_dir(&instance.id, &instance.dir);
+    let (proxy_config_path, proxy_config) =
+        write_proxy_config_file_in(&instance.dir, &auth_dir, config)?;
+    *instance.loaded_proxy_config.lock().unwrap() = Some(proxy_config.yaml);
+    if !proxy_config.conflicts.is_empty() && instance.is_default() {
+        let _ = app.emit(events::PROXY_CONFIG_CONFLICTS, &proxy_config.conflicts);
+    }
+
+    Ok(proxy_config_path)
+}
 
-    match sys_proxy {
-        Ok(proxy) if proxy.enable => Ok(Some(normalize_system_proxy(&proxy.host, proxy.port))),
-        Ok(_) => Ok(None),
-        Err(e) => Err(format!("Failed to detect system proxy: {}", e)),
+/// The config an instance runs on: the app config for the default one,
+/// otherwise its own config.json, management key included
+pub(crate) fn instance_config(state: &AppState, instance: &ProxyInstance) -> Result<AppConfig, String> {
+    if instance.is_default() {
+        return Ok(state.config.lock().unwrap().clone());
     }
+    load_instance_config(&instance.dir)
 }
 
-/// Build the complete proxy-config.yaml content from AppConfig.
-/// Includes all provider configs, API keys, routing, payload injection,
-/// and appends user customizations from proxy-config-custom.yaml.
-fn build_proxy_config_yaml(config: &AppConfig, config_dir: &std::path::Path) -> Result<String, String> {
-    let proxy_url_line = build_proxy_url_line(config);
-    let amp_api_key_line = build_amp_api_key_line(config);
-    let amp_model_mappings_section = build_amp_model_mappings_section(config);
-    let openai_compat_section = build_openai_compat_section(config);
-    let claude_api_key_section = build_claude_api_key_section(config);
-    let gemini_api_key_section = build_gemini_api_key_section(config);
-    let codex_api_key_section = build_codex_api_key_section(config);
-    let vertex_api_key_section = build_vertex_api_key_section(config);
-    let (thinking_budget, thinking_mode_display) = resolve_thinking_budget(config);
-    let payload_section = build_payload_section(config, thinking_budget, thinking_mode_display);
-    let routing_section = format!(
-        "# Routing strategy for multiple API keys\nrouting:\n  strategy: \"{}\"\n\n",
-        config.routing_strategy
-    );
+/// Where to reach an instance's Management API: its port and the key it
+/// wrote into its proxy-config.yaml
+pub(crate) fn management_target(
+    state: &AppState,
+    instance: &ProxyInstance,
+) -> Result<(u16, String), String> {
+    let config = instance_config(state, instance)?;
+    Ok((config.port, resolve_secret(&config.management_key)?))
+}
 
-    let mut proxy_config = format!(
-        r#"# ProxyPal generated config
-port: {}
-auth-dir: "~/.cli-proxy-api"
-api-keys:
-  - "{}"
-debug: {}
-usage-statistics-enabled: {}
-logging-to-file: {}
-logs-max-total-size-mb: {}
-request-retry: {}
-max-retry-interval: {}
-{}
-# Quota exceeded behavior
-quota-exceeded:
-  switch-project: {}
-  switch-preview-model: {}
-
-# Enable Management API for OAuth flows
-remote-management:
-  allow-remote: true
-  secret-key: "{}"
-  disable-control-panel: {}
-
-{}{}{}{}{}{}{}# Amp CLI Integration - enables amp login and management routes
-# See: https://help.router-for.me/agent-client/amp-cli.html
-# Get API key from: https://ampcode.com/settings
-ampcode:
-  upstream-url: "https://ampcode.com"
-{}
-{}
-  restrict-management-to-localhost: false
-  force-model-mappings: {}
-
-# Additional settings
-request-log: {}
-commercial-mode: {}
-ws-auth: {}
-"#,
-        config.port,
-        config.proxy_api_key,
-        config.debug,
-        config.usage_stats_enabled,
-        config.logging_to_file,
-        config.logs_max_total_size_mb,
-        config.request_retry,
-        config.max_retry_interval,
-        proxy_url_line,
-        config.quota_switch_project,
-        config.quota_switch_preview_model,
-        config.management_key,
-        config.disable_control_panel,
-        openai_compat_section,
-        claude_api_key_section,
-        gemini_api_key_section,
-        codex_api_key_section,
-        vertex_api_key_section,
-        routing_section,
-        payload_section,
-        amp_api_key_line,
-        amp_model_mappings_section,
-        config.force_model_mappings,
-        config.request_logging,
-        config.commercial_mode,
-        config.ws_auth
+/// Tell the UI about a status change: `proxy-status-changed` keeps meaning
+/// the default instance, `proxy-instance-status-changed` covers them all
+pub(crate) fn emit_instance_status(app: &tauri::AppHandle, instance: &ProxyInstance, status: &ProxyStatus) {
+    if instance.is_default() {
+        let _ = app.emit(events::PROXY_STATUS_CHANGED, status.clone());
+    }
+    let _ = app.emit(
+        events::PROXY_INSTANCE_STATUS_CHANGED,
+        InstanceStatusEvent {
+            id: instance.id.clone(),
+            status: status.clone(),
+        },
     );
+    crate::refresh_tray_menu(app);
+}
 
-    // Append user customizations from proxy-config-custom.yaml if it exists
-    let custom_config_path = config_dir.join("proxy-config-custom.yaml");
-    if custom_config_path.exists() {
-        if let Ok(custom_yaml) = std::fs::read_to_string(&custom_config_path) {
-            if !custom_yaml.trim().is_empty() {
-                proxy_config.push_str("\n# User customizations (from proxy-config-custom.yaml)\n");
-                proxy_config.push_str(&custom_yaml);
-                proxy_config.push('\n');
-            }
-        }
+/// "CLIProxyAPI", or "CLIProxyAPI (team)" for an extra instance, for messages
+fn sidecar_label(instance: &ProxyInstance) -> String {
+    if instance.is_default() {
+        "CLIProxyAPI".to_string()
+    } else {
+        format!("CLIProxyAPI ({})", instance.id)
     }
-
-    Ok(proxy_config)
 }
 
-fn build_proxy_url_line(config: &AppConfig) -> String {
-    let mut effective_proxy_url = if config.use_system_proxy {
-        get_system_proxy().ok().flatten().unwrap_or_default()
-    } else {
-        config.proxy_url.clone()
+/// PUT one setting through the Management API; a missing value is DELETEd
+async fn push_management_field(
+    client: &reqwest::Client,
+    port: u16,
+    management_key: &str,
+    endpoint: &str,
+    value: Option<serde_json::Value>,
+) -> Result<(), String> {
+    let url = crate::get_management_url(port, endpoint);
+    let request = match value.filter(|v| !v.is_null()) {
+        Some(value) => client.put(&url).json(&serde_json::json!({ "value": value })),
+        None => client.delete(&url),
     };
+    let response = request
+        .header("X-Management-Key", management_key)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("{} returned {}", endpoint, response.status()));
+    }
+    Ok(())
+}
 
-    if effective_proxy_url.is_empty() {
-        return String::new();
-    }
+/// Apply a config change to the running proxy by the least disruptive path:
+/// Management API for the settings it covers, CLIProxyAPI's file watcher for
+/// the rest, and a restart only for settings read at startup. Expects `new`
+/// to be saved and in AppState already.
+pub(crate) async fn reconcile_proxy_config(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    old: &AppConfig,
+    new: &AppConfig,
+) -> Result<ReloadReport, String> {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("proxypal");
+    let instance = state.default_instance();
+    let running = instance.proxy_status.lock().unwrap().running;
+
+    // Compare against what the sidecar actually loaded when we know it
+    let loaded = instance.loaded_proxy_config.lock().unwrap().clone();
+    let (plan, new_value) = plan_config_change(loaded.as_deref(), old, new, &config_dir)?;
+
+    let mut report = ReloadReport {
+        path: plan.path(),
+        management_fields: plan.management.clone(),
+        file_reload_fields: plan.file_reload.clone(),
+        restart_fields: plan.restart.clone(),
+    };
 
-    if !config.proxy_username.is_empty() && !config.proxy_password.is_empty() {
-        if let Ok(mut url) = url::Url::parse(&effective_proxy_url) {
-            let _ = url.set_username(&config.proxy_username);
-            let _ = url.set_password(Some(&config.proxy_password));
-            effective_proxy_url = url.to_string();
+    if !running {
+        write_proxy_config(app, new)?;
+        report.path = ReloadPath::None;
+    } else if report.path == ReloadPath::Restart {
+        println!(
+            "[ProxyPal] Restarting proxy for {}",
+            plan.restart.join(", ")
+        );
+        stop_proxy(app.clone(), state.clone()).await?;
+        start_proxy(app.clone(), state.clone()).await?;
+    } else if report.path != ReloadPath::None {
+        // The file carries every change; the PUTs just make covered ones immediate
+        write_proxy_config(app, new)?;
+        let client = crate::build_management_client();
+        let management_key = resolve_secret(&new.management_key)?;
+        for field in &plan.management {
+            let Some((_, endpoint)) = MANAGEMENT_FIELDS.iter().find(|(path, _)| path == field) else {
+                continue;
+            };
+            let value = value_at(&new_value, field).and_then(|v| serde_json::to_value(v).ok());
+            if let Err(e) = push_management_field(&client, new.port, &management_key, endpoint, value).await {
+                eprintln!(
+                    "[ProxyPal] Management update of {} failed, leaving it to file reload: {}",
+                    field, e
+                );
+                report.management_fields.retain(|f| f != field);
+                report.file_reload_fields.push(field.clone());
+            }
+        }
+        if report.management_fields.is_empty() {
+            report.path = ReloadPath::FileReload;
         }
     }
-    format!("proxy-url: \"{}\"\n", effective_proxy_url)
+
+    println!("[ProxyPal] Applied config change via {:?}", report.path);
+    let _ = app.emit(events::PROXY_CONFIG_APPLIED, &report);
+    Ok(report)
 }
 
-fn build_amp_api_key_line(config: &AppConfig) -> String {
-    if config.amp_api_key.is_empty() {
-        "  # upstream-api-key: \"\"  # Set your Amp API key from https://ampcode.com/settings".to_string()
-    } else {
-        format!("  upstream-api-key: \"{}\"", config.amp_api_key)
-    }
+/// Move the proxy to `new_port`: save it in AppConfig together with the
+/// tunnels that forwarded the old port, then carry it over to agent configs.
+/// Doesn't restart anything.
+pub(crate) fn switch_proxy_port(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    new_port: u16,
+) -> Result<PortChangeReport, String> {
+    let mut config = state.config.lock().unwrap().clone();
+    let old_port = config.port;
+    let tunnels = apply_port_change(&mut config, old_port, new_port);
+    snapshot_config("port-change", &config.snapshot_retention);
+    save_config_to_file(&config)?;
+    *state.config.lock().unwrap() = config.clone();
+    Ok(propagate_port_change(app, &config, old_port, &tunnels))
 }
 
-fn build_amp_model_mappings_section(config: &AppConfig) -> String {
-    let enabled_mappings: Vec<_> = config.amp_model_mappings.iter()
-        .filter(|m| m.enabled)
-        .collect();
+/// Rewrite agent configs that point at `old_port` and reconnect the given
+/// tunnels, which keep forwarding the old port until then. Emits
+/// `proxy-port-changed` with what was updated.
+pub(crate) fn propagate_port_change(
+    app: &tauri::AppHandle,
+    config: &AppConfig,
+    old_port: u16,
+    tunnels: &[String],
+) -> PortChangeReport {
+    let mut report = PortChangeReport {
+        old_port,
+        new_port: config.port,
+        ..Default::default()
+    };
 
-    if enabled_mappings.is_empty() {
-        "  # model-mappings:  # Optional: map Amp model requests to different models\n  #   - from: claude-opus-4-5-20251101\n  #     to: your-preferred-model".to_string()
-    } else {
-        let mut mappings = String::from("  model-mappings:");
-        for mapping in &enabled_mappings {
-            mappings.push_str(&format!("\n    - from: {}\n      to: {}", mapping.name, mapping.alias));
-            if mapping.fork {
-                mappings.push_str("\n      fork: true");
-            }
+    match dirs::home_dir() {
+        Some(home) => {
+            let (files, errors) = propagate_port_to_agents(&home, old_port, config.port);
+            report.updated_files = files;
+            report.errors = errors;
         }
-        mappings
+        None => report.errors.push("Could not find home directory".to_string()),
     }
-}
 
-fn build_openai_compat_section(config: &AppConfig) -> String {
-    let mut entries = Vec::new();
-
-    // Custom providers
-    for provider in &config.amp_openai_providers {
-        if !provider.name.is_empty() && !provider.base_url.is_empty() && !provider.api_key.is_empty() {
-            let mut entry = format!("  # Custom OpenAI-compatible provider: {}\n", provider.name);
-            entry.push_str(&format!("  - name: \"{}\"\n", provider.name));
-            entry.push_str(&format!("    base-url: \"{}\"\n", provider.base_url));
-            entry.push_str("    schema-cleaner: true\n");
-            entry.push_str("    api-key-entries:\n");
-            entry.push_str(&format!("      - api-key: \"{}\"\n", provider.api_key));
-
-            if !provider.models.is_empty() {
-                entry.push_str("    models:\n");
-                for model in &provider.models {
-                    entry.push_str(&format!("      - alias: \"{}\"\n", model.alias));
-                    entry.push_str(&format!("        name: \"{}\"\n", model.name));
-                }
-            }
-            entries.push(entry);
+    let ssh_manager = app.state::<SshManager>();
+    for ssh in config.ssh_configs.iter().filter(|c| tunnels.contains(&c.id)) {
+        report.updated_tunnels.push(format!("SSH {}@{}", ssh.username, ssh.host));
+        if ssh.enabled {
+            ssh_manager.disconnect(&ssh.id);
+            ssh_manager.connect(app.clone(), ssh.clone());
         }
     }
-
-    // Copilot OpenAI-compatible entry
-    if config.copilot.enabled {
-        entries.push(build_copilot_openai_entry(&config.copilot));
+    let cloudflare_manager = app.state::<CloudflareManager>();
+    for cf in config.cloudflare_configs.iter().filter(|c| tunnels.contains(&c.id)) {
+        report.updated_tunnels.push(format!("Cloudflare {}", cf.name));
+        if cloudflare_manager.get_status(&cf.id) == "active" {
+            cloudflare_manager.disconnect(&cf.id);
+            cloudflare_manager.connect(app.clone(), cf.clone());
+        }
     }
 
-    if entries.is_empty() {
-        String::new()
-    } else {
-        let mut section = String::from("# OpenAI-compatible providers\nopenai-compatibility:\n");
-        for entry in entries {
-            section.push_str(&entry);
-        }
-        section.push('\n');
-        section
+    println!(
+        "[ProxyPal] Proxy port changed from {} to {}: {} agent file(s), {} tunnel(s) updated",
+        old_port,
+        config.port,
+        report.updated_files.len(),
+        report.updated_tunnels.len()
+    );
+    for error in &report.errors {
+        eprintln!("[ProxyPal] {}", error);
     }
+    let _ = app.emit(events::PROXY_PORT_CHANGED, &report);
+    report
 }
 
-fn build_copilot_openai_entry(copilot: &crate::types::copilot::CopilotConfig) -> String {
-    let port = copilot.port;
-    let mut entry = String::from("  # GitHub Copilot GPT/OpenAI models (via copilot-api)\n");
-    entry.push_str("  - name: \"copilot\"\n");
-    entry.push_str(&format!("    base-url: \"http://localhost:{}/v1\"\n", port));
-    entry.push_str("    schema-cleaner: true\n");
-    entry.push_str("    api-key-entries:\n");
-    entry.push_str("      - api-key: \"dummy\"\n");
-    entry.push_str("    models:\n");
-
-    // OpenAI GPT models
-    entry.push_str("      - alias: \"gpt-4.1\"\n");
-    entry.push_str("        name: \"gpt-4.1\"\n");
-
-    // GPT-5 models with reasoning suffixes
-    for model in GPT5_BASE_MODELS {
-        entry.push_str(&format!("      - alias: \"{}\"\n", model));
-        entry.push_str(&format!("        name: \"{}\"\n", model));
-        for suffix in GPT5_REASONING_SUFFIXES {
-            let suffixed = format!("{}({})", model, suffix);
-            entry.push_str(&format!("      - alias: \"{}\"\n", suffixed));
-            entry.push_str(&format!("        name: \"{}\"\n", suffixed));
-        }
-    }
+// Tauri commands
+#[tauri::command]
+pub fn get_proxy_status(state: State<AppState>) -> ProxyStatus {
+    let instance = state.default_instance();
+    refresh_adopted_status(&instance);
+    let status = instance.proxy_status.lock().unwrap().clone();
+    status
+}
 
-    // Legacy OpenAI models
-    for name in ["gpt-4o", "gpt-4", "gpt-4-turbo", "o1", "o1-mini"] {
-        entry.push_str(&format!("      - alias: \"{}\"\n", name));
-        entry.push_str(&format!("        name: \"{}\"\n", name));
-    }
+/// Render proxy-config.yaml from `config` (unsaved edits, or the saved config
+/// when omitted) and diff it against what the sidecar loaded, secrets masked
+#[tauri::command]
+pub fn preview_proxy_config(
+    state: State<AppState>,
+    config: Option<AppConfig>,
+) -> Result<ProxyConfigPreview, String> {
+    let config = config.unwrap_or_else(|| state.config.lock().unwrap().clone());
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("proxypal");
 
-    // xAI, fine-tuned, Gemini, Claude models
-    let extra_models = [
-        "grok-code-fast-1", "raptor-mini",
-        "gemini-2.5-pro", "gemini-3-pro-preview", "gemini-3.1-pro-high", "gemini-3.1-pro-low",
-        "claude-haiku-4.5", "claude-opus-4.1", "claude-sonnet-4", "claude-sonnet-4.5",
-        "claude-opus-4.5", "claude-opus-4.6",
-    ];
-    for name in extra_models {
-        entry.push_str(&format!("      - alias: \"{}\"\n", name));
-        entry.push_str(&format!("        name: \"{}\"\n", name));
-    }
+    let instance = state.default_instance();
+    let running = instance.proxy_status.lock().unwrap().running;
+    let loaded = instance.loaded_proxy_config.lock().unwrap().clone();
+    let (baseline, baseline_yaml) = match loaded {
+        Some(yaml) if running => (ProxyConfigBaseline::Running, yaml),
+        _ => match std::fs::read_to_string(config_dir.join("proxy-config.yaml")) {
+            Ok(yaml) => (ProxyConfigBaseline::File, yaml),
+            Err(_) => (ProxyConfigBaseline::None, String::new()),
+        },
+    };
 
-    entry
+    build_proxy_config_preview(&config, &config_dir, baseline, &baseline_yaml)
 }
 
-fn build_claude_api_key_section(config: &AppConfig) -> String {
-    let mut entries: Vec<String> = Vec::new();
-    for key in &config.claude_api_keys {
-        let mut entry = format!("  - api-key: \"{}\"\n", key.api_key);
-        if let Some(ref base_url) = key.base_url {
-            entry.push_str(&format!("    base-url: \"{}\"\n", base_url));
-        }
-        if let Some(ref proxy_url) = key.proxy_url {
-            if !proxy_url.is_empty() {
-                entry.push_str(&format!("    proxy-url: \"{}\"\n", proxy_url));
+#[tauri::command]
+pub async fn start_proxy(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ProxyStatus, String> {
+    start_instance(app, state.default_instance()).await
+}
+
+/// Start one proxy instance
+pub(crate) async fn start_instance(
+    app: tauri::AppHandle,
+    instance: Arc<ProxyInstance>,
+) -> Result<ProxyStatus, String> {
+    // Starting by hand gives a crash-looping sidecar a fresh chance
+    instance.proxy_supervisor.lock().unwrap().reset();
+    instance.proxy_status.lock().unwrap().last_error = None;
+    launch_proxy(app, instance).await
+}
+
+/// Restart the sidecar after it exited on its own, backing off between
+/// attempts, until it comes back up or exits too often. Boxed because it
+/// relaunches through `launch_proxy`, which spawns it again.
+fn supervise_exit(
+    app: tauri::AppHandle,
+    instance: Arc<ProxyInstance>,
+    token: u64,
+    exit_code: Option<i32>,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+    Box::pin(supervise_exit_loop(app, instance, token, exit_code))
+}
+
+async fn supervise_exit_loop(
+    app: tauri::AppHandle,
+    instance: Arc<ProxyInstance>,
+    mut token: u64,
+    mut exit_code: Option<i32>,
+) {
+    loop {
+        let decision = instance
+            .proxy_supervisor
+            .lock()
+            .unwrap()
+            .record_exit(std::time::Instant::now());
+        let (attempt, delay) = match decision {
+            ExitDecision::Restart { attempt, delay } => (attempt, delay),
+            ExitDecision::CrashLoop { exits } => {
+                report_crash_loop(&app, &instance, exits, exit_code);
+                return;
             }
+        };
+
+        println!(
+            "[ProxyPal] {} exited unexpectedly, restarting in {}ms (attempt {})",
+            sidecar_label(&instance),
+            delay.as_millis(),
+            attempt
+        );
+        tokio::time::sleep(delay).await;
+        if !instance.proxy_supervisor.lock().unwrap().is_current(token) {
+            return; // Stopped or started by hand in the meantime
         }
-        entries.push(entry);
-    }
 
-    if entries.is_empty() {
-        String::new()
-    } else {
-        let mut section = String::from("# Claude API keys\nclaude-api-key:\n");
-        for entry in entries {
-            section.push_str(&entry);
+        match launch_proxy(app.clone(), instance.clone()).await {
+            Ok(_) => {
+                let status = {
+                    let mut status = instance.proxy_status.lock().unwrap();
+                    status.restart_count += 1;
+                    status.clone()
+                };
+                emit_instance_status(&app, &instance, &status);
+                if instance.is_default() {
+                    let _ = app.emit(
+                        events::PROXY_RESTARTED,
+                        ProxyRestartedEvent {
+                            attempt,
+                            restart_count: status.restart_count,
+                            exit_code,
+                            delay_ms: delay.as_millis() as u64,
+                        },
+                    );
+                }
+                return;
+            }
+            Err(e) => {
+                eprintln!("[ProxyPal] Failed to restart {}: {}", sidecar_label(&instance), e);
+                let mut supervisor = instance.proxy_supervisor.lock().unwrap();
+                // A launch moves the generation on once. Any further and the
+                // user stepped in, or the new process died and its exit is
+                // being supervised separately.
+                if !supervisor.is_current(token) && !supervisor.is_current(token + 1) {
+                    return;
+                }
+                supervisor.push_stderr(&e);
+                token = supervisor.generation();
+                exit_code = None;
+            }
         }
-        section.push('\n');
-        section
     }
 }
 
-fn build_gemini_api_key_section(config: &AppConfig) -> String {
-    if config.gemini_api_keys.is_empty() {
-        return String::new();
+fn report_crash_loop(
+    app: &tauri::AppHandle,
+    instance: &ProxyInstance,
+    exits: usize,
+    exit_code: Option<i32>,
+) {
+    let (window_secs, stderr_tail) = {
+        let supervisor = instance.proxy_supervisor.lock().unwrap();
+        (supervisor.policy().window.as_secs(), supervisor.stderr_tail())
+    };
+
+    let mut message = format!(
+        "{} exited {} times within {}s and was not restarted",
+        sidecar_label(instance),
+        exits,
+        window_secs
+    );
+    if let Some(last) = stderr_tail.last() {
+        message.push_str(&format!(". Last error: {}", last));
     }
-    let mut section = String::from("# Gemini API keys\ngemini-api-key:\n");
-    for key in &config.gemini_api_keys {
-        section.push_str(&format!("  - api-key: \"{}\"\n", key.api_key));
-        section.push_str("    signature-cache: false\n");
-        if let Some(ref base_url) = key.base_url {
-            section.push_str(&format!("    base-url: \"{}\"\n", base_url));
-        }
-        if let Some(ref proxy_url) = key.proxy_url {
-            if !proxy_url.is_empty() {
-                section.push_str(&format!("    proxy-url: \"{}\"\n", proxy_url));
-            }
-        }
+    eprintln!("[ProxyPal] {}", message);
+    for line in &stderr_tail {
+        eprintln!("[ProxyPal]   {}", line);
     }
-    section.push('\n');
-    section
-}
 
-fn build_codex_api_key_section(config: &AppConfig) -> String {
-    if config.codex_api_keys.is_empty() {
-        return String::new();
+    let status = {
+        let mut status = instance.proxy_status.lock().unwrap();
+        status.running = false;
+        status.last_error = Some(message.clone());
+        status.clone()
+    };
+    emit_instance_status(app, instance, &status);
+
+    {
+        use tauri_plugin_notification::NotificationExt;
+        let _ = app
+            .notification()
+            .builder()
+            .title("ProxyPal proxy keeps crashing")
+            .body(&message)
+            .show();
+    }
+
+    if instance.is_default() {
+        let _ = app.emit(
+            events::PROXY_CRASH_LOOP,
+            ProxyCrashLoopEvent {
+                message,
+                exits,
+                window_secs,
+                exit_code,
+                stderr_tail,
+            },
+        );
     }
-    let mut section = String::from("# Codex API keys\ncodex-api-key:\n");
-    for key in &config.codex_api_keys {
-        section.push_str(&format!("  - api-key: \"{}\"\n", key.api_key));
-        if let Some(ref base_url) = key.base_url {
-            section.push_str(&format!("    base-url: \"{}\"\n", base_url));
-        }
-        if let Some(ref proxy_url) = key.proxy_url {
-            if !proxy_url.is_empty() {
-                section.push_str(&format!("    proxy-url: \"{}\"\n", proxy_url));
+}
+
+/// Poll the management API until the sidecar answers, for up to 5s.
+/// None if it never did, otherwise the version header it sent, if any.
+pub(crate) async fn wait_until_ready(
+    client: &reqwest::Client,
+    port: u16,
+    management_key: &str,
+) -> Option<Option<String>> {
+    let health_url = format!("http://127.0.0.1:{}/v0/management/config.yaml", port);
+    for _ in 0..25 {
+        // 25 attempts × 200ms = 5s max
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        match client
+            .get(&health_url)
+            .header("X-Management-Key", management_key)
+            .send()
+            .await
+        {
+            Ok(resp) if resp.status().is_success() => {
+                let version = resp
+                    .headers()
+                    .get(VERSION_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty());
+                return Some(version);
             }
+            _ => {}
         }
     }
-    section.push('\n');
-    section
+    None
 }
 
-fn build_vertex_api_key_section(config: &AppConfig) -> String {
-    if config.vertex_api_keys.is_empty() {
-        return String::new();
+/// Work out what the sidecar that just started supports, from the version
+/// header or else its startup banner, and warn if it is too old
+fn detect_capabilities(
+    app: &tauri::AppHandle,
+    instance: &ProxyInstance,
+    version_header: Option<String>,
+    output_start: u64,
+) {
+    let version = version_header.or_else(|| {
+        let output = instance.sidecar_output.lock().unwrap();
+        let lines = output.query(Some(output_start), None);
+        version_from_banner(lines.iter().map(|l| l.line.as_str()))
+    });
+    let capabilities = capabilities_for(version.as_deref());
+    match &capabilities.version {
+        Some(version) => println!("[ProxyPal] {} version {}", sidecar_label(instance), version),
+        None => eprintln!("[ProxyPal] Could not detect the CLIProxyAPI version, assuming a current one"),
     }
-    let mut section = String::from("# Vertex API keys\nvertex-api-key:\n");
-    for key in &config.vertex_api_keys {
-        section.push_str(&format!("  - api-key: \"{}\"\n", key.api_key));
-        if let Some(ref project_id) = key.project_id {
-            if !project_id.is_empty() {
-                section.push_str(&format!("    project-id: \"{}\"\n", project_id));
-            }
-        }
-        if let Some(ref location) = key.location {
-            if !location.is_empty() {
-                section.push_str(&format!("    location: \"{}\"\n", location));
-            }
-        }
-        if let Some(ref base_url) = key.base_url {
-            if !base_url.is_empty() {
-                section.push_str(&format!("    base-url: \"{}\"\n", base_url));
-            }
-        }
-        if let Some(ref prefix) = key.prefix {
-            if !prefix.is_empty() {
-                section.push_str(&format!("    prefix: \"{}\"\n", prefix));
-            }
-        }
+
+    let changed = {
+        let mut current = instance.sidecar_capabilities.lock().unwrap();
+        let changed = *current != capabilities;
+        *current = capabilities.clone();
+        changed
+    };
+
+    if capabilities.outdated && changed {
+        let message = format!(
+            "CLIProxyAPI {} is older than the minimum supported version {}. Some features are disabled; please update the sidecar.",
+            capabilities.version.as_deref().unwrap_or("unknown"),
+            capabilities.minimum_version
+        );
+        eprintln!("[ProxyPal] {}", message);
+        use tauri_plugin_notification::NotificationExt;
+        let _ = app
+            .notification()
+            .builder()
+            .title("CLIProxyAPI update recommended")
+            .body(&message)
+            .show();
+    }
+    if instance.is_default() {
+        let _ = app.emit(events::SIDECAR_CAPABILITIES, capabilities);
     }
-    section.push('\n');
-    section
 }
 
-fn resolve_thinking_budget(config: &AppConfig) -> (u32, &str) {
-    let mode = if config.thinking_budget_mode.is_empty() {
-        "medium"
-    } else {
-        &config.thinking_budget_mode
-    };
-    let custom = if config.thinking_budget_custom == 0 { 16000 } else { config.thinking_budget_custom };
-    let budget = match mode {
-        "low" => 2048,
-        "medium" => 8192,
-        "high" => 32768,
-        "custom" => custom,
-        _ => 8192,
-    };
-    (budget, mode)
+/// Features of the running sidecar
+#[tauri::command]
+pub fn get_sidecar_capabilities(state: State<'_, AppState>) -> SidecarCapabilities {
+    state.default_instance().sidecar_capabilities.lock().unwrap().clone()
 }
 
-fn build_payload_section(config: &AppConfig, thinking_budget: u32, thinking_mode_display: &str) -> String {
-    let gemini3_thinking_level = match thinking_budget {
-        2048 => "low",
-        8192 => "medium",
-        _ => "high",
-    };
+/// Buffer a line of sidecar output and pass the default instance's on to the UI
+fn capture_output(
+    app: &tauri::AppHandle,
+    instance: &ProxyInstance,
+    stream: SidecarStream,
+    text: &str,
+) {
+    let line = instance.sidecar_output.lock().unwrap().push(stream, text);
+    if let Some(line) = line.filter(|_| instance.is_default()) {
+        let _ = app.emit(events::SIDECAR_OUTPUT, line);
+    }
+}
 
-    let gemini_override_section = if config.gemini_thinking_injection {
-        build_gemini_override_section(gemini3_thinking_level)
-    } else {
-        String::new()
-    };
+/// Track the instance's requests from the sidecar log. Only the default
+/// instance's requests reach the UI live.
+fn watch_requests(app: &tauri::AppHandle, instance: &ProxyInstance, retention_days: u32) {
+    // Signal any existing watcher to stop, then start new one
+    let log_watcher_running = instance.log_watcher_running.clone();
+    log_watcher_running.store(false, Ordering::SeqCst);
+    std::thread::sleep(std::time::Duration::from_millis(100)); // Give old watcher time to stop
+    log_watcher_running.store(true, Ordering::SeqCst);
 
-    format!(r#"# Payload injection for thinking models
-# Antigravity Claude: Thinking budget mode: {} ({} tokens)
-# Gemini 3: Thinking injection: {}
-payload:
-  default:
-    # Antigravity Claude models - thinking budget
-    - models:
-        - name: "claude-sonnet-4-5"
-          protocol: "claude"
-        - name: "claude-sonnet-4-5-thinking"
-          protocol: "claude"
-        - name: "gemini-claude-sonnet-4-5"
-          protocol: "claude"
-        - name: "gemini-claude-sonnet-4-5-thinking"
-          protocol: "claude"
-      params:
-        "thinking.budget_tokens": {}
-    - models:
-        - name: "claude-opus-4-5"
-          protocol: "claude"
-        - name: "claude-opus-4-5-thinking"
-          protocol: "claude"
-        - name: "gemini-claude-opus-4-5"
-          protocol: "claude"
-        - name: "gemini-claude-opus-4-5-thinking"
-          protocol: "claude"
-        - name: "claude-opus-4-6"
-          protocol: "claude"
-        - name: "claude-opus-4-6-thinking"
-          protocol: "claude"
-        - name: "gemini-claude-opus-4-6"
-          protocol: "claude"
-        - name: "gemini-claude-opus-4-6-thinking"
-          protocol: "claude"
-      params:
-        "thinking.budget_tokens": {}
-{}
-"#,
-        thinking_mode_display,
-        thinking_budget,
-        if config.gemini_thinking_injection { format!("enabled ({})", gemini3_thinking_level) } else { "disabled".to_string() },
-        thinking_budget,
-        thinking_budget,
-        gemini_override_section
-    )
+    let app_handle = app.clone();
+    let is_default = instance.is_default();
+    start_log_watcher(
+        instance.dir.clone(),
+        log_watcher_running,
+        instance.request_counter.clone(),
+        retention_days,
+        move |request_log| {
+            if is_default {
+                let _ = app_handle.emit(events::REQUEST_LOG, request_log.clone());
+            }
+        },
+    );
 }
 
-fn build_gemini_override_section(thinking_level: &str) -> String {
-    format!(r#"  override:
-    # Gemini 3 models - thinking level
-    - models:
-        - name: "gemini-3-pro-preview*"
-      params:
-        generationConfig.thinkingConfig.thinkingLevel: "{0}"
-    - models:
-        - name: "gemini-3-flash-preview*"
-      params:
-        generationConfig.thinkingConfig.thinkingLevel: "{0}"
-    - models:
-        - name: "gemini-3.1-pro-high*"
-      params:
-        generationConfig.thinkingConfig.thinkingLevel: "high"
-    - models:
-        - name: "gemini-3.1-pro-low*"
-      params:
-        generationConfig.thinkingConfig.thinkingLevel: "low"
-    - models:
-        - name: "gemini-3-pro-high"
-      params:
-        generationConfig.thinkingConfig.thinkingLevel: "high"
-    - models:
-        - name: "gemini-3-pro-low"
-      params:
-        generationConfig.thinkingConfig.thinkingLevel: "low"
-    - models:
-        - name: "gemini-3-flash"
-      params:
-        generationConfig.thinkingConfig.thinkingLevel: "{0}"
-"#, thinking_level)
+/// Take over a sidecar recorded in the instance's PID file that the app
+/// didn't spawn, e.g. one started with `proxypal start`, instead of
+/// starting a second one. It is stopped through the PID file like our own,
+/// but only `proxypal run` restarts it if it crashes.
+pub(crate) fn adopt_sidecar(app: &tauri::AppHandle, instance: &ProxyInstance) -> Option<ProxyStatus> {
+    if instance.proxy_process.lock().unwrap().is_some() {
+        return None;
+    }
+    let (pid, port) = running_sidecar_at(&instance.pid_path())?;
+    println!(
+        "[ProxyPal] {} is already running (PID {}, port {}), adopting it",
+        sidecar_label(instance),
+        pid,
+        port
+    );
+
+    let retention_days = app.state::<AppState>().config.lock().unwrap().usage_retention_days;
+    watch_requests(app, instance, retention_days);
+
+    let status = {
+        let mut status = instance.proxy_status.lock().unwrap();
+        status.running = true;
+        status.port = port;
+        status.endpoint = format!("http://localhost:{}/v1", port);
+        status.last_error = None;
+        status.clone()
+    };
+    emit_instance_status(app, instance, &status);
+    Some(status)
 }
 
-// Tauri commands
-#[tauri::command]
-pub fn get_proxy_status(state: State<AppState>) -> ProxyStatus {
-    state.proxy_status.lock().unwrap().clone()
+/// Notice that an adopted sidecar went away; the app gets no exit event for
+/// a process it didn't spawn
+pub(crate) fn refresh_adopted_status(instance: &ProxyInstance) {
+    if instance.proxy_process.lock().unwrap().is_some() {
+        return;
+    }
+    let mut status = instance.proxy_status.lock().unwrap();
+    if status.running && running_sidecar_at(&instance.pid_path()).is_none() {
+        status.running = false;
+        instance.log_watcher_running.store(false, Ordering::SeqCst);
+    }
 }
 
-#[tauri::command]
-pub async fn start_proxy(
+async fn launch_proxy(
     app: tauri::AppHandle,
-    state: State<'_, AppState>,
+    instance: Arc<ProxyInstance>,
 ) -> Result<ProxyStatus, String> {
-    let config = state.config.lock().unwrap().clone();
-    
+    let state = app.state::<AppState>();
+    let mut config = instance_config(&state, &instance)?;
+
     // Check if already running (according to our tracked state)
     {
-        let status = state.proxy_status.lock().unwrap();
+        let status = instance.proxy_status.lock().unwrap();
         if status.running {
             return Ok(status.clone());
         }
     }
+    if let Some(status) = adopt_sidecar(&app, &instance) {
+        return Ok(status);
+    }
 
-    // Kill any existing tracked proxy process first
-    {
-        let mut process = state.proxy_process.lock().unwrap();
-        if let Some(child) = process.take() {
-            println!("[ProxyPal] Killing tracked proxy process");
-            let _ = child.kill(); // Ignore errors, process might already be dead
-        }
+    // Refuse to launch on config errors rather than waiting out the readiness loop.
+    // Warnings go to the UI alongside. With no proxy up to list its models, Amp
+    // targets are checked against those the config declares.
+    let validation = validate_config(&config, Some(&configured_models(&config)));
+    for warning in validation.warnings() {
+        eprintln!("[ProxyPal] Config warning: {}: {}", warning.field, warning.message);
+    }
+    if !validation.diagnostics.is_empty() && instance.is_default() {
+        let _ = app.emit(events::CONFIG_VALIDATION, &validation);
     }
+    if !validation.valid {
+        return Err(format!(
+            "Invalid configuration: {}",
+            summarize_errors(&validation)
+        ));
+    }
+    // The key this instance writes into its proxy-config.yaml
+    let management_key = resolve_secret(&config.management_key)?;
+
+    // From here on, exits of the previous process are expected
+    let generation = instance.proxy_supervisor.lock().unwrap().begin_launch();
 
-    // Kill any external process using our port (handles orphaned processes from previous runs)
+    // Stop the sidecar we started earlier, and one a crashed ProxyPal left
+    // behind. Nothing we didn't spawn is touched.
+    let previous = instance.proxy_process.lock().unwrap().take().map(|child| child.pid());
+    let pid_path = instance.pid_path();
+    let _ = tauri::async_runtime::spawn_blocking(move || {
+        if let Some(pid) = previous {
+            stop_sidecar_at(&pid_path, pid);
+        }
+        cleanup_stale_sidecar_at(&pid_path);
+    })
+    .await;
+
+    // Someone else's process on our port is the user's call, not ours: move
+    // to a free port if allowed, otherwise say who has it
+    let port = config.port;
+    let occupant = port_owner(port)
+        .map(|(pid, name)| format!("PID {} ({})", pid, name))
+        .or_else(|| (!is_port_free(port)).then(|| "another process".to_string()));
+    if let Some(occupant) = occupant {
+        let reserved: Vec<u16> = if config.copilot.enabled {
+            vec![config.copilot.port]
+        } else {
+            Vec::new()
+        };
+        let free_port = config
+            .auto_select_port
+            .then(|| find_free_port(port, &reserved))
+            .flatten();
+        let Some(free_port) = free_port else {
+            return Err(format!(
+                "Port {} is in use by {}. Stop it, choose another port, or enable automatic port selection.",
+                port, occupant
+            ));
+        };
+        println!(
+            "[ProxyPal] Port {} is in use by {}, switching to port {}",
+            port, occupant, free_port
+        );
+        if instance.is_default() {
+            switch_proxy_port(&app, &state, free_port)?;
+            config = state.config.lock().unwrap().clone();
+        } else {
+            // Agents and tunnels only ever point at the default instance
+            let mut own = load_instance_config(&instance.dir)?;
+            own.port = free_port;
+            save_instance_config(&instance.dir, &own)?;
+            config.port = free_port;
+        }
+    }
     let port = config.port;
-    #[cfg(unix)]
-    {
-        // Kill by port
-        println!("[ProxyPal] Killing any process on port {}", port);
-        let _ = std::process::Command::new("sh")
-            .args(["-c", &format!("lsof -ti :{} | xargs kill -9 2>/dev/null", port)])
-            .output();
-        
-        // Also kill any orphaned cliproxyapi processes by name
-        println!("[ProxyPal] Killing any orphaned cliproxyapi processes");
-        let _ = std::process::Command::new("sh")
-            .args(["-c", "pkill -9 -f cliproxyapi 2>/dev/null"])
-            .output();
-    }
-    #[cfg(windows)]
-    {
-        // On Windows, use netstat and taskkill for port (hidden window)
-        let mut cmd = std::process::Command::new("cmd");
-        cmd.args(["/C", &format!("for /f \"tokens=5\" %a in ('netstat -aon ^| findstr :{} ^| findstr LISTENING') do taskkill /F /PID %a 2>nul", port)]);
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(CREATE_NO_WINDOW);
-        let _ = cmd.output();
-        
-        // Also kill by process name on Windows (hidden window)
-        let mut cmd2 = std::process::Command::new("cmd");
-        cmd2.args(["/C", "taskkill /F /IM cliproxyapi*.exe 2>nul"]);
-        #[cfg(target_os = "windows")]
-        cmd2.creation_flags(CREATE_NO_WINDOW);
-        let _ = cmd2.output();
-    }
-
-    // Longer delay to ensure port is fully released
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
     // Create config directory and config file for CLIProxyAPI
-    let config_dir = dirs::config_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("proxypal");
-    std::fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
-    
-    let proxy_config_path = config_dir.join("proxy-config.yaml");
-
-    // Build YAML config and append user customizations
-    let proxy_config = build_proxy_config_yaml(&config, &config_dir)?;
-    std::fs::write(&proxy_config_path, proxy_config).map_err(|e| e.to_string())?;
+    let proxy_config_path = write_instance_proxy_config(&app, &instance, &config)?;
+    let config_dir = proxy_config_path
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| std::path::PathBuf::from("."));
 
     // Spawn the sidecar process with WRITABLE_PATH set to app config dir
     // This prevents CLIProxyAPI from writing logs to src-tauri/logs/ which triggers hot reload
-    let sidecar = app
-        .shell()
-        .sidecar("cli-proxy-api")
-        .map_err(|e| format!("Failed to create sidecar command: {}", e))?
+    let command = match active_binary(&get_sidecars_dir()) {
+        Some(path) => {
+            println!("[ProxyPal] Launching pinned CLIProxyAPI {}", path.display());
+            app.shell().command(path)
+        }
+        None => app
+            .shell()
+            .sidecar("cli-proxy-api")
+            .map_err(|e| format!("Failed to create sidecar command: {}", e))?,
+    };
+    let sidecar = command
         .env("WRITABLE_PATH", config_dir.to_str().unwrap())
         .args(["--config", proxy_config_path.to_str().unwrap()]);
 
+    // Output from here on belongs to this launch
+    let output_start = {
+        let mut output = instance.sidecar_output.lock().unwrap();
+        let file = config
+            .sidecar_output_to_file
+            .then(|| instance.output_log_path());
+        if let Err(e) = output.set_file(file.as_deref()) {
+            eprintln!("[ProxyPal] {}", e);
+        }
+        output.last_seq()
+    };
+
     let (mut rx, child) = sidecar.spawn().map_err(|e| format!("Failed to spawn sidecar: {}", e))?;
+    let child_pid = child.pid();
+    record_sidecar_at(&instance.pid_path(), child_pid, port);
 
     // Store the child process
     {
-        let mut process = state.proxy_process.lock().unwrap();
+        let mut process = instance.proxy_process.lock().unwrap();
         *process = Some(child);
     }
 
     // Listen for stdout/stderr in a separate task (for logging only)
     let app_handle = app.clone();
+    let watched = instance.clone();
     tauri::async_runtime::spawn(async move {
         use tauri_plugin_shell::process::CommandEvent;
-        
+        let label = sidecar_label(&watched);
+
         while let Some(event) = rx.recv().await {
             match event {
                 CommandEvent::Stdout(line) => {
                     let text = String::from_utf8_lossy(&line);
-                    println!("[CLIProxyAPI] {}", text);
+                    println!("[{}] {}", label, text);
+                    capture_output(&app_handle, &watched, SidecarStream::Stdout, &text);
                 }
                 CommandEvent::Stderr(line) => {
                     let text = String::from_utf8_lossy(&line);
-                    eprintln!("[CLIProxyAPI ERROR] {}", text);
+                    eprintln!("[{} ERROR] {}", label, text);
+                    watched.proxy_supervisor.lock().unwrap().push_stderr(&text);
+                    capture_output(&app_handle, &watched, SidecarStream::Stderr, &text);
                 }
                 CommandEvent::Terminated(payload) => {
-                    println!("[CLIProxyAPI] Process terminated: {:?}", payload);
-                    // Update status when process dies unexpectedly
-                    if let Some(state) = app_handle.try_state::<AppState>() {
-                        let mut status = state.proxy_status.lock().unwrap();
-                        status.running = false;
-                        let _ = app_handle.emit("proxy-status-changed", status.clone());
+                    println!("[{}] Process terminated: {:?}", label, payload);
+                    // Exits we caused by stopping or relaunching are not crashes
+                    let token = watched.proxy_supervisor.lock().unwrap().mark_exited(generation);
+                    if let Some(token) = token {
+                        let status = {
+                            let mut status = watched.proxy_status.lock().unwrap();
+                            status.running = false;
+                            status.clone()
+                        };
+                        emit_instance_status(&app_handle, &watched, &status);
+                        // Neither is one `proxypal stop` caused: it forgets the PID first
+                        if !is_recorded_at(&watched.pid_path(), child_pid) {
+                            println!("[{}] Stopped outside the app, not restarting", label);
+                            watched.proxy_process.lock().unwrap().take();
+                            watched.log_watcher_running.store(false, Ordering::SeqCst);
+                            break;
+                        }
+                        tauri::async_runtime::spawn(supervise_exit(
+                            app_handle.clone(),
+                            watched.clone(),
+                            token,
+                            payload.code,
+                        ));
                     }
                     break;
                 }
@@ -630,76 +818,26 @@ pub async fn start_proxy(
     // Wait for the proxy to be ready before syncing settings
     let port = config.port;
     let client = crate::build_management_client();
-    let health_url = format!("http://127.0.0.1:{}/v0/management/config.yaml", port);
-    let mut ready = false;
-    for attempt in 0..25 {
-        // 25 attempts × 200ms = 5s max
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        match client
-            .get(&health_url)
-            .header("X-Management-Key", &get_management_key())
-            .send()
-            .await
-        {
-            Ok(resp) if resp.status().is_success() => {
-                ready = true;
-                break;
-            }
-            _ => {
-                if attempt == 24 {
-                    eprintln!("[ProxyPal Debug] Proxy not ready after 5s, proceeding anyway");
-                }
-            }
-        }
-    }
-
-    // Sync settings via Management API (best-effort, don't fail proxy start)
-    if ready {
-        let _ = client
-            .put(&format!(
-                "http://127.0.0.1:{}/v0/management/usage-statistics-enabled",
-                port
-            ))
-            .header("X-Management-Key", &get_management_key())
-            .json(&serde_json::json!({"value": config.usage_stats_enabled}))
-            .send()
-            .await;
+    let Some(version_header) = wait_until_ready(&client, port, &management_key).await else {
+        return Err(abandon_launch(&instance, generation, output_start).await);
+    };
 
-        let _ = client
-            .put(&format!(
-                "http://127.0.0.1:{}/v0/management/ampcode/force-model-mappings",
-                port
-            ))
-            .header("X-Management-Key", &get_management_key())
-            .json(&serde_json::json!({"value": config.force_model_mappings}))
-            .send()
-            .await;
+    detect_capabilities(&app, &instance, version_header, output_start);
 
-        let _ = client
-            .put(&format!(
-                "http://127.0.0.1:{}/v0/management/max-retry-interval",
-                port
-            ))
-            .header("X-Management-Key", &get_management_key())
-            .json(&serde_json::json!({"value": config.max_retry_interval}))
-            .send()
-            .await;
+    // Sync settings via Management API (best-effort, don't fail proxy start)
+    let synced = [
+        ("usage-statistics-enabled", serde_json::json!(config.usage_stats_enabled)),
+        ("ampcode/force-model-mappings", serde_json::json!(config.force_model_mappings)),
+        ("max-retry-interval", serde_json::json!(config.max_retry_interval)),
+    ];
+    for (endpoint, value) in synced {
+        let _ = push_management_field(&client, port, &management_key, endpoint, Some(value)).await;
     }
-    
+
     // Start log file watcher for request tracking
     // This replaces the old polling approach and captures ALL requests including Amp proxy forwarding
-    let log_path = config_dir.join("logs").join("main.log");
-    let log_watcher_running = state.log_watcher_running.clone();
-    let request_counter = state.request_counter.clone();
-    
-    // Signal any existing watcher to stop, then start new one
-    log_watcher_running.store(false, Ordering::SeqCst);
-    std::thread::sleep(std::time::Duration::from_millis(100)); // Give old watcher time to stop
-    log_watcher_running.store(true, Ordering::SeqCst);
-    
-    let app_handle2 = app.clone();
-    start_log_watcher(app_handle2, log_path, log_watcher_running, request_counter);
-    
+    watch_requests(&app, &instance, config.usage_retention_days);
+
     // Sync usage statistics from proxy to local history on startup (in background)
     // This ensures analytics page shows data without requiring restart or manual refresh
     let port = config.port;
@@ -709,15 +847,25 @@ pub async fn start_proxy(
         let usage_url = format!("http://127.0.0.1:{}/v0/management/usage", port);
         let _ = client
             .get(&usage_url)
-            .header("X-Management-Key", &get_management_key())
+            .header("X-Management-Key", &management_key)
             .timeout(std::time::Duration::from_secs(5))
             .send()
             .await;
     });
 
+    // Don't report a process that already died during startup as running
+    if !instance.proxy_supervisor.lock().unwrap().is_current(generation) {
+        let tail = instance
+            .sidecar_output
+            .lock()
+            .unwrap()
+            .tail_text(output_start, START_FAILURE_LINES);
+        return Err(with_output(format!("{} exited during startup", sidecar_label(&instance)), &tail));
+    }
+
     // Update status
     let new_status = {
-        let mut status = state.proxy_status.lock().unwrap();
+        let mut status = instance.proxy_status.lock().unwrap();
         status.running = true;
         status.port = config.port;
         status.endpoint = format!("http://localhost:{}/v1", config.port);
@@ -725,124 +873,87 @@ pub async fn start_proxy(
     };
 
     // Emit status update
-    let _ = app.emit("proxy-status-changed", new_status.clone());
+    emit_instance_status(&app, &instance, &new_status);
 
     Ok(new_status)
 }
 
+/// Stop a sidecar that never answered, or say how it died if it exited, and
+/// describe the failure with the output of this launch
+async fn abandon_launch(instance: &ProxyInstance, generation: u64, output_start: u64) -> String {
+    let label = sidecar_label(instance);
+    let exited = !instance.proxy_supervisor.lock().unwrap().is_current(generation);
+    let message = if exited {
+        format!("{} exited during startup", label)
+    } else {
+        // Not a crash, but a pending automatic restart may try again
+        instance.proxy_supervisor.lock().unwrap().abandon(generation);
+        let child = instance.proxy_process.lock().unwrap().take();
+        if let Some(child) = child {
+            let (pid_path, pid) = (instance.pid_path(), child.pid());
+            let _ = tauri::async_runtime::spawn_blocking(move || stop_sidecar_at(&pid_path, pid)).await;
+        }
+        format!("{} did not answer within 5s and was stopped", label)
+    };
+    let tail = instance
+        .sidecar_output
+        .lock()
+        .unwrap()
+        .tail_text(output_start, START_FAILURE_LINES);
+    with_output(message, &tail)
+}
+
 #[tauri::command]
 pub async fn stop_proxy(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<ProxyStatus, String> {
+    stop_instance(app, state.default_instance()).await
+}
+
+/// Stop one proxy instance
+pub(crate) async fn stop_instance(
+    app: tauri::AppHandle,
+    instance: Arc<ProxyInstance>,
+) -> Result<ProxyStatus, String> {
+    // Cancel any pending automatic restart, even if the proxy is already down
+    instance.proxy_supervisor.lock().unwrap().stop();
+
+    // Our child, or else a sidecar recorded by `proxypal start` or `run`
+    let pid = instance
+        .proxy_process
+        .lock()
+        .unwrap()
+        .take()
+        .map(|child| child.pid())
+        .or_else(|| running_sidecar_at(&instance.pid_path()).map(|(pid, _)| pid));
+
     // Check if running
     {
-        let status = state.proxy_status.lock().unwrap();
-        if !status.running {
+        let status = instance.proxy_status.lock().unwrap();
+        if !status.running && pid.is_none() {
             return Ok(status.clone());
         }
     }
 
     // Stop the log watcher
-    state.log_watcher_running.store(false, Ordering::SeqCst);
-
-    // Kill the tracked child process
-    {
-        let mut process = state.proxy_process.lock().unwrap();
-        if let Some(child) = process.take() {
-            println!("[ProxyPal] Killing tracked proxy process");
-            let _ = child.kill();
-        }
-    }
+    instance.log_watcher_running.store(false, Ordering::SeqCst);
 
-    // Also kill any orphaned cliproxyapi processes by name (belt and suspenders)
-    #[cfg(unix)]
-    {
-        println!("[ProxyPal] Cleaning up any orphaned cliproxyapi processes");
-        let _ = std::process::Command::new("sh")
-            .args(["-c", "pkill -9 -f cliproxyapi 2>/dev/null"])
-            .output();
-    }
-    #[cfg(windows)]
-    {
-        let mut cmd = std::process::Command::new("cmd");
-        cmd.args(["/C", "taskkill /F /IM cliproxyapi*.exe 2>nul"]);
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(CREATE_NO_WINDOW);
-        let _ = cmd.output();
+    // Stop the process, giving it a chance to exit cleanly
+    if let Some(pid) = pid {
+        let pid_path = instance.pid_path();
+        let _ = tauri::async_runtime::spawn_blocking(move || stop_sidecar_at(&pid_path, pid)).await;
     }
 
     // Update status
     let new_status = {
-        let mut status = state.proxy_status.lock().unwrap();
+        let mut status = instance.proxy_status.lock().unwrap();
         status.running = false;
         status.clone()
     };
 
     // Emit status update
-    let _ = app.emit("proxy-status-changed", new_status.clone());
+    emit_instance_status(&app, &instance, &new_status);
 
     Ok(new_status)
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn env_var_lock() -> &'static std::sync::Mutex<()> {
-        static LOCK: std::sync::OnceLock<std::sync::Mutex<()>> = std::sync::OnceLock::new();
-        LOCK.get_or_init(|| std::sync::Mutex::new(()))
-    }
-
-    #[test]
-    fn env_proxy_for_url_returns_none_for_invalid_target() {
-        assert!(env_proxy_for_url("not-a-url").is_none());
-    }
-
-    #[test]
-    fn env_proxy_for_url_returns_none_when_env_is_missing() {
-        let _guard = env_var_lock().lock().unwrap();
-        let old_http_upper = std::env::var_os("HTTP_PROXY");
-        let old_https_upper = std::env::var_os("HTTPS_PROXY");
-        let old_http_lower = std::env::var_os("http_proxy");
-        let old_https_lower = std::env::var_os("https_proxy");
-
-        std::env::remove_var("HTTP_PROXY");
-        std::env::remove_var("HTTPS_PROXY");
-        std::env::remove_var("http_proxy");
-        std::env::remove_var("https_proxy");
-
-        let detected = env_proxy_for_url(DEFAULT_PROXY_CHECK_URL);
-
-        if let Some(value) = old_http_upper {
-            std::env::set_var("HTTP_PROXY", value);
-        }
-        if let Some(value) = old_https_upper {
-            std::env::set_var("HTTPS_PROXY", value);
-        }
-        if let Some(value) = old_http_lower {
-            std::env::set_var("http_proxy", value);
-        }
-        if let Some(value) = old_https_lower {
-            std::env::set_var("https_proxy", value);
-        }
-
-        assert!(detected.is_none());
-    }
-
-    #[test]
-    fn normalize_system_proxy_uses_http_for_regular_hosts() {
-        assert_eq!(
-            normalize_system_proxy("127.0.0.1", 8080),
-            "http://127.0.0.1:8080"
-        );
-    }
-
-    #[test]
-    fn normalize_system_proxy_uses_socks5_for_socks_hosts() {
-        assert_eq!(
-            normalize_system_proxy("socks-proxy.local", 1080),
-            "socks5://socks-proxy.local:1080"
-        );
-    }
-}
@@ -1,6 +1,7 @@
 //! Quota management - fetch quota/usage for all providers.
 
 use tauri::{Emitter, State};
+use crate::events;
 use crate::state::AppState;
 use crate::types::{AuthStatus, ProviderTestResult};
 
@@ -1103,7 +1104,7 @@ pub async fn import_vertex_credential(
     crate::save_auth_to_file(&auth)?;
     
     // Emit auth status update
-    let _ = app.emit("auth-status-changed", auth.clone());
+    let _ = app.emit(events::AUTH_STATUS_CHANGED, auth.clone());
     
     Ok(auth.clone())
 }
@@ -0,0 +1,50 @@
+//! Secret vault commands: status, enable, unlock and lock.
+
+use tauri::State;
+
+use crate::config::load_config;
+use crate::helpers::secrets;
+use crate::state::AppState;
+use crate::types::{VaultMode, VaultStatus};
+
+#[tauri::command]
+pub fn get_vault_status() -> VaultStatus {
+    secrets::vault_status()
+}
+
+/// Create the vault, move every plaintext key in config.json, the profiles,
+/// instance configs and snapshots into it, and delete config backups
+#[tauri::command]
+pub fn enable_secret_vault(
+    state: State<AppState>,
+    mode: VaultMode,
+    passphrase: Option<String>,
+) -> Result<VaultStatus, String> {
+    secrets::enable_vault(mode, passphrase.as_deref())?;
+    secrets::migrate_plaintext_secrets()?;
+    secrets::remove_plaintext_backups();
+    *state.config.lock().unwrap() = load_config();
+    Ok(secrets::vault_status())
+}
+
+/// Unlock with the passphrase (ignored in key-file mode), then seal any
+/// plaintext keys that were added while it was locked
+#[tauri::command]
+pub fn unlock_secret_vault(
+    state: State<AppState>,
+    passphrase: Option<String>,
+) -> Result<VaultStatus, String> {
+    secrets::unlock_vault(passphrase.as_deref().unwrap_or_default())?;
+    if secrets::migrate_plaintext_secrets()? > 0 {
+        *state.config.lock().unwrap() = load_config();
+    }
+    Ok(secrets::vault_status())
+}
+
+/// Forget the key until the next unlock. The running proxy keeps the config it
+/// already loaded.
+#[tauri::command]
+pub fn lock_secret_vault() -> VaultStatus {
+    secrets::lock_vault();
+    secrets::vault_status()
+}
@@ -4,7 +4,7 @@ use crate::config::save_config_to_file;
 use crate::state::AppState;
 use crate::types::{ReasoningEffortSettings, ThinkingBudgetSettings};
 use crate::{build_management_client, get_management_key, get_management_url};
-use tauri::State;
+use tauri::{AppHandle, State};
 
 // ============================================
 // Claude Code Settings (from ~/.claude/settings.json)
@@ -74,6 +74,7 @@ pub async fn get_thinking_budget_settings(
 
 #[tauri::command]
 pub async fn set_thinking_budget_settings(
+    app: AppHandle,
     state: State<'_, AppState>,
     settings: ThinkingBudgetSettings,
 ) -> Result<(), String> {
@@ -86,7 +87,7 @@ pub async fn set_thinking_budget_settings(
         let config = state.config.lock().unwrap();
         config.clone()
     };
-    crate::commands::config::save_config(state, config_to_save)?;
+    crate::commands::config::save_config(app, state, config_to_save)?;
 
     // Config is saved - proxy will pick up new thinking budget on next request
 
@@ -112,6 +113,7 @@ pub async fn get_reasoning_effort_settings(
 
 #[tauri::command]
 pub async fn set_reasoning_effort_settings(
+    app: AppHandle,
     state: State<'_, AppState>,
     settings: ReasoningEffortSettings,
 ) -> Result<(), String> {
@@ -132,7 +134,7 @@ pub async fn set_reasoning_effort_settings(
         let config = state.config.lock().unwrap();
         config.clone()
     };
-    crate::commands::config::save_config(state, config_to_save)?;
+    crate::commands::config::save_config(app, state, config_to_save)?;
 
     Ok(())
 }
@@ -149,6 +151,7 @@ pub async fn get_close_to_tray(state: State<'_, AppState>) -> Result<bool, Strin
 
 #[tauri::command]
 pub async fn set_close_to_tray(
+    app: AppHandle,
     state: State<'_, AppState>,
     enabled: bool,
 ) -> Result<(), String> {
@@ -160,7 +163,7 @@ pub async fn set_close_to_tray(
         let config = state.config.lock().unwrap();
         config.clone()
     };
-    crate::commands::config::save_config(state, config_to_save)?;
+    crate::commands::config::save_config(app, state, config_to_save)?;
     Ok(())
 }
 
@@ -177,7 +180,7 @@ pub async fn get_max_retry_interval(state: State<'_, AppState>) -> Result<i32, S
     let client = build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to get max retry interval: {}", e))?;
@@ -202,7 +205,7 @@ pub async fn set_max_retry_interval(
     let client = build_management_client();
     let response = client
         .put(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .json(&serde_json::json!({ "value": value }))
         .send()
         .await
@@ -234,7 +237,7 @@ pub async fn get_log_size(state: State<'_, AppState>) -> Result<u32, String> {
     let client = build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to get log size: {}", e))?;
@@ -256,7 +259,7 @@ pub async fn set_log_size(state: State<'_, AppState>, size: u32) -> Result<(), S
     let client = build_management_client();
     let response = client
         .put(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .json(&serde_json::json!({ "value": size }))
         .send()
         .await
@@ -280,7 +283,7 @@ pub async fn get_websocket_auth(state: State<'_, AppState>) -> Result<bool, Stri
     let client = build_management_client();
     let response = client
         .get(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .send()
         .await
         .map_err(|e| format!("Failed to get WebSocket auth: {}", e))?;
@@ -305,7 +308,7 @@ pub async fn set_websocket_auth(
     let client = build_management_client();
     let response = client
         .put(&url)
-        .header("X-Management-Key", &get_management_key())
+        .header("X-Management-Key", &get_management_key()?)
         .json(&serde_json::json!({ "value": value }))
         .send()
         .await
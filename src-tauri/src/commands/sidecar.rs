@@ -0,0 +1,211 @@
+//! Runtime CLIProxyAPI versions: list, install from a release archive, and
+//! switch the one `start_proxy` launches.
+
+use tauri::State;
+
+use crate::{build_management_client, get_management_key};
+use crate::commands::proxy::{start_proxy, stop_proxy, wait_until_ready};
+use crate::config::get_sidecars_dir;
+use crate::proxy::binaries::{
+    active_binary, active_version, install_archive, installed_versions, parse_manifest,
+    probe_binary, set_active_version, ArchiveSource, MANIFEST_NAME,
+};
+use crate::state::AppState;
+use crate::types::SidecarBinary;
+
+fn is_url(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+/// Last path segment of a URL or file path
+fn file_name_of(location: &str) -> String {
+    let path = location.split(['?', '#']).next().unwrap_or(location);
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+}
+
+/// `checksums.txt` next to the archive, for URLs and existing local files.
+/// Coming from the same origin as the archive, it only detects corruption;
+/// anyone able to replace the archive can replace its checksum too.
+fn default_manifest(source: &str) -> Option<String> {
+    if is_url(source) {
+        let path = source.split(['?', '#']).next().unwrap_or(source);
+        let (base, _) = path.rsplit_once('/')?;
+        return Some(format!("{}/{}", base, MANIFEST_NAME));
+    }
+    let manifest = std::path::Path::new(source).parent()?.join(MANIFEST_NAME);
+    manifest
+        .is_file()
+        .then(|| manifest.to_string_lossy().to_string())
+}
+
+async fn fetch(location: &str) -> Result<Vec<u8>, String> {
+    if !is_url(location) {
+        return std::fs::read(location).map_err(|e| format!("Failed to read {}: {}", location, e));
+    }
+    let response = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .build()
+        .map_err(|e| e.to_string())?
+        .get(location)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", location, e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to download {}: {}",
+            location,
+            response.status()
+        ));
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", location, e))?;
+    Ok(bytes.to_vec())
+}
+
+fn describe(version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("CLIProxyAPI {}", version),
+        None => "the bundled CLIProxyAPI".to_string(),
+    }
+}
+
+/// Restart the proxy and make sure the new process answers
+async fn restart_and_probe(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+) -> Result<(), String> {
+    stop_proxy(app.clone(), state.clone()).await?;
+    start_proxy(app.clone(), state.clone()).await?;
+    let port = state.config.lock().unwrap().port;
+    let management_key = get_management_key()?;
+    if wait_until_ready(&build_management_client(), port, &management_key)
+        .await
+        .is_none()
+    {
+        return Err("it did not answer within 5s".to_string());
+    }
+    Ok(())
+}
+
+/// Check the pinned binary without a running proxy to restart
+async fn probe_active_binary() -> Result<(), String> {
+    // The bundled sidecar shipped with the app
+    let Some(binary) = active_binary(&get_sidecars_dir()) else {
+        return Ok(());
+    };
+    tauri::async_runtime::spawn_blocking(move || probe_binary(&binary))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Pin `version` (None for the bundled sidecar). A running proxy is
+/// restarted on it, a stopped one only has the binary probed; if either
+/// fails the previous pin is restored.
+async fn switch_sidecar_version(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    version: Option<String>,
+) -> Result<(), String> {
+    let dir = get_sidecars_dir();
+    let previous = active_version(&dir);
+    if previous == version {
+        return Ok(());
+    }
+    set_active_version(&dir, version.as_deref())?;
+    println!("[ProxyPal] Switched to {}", describe(version.as_deref()));
+
+    let running = state.default_instance().proxy_status.lock().unwrap().running;
+    let result = if running {
+        restart_and_probe(app, state).await
+    } else {
+        probe_active_binary().await
+    };
+    let Err(e) = result else {
+        return Ok(());
+    };
+
+    let message = format!(
+        "{} failed to start ({}), rolled back to {}",
+        describe(version.as_deref()),
+        e,
+        describe(previous.as_deref())
+    );
+    eprintln!("[ProxyPal] {}", message);
+    set_active_version(&dir, previous.as_deref())?;
+    if running {
+        if let Err(e) = restart_and_probe(app, state).await {
+            eprintln!("[ProxyPal] Restart after rollback failed: {}", e);
+        }
+    }
+    Err(message)
+}
+
+#[tauri::command]
+pub fn list_sidecar_versions() -> Vec<SidecarBinary> {
+    installed_versions(&get_sidecars_dir())
+}
+
+/// Install a release archive from a URL or local path. The checksum comes from
+/// `sha256` or the manifest (by default `checksums.txt` beside the archive);
+/// nothing is installed without one. `version` defaults to the one in the
+/// archive name.
+#[tauri::command]
+pub async fn install_sidecar_version(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    source: String,
+    manifest: Option<String>,
+    sha256: Option<String>,
+    version: Option<String>,
+    activate: bool,
+) -> Result<SidecarBinary, String> {
+    let asset_name = file_name_of(&source);
+    let expected = match sha256 {
+        Some(sha256) => sha256,
+        None => {
+            let manifest = manifest
+                .or_else(|| default_manifest(&source))
+                .ok_or_else(|| {
+                    format!(
+                        "No checksum for {}: provide a {} manifest or the SHA-256",
+                        asset_name, MANIFEST_NAME
+                    )
+                })?;
+            let text = String::from_utf8_lossy(&fetch(&manifest).await?).to_string();
+            parse_manifest(&text)
+                .remove(&asset_name)
+                .ok_or_else(|| format!("{} is not listed in {}", asset_name, manifest))?
+        }
+    };
+    let bytes = fetch(&source).await?;
+
+    let mut binary = tauri::async_runtime::spawn_blocking(move || {
+        let archive = ArchiveSource {
+            bytes: &bytes,
+            asset_name: &asset_name,
+            source: &source,
+        };
+        install_archive(&get_sidecars_dir(), archive, &expected, version.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if activate {
+        switch_sidecar_version(&app, &state, Some(binary.version.clone())).await?;
+        binary.active = true;
+    }
+    Ok(binary)
+}
+
+/// Launch `version` from now on, or the bundled sidecar with None
+#[tauri::command]
+pub async fn set_active_sidecar_version(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    version: Option<String>,
+) -> Result<Vec<SidecarBinary>, String> {
+    switch_sidecar_version(&app, &state, version).await?;
+    Ok(installed_versions(&get_sidecars_dir()))
+}
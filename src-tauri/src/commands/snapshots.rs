@@ -0,0 +1,49 @@
+//! Config snapshot commands: list, diff and restore.
+
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::control_api::apply_control_api_change;
+use crate::commands::proxy::{propagate_port_change, reconcile_proxy_config};
+use crate::config::{get_config_path, load_config_from_path, AppConfig};
+use crate::events;
+use crate::helpers::snapshots::{diff_snapshot, list_snapshots, restore_snapshot};
+use crate::state::AppState;
+use crate::types::{ConfigSnapshot, SnapshotFileDiff};
+
+#[tauri::command]
+pub fn list_config_snapshots() -> Vec<ConfigSnapshot> {
+    list_snapshots()
+}
+
+/// Compare a snapshot with the config files currently on disk
+#[tauri::command]
+pub fn diff_config_snapshot(id: String) -> Result<Vec<SnapshotFileDiff>, String> {
+    diff_snapshot(&id)
+}
+
+/// Roll the config files back to a snapshot and reload them. The state being
+/// replaced is snapshotted first, so the restore shows up in the history too.
+#[tauri::command]
+pub async fn restore_config_snapshot(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<AppConfig, String> {
+    let current = state.config.lock().unwrap().clone();
+    restore_snapshot(&id, &current.snapshot_retention)?;
+
+    let restored = load_config_from_path(&get_config_path()).map_err(|e| e.message)?;
+    *state.config.lock().unwrap() = restored.clone();
+
+    // Applied like apply_config: settings the Management API covers or read
+    // at startup don't follow the restored proxy-config.yaml by themselves
+    apply_control_api_change(&app, &current.control_api, &restored.control_api);
+    reconcile_proxy_config(&app, &state, &current, &restored).await?;
+    if restored.port != current.port {
+        propagate_port_change(&app, &restored, current.port, &[]);
+    }
+
+    crate::refresh_tray_menu(&app);
+    let _ = app.emit(events::CONFIG_RESTORED, &id);
+    Ok(restored)
+}
@@ -0,0 +1,499 @@
+//! ProxyPal's control API: an optional HTTP/JSON server on 127.0.0.1 so
+//! scripts and editor extensions can drive the app without CLIProxyAPI's
+//! management key.
+//!
+//! Every request needs `Authorization: Bearer <token>`; `/v1/events` also
+//! takes `?token=` for `EventSource`, which can't set headers. Routes mirror
+//! the Tauri commands:
+//!
+//! | Route                              | Command                |
+//! |------------------------------------|------------------------|
+//! | `GET  /v1/status`                  | `get_proxy_status`     |
+//! | `POST /v1/proxy/start`             | `start_proxy`          |
+//! | `POST /v1/proxy/stop`              | `stop_proxy`           |
+//! | `GET  /v1/usage`                   | `get_usage_stats`      |
+//! | `GET  /v1/requests`                | `get_request_history`  |
+//! | `GET  /v1/models`                  | `get_available_models` |
+//! | `POST /v1/agents/<id>/configure`   | `configure_cli_agent`  |
+//! | `GET  /v1/events`                  | server-sent events     |
+//!
+//! `/v1/events` streams the same events the frontend receives, named as in
+//! `events::FORWARDED`, with the JSON payload as `data`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tauri::{AppHandle, Listener, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, watch};
+
+use crate::commands;
+use crate::events;
+use crate::state::AppState;
+use crate::types::{AvailableModel, ControlApiConfig};
+
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+struct ForwardedEvent {
+    name: String,
+    payload: String,
+}
+
+struct RunningServer {
+    port: u16,
+    stop: watch::Sender<bool>,
+}
+
+pub struct ControlApi {
+    events: broadcast::Sender<ForwardedEvent>,
+    server: Mutex<Option<RunningServer>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl ControlApi {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self {
+            events,
+            server: Mutex::new(None),
+            last_error: Mutex::new(None),
+        }
+    }
+
+    /// Copy app events into the SSE channel. Call once at setup.
+    pub fn forward_events(&self, app: &AppHandle) {
+        for name in events::FORWARDED {
+            let events = self.events.clone();
+            app.listen_any(*name, move |event| {
+                // Nobody listening is the normal case
+                let _ = events.send(ForwardedEvent {
+                    name: name.to_string(),
+                    payload: event.payload().to_string(),
+                });
+            });
+        }
+    }
+
+    /// Port the server is listening on, if it is
+    pub fn running_port(&self) -> Option<u16> {
+        self.server.lock().unwrap().as_ref().map(|s| s.port)
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    /// Start, restart or stop the server to match `config`
+    pub fn apply(&self, app: &AppHandle, config: &ControlApiConfig) -> Result<(), String> {
+        self.stop();
+        let result = if config.enabled {
+            self.start(app, config)
+        } else {
+            Ok(())
+        };
+        *self.last_error.lock().unwrap() = result.as_ref().err().cloned();
+        result
+    }
+
+    fn start(&self, app: &AppHandle, config: &ControlApiConfig) -> Result<(), String> {
+        let token = crate::helpers::secrets::resolve_secret(&config.token)?;
+        if token.is_empty() {
+            return Err("The control API has no token".to_string());
+        }
+        // Bind here so a taken port is reported to the caller
+        let listener = std::net::TcpListener::bind(("127.0.0.1", config.port))
+            .map_err(|e| format!("Control API can't listen on port {}: {}", config.port, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        let (stop, stop_rx) = watch::channel(false);
+        let context = Arc::new(ServerContext {
+            app: app.clone(),
+            token,
+            port: config.port,
+            events: self.events.clone(),
+            stop: stop_rx,
+        });
+        tauri::async_runtime::spawn(async move {
+            let listener = match tokio::net::TcpListener::from_std(listener) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    eprintln!("[ProxyPal] Control API failed to start: {}", e);
+                    return;
+                }
+            };
+            let mut stop = context.stop.clone();
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            let context = context.clone();
+                            tauri::async_runtime::spawn(async move {
+                                handle_connection(stream, context).await;
+                            });
+                        }
+                        Err(e) => eprintln!("[ProxyPal] Control API accept failed: {}", e),
+                    },
+                    _ = stop.changed() => break,
+                }
+            }
+        });
+
+        println!(
+            "[ProxyPal] Control API listening on http://127.0.0.1:{}",
+            config.port
+        );
+        *self.server.lock().unwrap() = Some(RunningServer {
+            port: config.port,
+            stop,
+        });
+        Ok(())
+    }
+
+    /// Stop listening and close open event streams
+    pub fn stop(&self) {
+        if let Some(server) = self.server.lock().unwrap().take() {
+            let _ = server.stop.send(true);
+            println!("[ProxyPal] Control API stopped");
+        }
+    }
+}
+
+struct ServerContext {
+    app: AppHandle,
+    token: String,
+    port: u16,
+    events: broadcast::Sender<ForwardedEvent>,
+    stop: watch::Receiver<bool>,
+}
+
+/// Request line and headers of one HTTP request
+#[derive(Debug, PartialEq)]
+struct RequestHead {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>, // Names lowercased
+}
+
+impl RequestHead {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    fn content_length(&self) -> usize {
+        self.header("content-length")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+fn parse_head(text: &str) -> Result<RequestHead, String> {
+    let mut lines = text.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err("Malformed request line".to_string());
+    };
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+
+    let mut headers = HashMap::new();
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("Malformed header: {}", line))?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    Ok(RequestHead {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        headers,
+    })
+}
+
+/// Compare without leaking how much of the token matched
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn is_authorized(head: &RequestHead, token: &str) -> bool {
+    let bearer = head
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // A token in the URL ends up in logs and history, so only for EventSource
+    let query = (head.path == "/v1/events")
+        .then(|| head.query.get("token").map(String::as_str))
+        .flatten();
+    let given = bearer.or(query);
+    given.is_some_and(|given| token_matches(given.trim(), token))
+}
+
+/// Only loopback host names, so a web page can't reach us by DNS rebinding
+fn is_allowed_host(head: &RequestHead, port: u16) -> bool {
+    head.header("host").is_some_and(|host| {
+        ["127.0.0.1", "localhost", "[::1]"]
+            .iter()
+            .any(|name| host == *name || host == format!("{}:{}", name, port))
+    })
+}
+
+/// One server-sent event; multi-line payloads become several `data:` lines
+fn sse_frame(name: &str, payload: &str) -> String {
+    let mut frame = format!("event: {}\n", name);
+    for line in payload.lines() {
+        frame.push_str("data: ");
+        frame.push_str(line);
+        frame.push('\n');
+    }
+    frame.push('\n');
+    frame
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+async fn write_json(stream: &mut TcpStream, status: u16, body: &Value) {
+    let body = body.to_string();
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        status_text(status),
+        body.len()
+    );
+    if status == 401 {
+        response.push_str("WWW-Authenticate: Bearer\r\n");
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn write_error(stream: &mut TcpStream, status: u16, message: &str) {
+    write_json(stream, status, &json!({ "error": message })).await;
+}
+
+/// Read the head and body of one request
+async fn read_request(stream: &mut TcpStream) -> Result<(RequestHead, Vec<u8>), (u16, String)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err((413, "Request headers too large".to_string()));
+        }
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| (400, e.to_string()))?;
+        if read == 0 {
+            return Err((400, "Connection closed mid-request".to_string()));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = parse_head(&String::from_utf8_lossy(&buffer[..head_end])).map_err(|e| (400, e))?;
+    let length = head.content_length();
+    if length > MAX_BODY_BYTES {
+        return Err((413, "Request body too large".to_string()));
+    }
+    let mut body = buffer.split_off(head_end + 4);
+    while body.len() < length {
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| (400, e.to_string()))?;
+        if read == 0 {
+            return Err((400, "Connection closed mid-request".to_string()));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(length);
+    Ok((head, body))
+}
+
+async fn handle_connection(mut stream: TcpStream, context: Arc<ServerContext>) {
+    // A client that stops sending mid-request would otherwise hold the
+    // connection open forever
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream))
+        .await
+        .unwrap_or_else(|_| Err((408, "Timed out reading the request".to_string())));
+    let (head, body) = match request {
+        Ok(request) => request,
+        Err((status, message)) => return write_error(&mut stream, status, &message).await,
+    };
+    if !is_allowed_host(&head, context.port) {
+        return write_error(&mut stream, 403, "Host not allowed").await;
+    }
+    if !is_authorized(&head, &context.token) {
+        return write_error(&mut stream, 401, "Missing or wrong control API token").await;
+    }
+
+    if head.method == "GET" && head.path == "/v1/events" {
+        return stream_events(stream, &context).await;
+    }
+    match route(&context.app, &head, &body).await {
+        Ok(value) => write_json(&mut stream, 200, &value).await,
+        Err((status, message)) => write_error(&mut stream, status, &message).await,
+    }
+}
+
+fn to_value<T: serde::Serialize>(result: Result<T, String>) -> Result<Value, (u16, String)> {
+    result
+        .and_then(|value| serde_json::to_value(value).map_err(|e| e.to_string()))
+        .map_err(|e| (500, e))
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ConfigureAgentBody {
+    models: Option<Vec<AvailableModel>>,
+}
+
+async fn route(app: &AppHandle, head: &RequestHead, body: &[u8]) -> Result<Value, (u16, String)> {
+    let state = app.state::<AppState>();
+    let segments: Vec<&str> = head.path.trim_matches('/').split('/').collect();
+    match (head.method.as_str(), segments.as_slice()) {
+        ("GET", ["v1", "status"]) => to_value(Ok(commands::proxy::get_proxy_status(state))),
+        ("POST", ["v1", "proxy", "start"]) => {
+            to_value(commands::proxy::start_proxy(app.clone(), state).await)
+        }
+        ("POST", ["v1", "proxy", "stop"]) => {
+            to_value(commands::proxy::stop_proxy(app.clone(), state).await)
+        }
+        ("GET", ["v1", "usage"]) => to_value(commands::usage::get_usage_stats(state)),
+        ("GET", ["v1", "requests"]) => to_value(commands::usage::get_request_history()),
+        ("GET", ["v1", "models"]) => to_value(commands::models::get_available_models(state).await),
+        ("POST", ["v1", "agents", agent_id, "configure"]) => {
+            let request: ConfigureAgentBody = if body.is_empty() {
+                ConfigureAgentBody::default()
+            } else {
+                serde_json::from_slice(body).map_err(|e| (400, e.to_string()))?
+            };
+            // Without a model list, use what the proxy offers right now
+            let models = match request.models {
+                Some(models) => models,
+                None => commands::models::get_available_models(app.state::<AppState>())
+                    .await
+                    .map_err(|e| (500, e))?,
+            };
+            to_value(
+                commands::agents::configure_cli_agent(state, agent_id.to_string(), models).await,
+            )
+        }
+        _ => Err((404, format!("No route for {} {}", head.method, head.path))),
+    }
+}
+
+async fn stream_events(mut stream: TcpStream, context: &ServerContext) {
+    let mut events = context.events.subscribe();
+    let mut stop = context.stop.clone();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    loop {
+        let frame = tokio::select! {
+            received = events.recv() => match received {
+                Ok(event) => sse_frame(&event.name, &event.payload),
+                // A slow reader misses events rather than stalling the app
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    format!(": {} events dropped\n\n", skipped)
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = keepalive.tick() => ": keepalive\n\n".to_string(),
+            _ = stop.changed() => break,
+        };
+        if stream.write_all(frame.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// A new random bearer token
+pub(crate) fn generate_token() -> String {
+    use rand::RngCore;
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(raw: &str) -> RequestHead {
+        parse_head(raw).unwrap()
+    }
+
+    #[test]
+    fn parses_request_head_and_checks_token_and_host() {
+        let request = head(
+            "POST /v1/agents/codex/configure?x=1 HTTP/1.1\r\nHost: 127.0.0.1:8320\r\nAuthorization: Bearer secret\r\nContent-Length: 12\r\n",
+        );
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/agents/codex/configure");
+        assert_eq!(request.query.get("x").map(String::as_str), Some("1"));
+        assert_eq!(request.content_length(), 12);
+        assert!(is_authorized(&request, "secret"));
+        assert!(!is_authorized(&request, "secret2"));
+        assert!(is_allowed_host(&request, 8320));
+        assert!(!is_allowed_host(&request, 8321));
+
+        let events = head("GET /v1/events?token=secret HTTP/1.1\r\nHost: localhost\r\n");
+        assert!(is_authorized(&events, "secret"));
+        assert!(is_allowed_host(&events, 8320));
+        let query_token = head("POST /v1/proxy/stop?token=secret HTTP/1.1\r\nHost: localhost\r\n");
+        assert!(!is_authorized(&query_token, "secret"));
+
+        let rebound =
+            head("GET /v1/status HTTP/1.1\r\nHost: evil.example:8320\r\nAuthorization: Bearer secret\r\n");
+        assert!(!is_allowed_host(&rebound, 8320));
+        assert!(!is_authorized(&head("GET / HTTP/1.1\r\n"), "secret"));
+        assert!(parse_head("garbage").is_err());
+    }
+
+    #[test]
+    fn sse_frames_split_payload_lines() {
+        assert_eq!(
+            sse_frame("proxy-status-changed", "{\"running\":true}"),
+            "event: proxy-status-changed\ndata: {\"running\":true}\n\n"
+        );
+        assert_eq!(sse_frame("x", "a\nb"), "event: x\ndata: a\ndata: b\n\n");
+        assert_eq!(generate_token().len(), 48);
+    }
+}
@@ -0,0 +1,53 @@
+//! Names of the events the backend emits to the frontend.
+//!
+//! Emit sites use these constants so the control API's `/v1/events` stream,
+//! which forwards `FORWARDED`, can't fall behind when an event is added.
+
+pub const AUTH_STATUS_CHANGED: &str = "auth-status-changed";
+pub const BUDGET_EXCEEDED: &str = "budget-exceeded";
+pub const CLOUDFLARE_STATUS_CHANGED: &str = "cloudflare-status-changed";
+pub const CONFIG_RESTORED: &str = "config-restored";
+pub const CONFIG_VALIDATION: &str = "config-validation";
+pub const COPILOT_AUTH_REQUIRED: &str = "copilot-auth-required";
+pub const COPILOT_STATUS_CHANGED: &str = "copilot-status-changed";
+pub const PROFILE_ACTIVATED: &str = "profile-activated";
+pub const PROXY_CONFIG_APPLIED: &str = "proxy-config-applied";
+pub const PROXY_CONFIG_CONFLICTS: &str = "proxy-config-conflicts";
+pub const PROXY_CRASH_LOOP: &str = "proxy-crash-loop";
+pub const PROXY_INSTANCE_STATUS_CHANGED: &str = "proxy-instance-status-changed";
+pub const PROXY_PORT_CHANGED: &str = "proxy-port-changed";
+pub const PROXY_RESTARTED: &str = "proxy-restarted";
+pub const PROXY_STATUS_CHANGED: &str = "proxy-status-changed";
+pub const REQUEST_LOG: &str = "request-log";
+pub const SIDECAR_CAPABILITIES: &str = "sidecar-capabilities";
+pub const SIDECAR_OUTPUT: &str = "sidecar-output";
+pub const SSH_STATUS_CHANGED: &str = "ssh-status-changed";
+
+/// Carries the OAuth authorization code, so it never leaves the app
+pub const OAUTH_CALLBACK: &str = "oauth-callback";
+/// Tray menu click handled by the app's own window
+pub const TRAY_TOGGLE_PROXY: &str = "tray-toggle-proxy";
+
+/// Events forwarded to `/v1/events`: everything except `OAUTH_CALLBACK` and
+/// `TRAY_TOGGLE_PROXY`
+pub const FORWARDED: &[&str] = &[
+    AUTH_STATUS_CHANGED,
+    BUDGET_EXCEEDED,
+    CLOUDFLARE_STATUS_CHANGED,
+    CONFIG_RESTORED,
+    CONFIG_VALIDATION,
+    COPILOT_AUTH_REQUIRED,
+    COPILOT_STATUS_CHANGED,
+    PROFILE_ACTIVATED,
+    PROXY_CONFIG_APPLIED,
+    PROXY_CONFIG_CONFLICTS,
+    PROXY_CRASH_LOOP,
+    PROXY_INSTANCE_STATUS_CHANGED,
+    PROXY_PORT_CHANGED,
+    PROXY_RESTARTED,
+    PROXY_STATUS_CHANGED,
+    REQUEST_LOG,
+    SIDECAR_CAPABILITIES,
+    SIDECAR_OUTPUT,
+    SSH_STATUS_CHANGED,
+];
@@ -1,5 +0,0 @@
-//! Internal helper modules.
-
-pub mod history;
-pub mod log_watcher;
-pub mod migration;
@@ -1,61 +1,29 @@
 mod commands;
-mod config;
-mod helpers;
-mod proxy;
+mod control_api;
+mod events;
 mod state;
-mod types;
-mod utils;
 mod ssh_manager;
 mod cloudflare_manager;
 
+// The GUI-independent core, also behind the `proxypal` CLI
+use proxypal_core::{config, helpers, proxy, types};
+use proxypal_core::{get_management_key, GPT5_BASE_MODELS};
+
 use crate::config::{get_auth_path, load_config};
-use crate::helpers::migration::migrate_to_split_storage;
 use crate::state::AppState;
-use crate::types::{ProxyStatus, AuthStatus, CopilotStatus};
+use crate::types::{AuthStatus, CopilotStatus};
 use crate::ssh_manager::SshManager;
 use crate::cloudflare_manager::CloudflareManager;
+use crate::control_api::ControlApi;
 use std::sync::Mutex;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     Emitter, Manager,
 };
 
-/// Get management key from config (used for internal proxy API calls)
-pub(crate) fn get_management_key() -> String {
-    load_config().management_key
-}
-
-// Windows-specific imports for hiding CMD windows
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
-
-// Windows CREATE_NO_WINDOW flag
-#[cfg(target_os = "windows")]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
-
-// GPT-5 base models that support reasoning level suffixes (single source of truth)
-// Used by both backend (proxy config generation) and frontend (Settings UI)
-pub(crate) const GPT5_BASE_MODELS: &[&str] = &[
-    "gpt-5",
-    "gpt-5-mini",
-    "gpt-5-codex",
-    "gpt-5-codex-mini",
-    "gpt-5.1",
-    "gpt-5.1-codex",
-    "gpt-5.1-codex-mini",
-    "gpt-5.1-codex-max",
-    "gpt-5.2",
-    "gpt-5.2-codex",
-    "gpt-5.3-codex",
-    "gpt-5.3-codex-spark",
-];
-
-// GPT-5 reasoning level suffixes
-pub(crate) const GPT5_REASONING_SUFFIXES: &[&str] = &["minimal", "low", "medium", "high", "xhigh"];
-
 // Load auth status from file
 pub(crate) fn load_auth_status() -> AuthStatus {
     let path = get_auth_path();
@@ -92,7 +60,7 @@ fn handle_deep_link(app: &tauri::AppHandle, urls: Vec<url::Url>) {
                     if oauth.state == state.as_ref() {
                         // Emit event to frontend
                         let _ = app.emit(
-                            "oauth-callback",
+                            events::OAUTH_CALLBACK,
                             serde_json::json!({
                                 "provider": oauth.provider,
                                 "code": code.as_ref()
@@ -112,19 +80,77 @@ fn handle_deep_link(app: &tauri::AppHandle, urls: Vec<url::Url>) {
     }
 }
 
+const TRAY_ID: &str = "main";
+const PROFILE_MENU_PREFIX: &str = "profile:";
+const INSTANCE_MENU_PREFIX: &str = "instance:";
+
+// Build the tray menu, including submenus listing config profiles and the
+// extra proxy instances
+fn build_tray_menu<R: tauri::Runtime, M: Manager<R>>(manager: &M) -> tauri::Result<Menu<R>> {
+    let toggle_item = MenuItem::with_id(manager, "toggle", "Toggle Proxy", true, None::<&str>)?;
+    let dashboard_item = MenuItem::with_id(manager, "dashboard", "Open Dashboard", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(manager, "quit", "Quit ProxyPal", true, None::<&str>)?;
+
+    let profiles = crate::helpers::profiles::list_profiles();
+    let profiles_menu = Submenu::with_id(manager, "profiles", "Profiles", !profiles.is_empty())?;
+    for profile in &profiles {
+        let item = CheckMenuItem::with_id(
+            manager,
+            format!("{}{}", PROFILE_MENU_PREFIX, profile.name),
+            &profile.name,
+            true,
+            profile.active,
+            None::<&str>,
+        )?;
+        profiles_menu.append(&item)?;
+    }
+
+    let definitions = crate::helpers::instances::load_definitions();
+    let instances_menu = Submenu::with_id(manager, "instances", "Instances", !definitions.is_empty())?;
+    for definition in &definitions {
+        let running = manager
+            .try_state::<AppState>()
+            .and_then(|state| state.instance(&definition.id).ok())
+            .map(|instance| instance.proxy_status.lock().unwrap().running)
+            .unwrap_or(false);
+        let item = CheckMenuItem::with_id(
+            manager,
+            format!("{}{}", INSTANCE_MENU_PREFIX, definition.id),
+            &definition.name,
+            true,
+            running,
+            None::<&str>,
+        )?;
+        instances_menu.append(&item)?;
+    }
+
+    Menu::with_items(
+        manager,
+        &[&toggle_item, &profiles_menu, &instances_menu, &dashboard_item, &quit_item],
+    )
+}
+
+// Rebuild the tray menu after profiles or instances change
+pub(crate) fn refresh_tray_menu(app: &tauri::AppHandle) {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        match build_tray_menu(app) {
+            Ok(menu) => {
+                let _ = tray.set_menu(Some(menu));
+            }
+            Err(e) => eprintln!("[ProxyPal] Failed to rebuild tray menu: {}", e),
+        }
+    }
+}
+
 // Setup system tray
 fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    let toggle_item = MenuItem::with_id(app, "toggle", "Toggle Proxy", true, None::<&str>)?;
-    let dashboard_item = MenuItem::with_id(app, "dashboard", "Open Dashboard", true, None::<&str>)?;
-    let quit_item = MenuItem::with_id(app, "quit", "Quit ProxyPal", true, None::<&str>)?;
-
-    let menu = Menu::with_items(app, &[&toggle_item, &dashboard_item, &quit_item])?;
+    let menu = build_tray_menu(app)?;
 
     // Use dedicated tray icon (22x22 @1x, 44x44 @2x for retina)
     let tray_icon = tauri::image::Image::from_bytes(include_bytes!("../icons/tray-icon@2x.png"))
         .expect("Failed to load tray icon");
     
-    let _tray = TrayIconBuilder::new()
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(tray_icon)
         .icon_as_template(true)
         .menu(&menu)
@@ -133,10 +159,10 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
         .on_menu_event(move |app, event| match event.id.as_ref() {
             "toggle" => {
                 let app_state = app.state::<AppState>();
-                let is_running = app_state.proxy_status.lock().unwrap().running;
+                let is_running = app_state.default_instance().proxy_status.lock().unwrap().running;
 
                 // Emit toggle event to frontend
-                let _ = app.emit("tray-toggle-proxy", !is_running);
+                let _ = app.emit(events::TRAY_TOGGLE_PROXY, !is_running);
             }
             "dashboard" => {
                 if let Some(window) = app.get_webview_window("main") {
@@ -148,6 +174,44 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
             "quit" => {
                 app.exit(0);
             }
+            id if id.starts_with(PROFILE_MENU_PREFIX) => {
+                let name = id[PROFILE_MENU_PREFIX.len()..].to_string();
+                let app_handle = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = app_handle.state::<AppState>();
+                    if let Err(e) =
+                        commands::profiles::activate_profile(app_handle.clone(), state, name).await
+                    {
+                        eprintln!("[ProxyPal] Failed to activate profile: {}", e);
+                        // Restore the check marks to the actual active profile
+                        refresh_tray_menu(&app_handle);
+                    }
+                });
+            }
+            id if id.starts_with(INSTANCE_MENU_PREFIX) => {
+                let id = id[INSTANCE_MENU_PREFIX.len()..].to_string();
+                let app_handle = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let instance = match app_handle.state::<AppState>().instance(&id) {
+                        Ok(instance) => instance,
+                        Err(e) => {
+                            eprintln!("[ProxyPal] {}", e);
+                            return;
+                        }
+                    };
+                    let running = instance.proxy_status.lock().unwrap().running;
+                    let result = if running {
+                        commands::proxy::stop_instance(app_handle.clone(), instance).await
+                    } else {
+                        commands::proxy::start_instance(app_handle.clone(), instance).await
+                    };
+                    if let Err(e) = result {
+                        eprintln!("[ProxyPal] Failed to toggle instance {}: {}", id, e);
+                    }
+                    // Check marks follow the actual state, also after a failure
+                    refresh_tray_menu(&app_handle);
+                });
+            }
             _ => {}
         })
         .on_tray_icon_event(|tray, event| {
@@ -216,25 +280,11 @@ fn is_updater_supported() -> Result<serde_json::Value, String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Migrate old format to split storage on first run
-    migrate_to_split_storage();
-
-    // Clean up any orphaned clipproxyapi processes from previous crashes
-    #[cfg(unix)]
-    {
-        println!("[ProxyPal] Cleaning up orphaned clipproxyapi processes on startup");
-        let _ = std::process::Command::new("sh")
-            .args(["-c", "pkill -9 -f clipproxyapi 2>/dev/null"])
-            .spawn()
-            .and_then(|mut child| child.wait());
-    }
-    #[cfg(windows)]
-    {
-        let mut cmd = std::process::Command::new("cmd");
-        cmd.args(["/C", "taskkill /F /IM clipproxyapi*.exe 2>nul"]);
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(CREATE_NO_WINDOW);
-        let _ = cmd.spawn().and_then(|mut child| child.wait());
+    // Open the secret vault if it uses a key file, and seal any plaintext
+    // keys left in config.json or the profiles
+    helpers::secrets::init_vault();
+    if let Err(e) = helpers::secrets::migrate_plaintext_secrets() {
+        eprintln!("[ProxyPal] Failed to move secrets into the vault: {}", e);
     }
 
     // Load persisted config and auth
@@ -242,16 +292,21 @@ pub fn run() {
     let auth = load_auth_status();
 
     let app_state = AppState {
-        proxy_status: Mutex::new(ProxyStatus::default()),
         auth_status: Mutex::new(auth),
         config: Mutex::new(config),
         pending_oauth: Mutex::new(None),
-        proxy_process: Mutex::new(None),
         copilot_status: Mutex::new(CopilotStatus::default()),
         copilot_process: Mutex::new(None),
-        log_watcher_running: Arc::new(AtomicBool::new(false)),
-        request_counter: Arc::new(AtomicU64::new(0)),
+        instances: state::default_instances(config::get_instance_dir(config::DEFAULT_INSTANCE_ID)),
     };
+    for definition in helpers::instances::load_definitions() {
+        let instance = state::ProxyInstance::new(&definition.id, config::get_instance_dir(&definition.id));
+        app_state
+            .instances
+            .lock()
+            .unwrap()
+            .insert(definition.id, Arc::new(instance));
+    }
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -282,6 +337,7 @@ pub fn run() {
         .manage(app_state)
         .manage(SshManager::new())
         .manage(CloudflareManager::new())
+        .manage(ControlApi::new())
         .setup(|app| {
             // Setup system tray
             #[cfg(desktop)]
@@ -300,6 +356,71 @@ pub fn run() {
                 });
             }
 
+            // Tell the user if config.json was unreadable and moved aside, or
+            // left in place and not saved over
+            if let Some(error) = crate::config::last_config_load_error() {
+                use tauri_plugin_notification::NotificationExt;
+                let body = match &error.quarantined_path {
+                    Some(path) => format!("{}. The file was moved to {}", error.message, path),
+                    None => format!("{}. Settings won't be saved until it loads.", error.message),
+                };
+                let _ = app
+                    .notification()
+                    .builder()
+                    .title("ProxyPal config could not be loaded")
+                    .body(body)
+                    .show();
+            }
+
+            // Serve the control API if enabled; its event stream is fed either way
+            let control_api = app.state::<ControlApi>();
+            control_api.forward_events(app.handle());
+            let control_api_config = app.state::<AppState>().config.lock().unwrap().control_api.clone();
+            if control_api_config.enabled {
+                if let Err(e) = control_api.apply(app.handle(), &control_api_config) {
+                    eprintln!("[ProxyPal] {}", e);
+                }
+            }
+
+            // Take over sidecars that are still running, whether `proxypal start`
+            // launched them or a previous ProxyPal left them behind
+            let instances: Vec<_> = {
+                let state = app.state::<AppState>();
+                let instances = state.instances.lock().unwrap();
+                instances.values().cloned().collect()
+            };
+            for instance in instances {
+                commands::proxy::adopt_sidecar(app.handle(), &instance);
+            }
+
+            // Keep the login item in line with the setting, e.g. after the app moved
+            let (launch_at_login, auto_start) = {
+                let config = app.state::<AppState>().config.lock().unwrap().clone();
+                (config.launch_at_login, config.auto_start)
+            };
+            helpers::autostart::sync_app_login_item(launch_at_login);
+
+            // Start the proxy on the saved port if configured
+            if auto_start {
+                let app_handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let instance = app_handle.state::<AppState>().default_instance();
+                    match commands::proxy::start_instance(app_handle.clone(), instance).await {
+                        Ok(status) => println!("[ProxyPal] Auto-started proxy on port {}", status.port),
+                        Err(e) => {
+                            eprintln!("[ProxyPal] Auto-start failed: {}", e);
+                            use tauri_plugin_notification::NotificationExt;
+                            let _ = app_handle
+                                .notification()
+                                .builder()
+                                .title("ProxyPal could not start the proxy")
+                                .body(&e)
+                                .show();
+                        }
+                    }
+                });
+            }
+
             // Auto-start SSH connections
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -325,6 +446,9 @@ pub fn run() {
                 }
             });
 
+            // Watch spending and token budgets
+            commands::budgets::start_budget_monitor(app.handle().clone());
+
             // Auto-start Copilot if enabled
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -348,6 +472,22 @@ pub fn run() {
             commands::models::get_gpt_reasoning_models,
             commands::proxy::start_proxy,
             commands::proxy::stop_proxy,
+            commands::proxy::preview_proxy_config,
+            commands::proxy::get_sidecar_capabilities,
+            // Proxy instances
+            commands::instances::list_proxy_instances,
+            commands::instances::create_proxy_instance,
+            commands::instances::delete_proxy_instance,
+            commands::instances::start_proxy_instance,
+            commands::instances::stop_proxy_instance,
+            commands::instances::get_instance_config,
+            commands::instances::save_proxy_instance_config,
+            commands::instances::get_instance_usage_stats,
+            commands::instances::get_instance_request_history,
+            // Sidecar Versions
+            commands::sidecar::list_sidecar_versions,
+            commands::sidecar::install_sidecar_version,
+            commands::sidecar::set_active_sidecar_version,
             // Copilot Management
             commands::copilot::get_copilot_status,
             commands::copilot::start_copilot,
@@ -374,9 +514,31 @@ pub fn run() {
             commands::quota::import_vertex_credential,
             commands::config::get_config,
             commands::config::save_config,
+            commands::config::apply_config,
             commands::config::get_config_yaml,
             commands::config::save_config_yaml,
             commands::config::reload_config,
+            commands::config::get_config_load_error,
+            commands::config::validate_config,
+            // Config Profiles
+            commands::profiles::get_profiles,
+            commands::profiles::create_profile,
+            commands::profiles::clone_profile,
+            commands::profiles::delete_profile,
+            commands::profiles::activate_profile,
+            // Config Snapshots
+            commands::snapshots::list_config_snapshots,
+            commands::snapshots::diff_config_snapshot,
+            commands::snapshots::restore_config_snapshot,
+            // Secret Vault
+            commands::secrets::get_vault_status,
+            commands::secrets::enable_secret_vault,
+            commands::secrets::unlock_secret_vault,
+            commands::secrets::lock_secret_vault,
+            // Control API
+            commands::control_api::get_control_api_status,
+            commands::control_api::set_control_api,
+            commands::control_api::regenerate_control_api_token,
             commands::proxy::get_system_proxy,
             // CLI Agent & IDE Tool detection
             commands::agents::detect_ai_tools,
@@ -396,6 +558,12 @@ pub fn run() {
             commands::usage::sync_usage_from_proxy,
             commands::usage::export_usage_stats,
             commands::usage::import_usage_stats,
+            // Pricing
+            commands::pricing::get_pricing,
+            commands::pricing::save_pricing_overrides,
+            commands::pricing::recompute_costs,
+            // Budgets
+            commands::budgets::get_budget_status,
             commands::models::get_available_models,
             commands::models::test_openai_provider,
             commands::models::test_provider_connection,
@@ -438,6 +606,7 @@ pub fn run() {
             // Log Viewer
             commands::logs::get_logs,
             commands::logs::clear_logs,
+            commands::logs::get_sidecar_output,
             // Management API Settings
             commands::settings::get_max_retry_interval,
             commands::settings::set_max_retry_interval,
@@ -499,16 +668,27 @@ pub fn run() {
                 tauri::RunEvent::ExitRequested { .. } => {
                     // Cleanup: Kill proxy and copilot processes before exit
                     if let Some(state) = app_handle.try_state::<AppState>() {
-                        // Stop log watcher thread
-                        state.log_watcher_running.store(false, Ordering::SeqCst);
-                        
-                        // Kill cliproxyapi process
-                        if let Ok(mut process_guard) = state.proxy_process.lock() {
-                            if let Some(child) = process_guard.take() {
-                                println!("[ProxyPal] Shutting down cliproxyapi...");
-                                let _ = child.kill();
+                        for instance in state.all_instances() {
+                            // Stop log watcher thread
+                            instance.log_watcher_running.store(false, Ordering::SeqCst);
+
+                            // Stop the cliproxyapi process; exits aren't restarted from here on
+                            if let Ok(mut supervisor) = instance.proxy_supervisor.lock() {
+                                supervisor.stop();
+                            }
+                            let proxy_pid = instance
+                                .proxy_process
+                                .lock()
+                                .ok()
+                                .and_then(|mut process_guard| process_guard.take())
+                                .map(|child| child.pid());
+                            if let Some(pid) = proxy_pid {
+                                println!("[ProxyPal] Shutting down cliproxyapi ({})...", instance.id);
+                                proxy::process::stop_sidecar_at(&instance.pid_path(), pid);
                             }
                         }
+                        // Commit usage the log watchers have queued
+                        helpers::usage_recorder::flush_all();
                         // Kill copilot-api process
                         if let Ok(mut process_guard) = state.copilot_process.lock() {
                             if let Some(child) = process_guard.take() {
@@ -518,6 +698,10 @@ pub fn run() {
                         }
                     }
 
+                    if let Some(control_api) = app_handle.try_state::<ControlApi>() {
+                        control_api.stop();
+                    }
+
                     // Cleaning up SSH connections
                     if let Some(ssh_manager) = app_handle.try_state::<SshManager>() {
                         ssh_manager.disconnect_all();
@@ -1 +0,0 @@
-// Proxy-specific helpers (config generation, log watcher, etc.) will live here.
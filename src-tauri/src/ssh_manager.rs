@@ -5,6 +5,7 @@ use tauri::{AppHandle, Emitter};
 use tokio::process::Command;
 use tokio::sync::Notify;
 
+use crate::events;
 use crate::types::ssh::SshConfig;
 
 #[derive(Clone, serde::Serialize)]
@@ -44,7 +45,7 @@ impl SshManager {
         
         // Helper to emit status
         let emit_status = move |status: &str, msg: Option<String>| {
-            let _ = app.emit("ssh-status-changed", SshStatusUpdate {
+            let _ = app.emit(events::SSH_STATUS_CHANGED, SshStatusUpdate {
                 id: config_clone.id.clone(),
                 status: status.to_string(),
                 message: msg,
@@ -1,36 +1,135 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64};
 use tauri_plugin_shell::process::CommandChild;
 
-use crate::types::{ProxyStatus, AuthStatus, OAuthState, CopilotStatus};
-use crate::config::AppConfig;
+use crate::types::{ProxyStatus, AuthStatus, OAuthState, CopilotStatus, SidecarCapabilities};
+use crate::config::{AppConfig, DEFAULT_INSTANCE_ID};
+use crate::proxy::output::SidecarOutput;
+use crate::proxy::supervisor::{RestartPolicy, Supervisor};
+
+/// One CLIProxyAPI process and everything that belongs to it. The default
+/// instance uses the app config; others keep their own config.json, auth dir,
+/// logs and usage files in their directory.
+pub struct ProxyInstance {
+    pub id: String,
+    /// Where proxy-config.yaml, logs/, the PID file and the usage files live
+    pub dir: PathBuf,
+    pub proxy_status: Mutex<ProxyStatus>,
+    pub proxy_process: Mutex<Option<CommandChild>>,
+    pub log_watcher_running: Arc<AtomicBool>,
+    pub request_counter: Arc<AtomicU64>,
+    /// proxy-config.yaml as last written for the sidecar to load
+    pub loaded_proxy_config: Mutex<Option<String>>,
+    /// Restart bookkeeping for the sidecar
+    pub proxy_supervisor: Mutex<Supervisor>,
+    /// Recent stdout/stderr lines of the sidecar
+    pub sidecar_output: Mutex<SidecarOutput>,
+    /// Features of the running sidecar, detected on each start
+    pub sidecar_capabilities: Mutex<SidecarCapabilities>,
+}
+
+impl ProxyInstance {
+    pub fn new(id: &str, dir: PathBuf) -> Self {
+        Self {
+            id: id.to_string(),
+            dir,
+            proxy_status: Mutex::new(ProxyStatus::default()),
+            proxy_process: Mutex::new(None),
+            log_watcher_running: Arc::new(AtomicBool::new(false)),
+            request_counter: Arc::new(AtomicU64::new(0)),
+            loaded_proxy_config: Mutex::new(None),
+            proxy_supervisor: Mutex::new(Supervisor::new(RestartPolicy::default())),
+            sidecar_output: Mutex::new(SidecarOutput::default()),
+            sidecar_capabilities: Mutex::new(SidecarCapabilities::default()),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_INSTANCE_ID
+    }
+
+    // Same file names as the default instance's, which lives in the config dir
+
+    pub fn pid_path(&self) -> PathBuf {
+        self.dir.join("cli-proxy-api.pid")
+    }
+
+    pub fn output_log_path(&self) -> PathBuf {
+        self.dir.join("sidecar-output.log")
+    }
+
+    /// Credential files CLIProxyAPI loads for this instance
+    pub fn auth_dir(&self) -> PathBuf {
+        if self.is_default() {
+            crate::helpers::auth_dir::get_cli_proxy_auth_dir()
+        } else {
+            self.dir.join("auth")
+        }
+    }
+}
 
 /// App state shared across all Tauri commands
 pub struct AppState {
-    pub proxy_status: Mutex<ProxyStatus>,
     pub auth_status: Mutex<AuthStatus>,
     pub config: Mutex<AppConfig>,
     pub pending_oauth: Mutex<Option<OAuthState>>,
-    pub proxy_process: Mutex<Option<CommandChild>>,
     pub copilot_status: Mutex<CopilotStatus>,
     pub copilot_process: Mutex<Option<CommandChild>>,
-    pub log_watcher_running: Arc<AtomicBool>,
-    pub request_counter: Arc<AtomicU64>,
+    /// Proxy instances by id; `DEFAULT_INSTANCE_ID` is always present
+    pub instances: Mutex<HashMap<String, Arc<ProxyInstance>>>,
+}
+
+impl AppState {
+    /// The instance driven by the app config and the un-scoped commands
+    pub fn default_instance(&self) -> Arc<ProxyInstance> {
+        self.instances
+            .lock()
+            .unwrap()
+            .get(DEFAULT_INSTANCE_ID)
+            .cloned()
+            .expect("default proxy instance is always registered")
+    }
+
+    pub fn instance(&self, id: &str) -> Result<Arc<ProxyInstance>, String> {
+        self.instances
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("Proxy instance '{}' not found", id))
+    }
+
+    /// The instance with `id`, or the default one when none is given
+    pub fn instance_or_default(&self, id: Option<&str>) -> Result<Arc<ProxyInstance>, String> {
+        match id {
+            Some(id) => self.instance(id),
+            None => Ok(self.default_instance()),
+        }
+    }
+
+    pub fn all_instances(&self) -> Vec<Arc<ProxyInstance>> {
+        self.instances.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Instance map holding just the default instance in `dir`
+pub fn default_instances(dir: PathBuf) -> Mutex<HashMap<String, Arc<ProxyInstance>>> {
+    let instance = Arc::new(ProxyInstance::new(DEFAULT_INSTANCE_ID, dir));
+    Mutex::new(HashMap::from([(DEFAULT_INSTANCE_ID.to_string(), instance)]))
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            proxy_status: Mutex::new(ProxyStatus::default()),
             auth_status: Mutex::new(AuthStatus::default()),
             config: Mutex::new(AppConfig::default()),
             pending_oauth: Mutex::new(None),
-            proxy_process: Mutex::new(None),
             copilot_status: Mutex::new(CopilotStatus::default()),
             copilot_process: Mutex::new(None),
-            log_watcher_running: Arc::new(AtomicBool::new(false)),
-            request_counter: Arc::new(AtomicU64::new(0)),
+            instances: default_instances(PathBuf::from(".")),
         }
     }
 }
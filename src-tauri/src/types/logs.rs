@@ -1,10 +0,0 @@
-use serde::{Deserialize, Serialize};
-
-// Log entry structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct LogEntry {
-    pub timestamp: String,
-    pub level: String,
-    pub message: String,
-}
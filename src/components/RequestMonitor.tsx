@@ -345,6 +345,13 @@ export function RequestMonitor() {
                       </span>
                     </Show>
 
+                    {/* Cost */}
+                    <Show when={log.costUsd}>
+                      <span class="hidden font-mono text-xs text-gray-400 dark:text-gray-500 sm:block">
+                        {formatCost(log.costUsd || 0)}
+                      </span>
+                    </Show>
+
                     {/* Status */}
                     <span
                       class={`font-mono text-xs font-semibold ${statusColors[log.status] || "text-gray-500"}`}
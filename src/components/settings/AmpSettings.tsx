@@ -2,8 +2,11 @@ import { createEffect, createMemo, createSignal, For, Show } from "solid-js";
 import { useI18n } from "../../i18n";
 import {
   AMP_MODEL_SLOTS,
+  applyConfig,
   getForceModelMappings,
   saveConfig,
+  secretFieldPlaceholder,
+  secretFieldValue,
   setForceModelMappings,
   startProxy,
   stopProxy,
@@ -218,13 +221,8 @@ export function AmpSettings(props: AmpSettingsProps) {
 
     props.setSaving(true);
     try {
-      await saveConfig(newConfig);
-      // Restart proxy to regenerate config YAML with updated mappings
-      if (appStore.proxyStatus().running) {
-        await stopProxy();
-        await new Promise((resolve) => setTimeout(resolve, 300));
-        await startProxy();
-      }
+      // Mapping changes are hot-reloaded by the running proxy
+      await applyConfig(newConfig);
       toastStore.success(t("settings.toasts.modelMappingUpdated"));
     } catch (error) {
       console.error("Failed to save config:", error);
@@ -418,13 +416,8 @@ export function AmpSettings(props: AmpSettingsProps) {
 
     props.setSaving(true);
     try {
-      await saveConfig(newConfig);
-      // Restart proxy to regenerate config YAML with updated mappings
-      if (appStore.proxyStatus().running) {
-        await stopProxy();
-        await new Promise((resolve) => setTimeout(resolve, 300));
-        await startProxy();
-      }
+      // Mapping changes are hot-reloaded by the running proxy
+      await applyConfig(newConfig);
       toastStore.success(t("settings.toasts.mappingUpdated"));
     } catch (error) {
       console.error("Failed to save config:", error);
@@ -446,9 +439,9 @@ export function AmpSettings(props: AmpSettingsProps) {
           <input
             class="transition-smooth mt-1 block w-full rounded-lg border border-gray-300 bg-white px-3 py-2 font-mono text-sm focus:border-transparent focus:ring-2 focus:ring-brand-500 dark:border-gray-600 dark:bg-gray-900"
             onInput={(e) => props.handleConfigChange("ampApiKey", e.currentTarget.value)}
-            placeholder="amp_..."
+            placeholder={secretFieldPlaceholder(props.config().ampApiKey, "amp_...")}
             type="password"
-            value={props.config().ampApiKey || ""}
+            value={secretFieldValue(props.config().ampApiKey)}
           />
           <p class="mt-1 text-xs text-gray-500 dark:text-gray-400">
             Get your API key from{" "}
@@ -3,6 +3,8 @@ import { useI18n } from "../../i18n";
 import {
   deleteCloudflareConfig,
   saveCloudflareConfig,
+  secretFieldPlaceholder,
+  secretFieldValue,
   setCloudflareConnection,
 } from "../../lib/tauri";
 import { appStore } from "../../stores/app";
@@ -226,9 +228,9 @@ export function CloudflareSettings(props: CloudflareSettingsProps) {
             <input
               class="w-full rounded-lg border border-gray-200 bg-white px-3 py-2 font-mono text-sm outline-none focus:ring-2 focus:ring-blue-500 dark:border-gray-600 dark:bg-gray-800"
               onInput={(e) => setCfToken(e.currentTarget.value)}
-              placeholder="eyJ..."
+              placeholder={secretFieldPlaceholder(cfToken(), "eyJ...")}
               type="password"
-              value={cfToken()}
+              value={secretFieldValue(cfToken())}
             />
             <p class="text-[10px] text-gray-400">
               Get token from Cloudflare Zero Trust Dashboard → Access → Tunnels
@@ -1,6 +1,11 @@
 import { createSignal, For, Show } from "solid-js";
 import { useI18n } from "../../i18n";
-import { saveConfig, testOpenAIProvider } from "../../lib/tauri";
+import {
+  saveConfig,
+  secretFieldPlaceholder,
+  secretFieldValue,
+  testOpenAIProvider,
+} from "../../lib/tauri";
 import { toastStore } from "../../stores/toast";
 import { Button } from "../ui";
 
@@ -365,9 +370,9 @@ export function OpenAIProviderSettings(props: OpenAIProviderSettingsProps) {
                 <input
                   class="transition-smooth mt-1 block w-full rounded-lg border border-gray-300 bg-white px-3 py-2 font-mono text-sm focus:border-transparent focus:ring-2 focus:ring-brand-500 dark:border-gray-600 dark:bg-gray-800"
                   onInput={(e) => setProviderApiKey(e.currentTarget.value)}
-                  placeholder="sk-..."
+                  placeholder={secretFieldPlaceholder(providerApiKey(), "sk-...")}
                   type="password"
-                  value={providerApiKey()}
+                  value={secretFieldValue(providerApiKey())}
                 />
               </label>
 
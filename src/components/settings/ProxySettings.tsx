@@ -3,7 +3,10 @@ import { useI18n } from "../../i18n";
 import {
   getLogSize,
   getMaxRetryInterval,
+  isSecretRef,
   saveConfig,
+  secretFieldPlaceholder,
+  secretFieldValue,
   setLogSize,
   setMaxRetryInterval,
   startProxy,
@@ -168,9 +171,9 @@ export function ProxySettings(props: ProxySettingsProps) {
               <input
                 class="transition-smooth block w-full rounded-lg border border-gray-300 bg-white px-3 py-2 pr-10 text-sm focus:border-transparent focus:ring-2 focus:ring-brand-500 dark:border-gray-600 dark:bg-gray-900"
                 onInput={(e) => local.handleConfigChange("proxyPassword", e.currentTarget.value)}
-                placeholder="Optional"
+                placeholder={secretFieldPlaceholder(local.config().proxyPassword, "Optional")}
                 type={showProxyPassword() ? "text" : "password"}
-                value={local.config().proxyPassword || ""}
+                value={secretFieldValue(local.config().proxyPassword)}
               />
               <button
                 class="absolute inset-y-0 right-0 flex items-center pr-3 text-gray-400 hover:text-gray-600 dark:hover:text-gray-300"
@@ -273,9 +276,16 @@ export function ProxySettings(props: ProxySettingsProps) {
                   e.currentTarget.value || "proxypal-mgmt-key",
                 )
               }
-              placeholder="proxypal-mgmt-key"
+              placeholder={secretFieldPlaceholder(
+                local.config().managementKey,
+                "proxypal-mgmt-key",
+              )}
               type={showManagementKey() ? "text" : "password"}
-              value={local.config().managementKey || "proxypal-mgmt-key"}
+              value={
+                isSecretRef(local.config().managementKey)
+                  ? ""
+                  : local.config().managementKey || "proxypal-mgmt-key"
+              }
             />
             <button
               class="absolute inset-y-0 right-0 flex items-center pr-3 text-gray-400 hover:text-gray-600 dark:hover:text-gray-300"
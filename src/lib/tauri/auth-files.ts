@@ -25,26 +25,40 @@ export interface AuthFile {
   updatedAt?: string;
 }
 
-export async function getAuthFiles(): Promise<AuthFile[]> {
-  return invoke("get_auth_files");
+// Every function targets the default proxy instance unless an instance id is given
+
+export async function getAuthFiles(instanceId?: string): Promise<AuthFile[]> {
+  return invoke("get_auth_files", { instanceId });
 }
 
-export async function uploadAuthFile(filePath: string, provider: string): Promise<void> {
-  return invoke("upload_auth_file", { filePath, provider });
+export async function uploadAuthFile(
+  filePath: string,
+  provider: string,
+  instanceId?: string,
+): Promise<void> {
+  return invoke("upload_auth_file", { filePath, instanceId, provider });
 }
 
-export async function deleteAuthFile(fileId: string): Promise<void> {
-  return invoke("delete_auth_file", { fileId });
+export async function deleteAuthFile(fileId: string, instanceId?: string): Promise<void> {
+  return invoke("delete_auth_file", { fileId, instanceId });
 }
 
-export async function toggleAuthFile(fileName: string, disabled: boolean): Promise<void> {
-  return invoke("toggle_auth_file", { disabled, fileName });
+export async function toggleAuthFile(
+  fileName: string,
+  disabled: boolean,
+  instanceId?: string,
+): Promise<void> {
+  return invoke("toggle_auth_file", { disabled, fileName, instanceId });
 }
 
-export async function downloadAuthFile(fileId: string, filename: string): Promise<string> {
-  return invoke("download_auth_file", { fileId, filename });
+export async function downloadAuthFile(
+  fileId: string,
+  filename: string,
+  instanceId?: string,
+): Promise<string> {
+  return invoke("download_auth_file", { fileId, filename, instanceId });
 }
 
-export async function deleteAllAuthFiles(): Promise<void> {
-  return invoke("delete_all_auth_files");
+export async function deleteAllAuthFiles(instanceId?: string): Promise<void> {
+  return invoke("delete_all_auth_files", { instanceId });
 }
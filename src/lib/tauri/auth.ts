@@ -13,8 +13,11 @@ export type Provider =
   | "antigravity"
   | "kimi";
 
-export async function openOAuth(provider: Provider): Promise<string> {
-  return invoke("open_oauth", { provider });
+// OAuth logins, disconnects and the proxy auth check target the default proxy
+// instance unless an instance id is given
+
+export async function openOAuth(provider: Provider, instanceId?: string): Promise<string> {
+  return invoke("open_oauth", { instanceId, provider });
 }
 
 export interface OAuthUrlResponse {
@@ -30,24 +33,33 @@ export interface DeviceCodeResponse {
   verificationUri: string;
 }
 
-export async function getOAuthUrl(provider: Provider): Promise<OAuthUrlResponse> {
-  return invoke("get_oauth_url", { provider });
+export async function getOAuthUrl(
+  provider: Provider,
+  instanceId?: string,
+): Promise<OAuthUrlResponse> {
+  return invoke("get_oauth_url", { instanceId, provider });
 }
 
-export async function getDeviceCode(provider: Provider): Promise<DeviceCodeResponse> {
-  return invoke("get_device_code", { provider });
+export async function getDeviceCode(
+  provider: Provider,
+  instanceId?: string,
+): Promise<DeviceCodeResponse> {
+  return invoke("get_device_code", { instanceId, provider });
 }
 
-export async function pollOAuthStatus(oauthState: string): Promise<boolean> {
-  return invoke("poll_oauth_status", { oauthState });
+export async function pollOAuthStatus(oauthState: string, instanceId?: string): Promise<boolean> {
+  return invoke("poll_oauth_status", { instanceId, oauthState });
 }
 
 export async function completeOAuth(provider: Provider, code: string): Promise<AuthStatus> {
   return invoke("complete_oauth", { code, provider });
 }
 
-export async function disconnectProvider(provider: Provider): Promise<AuthStatus> {
-  return invoke("disconnect_provider", { provider });
+export async function disconnectProvider(
+  provider: Provider,
+  instanceId?: string,
+): Promise<AuthStatus> {
+  return invoke("disconnect_provider", { instanceId, provider });
 }
 
 export async function importVertexCredential(filePath: string): Promise<AuthStatus> {
@@ -125,6 +137,6 @@ export interface ProxyAuthStatus {
 }
 
 // Verify auth status from CLIProxyAPI (v6.6.72+)
-export async function verifyProxyAuthStatus(): Promise<ProxyAuthStatus> {
-  return invoke("verify_proxy_auth_status");
+export async function verifyProxyAuthStatus(instanceId?: string): Promise<ProxyAuthStatus> {
+  return invoke("verify_proxy_auth_status", { instanceId });
 }
@@ -0,0 +1,51 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+export type BudgetPeriod = "day" | "week" | "month"; // Weeks start on Monday
+export type BudgetMetric = "cost" | "tokens"; // USD, or input plus output tokens
+export type BudgetScope = "global" | "provider" | "model";
+
+// Announced at 50, 80 and 100% of the limit
+export interface Budget {
+  enabled?: boolean;
+  id: string;
+  limit: number;
+  metric: BudgetMetric;
+  name: string;
+  pauseAccounts?: boolean; // Disable the matching auth files at 100% until the period ends
+  period: BudgetPeriod;
+  scope?: BudgetScope;
+  target?: string; // Provider or model name for those scopes
+}
+
+export interface BudgetStatus {
+  id: string;
+  limit: number;
+  name: string;
+  pausedAccounts: string[];
+  percent: number;
+  periodStart: number; // Unix millis
+  used: number;
+}
+
+export interface BudgetAlert {
+  id: string;
+  limit: number;
+  metric: BudgetMetric;
+  name: string;
+  pausedAccounts: string[];
+  period: BudgetPeriod;
+  used: number;
+}
+
+export async function getBudgetStatus(): Promise<BudgetStatus[]> {
+  return invoke("get_budget_status");
+}
+
+export async function onBudgetExceeded(
+  callback: (alert: BudgetAlert) => void,
+): Promise<UnlistenFn> {
+  return listen<BudgetAlert>("budget-exceeded", (event) => {
+    callback(event.payload);
+  });
+}
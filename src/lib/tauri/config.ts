@@ -1,20 +1,36 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
+import type { Budget } from "./budgets";
 import type { CloudflareConfig } from "./cloudflare";
+import type { ControlApiConfig } from "./control-api";
 import type { AmpModelMapping, AmpOpenAIProvider, CopilotConfig } from "./models";
+import type { ReloadReport } from "./proxy";
+import type { SnapshotRetention } from "./snapshots";
 import type { SshConfig } from "./ssh";
 
+// How lists in proxy-config-custom.yaml combine with generated lists
+export type ListMergeStrategy = "append" | "prepend" | "replace";
+
+export interface CustomConfigMergeSettings {
+  listStrategy: ListMergeStrategy;
+  pathStrategies: Record<string, ListMergeStrategy>; // Keyed by dotted path, e.g. "payload.default"
+}
+
 // Config
 export interface AppConfig {
   ampApiKey: string;
   ampModelMappings: AmpModelMapping[];
-  ampOpenaiProvider?: AmpOpenAIProvider; // Deprecated: for migration only
   ampOpenaiProviders: AmpOpenAIProvider[]; // Array of custom providers
   ampRoutingMode: string; // "mappings" or "openai"
+  autoSelectPort?: boolean; // Move to the next free port when the configured one is taken
   autoStart: boolean;
+  budgets?: Budget[]; // Spending and token limits, checked every minute
   cloudflareConfigs?: CloudflareConfig[];
   commercialMode?: boolean; // Disable request logging for lower memory usage
+  controlApi?: ControlApiConfig; // ProxyPal's own localhost API
   copilot: CopilotConfig;
+  customConfigMerge?: CustomConfigMergeSettings; // How proxy-config-custom.yaml is merged
   debug: boolean;
   disableControlPanel?: boolean; // Hide CLIProxyAPI's web management UI
   forceModelMappings: boolean; // Force model mappings to take precedence over local API keys
@@ -35,7 +51,10 @@ export interface AppConfig {
   requestRetry: number;
   routingStrategy: string; // "round-robin", "fill-first", "sequential"
   sidebarPinned?: boolean;
+  sidecarOutputToFile?: boolean; // Also write CLIProxyAPI output to sidecar-output.log
+  snapshotRetention?: SnapshotRetention; // How many config snapshots to keep
   sshConfigs?: SshConfig[];
+  usageRetentionDays?: number; // Days of per-request usage to keep, 0 for all
   usageStatsEnabled: boolean;
   useSystemProxy?: boolean;
   wsAuth?: boolean; // Require authentication for WebSocket connections
@@ -49,10 +68,53 @@ export async function saveConfig(config: AppConfig): Promise<void> {
   return invoke("save_config", { config });
 }
 
+// Saves and applies to the running proxy, restarting only if a setting requires it
+export async function applyConfig(config: AppConfig): Promise<ReloadReport> {
+  return invoke("apply_config", { config });
+}
+
 export async function reloadConfig(): Promise<AppConfig> {
   return invoke("reload_config");
 }
 
+// Set when config.json could not be loaded and defaults were used instead
+export interface ConfigLoadError {
+  message: string;
+  quarantinedPath?: string; // Where the unreadable file was moved
+}
+
+export async function getConfigLoadError(): Promise<ConfigLoadError | null> {
+  return invoke("get_config_load_error");
+}
+
+// Pre-flight diagnostics; start_proxy refuses to launch when any are errors
+export type DiagnosticSeverity = "error" | "warning";
+
+export interface ConfigDiagnostic {
+  code: string; // e.g. "invalid-base-url", "duplicate-model-alias"
+  field: string; // e.g. "claudeApiKeys[0].baseUrl"
+  message: string;
+  severity: DiagnosticSeverity;
+}
+
+export interface ConfigValidation {
+  diagnostics: ConfigDiagnostic[];
+  valid: boolean;
+}
+
+// Validates the given config, or the saved one when omitted
+export async function validateConfig(config?: AppConfig): Promise<ConfigValidation> {
+  return invoke("validate_config", { config });
+}
+
+export async function onConfigValidation(
+  callback: (validation: ConfigValidation) => void,
+): Promise<UnlistenFn> {
+  return listen<ConfigValidation>("config-validation", (event) => {
+    callback(event.payload);
+  });
+}
+
 // Raw Config YAML - for power users
 export async function getConfigYaml(): Promise<string> {
   return invoke("get_config_yaml");
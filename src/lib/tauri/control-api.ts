@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// ProxyPal's localhost HTTP/JSON API for scripts and editor extensions.
+// Requests need "Authorization: Bearer <token>"; GET /v1/events streams the
+// app's events as server-sent events.
+export interface ControlApiConfig {
+  enabled: boolean;
+  port: number;
+  token: string; // May be a vault reference
+}
+
+export interface ControlApiStatus {
+  enabled: boolean;
+  error?: string | null; // Why the server isn't listening, if it should be
+  port: number;
+  running: boolean;
+  token: string;
+  url: string;
+}
+
+export async function getControlApiStatus(): Promise<ControlApiStatus> {
+  return invoke("get_control_api_status");
+}
+
+// Generates a token the first time the API is enabled
+export async function setControlApi(
+  enabled: boolean,
+  port?: number,
+): Promise<ControlApiStatus> {
+  return invoke("set_control_api", { enabled, port });
+}
+
+export async function regenerateControlApiToken(): Promise<ControlApiStatus> {
+  return invoke("regenerate_control_api_token");
+}
@@ -1,9 +1,16 @@
 export * from "./proxy";
+export * from "./instances";
 export * from "./auth";
 export * from "./config";
+export * from "./profiles";
+export * from "./snapshots";
+export * from "./secrets";
+export * from "./control-api";
 export * from "./models";
 export * from "./copilot";
 export * from "./usage";
+export * from "./pricing";
+export * from "./budgets";
 export * from "./quota";
 export * from "./api-keys";
 export * from "./settings";
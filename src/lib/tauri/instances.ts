@@ -0,0 +1,81 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+import type { AppConfig } from "./config";
+import type { ProxyStatus } from "./proxy";
+import type { RequestHistory, UsageStats } from "./usage";
+
+// A CLIProxyAPI process with its own config, auth dir, logs and usage.
+// The default instance is the one the regular proxy commands drive.
+export interface ProxyInstanceInfo {
+  authDir: string;
+  dir: string;
+  id: string;
+  isDefault: boolean;
+  name: string;
+  status: ProxyStatus;
+}
+
+export interface InstanceStatusEvent {
+  id: string;
+  status: ProxyStatus;
+}
+
+export async function listProxyInstances(): Promise<ProxyInstanceInfo[]> {
+  return invoke("list_proxy_instances");
+}
+
+// Without a port, the first free one above the instances' ports is used
+export async function createProxyInstance(
+  name: string,
+  port?: number,
+  copyCurrentConfig = false,
+): Promise<ProxyInstanceInfo> {
+  return invoke("create_proxy_instance", { name, port, copyCurrentConfig });
+}
+
+// Also removes the instance's directory and the credentials in it
+export async function deleteProxyInstance(id: string): Promise<void> {
+  return invoke("delete_proxy_instance", { id });
+}
+
+export async function startProxyInstance(id: string): Promise<ProxyStatus> {
+  return invoke("start_proxy_instance", { id });
+}
+
+export async function stopProxyInstance(id: string): Promise<ProxyStatus> {
+  return invoke("stop_proxy_instance", { id });
+}
+
+export async function getInstanceConfig(id: string): Promise<AppConfig> {
+  return invoke("get_instance_config", { id });
+}
+
+// Restarts the instance if it is running
+export async function saveProxyInstanceConfig(
+  id: string,
+  config: AppConfig,
+): Promise<ProxyStatus> {
+  return invoke("save_proxy_instance_config", { id, config });
+}
+
+export async function getInstanceUsageStats(id: string): Promise<UsageStats> {
+  return invoke("get_instance_usage_stats", { id });
+}
+
+export async function getInstanceRequestHistory(
+  id: string,
+): Promise<RequestHistory> {
+  return invoke("get_instance_request_history", { id });
+}
+
+export async function onInstanceStatusChanged(
+  callback: (event: InstanceStatusEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<InstanceStatusEvent>(
+    "proxy-instance-status-changed",
+    (event) => {
+      callback(event.payload);
+    },
+  );
+}
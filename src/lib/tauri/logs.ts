@@ -3,14 +3,17 @@ import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
 // Request log for live monitoring
 export interface RequestLog {
+  costUsd?: number;
   durationMs: number;
   id: string;
   method: string;
   model: string;
   path: string;
   provider: string;
+  requestId?: string;
   status: number;
   timestamp: number;
+  tokensCached?: number;
   tokensIn?: number;
   tokensOut?: number;
 }
@@ -39,6 +42,28 @@ export async function clearLogs(): Promise<void> {
   return invoke("clear_logs");
 }
 
+// Sidecar output - CLIProxyAPI stdout/stderr captured by ProxyPal
+export type SidecarStream = "stdout" | "stderr";
+
+export interface SidecarOutputLine {
+  line: string;
+  seq: number; // Pass the last seen value as `since` to fetch only newer lines
+  stream: SidecarStream;
+  timestamp: number;
+}
+
+export async function getSidecarOutput(since?: number, limit?: number): Promise<SidecarOutputLine[]> {
+  return invoke("get_sidecar_output", { limit, since });
+}
+
+export async function onSidecarOutput(
+  callback: (line: SidecarOutputLine) => void,
+): Promise<UnlistenFn> {
+  return listen<SidecarOutputLine>("sidecar-output", (event) => {
+    callback(event.payload);
+  });
+}
+
 // Request Error Logs - view error-specific logs
 export async function getRequestErrorLogs(): Promise<string[]> {
   return invoke("get_request_error_logs");
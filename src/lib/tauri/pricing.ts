@@ -0,0 +1,42 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// USD per million tokens; cache rates default to the input rate
+export interface TokenRates {
+  cacheRead?: number;
+  cacheWrite?: number;
+  input: number;
+  output: number;
+}
+
+// Rates for requests whose prompt is larger than aboveTokens
+export interface LongContextTier extends TokenRates {
+  aboveTokens: number;
+}
+
+// `model` is a glob such as "claude-*-opus-*", or a regex between slashes
+// such as "/^o[134](-mini)?$/". Rules with a provider are tried first.
+export interface PricingRule extends TokenRates {
+  longContext?: LongContextTier;
+  model: string;
+  provider?: string;
+}
+
+export interface PricingTables {
+  defaults: PricingRule[];
+  overrides: PricingRule[];
+  overridesPath: string;
+}
+
+export async function getPricing(): Promise<PricingTables> {
+  return invoke("get_pricing");
+}
+
+// Stored costs keep their old prices until recomputeCosts runs
+export async function savePricingOverrides(rules: PricingRule[]): Promise<void> {
+  return invoke("save_pricing_overrides", { rules });
+}
+
+// Reprices stored requests with the current pricing; returns how many
+export async function recomputeCosts(): Promise<number> {
+  return invoke("recompute_costs");
+}
@@ -0,0 +1,37 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+import type { AppConfig } from "./config";
+
+// Named config profiles
+export interface ProfileInfo {
+  active: boolean;
+  modifiedAt?: number;
+  name: string;
+}
+
+export async function getProfiles(): Promise<ProfileInfo[]> {
+  return invoke("get_profiles");
+}
+
+export async function createProfile(name: string): Promise<ProfileInfo[]> {
+  return invoke("create_profile", { name });
+}
+
+export async function cloneProfile(source: string, name: string): Promise<ProfileInfo[]> {
+  return invoke("clone_profile", { source, name });
+}
+
+export async function deleteProfile(name: string): Promise<ProfileInfo[]> {
+  return invoke("delete_profile", { name });
+}
+
+export async function activateProfile(name: string): Promise<AppConfig> {
+  return invoke("activate_profile", { name });
+}
+
+export async function onProfileActivated(callback: (name: string) => void): Promise<UnlistenFn> {
+  return listen<string>("profile-activated", (event) => {
+    callback(event.payload);
+  });
+}
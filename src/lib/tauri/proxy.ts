@@ -1,13 +1,25 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
+import type { AppConfig } from "./config";
+import type { DiffLine } from "./snapshots";
+
 // Proxy management
 export interface ProxyStatus {
   endpoint: string;
+  lastError?: string; // Set when the sidecar kept crashing and was left stopped
   port: number;
+  restartCount: number; // Automatic restarts after unexpected exits
   running: boolean;
 }
 
+// Generated value overridden by proxy-config-custom.yaml
+export interface MergeConflict {
+  custom: string;
+  generated: string;
+  path: string;
+}
+
 export async function startProxy(): Promise<ProxyStatus> {
   return invoke("start_proxy");
 }
@@ -28,6 +40,119 @@ export async function onProxyStatusChanged(
   });
 }
 
+// The proxy port changed, automatically or in settings
+export interface PortChangeReport {
+  errors: string[];
+  newPort: number;
+  oldPort: number;
+  updatedFiles: string[]; // Agent config files rewritten to the new port
+  updatedTunnels: string[]; // e.g. "SSH me@example.com", "Cloudflare office"
+}
+
+export async function onProxyPortChanged(
+  callback: (report: PortChangeReport) => void,
+): Promise<UnlistenFn> {
+  return listen<PortChangeReport>("proxy-port-changed", (event) => {
+    callback(event.payload);
+  });
+}
+
+// The sidecar exited unexpectedly and was restarted after a backoff delay
+export interface ProxyRestartedEvent {
+  attempt: number;
+  delayMs: number;
+  exitCode?: number;
+  restartCount: number;
+}
+
+export async function onProxyRestarted(
+  callback: (event: ProxyRestartedEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<ProxyRestartedEvent>("proxy-restarted", (event) => {
+    callback(event.payload);
+  });
+}
+
+// The sidecar exited too often in a short window; it stays stopped until started again
+export interface ProxyCrashLoopEvent {
+  exitCode?: number;
+  exits: number;
+  message: string;
+  stderrTail: string[];
+  windowSecs: number;
+}
+
+export async function onProxyCrashLoop(
+  callback: (event: ProxyCrashLoopEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<ProxyCrashLoopEvent>("proxy-crash-loop", (event) => {
+    callback(event.payload);
+  });
+}
+
+// What the running CLIProxyAPI supports, detected from its version on start
+export type SidecarLogFormat = "bracketed" | "iso" | "unknown";
+
+export interface SidecarCapabilities {
+  authFileStatusApi: boolean; // PATCH auth-files/status (v6.7.18+)
+  authStatusApi: boolean; // GET /api/auth/status (v6.6.72+)
+  logFormat: SidecarLogFormat;
+  minimumVersion: string;
+  outdated: boolean; // Older than minimumVersion; show an upgrade warning
+  version?: string; // Undefined if detection failed
+}
+
+export async function getSidecarCapabilities(): Promise<SidecarCapabilities> {
+  return invoke("get_sidecar_capabilities");
+}
+
+export async function onSidecarCapabilities(
+  callback: (capabilities: SidecarCapabilities) => void,
+): Promise<UnlistenFn> {
+  return listen<SidecarCapabilities>("sidecar-capabilities", (event) => {
+    callback(event.payload);
+  });
+}
+
+// CLIProxyAPI versions installed at runtime; the active one replaces the bundled sidecar
+export interface SidecarBinary {
+  active: boolean;
+  installedAt: string;
+  path: string;
+  sha256: string; // Of the release archive
+  source: string; // URL or local path of the archive
+  version: string;
+}
+
+export interface InstallSidecarOptions {
+  activate?: boolean; // Switch to it right away, rolling back if it fails to start
+  manifest?: string; // checksums.txt URL or path; defaults to the one beside the archive
+  sha256?: string; // Expected archive checksum instead of a manifest
+  version?: string; // Defaults to the version in the archive name
+}
+
+export async function listSidecarVersions(): Promise<SidecarBinary[]> {
+  return invoke("list_sidecar_versions");
+}
+
+export async function installSidecarVersion(
+  source: string,
+  options: InstallSidecarOptions = {},
+): Promise<SidecarBinary> {
+  return invoke("install_sidecar_version", {
+    activate: options.activate ?? false,
+    manifest: options.manifest,
+    sha256: options.sha256,
+    source,
+    version: options.version,
+  });
+}
+
+// Pass null to go back to the bundled sidecar
+export async function setActiveSidecarVersion(version: string | null): Promise<SidecarBinary[]> {
+  return invoke("set_active_sidecar_version", { version });
+}
+
 export async function onTrayToggleProxy(
   callback: (shouldStart: boolean) => void,
 ): Promise<UnlistenFn> {
@@ -35,3 +160,45 @@ export async function onTrayToggleProxy(
     callback(event.payload);
   });
 }
+
+export async function onProxyConfigConflicts(
+  callback: (conflicts: MergeConflict[]) => void,
+): Promise<UnlistenFn> {
+  return listen<MergeConflict[]>("proxy-config-conflicts", (event) => {
+    callback(event.payload);
+  });
+}
+
+// Generated proxy-config.yaml preview; secrets are masked
+export type ProxyConfigBaseline = "running" | "file" | "none";
+
+export interface ProxyConfigPreview {
+  baseline: ProxyConfigBaseline; // What the diff compares against
+  changed: boolean;
+  conflicts: MergeConflict[];
+  diff: DiffLine[];
+  yaml: string;
+}
+
+// Previews the given (unsaved) config, or the saved one when omitted
+export async function previewProxyConfig(config?: AppConfig): Promise<ProxyConfigPreview> {
+  return invoke("preview_proxy_config", { config });
+}
+
+// How a config change reached the running proxy
+export type ReloadPath = "none" | "management" | "file-reload" | "restart";
+
+export interface ReloadReport {
+  fileReloadFields: string[];
+  managementFields: string[];
+  path: ReloadPath;
+  restartFields: string[];
+}
+
+export async function onProxyConfigApplied(
+  callback: (report: ReloadReport) => void,
+): Promise<UnlistenFn> {
+  return listen<ReloadReport>("proxy-config-applied", (event) => {
+    callback(event.payload);
+  });
+}
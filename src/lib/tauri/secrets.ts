@@ -0,0 +1,49 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Encrypted vault for API keys and tokens. While enabled, config values hold
+// "vault:<name>" references instead of the keys themselves.
+export type VaultMode = "passphrase" | "key-file";
+
+export interface VaultStatus {
+  enabled: boolean;
+  mode?: VaultMode;
+  secretCount: number; // 0 while locked
+  unlocked: boolean;
+}
+
+export function isSecretRef(value: string | undefined): boolean {
+  return value?.startsWith("vault:") ?? false;
+}
+
+// A key field holding a reference shows empty, with this placeholder, so the
+// reference is never displayed or saved back as if it were the key. Typing
+// replaces the stored key; leaving the field alone keeps it.
+export const STORED_IN_VAULT = "Stored in vault";
+
+export function secretFieldValue(value: string | undefined): string {
+  return isSecretRef(value) ? "" : (value ?? "");
+}
+
+export function secretFieldPlaceholder(value: string | undefined, placeholder: string): string {
+  return isSecretRef(value) ? STORED_IN_VAULT : placeholder;
+}
+
+export async function getVaultStatus(): Promise<VaultStatus> {
+  return invoke("get_vault_status");
+}
+
+// Creates the vault and moves existing plaintext keys into it
+export async function enableSecretVault(
+  mode: VaultMode,
+  passphrase?: string,
+): Promise<VaultStatus> {
+  return invoke("enable_secret_vault", { mode, passphrase });
+}
+
+export async function unlockSecretVault(passphrase?: string): Promise<VaultStatus> {
+  return invoke("unlock_secret_vault", { passphrase });
+}
+
+export async function lockSecretVault(): Promise<VaultStatus> {
+  return invoke("lock_secret_vault");
+}
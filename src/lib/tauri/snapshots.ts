@@ -0,0 +1,48 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+import type { AppConfig } from "./config";
+
+// Rolling snapshots of config.json, proxy-config.yaml and proxy-config-custom.yaml
+export interface SnapshotRetention {
+  maxAgeDays: number; // 0 = no age limit
+  maxCount: number; // 0 = no count limit
+}
+
+export interface ConfigSnapshot {
+  createdAt: number; // Unix millis
+  files: string[];
+  id: string;
+  reason: string; // e.g. "save-config", "save-config-yaml", "before-restore"
+}
+
+export type DiffLineKind = "equal" | "added" | "removed";
+
+export interface DiffLine {
+  kind: DiffLineKind;
+  text: string;
+}
+
+export interface SnapshotFileDiff {
+  changed: boolean;
+  file: string;
+  lines: DiffLine[];
+}
+
+export async function listConfigSnapshots(): Promise<ConfigSnapshot[]> {
+  return invoke("list_config_snapshots");
+}
+
+export async function diffConfigSnapshot(id: string): Promise<SnapshotFileDiff[]> {
+  return invoke("diff_config_snapshot", { id });
+}
+
+export async function restoreConfigSnapshot(id: string): Promise<AppConfig> {
+  return invoke("restore_config_snapshot", { id });
+}
+
+export async function onConfigRestored(callback: (id: string) => void): Promise<UnlistenFn> {
+  return listen<string>("config-restored", (event) => {
+    callback(event.payload);
+  });
+}
@@ -9,7 +9,9 @@ import {
   onAuthStatusChanged,
   onCloudflareStatusChanged,
   onOAuthCallback,
+  onProxyPortChanged,
   onProxyStatusChanged,
+  onSidecarCapabilities,
   onSshStatusChanged,
   onTrayToggleProxy,
   refreshAuthStatus,
@@ -19,6 +21,7 @@ import {
   stopProxy,
   syncUsageFromProxy,
 } from "../lib/tauri";
+import { toastStore } from "./toast";
 
 import type {
   AppConfig,
@@ -34,6 +37,7 @@ function createAppStore() {
   const [proxyStatus, setProxyStatus] = createSignal<ProxyStatus>({
     endpoint: "http://localhost:8317/v1",
     port: 8317,
+    restartCount: 0,
     running: false,
   });
 
@@ -54,7 +58,6 @@ function createAppStore() {
   const [config, setConfig] = createSignal<AppConfig>({
     ampApiKey: "",
     ampModelMappings: [],
-    ampOpenaiProvider: undefined,
     ampOpenaiProviders: [],
     ampRoutingMode: "mappings",
     autoStart: true,
@@ -213,19 +216,37 @@ function createAppStore() {
         setCloudflareStatus((prev) => ({ ...prev, [status.id]: status }));
       });
 
+      // The port moved (taken by another process, or changed in settings)
+      const unlistenPort = await onProxyPortChanged(async (report) => {
+        setConfig(await getConfig());
+        const updated = [...report.updatedFiles, ...report.updatedTunnels];
+        toastStore.info(
+          `Proxy moved to port ${report.newPort}`,
+          updated.length > 0 ? `Updated: ${updated.join(", ")}` : undefined,
+        );
+      });
+
+      // The sidecar is older than ProxyPal supports
+      const unlistenCapabilities = await onSidecarCapabilities((capabilities) => {
+        if (capabilities.outdated) {
+          toastStore.warning(
+            `CLIProxyAPI ${capabilities.version} is outdated`,
+            `Update to ${capabilities.minimumVersion} or newer; some features are disabled.`,
+          );
+        }
+      });
+
       onCleanup(() => {
         unlistenSsh();
         unlistenCf();
+        unlistenPort();
+        unlistenCapabilities();
       });
 
-      // Auto-start proxy if configured
+      // The backend auto-starts the proxy on launch; catch up on its status
+      // in case it came up before the listener above
       if (nextConfig.autoStart) {
-        try {
-          const status = await startProxy();
-          updateProxyStatus(status);
-        } catch (error) {
-          console.error("Failed to auto-start proxy:", error);
-        }
+        updateProxyStatus(await getProxyStatus());
       }
 
       // Sync usage data from CLIProxyAPI on startup